@@ -12,31 +12,212 @@ fn main() {
                     "add",
                     "remove",
                     "novacom_getkey",
+                    "devmode_setup",
                     "localkey_verify",
                     "privkey_read",
+                    "host_key_read",
+                    "host_key_pin",
+                    "bookmark_add",
+                    "bookmark_remove",
+                    "discover",
+                    "import_ares",
+                    "export",
+                    "import",
+                    "keygen",
+                    "key_deploy",
+                    "wake",
+                    "status_poll",
+                    "status_list",
+                    "info",
+                    "capabilities",
+                    "encryption_get",
+                    "encryption_set",
+                    "clone",
+                    "diagnose",
+                    "get_default",
+                    "reorder",
+                    "metadata_set",
+                    "detect",
+                    "preset",
+                    "history",
+                    "share",
+                    "share_decode",
+                    "novacom_list",
+                    "validate",
                 ]),
             )
             .plugin(
                 "remote-command",
-                InlinedPlugin::new().commands(&["exec", "spawn"]),
+                InlinedPlugin::new().commands(&[
+                    "exec",
+                    "spawn",
+                    "forward",
+                    "unforward",
+                    "forwards",
+                ]),
             )
             .plugin(
                 "remote-shell",
-                InlinedPlugin::new()
-                    .commands(&["open", "close", "write", "resize", "screen", "list"]),
+                InlinedPlugin::new().commands(&[
+                    "open",
+                    "attach",
+                    "close",
+                    "write",
+                    "resize",
+                    "screen",
+                    "scrollback",
+                    "search",
+                    "rename",
+                    "zmodem_receive",
+                    "zmodem_send",
+                    "zmodem_end",
+                    "focus",
+                    "sessions",
+                    "list",
+                ]),
             )
             .plugin(
                 "remote-file",
-                InlinedPlugin::new()
-                    .commands(&["ls", "read", "write", "get", "put", "get_temp", "serve"]),
+                InlinedPlugin::new().commands(&[
+                    "ls",
+                    "stat",
+                    "readlink",
+                    "search",
+                    "disk_usage",
+                    "mounts",
+                    "archive_create",
+                    "archive_extract",
+                    "read",
+                    "write",
+                    "rename",
+                    "remove",
+                    "trash_list",
+                    "trash_restore",
+                    "trash_empty",
+                    "mkdir",
+                    "chmod",
+                    "chown",
+                    "touch",
+                    "get",
+                    "put",
+                    "get_dir",
+                    "get_archive",
+                    "put_dir",
+                    "sync",
+                    "sync_profiles",
+                    "sync_profile_save",
+                    "sync_profile_remove",
+                    "watch",
+                    "unwatch",
+                    "edit_open",
+                    "edit_save",
+                    "edit_close",
+                    "queue_add",
+                    "queue_list",
+                    "queue_pause",
+                    "queue_resume",
+                    "queue_cancel",
+                    "queue_reorder",
+                    "queue_clear",
+                    "set_rate_limit",
+                    "cancel",
+                    "get_temp",
+                    "serve",
+                ]),
             )
             .plugin(
                 "dev-mode",
-                InlinedPlugin::new().commands(&["status", "token"]),
+                InlinedPlugin::new().commands(&[
+                    "status",
+                    "token",
+                    "extend",
+                    "renew_set",
+                    "expiry_threshold_set",
+                ]),
             )
             .plugin(
                 "local-file",
                 InlinedPlugin::new().commands(&["checksum", "download", "remove", "temp_path"]),
+            )
+            .plugin(
+                "app-manager",
+                InlinedPlugin::new().commands(&[
+                    "list",
+                    "details",
+                    "launch",
+                    "close",
+                    "inspect",
+                    "install",
+                    "package",
+                    "repackage",
+                    "web_inspect",
+                    "debug_service",
+                    "backup",
+                    "restore",
+                    "backups",
+                    "logs",
+                ]),
+            )
+            .plugin(
+                "device-batch",
+                InlinedPlugin::new().commands(&["exec", "push", "install"]),
+            )
+            .plugin(
+                "emulator",
+                InlinedPlugin::new().commands(&["list", "start", "stop"]),
+            )
+            .plugin(
+                "homebrew",
+                InlinedPlugin::new().commands(&["packages", "updates", "install"]),
+            )
+            .plugin(
+                "screen",
+                InlinedPlugin::new().commands(&[
+                    "capture",
+                    "stream_start",
+                    "stream_stop",
+                    "stream_record",
+                ]),
+            )
+            .plugin(
+                "remote-input",
+                InlinedPlugin::new().commands(&["key", "pointer", "disconnect"]),
+            )
+            .plugin(
+                "luna",
+                InlinedPlugin::new().commands(&[
+                    "call",
+                    "subscribe",
+                    "bookmark_call",
+                    "bookmark_add",
+                    "bookmark_remove",
+                ]),
+            )
+            .plugin(
+                "system",
+                InlinedPlugin::new().commands(&[
+                    "system_info",
+                    "processes",
+                    "kill",
+                    "crash_reports",
+                    "crash_download",
+                    "core_dumps",
+                    "core_download",
+                    "services",
+                    "service_control",
+                    "monitor_start",
+                    "monitor_stop",
+                    "metrics_query",
+                    "metrics_clear",
+                ]),
+            )
+            .plugin(
+                "device-logs",
+                InlinedPlugin::new().commands(&["follow", "stop", "kernel", "export"]),
+            )
+            .plugin(
+                "network",
+                InlinedPlugin::new().commands(&["status", "ping", "traceroute", "lookup"]),
             ),
     )
     .expect("failed to run tauri-build");
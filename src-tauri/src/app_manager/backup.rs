@@ -0,0 +1,195 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Component, Path};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use flate2::read::GzDecoder;
+
+use crate::app_manager::{lifecycle, AppBackup};
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::session_manager::command::quote;
+use crate::transfer_manager::tar_pipe::finish;
+
+/// Where apps keep their data, `{id}` being replaced with the app ID. Only existing ones are
+/// backed up.
+const DATA_DIRS: [&str; 3] = [
+    "/var/palm/data/{id}",
+    "/var/preferences/{id}",
+    "/media/developer/apps/usr/palm/data/{id}",
+];
+
+/// Closes app `id`, and archives its data directories, or `paths` within them if given, into a new
+/// timestamped backup in local directory `dir`.
+pub(crate) fn backup(
+    session: &DeviceConnection,
+    id: &str,
+    paths: Option<&[String]>,
+    dir: &Path,
+) -> Result<AppBackup, Error> {
+    validate_id(id)?;
+    let candidates: Vec<String> = match paths {
+        Some(paths) => paths.to_vec(),
+        None => DATA_DIRS.iter().map(|d| d.replace("{id}", id)).collect(),
+    };
+    let existing: Vec<String> = candidates
+        .into_iter()
+        .filter(|path| in_data_dirs(path.trim_start_matches('/'), id))
+        .filter(|path| {
+            return session
+                .exec(&format!("test -e {}", quote(path)), None)
+                .is_ok();
+        })
+        .collect();
+    if existing.is_empty() {
+        return Err(Error::new(format!("No data of {id} found")));
+    }
+    lifecycle::close(session, id).unwrap_or(());
+
+    std::fs::create_dir_all(dir)?;
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let target = dir.join(format!("{id}-{created}.tar.gz"));
+    // Paths are relative to / so they can be extracted back in place
+    let relative: Vec<String> = existing
+        .iter()
+        .map(|path| quote(path.trim_start_matches('/')))
+        .collect();
+    let command = format!("tar czf - -C / {}", relative.join(" "));
+    let _permit = session.acquire_channel();
+    let ch = session.new_channel()?;
+    ch.open_session()?;
+    ch.request_exec(&command)?;
+    let mut file = File::create(&target)?;
+    let result = match std::io::copy(&mut ch.stdout(), &mut file) {
+        Ok(_) => finish(ch, &command),
+        Err(e) => {
+            ch.close().unwrap_or(());
+            Err(e.into())
+        }
+    };
+    if let Err(e) = result {
+        std::fs::remove_file(&target).unwrap_or(());
+        return Err(e);
+    }
+    return Ok(AppBackup {
+        id: String::from(id),
+        created,
+        size: target.metadata()?.len(),
+        path: target.to_string_lossy().to_string(),
+    });
+}
+
+/// Closes the app of `backup`, which must be in local directory `dir`, and extracts its data back
+/// in place. Archives with anything outside the data directories of the app are refused.
+pub(crate) fn restore(session: &DeviceConnection, backup: &Path, dir: &Path) -> Result<(), Error> {
+    let backup = backup.canonicalize()?;
+    if backup.parent() != Some(dir.canonicalize()?.as_path()) {
+        return Err(Error::new("Not an app backup"));
+    }
+    let info = parse_name(&backup).ok_or_else(|| Error::new("Not an app backup"))?;
+    validate_id(&info.id)?;
+    check_members(&backup, &info.id)?;
+    lifecycle::close(session, &info.id).unwrap_or(());
+    let command = "tar xzf - -C /";
+    let _permit = session.acquire_channel();
+    let ch = session.new_channel()?;
+    ch.open_session()?;
+    ch.request_exec(command)?;
+    let mut file = File::open(&backup)?;
+    let mut buf = [0; 8192];
+    loop {
+        let size = file.read(&mut buf)?;
+        if size == 0 {
+            break;
+        }
+        if let Err(e) = ch.stdin().write_all(&buf[..size]) {
+            ch.close().unwrap_or(());
+            return Err(e.into());
+        }
+    }
+    ch.send_eof()?;
+    return finish(ch, command);
+}
+
+/// Checks that all members of archive `backup` are within the data directories of app `id`, and
+/// that links don't point elsewhere.
+fn check_members(backup: &Path, id: &str) -> Result<(), Error> {
+    let escapes = |path: &Path| {
+        return path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+    };
+    let mut archive = tar::Archive::new(GzDecoder::new(File::open(backup)?));
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let path = entry.path()?;
+        let name = path.to_string_lossy();
+        let name = name.trim_start_matches("./").trim_end_matches('/');
+        if escapes(&path) || !in_data_dirs(name, id) {
+            return Err(Error::new(format!(
+                "Backup contains {name}, which isn't app data of {id}"
+            )));
+        }
+        if let Some(target) = entry.link_name()? {
+            if target.is_absolute() || escapes(&target) {
+                return Err(Error::new(format!(
+                    "Backup contains link {name} pointing outside app data of {id}"
+                )));
+            }
+        }
+    }
+    return Ok(());
+}
+
+/// Whether `path`, relative to /, is one of the data directories of app `id` or within one.
+fn in_data_dirs(path: &str, id: &str) -> bool {
+    return DATA_DIRS.iter().any(|dir| {
+        let dir = dir.replace("{id}", id);
+        let dir = dir.trim_start_matches('/');
+        return path == dir
+            || path
+                .strip_prefix(dir)
+                .is_some_and(|rest| rest.starts_with('/'));
+    });
+}
+
+/// Lists backups in local directory `dir`, of app `id` if given, newest first.
+pub(crate) fn backups(dir: &Path, id: Option<&str>) -> Result<Vec<AppBackup>, Error> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut backups = Vec::<AppBackup>::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(backup) = parse_name(&path) else {
+            continue;
+        };
+        if id.is_some_and(|id| id != backup.id) {
+            continue;
+        }
+        backups.push(backup);
+    }
+    backups.sort_by(|a, b| b.created.cmp(&a.created));
+    return Ok(backups);
+}
+
+/// Reads app ID and creation time from the file name of a backup, `{id}-{created}.tar.gz`.
+fn parse_name(path: &Path) -> Option<AppBackup> {
+    let name = path.file_name()?.to_str()?.strip_suffix(".tar.gz")?;
+    let (id, created) = name.rsplit_once('-')?;
+    return Some(AppBackup {
+        id: String::from(id),
+        created: created.parse().ok()?,
+        size: path.metadata().ok()?.len(),
+        path: path.to_string_lossy().to_string(),
+    });
+}
+
+fn validate_id(id: &str) -> Result<(), Error> {
+    if id.is_empty() || id.starts_with('.') || id.contains('/') {
+        return Err(Error::new(format!("Invalid app ID {id}")));
+    }
+    return Ok(());
+}
@@ -0,0 +1,141 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::app_manager::{lifecycle, NodeInspector, WebInspector, APPS_ROOT};
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::session_manager::command::quote;
+use crate::session_manager::PortForward;
+
+/// Ports the web inspector listens on, on the device only: webOS TV first, then webOS OSE
+const WEB_INSPECTOR_PORTS: [u16; 2] = [9998, 9223];
+/// Ports the Node inspector listens on, for Node versions with `--inspect` first, then with
+/// `--debug` like older webOS has. Other services may hold these too, so only ports that start
+/// listening with the restarted service count.
+const NODE_INSPECTOR_PORTS: [u16; 3] = [9229, 8080, 5858];
+const NODE_INSPECTOR_ATTEMPTS: usize = 10;
+/// How long a freshly launched app takes to show up in the inspector
+const INSPECTOR_WAIT: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InspectorPage {
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    web_socket_debugger_url: Option<String>,
+}
+
+/// Closes app `id` if it's running and launches it again, so the inspector sees it load from the
+/// start, then returns the port the web inspector listens on.
+pub(crate) fn debug_app(session: &DeviceConnection, id: &str) -> Result<u16, Error> {
+    lifecycle::close(session, id).unwrap_or(());
+    lifecycle::launch(session, id, None)?;
+    // Give the app a moment to show up in the inspector
+    std::thread::sleep(INSPECTOR_WAIT);
+    let listening = listening_ports(session)?;
+    return WEB_INSPECTOR_PORTS
+        .into_iter()
+        .find(|port| listening.contains(port))
+        .ok_or_else(|| {
+            Error::new("Web inspector isn't listening on the device, is Developer Mode enabled?")
+        });
+}
+
+/// Finds the page of app `id` among the ones listed by the inspector forwarded to `forward`, and
+/// returns DevTools URLs pointing through the forward. Falls back to the inspector's own page
+/// listing if the app doesn't show up.
+pub(crate) async fn web_inspector(forward: PortForward, id: &str) -> Result<WebInspector, Error> {
+    let base = format!("http://127.0.0.1:{}", forward.local_port);
+    let pages: Vec<InspectorPage> = match reqwest::get(format!("{base}/json/list")).await {
+        Ok(resp) => resp.json().await.unwrap_or_default(),
+        Err(_) => Vec::new(),
+    };
+    let page = pages
+        .iter()
+        .filter(|page| page.url.contains(id))
+        .find_map(|page| page.web_socket_debugger_url.as_deref())
+        .and_then(|ws| ws.find("/devtools/page/").map(|index| &ws[index..]));
+    let url = match page {
+        Some(path) => format!(
+            "{base}/devtools/inspector.html?ws=127.0.0.1:{}{path}",
+            forward.local_port
+        ),
+        None => format!("{base}/"),
+    };
+    return Ok(WebInspector { url, forward });
+}
+
+/// Restarts JS service `id` with the Node inspector enabled, and returns the port it listens on.
+pub(crate) fn debug_service(session: &DeviceConnection, id: &str) -> Result<u16, Error> {
+    if id.is_empty() || id.starts_with('.') || id.contains('/') {
+        return Err(Error::new(format!("Invalid service ID {id}")));
+    }
+    let dir = [
+        format!("{APPS_ROOT}/usr/palm/services/{id}"),
+        format!("/usr/palm/services/{id}"),
+    ]
+    .into_iter()
+    .find(|dir| {
+        let dir = quote(dir);
+        return session.exec(&format!("test -d {dir}"), None).is_ok();
+    })
+    .ok_or(Error::NotFound)?;
+    let dir = quote(&dir);
+    session
+        .exec(&format!("run-js-service -k {dir}"), None)
+        .unwrap_or_default();
+    let before = listening_ports(session)?;
+    session.exec(
+        &format!("nohup run-js-service -d {dir} >/dev/null 2>&1 &"),
+        None,
+    )?;
+    for _ in 0..NODE_INSPECTOR_ATTEMPTS {
+        std::thread::sleep(Duration::from_millis(500));
+        let listening = listening_ports(session)?;
+        let started: HashSet<&u16> = listening.difference(&before).collect();
+        if let Some(port) = NODE_INSPECTOR_PORTS
+            .into_iter()
+            .find(|p| started.contains(p))
+        {
+            return Ok(port);
+        }
+    }
+    return Err(Error::new(format!(
+        "Node inspector of {id} didn't start listening"
+    )));
+}
+
+/// Local TCP ports listened on by the device
+fn listening_ports(session: &DeviceConnection) -> Result<HashSet<u16>, Error> {
+    let output = session.exec("netstat -ltn", None)?;
+    return Ok(String::from_utf8_lossy(&output)
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(3))
+        .filter_map(|addr| addr.rsplit_once(':')?.1.parse().ok())
+        .collect());
+}
+
+/// Finds the DevTools URL of the Node inspector forwarded to `forward`, if it lists one.
+pub(crate) async fn node_inspector(forward: PortForward) -> Result<NodeInspector, Error> {
+    let target = format!("127.0.0.1:{}", forward.local_port);
+    let pages: Vec<InspectorPage> = match reqwest::get(format!("http://{target}/json/list")).await {
+        Ok(resp) => resp.json().await.unwrap_or_default(),
+        Err(_) => Vec::new(),
+    };
+    let devtools_url = pages
+        .iter()
+        .find_map(|page| page.web_socket_debugger_url.as_deref())
+        .and_then(|ws| ws.find("://").map(|index| &ws[index + 3..]))
+        .and_then(|ws| ws.find('/').map(|index| &ws[index..]))
+        .map(|path| {
+            format!("devtools://devtools/bundled/js_app.html?experiments=true&v8only=true&ws={target}{path}")
+        });
+    return Ok(NodeInspector {
+        target,
+        devtools_url,
+        forward,
+    });
+}
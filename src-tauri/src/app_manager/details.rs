@@ -0,0 +1,80 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::app_manager::{list, AppDetails, APPS_ROOT};
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::session_manager::command::quote;
+
+/// Icons larger than this are left out, they aren't meant for showing in a list anyway
+const MAX_ICON_SIZE: usize = 1024 * 1024;
+
+/// Reads `appinfo.json` of app `id`. With `with_icon`, the icon is included as a data URL.
+pub(crate) fn details(
+    session: &DeviceConnection,
+    id: &str,
+    with_icon: bool,
+) -> Result<AppDetails, Error> {
+    if id.is_empty() || id.starts_with('.') || id.contains('/') {
+        return Err(Error::new(format!("Invalid app ID {id}")));
+    }
+    let folder = match list::list(session) {
+        Ok(apps) => apps
+            .into_iter()
+            .find(|app| app.id == id)
+            .and_then(|app| app.folder_path),
+        Err(Error::Unsupported) => None,
+        Err(e) => return Err(e),
+    }
+    .unwrap_or_else(|| format!("{APPS_ROOT}/usr/palm/applications/{id}"));
+    let appinfo = read_file(session, &format!("{folder}/appinfo.json"))?.ok_or(Error::NotFound)?;
+    let mut details: AppDetails = serde_json::from_slice(&appinfo)
+        .map_err(|e| Error::new(format!("Bad appinfo.json of {id}: {e}")))?;
+    if with_icon {
+        if let Some(icon) = details.icon.as_deref() {
+            details.icon_data = icon_data(session, &folder, icon)?;
+        }
+    }
+    details.folder_path = Some(folder);
+    return Ok(details);
+}
+
+fn icon_data(
+    session: &DeviceConnection,
+    folder: &str,
+    icon: &str,
+) -> Result<Option<String>, Error> {
+    let mime = match icon
+        .rsplit('.')
+        .next()
+        .map(|ext| ext.to_lowercase())
+        .as_deref()
+    {
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        _ => return Ok(None),
+    };
+    let path = format!("{folder}/{}", icon.trim_start_matches("./"));
+    let Some(data) = read_file(session, &path)? else {
+        return Ok(None);
+    };
+    if data.len() > MAX_ICON_SIZE {
+        return Ok(None);
+    }
+    return Ok(Some(format!(
+        "data:{mime};base64,{}",
+        STANDARD.encode(data)
+    )));
+}
+
+/// Reads a file on the device, or returns `None` if it doesn't exist.
+fn read_file(session: &DeviceConnection, path: &str) -> Result<Option<Vec<u8>>, Error> {
+    let path = quote(path);
+    let output = session.exec(&format!("if [ -f {path} ]; then cat {path}; fi"), None)?;
+    if output.is_empty() {
+        return Ok(None);
+    }
+    return Ok(Some(output));
+}
@@ -0,0 +1,185 @@
+use std::cmp::Ordering;
+use std::env::temp_dir;
+use std::path::{Path, PathBuf};
+
+use reqwest::Url;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+use crate::app_manager::inspect::compare_versions;
+use crate::app_manager::{AppUpdate, InstalledApp, PackageManifest, RepoPackage};
+use crate::error::Error;
+
+/// Package index of the webOS Homebrew Channel
+pub(crate) const DEFAULT_REPO: &str = "https://repo.webosbrew.org/api/apps.json";
+
+#[derive(Deserialize)]
+struct RepoPage {
+    #[serde(default)]
+    paging: Option<RepoPaging>,
+    #[serde(default)]
+    packages: Vec<RepoPackage>,
+}
+
+#[derive(Deserialize)]
+struct RepoPaging {
+    /// Relative to the current page
+    #[serde(default)]
+    next: Option<String>,
+}
+
+/// Fetches all packages listed in repository index `repo`, following its pages.
+pub(crate) async fn packages(repo: &str) -> Result<Vec<RepoPackage>, Error> {
+    let mut url = parse_url(repo)?;
+    let mut packages = Vec::<RepoPackage>::new();
+    loop {
+        let page: RepoPage = reqwest::get(url.clone())
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        for mut package in page.packages {
+            package.manifest_url = resolve(&url, &package.manifest_url)?;
+            packages.push(package);
+        }
+        match page.paging.and_then(|paging| paging.next) {
+            Some(next) => url = parse_url(&resolve(&url, &next)?)?,
+            None => break,
+        }
+    }
+    return Ok(packages);
+}
+
+/// Fills in installed versions of `packages`, and whether they have an update.
+pub(crate) fn compare_installed(packages: &mut [RepoPackage], installed: &[InstalledApp]) {
+    for package in packages.iter_mut() {
+        package.installed_version = installed
+            .iter()
+            .find(|app| app.id == package.id)
+            .and_then(|app| app.version.clone());
+        package.update_available = match (&package.installed_version, &package.manifest) {
+            (Some(installed), Some(manifest)) => {
+                compare_versions(&manifest.version, installed) == Ordering::Greater
+            }
+            _ => false,
+        };
+    }
+}
+
+/// Finds installed apps with a newer version in `repo` or one of `manifests`. Manifests given
+/// explicitly take precedence over the repository.
+pub(crate) async fn updates(
+    installed: &[InstalledApp],
+    repo: Option<&str>,
+    manifests: &[String],
+) -> Result<Vec<AppUpdate>, Error> {
+    let mut candidates = Vec::<(PackageManifest, String)>::new();
+    for url in manifests {
+        candidates.push((fetch_manifest(url).await?, url.clone()));
+    }
+    if let Some(repo) = repo {
+        for package in packages(repo).await? {
+            if candidates.iter().any(|(m, _)| m.id == package.id)
+                || !installed.iter().any(|app| app.id == package.id)
+            {
+                continue;
+            }
+            let manifest = match package.manifest {
+                Some(manifest) => manifest,
+                None => fetch_manifest(&package.manifest_url).await?,
+            };
+            candidates.push((manifest, package.manifest_url));
+        }
+    }
+    let mut updates = Vec::<AppUpdate>::new();
+    for (manifest, manifest_url) in candidates {
+        let Some(app) = installed.iter().find(|app| app.id == manifest.id) else {
+            continue;
+        };
+        let Some(installed_version) = app.version.clone() else {
+            continue;
+        };
+        if compare_versions(&manifest.version, &installed_version) != Ordering::Greater {
+            continue;
+        }
+        updates.push(AppUpdate {
+            id: manifest.id.clone(),
+            title: app.title.clone().or(manifest.title.clone()),
+            installed_version,
+            version: manifest.version,
+            manifest_url,
+        });
+    }
+    return Ok(updates);
+}
+
+/// Fetches the manifest of `package`, which tells where its IPK is.
+pub(crate) async fn manifest(package: &RepoPackage) -> Result<PackageManifest, Error> {
+    if let Some(manifest) = package.manifest.as_ref().filter(|m| m.ipk_url.is_some()) {
+        return Ok(manifest.clone());
+    }
+    return fetch_manifest(&package.manifest_url).await;
+}
+
+/// Fetches the package manifest at `url`.
+pub(crate) async fn fetch_manifest(url: &str) -> Result<PackageManifest, Error> {
+    let url = parse_url(url)?;
+    let mut manifest: PackageManifest = reqwest::get(url.clone())
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    if let Some(ipk_url) = manifest.ipk_url.as_deref() {
+        manifest.ipk_url = Some(resolve(&url, ipk_url)?);
+    }
+    return Ok(manifest);
+}
+
+/// Downloads the IPK of `manifest` to a temporary file, and checks its hash if the manifest has
+/// one. The caller removes the file when done with it.
+pub(crate) async fn download(manifest: &PackageManifest) -> Result<PathBuf, Error> {
+    let ipk_url = manifest
+        .ipk_url
+        .as_deref()
+        .ok_or_else(|| Error::new(format!("No IPK is available for {}", manifest.id)))?;
+    let path = temp_dir().join(format!("webos-dev-tmp-{}.ipk", Uuid::new_v4()));
+    let result = download_to(manifest, ipk_url, &path).await;
+    if result.is_err() {
+        tokio::fs::remove_file(&path).await.unwrap_or(());
+    }
+    return result.map(|_| path);
+}
+
+/// Streams the package into `path`, hashing it on the way.
+async fn download_to(manifest: &PackageManifest, url: &str, path: &Path) -> Result<(), Error> {
+    let mut response = reqwest::get(parse_url(url)?).await?.error_for_status()?;
+    let mut file = tokio::fs::File::create(path).await?;
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = response.chunk().await? {
+        hasher.update(&chunk);
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    if let Some(expected) = manifest.ipk_hash.as_ref().and_then(|h| h.sha256.as_deref()) {
+        if !hex::encode(hasher.finalize()).eq_ignore_ascii_case(expected) {
+            return Err(Error::new(format!(
+                "Checksum mismatch of downloaded package {}",
+                manifest.id
+            )));
+        }
+    }
+    return Ok(());
+}
+
+fn parse_url(url: &str) -> Result<Url, Error> {
+    return Url::parse(url).map_err(|e| Error::new(format!("Bad repository URL {url}: {e}")));
+}
+
+fn resolve(base: &Url, url: &str) -> Result<String, Error> {
+    return base
+        .join(url)
+        .map(|url| url.to_string())
+        .map_err(|e| Error::new(format!("Bad repository URL {url}: {e}")));
+}
@@ -0,0 +1,166 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+
+use crate::app_manager::{AppDetails, IpkInfo};
+use crate::error::Error;
+
+/// Reads control fields, apps and services of IPK file `path`, without unpacking it to disk.
+pub(crate) fn inspect(path: &Path) -> Result<IpkInfo, Error> {
+    let size = path.metadata()?.len();
+    let mut archive = ar::Archive::new(File::open(path)?);
+    let mut control: Option<BTreeMap<String, String>> = None;
+    let mut apps = Vec::<AppDetails>::new();
+    let mut services = Vec::<String>::new();
+    while let Some(entry) = archive.next_entry() {
+        let entry = entry?;
+        let name = String::from_utf8_lossy(entry.header().identifier()).to_string();
+        match name.trim_end_matches('/') {
+            "control.tar.gz" => {
+                let mut tar = tar::Archive::new(GzDecoder::new(entry));
+                for file in tar.entries()? {
+                    let mut file = file?;
+                    if file.path()?.file_name().and_then(|n| n.to_str()) == Some("control") {
+                        let mut text = String::new();
+                        file.read_to_string(&mut text)?;
+                        control = Some(parse_control(&text));
+                    }
+                }
+            }
+            "data.tar.gz" => {
+                let mut tar = tar::Archive::new(GzDecoder::new(entry));
+                for file in tar.entries()? {
+                    let mut file = file?;
+                    let path = file.path()?.to_string_lossy().to_string();
+                    let parts: Vec<&str> = path
+                        .trim_start_matches("./")
+                        .trim_end_matches('/')
+                        .split('/')
+                        .collect();
+                    match parts[..] {
+                        ["usr", "palm", "applications", id, "appinfo.json"] => {
+                            let mut data = Vec::new();
+                            file.read_to_end(&mut data)?;
+                            let app: AppDetails = serde_json::from_slice(&data).map_err(|e| {
+                                Error::new(format!("Bad appinfo.json of {id}: {e}"))
+                            })?;
+                            apps.push(app);
+                        }
+                        ["usr", "palm", "services", id] if !services.iter().any(|s| s == id) => {
+                            services.push(String::from(id));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    let control = control.ok_or_else(|| Error::new("Not an IPK file, control is missing"))?;
+    let package = control
+        .get("Package")
+        .cloned()
+        .ok_or_else(|| Error::new("Package field is missing from control"))?;
+    let version = control.get("Version").cloned().unwrap_or_default();
+    return Ok(IpkInfo {
+        package,
+        version,
+        size,
+        control,
+        apps,
+        services,
+        installed_version: None,
+        downgrade: false,
+    });
+}
+
+/// Parses `Key: value` lines of a control file. Continuation lines are appended to the previous
+/// value.
+fn parse_control(text: &str) -> BTreeMap<String, String> {
+    let mut fields = BTreeMap::<String, String>::new();
+    let mut last: Option<String> = None;
+    for line in text.lines() {
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some(value) = last.as_ref().and_then(|key| fields.get_mut(key)) {
+                value.push('\n');
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            let key = String::from(key.trim());
+            fields.insert(key.clone(), String::from(value.trim()));
+            last = Some(key);
+        }
+    }
+    return fields;
+}
+
+/// Compares dotted versions like `1.2.10` numerically, component by component. Components may have
+/// a suffix after the number, like `0b2` or `beta`, which makes them a pre-release of the number
+/// alone.
+pub(crate) fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parse = |v: &str| -> Vec<(u64, String)> {
+        return v
+            .split(|c: char| c == '.' || c == '-')
+            .map(|part| {
+                let digits =
+                    part.len() - part.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+                let number = part[..digits].parse::<u64>().unwrap_or(0);
+                return (number, String::from(&part[digits..]));
+            })
+            .collect();
+    };
+    let (a, b) = (parse(a), parse(b));
+    let none = (0, String::new());
+    for i in 0..a.len().max(b.len()) {
+        let (a_number, a_suffix) = a.get(i).unwrap_or(&none);
+        let (b_number, b_suffix) = b.get(i).unwrap_or(&none);
+        let ordering = a_number.cmp(b_number).then_with(|| {
+            return match (a_suffix.is_empty(), b_suffix.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => a_suffix.cmp(b_suffix),
+            };
+        });
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    return Ordering::Equal;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_control_fields() {
+        let fields = parse_control(
+            "Package: com.example.app\nDescription: Short\n Longer text\n\tmore\nVersion: 1.0.0\n",
+        );
+        assert_eq!(fields["Package"], "com.example.app");
+        assert_eq!(fields["Description"], "Short\nLonger text\nmore");
+        assert_eq!(fields["Version"], "1.0.0");
+    }
+
+    #[test]
+    fn compares_versions_numerically() {
+        assert_eq!(compare_versions("1.2.10", "1.2.9"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0", "1.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("0.6.3-1", "0.6.3"), Ordering::Greater);
+    }
+
+    #[test]
+    fn compares_version_suffixes() {
+        assert_eq!(compare_versions("1.0.0-beta", "1.0.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.2.0b2", "1.2.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.2.0b2", "1.2.0b1"), Ordering::Greater);
+        assert_eq!(compare_versions("1.2.1b1", "1.2.0"), Ordering::Greater);
+    }
+}
@@ -0,0 +1,183 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use serde_json::{json, Value};
+use tauri::ipc::Channel;
+use uuid::Uuid;
+
+use crate::app_manager::InstallStatus;
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::remote_files::mounts::MountPoint;
+use crate::session_manager::command::quote;
+use crate::transfer_manager::Transfer;
+
+/// Where packages are uploaded to before installing, like `ares-install` does
+const STAGING_DIR: &str = "/media/developer/temp";
+const INSTALL_URI: &str = "luna://com.webos.appInstallService/dev/install";
+
+/// Uploads IPK file `source` to the device, installs it, and removes the uploaded copy. Each
+/// change of the installation status is passed to `on_status`. Installing when the developer
+/// mount is low on space needs `confirmed`, otherwise [Error::ConfirmationRequired] is returned.
+pub(crate) fn install_file<F>(
+    session: &DeviceConnection,
+    source: &Path,
+    confirmed: bool,
+    on_status: F,
+) -> Result<(), Error>
+where
+    F: Fn(InstallStatus) -> Result<(), Error>,
+{
+    check_space(session, confirmed)?;
+    on_status(InstallStatus::Uploading)?;
+    return staged(
+        session,
+        |remote| session.push_file(source, remote),
+        on_status,
+    );
+}
+
+/// Like [install_file], reporting upload progress through `transfer` and `progress`, and each
+/// change of the installation status to `on_status`.
+pub(crate) fn install_file_with_progress<F>(
+    session: &DeviceConnection,
+    source: &Path,
+    transfer: &Transfer,
+    progress: &Channel,
+    confirmed: bool,
+    on_status: F,
+) -> Result<(), Error>
+where
+    F: Fn(InstallStatus) -> Result<(), Error>,
+{
+    check_space(session, confirmed)?;
+    on_status(InstallStatus::Uploading)?;
+    return staged(
+        session,
+        |remote| {
+            if session.use_stream() {
+                return transfer.upload_stream(session, source, remote, progress);
+            }
+            let mut file = File::open(source)?;
+            transfer.expect(file.metadata()?.len() as usize);
+            let sftp = session.sftp()?;
+            let mut sfile = sftp.open(
+                remote,
+                libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC,
+                0o644,
+            )?;
+            transfer.copy(&mut file, &mut sfile, progress)?;
+            return Ok(());
+        },
+        on_status,
+    );
+}
+
+/// Fails with [Error::ConfirmationRequired] if the mount holding developer apps is low on space,
+/// unless `confirmed`. When the mounts can't be listed, the installer is left to find out.
+fn check_space(session: &DeviceConnection, confirmed: bool) -> Result<(), Error> {
+    if confirmed {
+        return Ok(());
+    }
+    let output = match session.exec(&MountPoint::command(), None) {
+        Ok(output) => output,
+        Err(e) => {
+            log::warn!("Failed to check free space before installing: {e:?}");
+            return Ok(());
+        }
+    };
+    let mounts = MountPoint::parse(&String::from_utf8_lossy(&output));
+    let Some(mount) = mounts.iter().find(|mount| mount.developer) else {
+        return Ok(());
+    };
+    if mount.low_space {
+        return Err(Error::ConfirmationRequired {
+            message: format!(
+                "Only {} MiB left on {}, the installation may fail",
+                mount.available / 1024 / 1024,
+                mount.mount
+            ),
+        });
+    }
+    return Ok(());
+}
+
+/// Uploads the package with `upload` to a staging path, installs it from there, and cleans up.
+fn staged<U, F>(session: &DeviceConnection, upload: U, on_status: F) -> Result<(), Error>
+where
+    U: FnOnce(&str) -> Result<(), Error>,
+    F: Fn(InstallStatus) -> Result<(), Error>,
+{
+    let remote = format!("{STAGING_DIR}/{}.ipk", Uuid::new_v4());
+    session.exec(&format!("mkdir -p {}", quote(STAGING_DIR)), None)?;
+    let mut result = upload(&remote);
+    if result.is_ok() {
+        result = install(session, &remote, on_status);
+    }
+    session
+        .exec(&format!("rm -f {}", quote(&remote)), None)
+        .unwrap_or_default();
+    return result;
+}
+
+/// Installs IPK file `path` already on the device, waiting until the installer is done with it.
+/// Status updates from the installer are passed to `on_status`.
+pub(crate) fn install<F>(session: &DeviceConnection, path: &str, on_status: F) -> Result<(), Error>
+where
+    F: Fn(InstallStatus) -> Result<(), Error>,
+{
+    let params = json!({
+        "id": "com.ares.defaultName",
+        "ipkUrl": path,
+        "subscribe": true,
+    });
+    let command = format!(
+        "luna-send-pub -i {INSTALL_URI} {}",
+        quote(&params.to_string())
+    );
+    let _permit = session.acquire_channel();
+    let ch = session.new_channel()?;
+    ch.open_session()?;
+    ch.request_exec(&command)?;
+    let mut result = Err(Error::new("Installer exited without a result"));
+    for line in BufReader::new(ch.stdout()).lines() {
+        let Ok(response) = serde_json::from_str::<Value>(&line?) else {
+            continue;
+        };
+        if response.get("returnValue") == Some(&Value::Bool(false)) {
+            let text = response["errorText"].as_str().unwrap_or("Unknown error");
+            result = Err(Error::new(format!("Failed to install: {text}")));
+            break;
+        }
+        let details = &response["details"];
+        match details["state"].as_str() {
+            Some("installed") => {
+                result = on_status(InstallStatus::Installed);
+                break;
+            }
+            Some(state) if state.contains("failed") => {
+                let reason = details["reason"]
+                    .as_str()
+                    .or_else(|| details["errorCode"].as_str())
+                    .unwrap_or(state);
+                result = Err(Error::new(format!("Failed to install: {reason}")));
+                break;
+            }
+            Some(state) => {
+                let status = InstallStatus::Installing {
+                    state: String::from(state),
+                    progress: details["progress"].as_u64().map(|p| p.min(100) as u8),
+                };
+                if let Err(e) = on_status(status) {
+                    result = Err(e);
+                    break;
+                }
+            }
+            None => {}
+        }
+    }
+    // luna-send keeps the subscription open, closing the channel ends it
+    ch.close()?;
+    return result;
+}
@@ -0,0 +1,204 @@
+use std::env::temp_dir;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::de::DeserializeOwned;
+use tauri::ipc::Channel;
+use uuid::Uuid;
+
+use crate::app_manager::{AppInfo, PackageInfo, APPS_ROOT};
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::session_manager::command::quote;
+use crate::transfer_manager::tar_pipe::finish;
+use crate::transfer_manager::Transfer;
+
+const PACKAGER_VERSION: &str = concat!("webos-dev-manager/", env!("CARGO_PKG_VERSION"));
+
+/// Repackages app `id`, installed in developer mode, and its services into IPK file `target`.
+/// Progress counts uncompressed bytes against the size reported by `du`, so it's approximate.
+pub(crate) fn extract(
+    session: &DeviceConnection,
+    transfer: &Transfer,
+    id: &str,
+    target: &Path,
+    progress: &Channel,
+) -> Result<(), Error> {
+    if id.is_empty() || id.starts_with('.') || id.contains('/') {
+        return Err(Error::new(format!("Invalid app ID {id}")));
+    }
+    let app_dir = format!("./usr/palm/applications/{id}");
+    let info: AppInfo =
+        read_json(session, &format!("{app_dir}/appinfo.json"))?.ok_or(Error::NotFound)?;
+    let arch = match (info.kind.as_deref(), info.main.as_deref()) {
+        (Some("native"), Some(main)) => {
+            remote_arch(session, &format!("{APPS_ROOT}/{app_dir}/{main}"))?
+        }
+        _ => "all",
+    };
+
+    let package_dir = format!("./usr/palm/packages/{id}");
+    let package: Option<PackageInfo> =
+        read_json(session, &format!("{package_dir}/packageinfo.json"))?;
+    let mut paths = vec![app_dir];
+    if let Some(package) = package {
+        paths.extend(
+            package
+                .services
+                .iter()
+                .map(|service| format!("./usr/palm/services/{service}")),
+        );
+        paths.push(package_dir);
+    }
+    let paths: Vec<String> = paths.iter().map(|p| quote(p)).collect();
+    let root = quote(APPS_ROOT);
+
+    let usage = session.exec(&format!("cd {root} && du -sk {}", paths.join(" ")), None)?;
+    let kbytes: usize = String::from_utf8_lossy(&usage)
+        .lines()
+        .filter_map(|line| line.split_whitespace().next()?.parse::<usize>().ok())
+        .sum();
+    transfer.expect(kbytes * 1024);
+
+    let data = temp_dir().join(format!("webos-dev-tmp-{}.tar.gz", Uuid::new_v4()));
+    let command = format!("tar cf - -C {root} {}", paths.join(" "));
+    let mut result = download_data(session, transfer, &command, &data, progress);
+    if result.is_ok() {
+        result = write_ipk(target, &control(&info, arch, kbytes), &data);
+        if result.is_err() {
+            std::fs::remove_file(target).unwrap_or(());
+        }
+    }
+    std::fs::remove_file(&data).unwrap_or(());
+    return result;
+}
+
+/// Reads and parses a JSON file under [APPS_ROOT], or returns `None` if it doesn't exist.
+fn read_json<T: DeserializeOwned>(
+    session: &DeviceConnection,
+    path: &str,
+) -> Result<Option<T>, Error> {
+    let path = quote(&format!("{APPS_ROOT}/{path}"));
+    let output = session.exec(&format!("if [ -f {path} ]; then cat {path}; fi"), None)?;
+    if output.is_empty() {
+        return Ok(None);
+    }
+    return serde_json::from_slice(&output)
+        .map(Some)
+        .map_err(|e| Error::new(format!("Malformed {path}: {e}")));
+}
+
+/// Package architecture of the native executable at `path`, from the machine in its ELF header.
+fn remote_arch(session: &DeviceConnection, path: &str) -> Result<&'static str, Error> {
+    let header = session.exec(&format!("head -c 20 {}", quote(path)), None)?;
+    return Ok(elf_arch(&header).unwrap_or_else(|| {
+        log::warn!("Unknown architecture of {path}, packaging for all");
+        "all"
+    }));
+}
+
+/// Streams the uncompressed tar output of `command` into gzipped file `target`.
+fn download_data(
+    session: &DeviceConnection,
+    transfer: &Transfer,
+    command: &str,
+    target: &Path,
+    progress: &Channel,
+) -> Result<(), Error> {
+    let _permit = session.acquire_channel();
+    let ch = session.new_channel()?;
+    ch.open_session()?;
+    ch.request_exec(command)?;
+    let mut encoder = GzEncoder::new(File::create(target)?, Compression::default());
+    if let Err(e) = transfer.copy(&mut ch.stdout(), &mut encoder, progress) {
+        ch.close()?;
+        return Err(e);
+    }
+    encoder.finish()?;
+    return finish(ch, command);
+}
+
+/// Control file of the package. `arch` is that of native code in it, or `all` if there's none.
+pub(super) fn control(info: &AppInfo, arch: &str, kbytes: usize) -> String {
+    let vendor = info.vendor.as_deref().unwrap_or("N/A");
+    let maintainer = match info.vendoremail.as_deref() {
+        Some(email) => format!("{vendor} <{email}>"),
+        None => String::from(vendor),
+    };
+    return format!(
+        "Package: {}\n\
+         Version: {}\n\
+         Section: misc\n\
+         Priority: optional\n\
+         Architecture: {arch}\n\
+         Installed-Size: {kbytes}\n\
+         Maintainer: {maintainer}\n\
+         Description: {}\n\
+         webOS-Package-Format-Version: 2\n\
+         webOS-Packager-Version: {PACKAGER_VERSION}\n",
+        info.id,
+        info.version,
+        info.title.as_deref().unwrap_or(&info.id),
+    );
+}
+
+/// Package architecture of an ELF file starting with `header`, named like `ares-package` does.
+pub(super) fn elf_arch(header: &[u8]) -> Option<&'static str> {
+    if header.len() < 20 || !header.starts_with(b"\x7fELF") {
+        return None;
+    }
+    return match u16::from_le_bytes([header[18], header[19]]) {
+        3 => Some("i686"),
+        40 => Some("arm"),
+        62 => Some("x86_64"),
+        183 => Some("aarch64"),
+        _ => None,
+    };
+}
+
+/// Writes the `ar` container of an IPK, with `control` and the already compressed `data` tarball.
+pub(super) fn write_ipk(target: &Path, control: &str, data: &Path) -> Result<(), Error> {
+    let mtime = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let mut header = tar::Header::new_gnu();
+    header.set_size(control.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    let mut control_tar = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    control_tar.append_data(&mut header, "./control", control.as_bytes())?;
+    let control_tar = control_tar.into_inner()?.finish()?;
+
+    let data = File::open(data)?;
+    let data_len = data.metadata()?.len();
+    let mut builder = ar::Builder::new(File::create(target)?);
+    append(&mut builder, "debian-binary", mtime, 4, &b"2.0\n"[..])?;
+    let control_len = control_tar.len() as u64;
+    append(
+        &mut builder,
+        "control.tar.gz",
+        mtime,
+        control_len,
+        &control_tar[..],
+    )?;
+    append(&mut builder, "data.tar.gz", mtime, data_len, data)?;
+    return Ok(());
+}
+
+fn append<W: Write, R: Read>(
+    builder: &mut ar::Builder<W>,
+    name: &str,
+    mtime: u64,
+    size: u64,
+    data: R,
+) -> Result<(), Error> {
+    let mut header = ar::Header::new(name.as_bytes().to_vec(), size);
+    header.set_mode(0o100644);
+    header.set_mtime(mtime);
+    builder.append(&header, data)?;
+    return Ok(());
+}
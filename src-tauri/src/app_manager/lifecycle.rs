@@ -0,0 +1,37 @@
+use serde_json::{json, Value};
+
+use crate::app_manager::service;
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::session_manager::luna;
+
+/// Launches app `id`, or relaunches it if already running, passing `params` to it as launch
+/// parameters.
+pub(crate) fn launch(
+    session: &DeviceConnection,
+    id: &str,
+    params: Option<Value>,
+) -> Result<(), Error> {
+    let mut request = json!({ "id": id });
+    if let Some(params) = params {
+        if !params.is_object() {
+            return Err(Error::new("Launch parameters must be a JSON object"));
+        }
+        request["params"] = params;
+    }
+    // Launching is public, so it works the same for the Developer Mode user
+    let public = !session.device.is_rooted();
+    luna::call::<Value>(
+        session,
+        "luna://com.webos.applicationManager/launch",
+        &request,
+        public,
+    )?;
+    return Ok(());
+}
+
+/// Closes running app `id`.
+pub(crate) fn close(session: &DeviceConnection, id: &str) -> Result<(), Error> {
+    service::call::<Value>(session, "closeByAppId", &json!({ "id": id }))?;
+    return Ok(());
+}
@@ -0,0 +1,23 @@
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::app_manager::{service, InstalledApp};
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+
+#[derive(Deserialize)]
+struct ListAppsResponse {
+    #[serde(default)]
+    apps: Vec<InstalledApp>,
+}
+
+/// Lists apps installed on the device, sorted by title.
+pub(crate) fn list(session: &DeviceConnection) -> Result<Vec<InstalledApp>, Error> {
+    let response: ListAppsResponse = service::call(session, "listApps", &json!({}))?;
+    let mut apps = response.apps;
+    apps.sort_by(|a, b| {
+        let title = |app: &InstalledApp| app.title.clone().unwrap_or_else(|| app.id.clone());
+        return title(a).to_lowercase().cmp(&title(b).to_lowercase());
+    });
+    return Ok(apps);
+}
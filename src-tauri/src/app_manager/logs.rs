@@ -0,0 +1,22 @@
+use crate::error::Error;
+use crate::session_manager::command::quote;
+
+/// Lines of history shown before following
+const LOG_HISTORY_LINES: u32 = 100;
+
+/// Command following device logs, with journald where available and `/var/log/messages` written
+/// by PmLogDaemon otherwise, printing only lines mentioning app or service `id`. Lines are
+/// filtered by the shell, as BusyBox `grep` would hold them back in its buffer.
+pub(crate) fn follow_command(id: &str) -> Result<String, Error> {
+    if id.is_empty() || id.contains(|c: char| c.is_whitespace() || c == '/') {
+        return Err(Error::new(format!("Invalid app ID {id}")));
+    }
+    let lines = LOG_HISTORY_LINES;
+    return Ok(format!(
+        "if command -v journalctl >/dev/null 2>&1; then \
+         journalctl -f -n {lines} --no-pager; \
+         else tail -n {lines} -F /var/log/messages; fi | \
+         while IFS= read -r line; do case \"$line\" in *{}*) echo \"$line\";; esac; done",
+        quote(id)
+    ));
+}
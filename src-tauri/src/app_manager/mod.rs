@@ -0,0 +1,263 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::session_manager::PortForward;
+
+pub(crate) mod backup;
+pub(crate) mod debug;
+pub(crate) mod details;
+pub(crate) mod homebrew;
+pub(crate) mod inspect;
+pub(crate) mod install;
+pub(crate) mod ipk;
+pub(crate) mod lifecycle;
+pub(crate) mod list;
+pub(crate) mod logs;
+pub(crate) mod package;
+pub(crate) mod service;
+
+/// Root of apps and services installed in developer mode
+pub(crate) const APPS_ROOT: &str = "/media/developer/apps";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AppInfo {
+    pub id: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor: Option<String>,
+    #[serde(
+        default,
+        alias = "vendorEmail",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub vendoremail: Option<String>,
+    /// Like `web` or `native`
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub main: Option<String>,
+}
+
+/// App as listed by the application manager
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledApp {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Like `web`, `native` or `qml`
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder_path: Option<String>,
+}
+
+/// Metadata of an app from its `appinfo.json`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AppDetails {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor: Option<String>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub main: Option<String>,
+    /// Relative to the app folder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub large_icon: Option<String>,
+    /// Like `1920x1080`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_permissions: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder_path: Option<String>,
+    /// Icon as a data URL, if requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon_data: Option<String>,
+}
+
+/// What's inside an IPK file
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IpkInfo {
+    pub package: String,
+    pub version: String,
+    /// Of the IPK file, in bytes
+    pub size: u64,
+    /// All fields of the `control` file
+    pub control: BTreeMap<String, String>,
+    pub apps: Vec<AppDetails>,
+    pub services: Vec<String>,
+    /// Version of the package already installed on the device, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub installed_version: Option<String>,
+    /// Whether installing would replace a newer version
+    pub downgrade: bool,
+}
+
+/// Package listed in a Homebrew Channel repository
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoPackage {
+    pub id: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon_uri: Option<String>,
+    pub manifest_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<PackageManifest>,
+    /// Version installed on the device, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub installed_version: Option<String>,
+    /// Whether the repository has a newer version than installed
+    #[serde(default)]
+    pub update_available: bool,
+}
+
+/// Manifest of a Homebrew Channel package
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageManifest {
+    pub id: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
+    #[serde(default)]
+    pub root_required: RootRequired,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipk_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipk_hash: Option<PackageHash>,
+}
+
+/// Whether a package needs a rooted device. Manifests give it as a boolean, or as `"optional"` for
+/// packages that only lose some features without root.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum RootRequired {
+    Flag(bool),
+    Level(String),
+}
+
+impl RootRequired {
+    pub fn required(&self) -> bool {
+        return match self {
+            RootRequired::Flag(required) => *required,
+            RootRequired::Level(level) => level == "required",
+        };
+    }
+}
+
+impl Default for RootRequired {
+    fn default() -> Self {
+        return RootRequired::Flag(false);
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PackageHash {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+/// Newer version of an installed app
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AppUpdate {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub installed_version: String,
+    pub version: String,
+    /// For installing the update
+    pub manifest_url: String,
+}
+
+/// Web inspector of an app, reachable on this machine through a port forward
+#[derive(Serialize, Clone, Debug)]
+pub struct WebInspector {
+    /// DevTools URL to open in a browser
+    pub url: String,
+    /// Stop it when done inspecting
+    pub forward: PortForward,
+}
+
+/// Node inspector of a JS service, reachable on this machine through a port forward
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeInspector {
+    /// `host:port` to add as a target in `chrome://inspect`
+    pub target: String,
+    /// DevTools URL to open directly, if the inspector lists one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub devtools_url: Option<String>,
+    /// Stop it when done debugging
+    pub forward: PortForward,
+}
+
+/// Local archive of an app's data
+#[derive(Serialize, Clone, Debug)]
+pub struct AppBackup {
+    pub id: String,
+    /// Seconds since the epoch
+    pub created: u64,
+    /// Of the archive, in bytes
+    pub size: u64,
+    pub path: String,
+}
+
+/// Stage of installing a package, in the order they happen
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "stage")]
+pub enum InstallStatus {
+    #[serde(rename = "downloading")]
+    Downloading,
+    #[serde(rename = "uploading")]
+    Uploading,
+    #[serde(rename = "installing")]
+    Installing {
+        /// As reported by the installer, like `installing` or `verifying`
+        state: String,
+        /// Percent, if reported
+        #[serde(skip_serializing_if = "Option::is_none")]
+        progress: Option<u8>,
+    },
+    #[serde(rename = "installed")]
+    Installed,
+}
+
+/// [InstallStatus] of one device when installing on several
+#[derive(Serialize, Clone, Debug)]
+pub struct DeviceInstallStatus {
+    pub device: String,
+    #[serde(flatten)]
+    pub status: InstallStatus,
+}
+
+/// Contents of `packageinfo.json`, which ties an app to the services installed along with it
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PackageInfo {
+    #[serde(default)]
+    pub services: Vec<String>,
+}
@@ -0,0 +1,177 @@
+use std::env::temp_dir;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Deserialize;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::app_manager::ipk::{control, elf_arch, write_ipk};
+use crate::app_manager::AppInfo;
+use crate::error::Error;
+
+#[derive(Deserialize)]
+struct ServicesJson {
+    id: String,
+}
+
+/// Builds IPK file `target` from the app in `app_dir`, and services in `service_dirs`, laid out
+/// like `ares-package` does.
+pub(crate) fn package(app_dir: &Path, service_dirs: &[&Path], target: &Path) -> Result<(), Error> {
+    let appinfo = std::fs::read(app_dir.join("appinfo.json"))
+        .map_err(|e| Error::new(format!("Failed to read appinfo.json: {e}")))?;
+    let info: AppInfo = serde_json::from_slice(&appinfo)
+        .map_err(|e| Error::new(format!("Bad appinfo.json: {e}")))?;
+    validate_id(&info.id)?;
+    validate_version(&info.version)?;
+    let main = info
+        .main
+        .clone()
+        .ok_or_else(|| Error::new("appinfo.json has no main"))?;
+    // Web apps may point to a URL instead of a file
+    if !main.contains("://") && !app_dir.join(&main).is_file() {
+        return Err(Error::new(format!("Main file {main} doesn't exist")));
+    }
+
+    let mut services = Vec::<String>::new();
+    for dir in service_dirs {
+        let json: ServicesJson = std::fs::read(dir.join("services.json"))
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .ok_or_else(|| Error::new(format!("Bad services.json in {}", dir.display())))?;
+        if json.id != info.id && !json.id.starts_with(&format!("{}.", info.id)) {
+            return Err(Error::new(format!(
+                "Service ID {} must start with app ID {}",
+                json.id, info.id
+            )));
+        }
+        validate_id(&json.id)?;
+        services.push(json.id);
+    }
+    let packageinfo = json!({
+        "app": info.id,
+        "id": info.id,
+        "loc_name": info.title.as_deref().unwrap_or(&info.id),
+        "package_format_version": 2,
+        "services": services,
+        "vendor": info.vendor.as_deref().unwrap_or(""),
+        "version": info.version,
+    });
+    let packageinfo = serde_json::to_vec_pretty(&packageinfo)?;
+
+    let mut bytes = dir_size(app_dir)? + packageinfo.len() as u64;
+    for dir in service_dirs {
+        bytes += dir_size(dir)?;
+    }
+    let kbytes = bytes.div_ceil(1024) as usize;
+
+    let arch = match info.kind.as_deref() {
+        Some("native") => local_arch(&app_dir.join(&main))?,
+        _ => "all",
+    };
+
+    let data = temp_dir().join(format!("webos-dev-tmp-{}.tar.gz", Uuid::new_v4()));
+    let mut result = write_data(
+        &data,
+        &info.id,
+        app_dir,
+        service_dirs,
+        &services,
+        &packageinfo,
+    );
+    if result.is_ok() {
+        result = write_ipk(target, &control(&info, arch, kbytes), &data);
+        if result.is_err() {
+            std::fs::remove_file(target).unwrap_or(());
+        }
+    }
+    std::fs::remove_file(&data).unwrap_or(());
+    return result;
+}
+
+/// Package architecture of the native executable `path`, from the machine in its ELF header.
+fn local_arch(path: &Path) -> Result<&'static str, Error> {
+    let mut header = Vec::new();
+    File::open(path)?.take(20).read_to_end(&mut header)?;
+    return Ok(elf_arch(&header).unwrap_or_else(|| {
+        log::warn!(
+            "Unknown architecture of {}, packaging for all",
+            path.display()
+        );
+        "all"
+    }));
+}
+
+/// Writes the `data.tar.gz` part of the package.
+fn write_data(
+    data: &Path,
+    id: &str,
+    app_dir: &Path,
+    service_dirs: &[&Path],
+    services: &[String],
+    packageinfo: &[u8],
+) -> Result<(), Error> {
+    let mut tar = tar::Builder::new(GzEncoder::new(File::create(data)?, Compression::default()));
+    tar.append_dir_all(format!("./usr/palm/applications/{id}"), app_dir)?;
+    for (dir, service) in service_dirs.iter().zip(services) {
+        tar.append_dir_all(format!("./usr/palm/services/{service}"), dir)?;
+    }
+    let mut header = tar::Header::new_gnu();
+    header.set_size(packageinfo.len() as u64);
+    header.set_mode(0o644);
+    tar.append_data(
+        &mut header,
+        format!("./usr/palm/packages/{id}/packageinfo.json"),
+        packageinfo,
+    )?;
+    tar.into_inner()?.finish()?;
+    return Ok(());
+}
+
+/// IDs are reverse domain names, like `com.example.app`.
+fn validate_id(id: &str) -> Result<(), Error> {
+    let valid = !id.is_empty()
+        && !id.starts_with('.')
+        && !id.ends_with('.')
+        && id.chars().all(|c| {
+            c.is_ascii_lowercase() || c.is_ascii_digit() || c == '.' || c == '-' || c == '+'
+        });
+    if !valid {
+        return Err(Error::new(format!(
+            "Invalid ID {id}, only lowercase letters, digits, '.', '-' and '+' are allowed"
+        )));
+    }
+    return Ok(());
+}
+
+/// Versions are up to three numbers, like `1.0.2`.
+fn validate_version(version: &str) -> Result<(), Error> {
+    let parts: Vec<&str> = version.split('.').collect();
+    let valid = parts.len() <= 3
+        && parts
+            .iter()
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+    if !valid {
+        return Err(Error::new(format!(
+            "Invalid version {version}, it should be like 1.0.0"
+        )));
+    }
+    return Ok(());
+}
+
+fn dir_size(dir: &Path) -> Result<u64, Error> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    return Ok(size);
+}
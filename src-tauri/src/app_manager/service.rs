@@ -0,0 +1,22 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::session_manager::luna;
+
+/// Calls `method` of the application manager. Rooted devices can use the full API, other users
+/// only get the `dev` category, restricted to apps installed in developer mode.
+pub(crate) fn call<T: DeserializeOwned>(
+    session: &DeviceConnection,
+    method: &str,
+    params: &Value,
+) -> Result<T, Error> {
+    return if session.device.is_rooted() {
+        let uri = format!("luna://com.webos.applicationManager/{method}");
+        luna::call(session, &uri, params, false)
+    } else {
+        let uri = format!("luna://com.webos.applicationManager/dev/{method}");
+        luna::call(session, &uri, params, true)
+    };
+}
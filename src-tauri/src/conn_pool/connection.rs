@@ -1,20 +1,77 @@
 use std::fmt::{Debug, Formatter};
-use std::io::Read;
+use std::io::{Read, Write};
 use std::ops::{Deref, DerefMut};
 use std::path::Path;
-use std::sync::Mutex;
-use std::time::Duration;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
 
-use libssh_rs::{AuthStatus, Session, SshKey, SshOption};
+use libssh_rs::{AuthStatus, Error as SshError, PublicKeyHashType, Session, SshKey, SshOption};
 use regex::Regex;
 use uuid::Uuid;
 
-use crate::conn_pool::{DeviceConnection, DeviceConnectionUserInfo, Id};
-use crate::device_manager::Device;
+use crate::conn_pool::{ChannelPermit, DeviceConnection, DeviceConnectionUserInfo, Id};
+use crate::device_manager::device::{ELEVATION_PROMPT, ELEVATION_PROMPT_TIMEOUT};
+use crate::device_manager::novacom::proxy_command;
+use crate::device_manager::{Device, DeviceFileTransfer};
 use crate::error::Error;
 
+const DEFAULT_CHANNEL_LIMIT: usize = 8;
+const PROMPT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
 impl DeviceConnection {
     pub(crate) fn new(device: Device, ssh_dir: Option<&Path>) -> Result<DeviceConnection, Error> {
+        let session = Self::handshake(&device)?;
+        let host_key = Self::fingerprint(&session)?;
+        if let Some(expected) = &device.host_key {
+            let actual = host_key.clone();
+            if !expected.eq_ignore_ascii_case(&actual) {
+                log::warn!(
+                    "Host key of {} changed: expected {expected}, got {actual}",
+                    device.name
+                );
+                return Err(Error::HostKeyMismatch {
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+
+        if let Some(private_key) = &device.private_key {
+            let passphrase = device.valid_passphrase();
+            let priv_key_content = private_key.content(ssh_dir)?;
+            let priv_key = SshKey::from_privkey_base64(&priv_key_content, passphrase.as_deref())?;
+
+            if session.userauth_publickey(None, &priv_key)? != AuthStatus::Success {
+                return Err(Error::Authorization {
+                    message: "Key authorization failed".to_string(),
+                });
+            }
+        } else if let Some(password) = &device.password {
+            if session.userauth_password(None, Some(password))? != AuthStatus::Success {
+                return Err(Error::Authorization {
+                    message: "Bad SSH password".to_string(),
+                });
+            }
+        } else if session.userauth_none(None)? != AuthStatus::Success {
+            return Err(Error::Authorization {
+                message: "Host needs authorization".to_string(),
+            });
+        }
+        let connection = DeviceConnection {
+            id: Uuid::new_v4(),
+            device: device.clone(),
+            user: DeviceConnectionUserInfo::new(&session)?,
+            host_key,
+            session,
+            last_ok: Mutex::new(true),
+            channels: Arc::new((Mutex::new(0), Condvar::new())),
+            sftp_missing: Mutex::default(),
+        };
+        log::info!("{:?} created", connection);
+        return Ok(connection);
+    }
+
+    pub(super) fn handshake(device: &Device) -> Result<Session, Error> {
         let kex = vec![
             "curve25519-sha256",
             "curve25519-sha256@libssh.org",
@@ -57,6 +114,12 @@ impl DeviceConnection {
         session.set_option(SshOption::HostKeys(key_types.join(",")))?;
         session.set_option(SshOption::PublicKeyAcceptedTypes(key_types.join(",")))?;
         session.set_option(SshOption::ProcessConfig(false))?;
+        if let Some(nduid) = &device.novacom {
+            session.set_option(SshOption::ProxyCommand(Some(proxy_command(
+                nduid,
+                device.port,
+            ))))?;
+        }
         #[cfg(windows)]
         {
             session.set_option(SshOption::KnownHosts(Some("C:\\nul".to_string())))?;
@@ -70,37 +133,24 @@ impl DeviceConnection {
         }
 
         session.connect()?;
+        return Ok(session);
+    }
 
-        if let Some(private_key) = &device.private_key {
-            let passphrase = device.valid_passphrase();
-            let priv_key_content = private_key.content(ssh_dir)?;
-            let priv_key = SshKey::from_privkey_base64(&priv_key_content, passphrase.as_deref())?;
+    /// Connects to the device without authenticating, and returns the SHA-256 fingerprint
+    /// of its host key.
+    pub(crate) fn host_key(device: &Device) -> Result<String, Error> {
+        let session = Self::handshake(device)?;
+        let fingerprint = Self::fingerprint(&session)?;
+        session.disconnect();
+        return Ok(fingerprint);
+    }
 
-            if session.userauth_publickey(None, &priv_key)? != AuthStatus::Success {
-                return Err(Error::Authorization {
-                    message: "Key authorization failed".to_string(),
-                });
-            }
-        } else if let Some(password) = &device.password {
-            if session.userauth_password(None, Some(password))? != AuthStatus::Success {
-                return Err(Error::Authorization {
-                    message: "Bad SSH password".to_string(),
-                });
-            }
-        } else if session.userauth_none(None)? != AuthStatus::Success {
-            return Err(Error::Authorization {
-                message: "Host needs authorization".to_string(),
-            });
-        }
-        let connection = DeviceConnection {
-            id: Uuid::new_v4(),
-            device: device.clone(),
-            user: DeviceConnectionUserInfo::new(&session)?,
-            session,
-            last_ok: Mutex::new(true),
-        };
-        log::info!("{:?} created", connection);
-        return Ok(connection);
+    pub(super) fn fingerprint(session: &Session) -> Result<String, Error> {
+        return Ok(hex::encode(
+            session
+                .get_server_public_key()?
+                .get_public_key_hash(PublicKeyHashType::Sha256)?,
+        ));
     }
 
     pub(super) fn reset_last_ok(&self) {
@@ -116,6 +166,132 @@ impl DeviceConnection {
             .lock()
             .expect("Failed to lock DeviceConnection::last_ok") = true;
     }
+
+    /// Runs the command on this connection, and returns its stdout. Non-zero exit codes are
+    /// reported as [Error::ExitStatus].
+    pub fn exec(&self, command: &str, stdin: Option<&[u8]>) -> Result<Vec<u8>, Error> {
+        return self.exec_prompted(command, None, stdin);
+    }
+
+    /// Like [DeviceConnection::exec], for a command from [Device::elevated_command]. The device
+    /// password is written only once the command prompts for it, so it doesn't become input of
+    /// the command when no password is asked for, then `stdin` follows.
+    pub fn exec_elevated(&self, command: &str, stdin: Option<&[u8]>) -> Result<Vec<u8>, Error> {
+        return self.exec_prompted(command, self.device.elevation_input(), stdin);
+    }
+
+    fn exec_prompted(
+        &self,
+        command: &str,
+        password: Option<Vec<u8>>,
+        stdin: Option<&[u8]>,
+    ) -> Result<Vec<u8>, Error> {
+        let _permit = self.acquire_channel();
+        let ch = self.new_channel()?;
+        ch.open_session()?;
+        ch.request_exec(command)?;
+        let mut buf = Vec::<u8>::new();
+        let mut stderr = Vec::<u8>::new();
+        if let Some(password) = password {
+            let prompt = regex::bytes::Regex::new(ELEVATION_PROMPT).unwrap();
+            let deadline = Instant::now() + ELEVATION_PROMPT_TIMEOUT;
+            let mut chunk = [0; 1024];
+            loop {
+                let size = ch.read_timeout(&mut chunk, true, Some(PROMPT_POLL_INTERVAL))?;
+                stderr.extend_from_slice(&chunk[..size]);
+                if prompt.is_match(&stderr) {
+                    ch.stdin().write_all(&password)?;
+                    break;
+                }
+                let size = ch.read_timeout(&mut chunk, false, Some(PROMPT_POLL_INTERVAL))?;
+                buf.extend_from_slice(&chunk[..size]);
+                // Output or exit before any prompt means no password is needed
+                if size != 0 || ch.is_eof() || ch.is_closed() || Instant::now() > deadline {
+                    break;
+                }
+            }
+        }
+        if let Some(stdin) = stdin {
+            ch.stdin().write_all(stdin)?;
+            ch.send_eof()?;
+        }
+        ch.stdout().read_to_end(&mut buf)?;
+        ch.stderr().read_to_end(&mut stderr)?;
+        let exit_code = ch.get_exit_status().unwrap_or(0);
+        ch.close()?;
+        self.mark_last_ok();
+        if exit_code != 0 {
+            return Err(Error::ExitStatus {
+                message: format!(""),
+                command: String::from(command),
+                exit_code,
+                stderr,
+                unhandled: true,
+            });
+        }
+        return Ok(buf);
+    }
+
+    /// Whether files should be transferred with commands over exec channels instead of SFTP,
+    /// either because the device is configured so, or it has no SFTP subsystem.
+    pub fn use_stream(&self) -> bool {
+        if matches!(self.device.files, Some(DeviceFileTransfer::Stream)) {
+            return true;
+        }
+        let mut missing = self.sftp_missing.lock().unwrap();
+        if let Some(missing) = *missing {
+            return missing;
+        }
+        let probe = {
+            let _permit = self.acquire_channel();
+            self.session.sftp()
+        };
+        return match probe {
+            Ok(_) => {
+                *missing = Some(false);
+                false
+            }
+            // Only a denied subsystem request means there's no SFTP, other failures may pass
+            Err(SshError::RequestDenied(msg)) if msg.contains("subsystem") => {
+                log::warn!("{self:?} has no SFTP, falling back to exec transfers: {msg}");
+                *missing = Some(true);
+                true
+            }
+            Err(e) => {
+                log::warn!("{self:?} can't open SFTP, using exec transfers this time: {e:?}");
+                true
+            }
+        };
+    }
+
+    /// Waits until a channel can be opened without exceeding the device's channel limit.
+    pub fn acquire_channel(&self) -> ChannelPermit {
+        let limit = self
+            .device
+            .channel_limit
+            .unwrap_or(DEFAULT_CHANNEL_LIMIT)
+            .max(1);
+        let (lock, cvar) = &*self.channels;
+        let mut count = lock.lock().unwrap();
+        if *count >= limit {
+            log::debug!("{self:?} reached channel limit {limit}, queueing");
+        }
+        while *count >= limit {
+            count = cvar.wait(count).unwrap();
+        }
+        *count += 1;
+        return ChannelPermit {
+            channels: self.channels.clone(),
+        };
+    }
+}
+
+impl Drop for ChannelPermit {
+    fn drop(&mut self) {
+        let (lock, cvar) = &*self.channels;
+        *lock.lock().unwrap() -= 1;
+        cvar.notify_one();
+    }
 }
 
 impl Deref for DeviceConnection {
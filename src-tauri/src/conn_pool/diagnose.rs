@@ -0,0 +1,149 @@
+use std::io::Read;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::conn_pool::{DeviceConnection, Diagnosis, DiagnosticResult, DiagnosticStage};
+use crate::device_manager::Device;
+use crate::error::Error;
+
+const DIAGNOSE_TIMEOUT: Duration = Duration::from_secs(5);
+/// RFC 4253 limits the identification string to 255 characters including CR LF
+const MAX_BANNER_LEN: usize = 255;
+
+impl DeviceConnection {
+    /// Connects to the device step by step, stopping at the first stage that fails, so that a
+    /// wrong address can be told apart from a wrong port or wrong credentials.
+    pub(crate) fn diagnose(device: &Device, ssh_dir: Option<&Path>) -> Diagnosis {
+        let mut diagnosis = Diagnosis {
+            stages: Vec::new(),
+            failed: None,
+        };
+        // Connections through novacom don't use the network
+        if device.novacom.is_none() && !diagnosis.check_network(device) {
+            return diagnosis;
+        }
+        if diagnosis
+            .check(DiagnosticStage::Negotiate, || {
+                let session = Self::handshake(device)?;
+                let actual = Self::fingerprint(&session);
+                session.disconnect();
+                let actual = actual?;
+                if let Some(expected) = &device.host_key {
+                    if !expected.eq_ignore_ascii_case(&actual) {
+                        return Err(Error::HostKeyMismatch {
+                            expected: expected.clone(),
+                            actual,
+                        });
+                    }
+                }
+                return Ok(((), format!("Host key fingerprint {actual}")));
+            })
+            .is_none()
+        {
+            return diagnosis;
+        }
+        diagnosis.check(DiagnosticStage::Authenticate, || {
+            let connection = Self::new(device.clone(), ssh_dir)?;
+            return Ok(((), format!("Logged in as {}", connection.device.username)));
+        });
+        return diagnosis;
+    }
+}
+
+impl Diagnosis {
+    /// Checks that the device resolves, accepts TCP connections, and speaks SSH.
+    fn check_network(&mut self, device: &Device) -> bool {
+        let Some(addrs) = self.check(DiagnosticStage::Resolve, || {
+            let addrs: Vec<SocketAddr> = (device.host.as_str(), device.port)
+                .to_socket_addrs()?
+                .collect();
+            if addrs.is_empty() {
+                return Err(Error::new(format!("{} has no addresses", device.host)));
+            }
+            let detail = addrs
+                .iter()
+                .map(|a| a.ip().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Ok((addrs, detail));
+        }) else {
+            return false;
+        };
+        let Some(mut stream) = self.check(DiagnosticStage::Connect, || {
+            let mut last_error = Error::Timeout;
+            for addr in &addrs {
+                match TcpStream::connect_timeout(addr, DIAGNOSE_TIMEOUT) {
+                    Ok(stream) => return Ok((stream, format!("Connected to {addr}"))),
+                    Err(e) => last_error = e.into(),
+                }
+            }
+            return Err(last_error);
+        }) else {
+            return false;
+        };
+        return self
+            .check(DiagnosticStage::Banner, || {
+                let banner = read_banner(&mut stream)?;
+                return Ok(((), banner));
+            })
+            .is_some();
+    }
+
+    fn check<T, F>(&mut self, stage: DiagnosticStage, action: F) -> Option<T>
+    where
+        F: FnOnce() -> Result<(T, String), Error>,
+    {
+        let started = Instant::now();
+        let result = action();
+        let elapsed = started.elapsed().as_millis() as u64;
+        return match result {
+            Ok((value, detail)) => {
+                self.stages.push(DiagnosticResult {
+                    stage,
+                    ok: true,
+                    detail: Some(detail),
+                    error: None,
+                    elapsed,
+                });
+                Some(value)
+            }
+            Err(e) => {
+                self.stages.push(DiagnosticResult {
+                    stage,
+                    ok: false,
+                    detail: None,
+                    error: Some(e),
+                    elapsed,
+                });
+                self.failed = Some(stage);
+                None
+            }
+        };
+    }
+}
+
+/// Reads the SSH identification string the server sends right after accepting the connection.
+fn read_banner(stream: &mut TcpStream) -> Result<String, Error> {
+    stream.set_read_timeout(Some(DIAGNOSE_TIMEOUT))?;
+    let mut line = Vec::new();
+    let mut buf = [0u8; 1];
+    while line.len() < MAX_BANNER_LEN {
+        if stream.read(&mut buf)? == 0 {
+            break;
+        }
+        if buf[0] == b'\n' {
+            break;
+        }
+        line.push(buf[0]);
+    }
+    let line = String::from_utf8_lossy(&line).trim_end().to_string();
+    if !line.starts_with("SSH-") {
+        return Err(Error::new(if line.is_empty() {
+            String::from("Server closed the connection without sending an SSH banner")
+        } else {
+            format!("Not an SSH server, it sent {line:?}")
+        }));
+    }
+    return Ok(line);
+}
@@ -2,19 +2,31 @@ use crate::device_manager::Device;
 use crate::error::Error;
 use libssh_rs::Session;
 use r2d2::{Pool, PooledConnection};
+use serde::Serialize;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Condvar, Mutex};
 use uuid::Uuid;
 
 pub mod connection;
+pub mod diagnose;
 pub mod pool;
 
 pub struct DeviceConnection {
     id: Uuid,
     pub device: Device,
     pub user: Option<DeviceConnectionUserInfo>,
+    /// SHA-256 fingerprint of the host key of the device
+    pub host_key: String,
     session: Session,
     last_ok: Mutex<bool>,
+    channels: Arc<(Mutex<usize>, Condvar)>,
+    /// Set once SFTP availability has been checked
+    sftp_missing: Mutex<Option<bool>>,
+}
+
+/// Holds one of the channel slots of a [DeviceConnection] until dropped.
+pub struct ChannelPermit {
+    channels: Arc<(Mutex<usize>, Condvar)>,
 }
 
 #[derive(Debug)]
@@ -39,4 +51,66 @@ pub struct DeviceConnectionPool {
 pub struct DeviceConnectionManager {
     device: Device,
     ssh_dir: Option<PathBuf>,
+    callback: Arc<Mutex<Option<Box<dyn ConnectionCallback + Send + Sync>>>>,
+}
+
+pub trait ConnectionCallback {
+    fn state(&self, event: ConnectionEvent);
+
+    /// Called with the host key of a device connected to for the first time, for pinning it.
+    fn host_key_seen(&self, _device: &str, _fingerprint: &str) {}
+}
+
+/// Result of [DeviceConnection::diagnose], with one entry per stage that was run
+#[derive(Clone, Serialize, Debug)]
+pub struct Diagnosis {
+    pub stages: Vec<DiagnosticResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failed: Option<DiagnosticStage>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct DiagnosticResult {
+    pub stage: DiagnosticStage,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Error>,
+    /// Milliseconds
+    pub elapsed: u64,
+}
+
+#[derive(Clone, Copy, Serialize, Debug, PartialEq)]
+pub enum DiagnosticStage {
+    /// DNS resolution of the host
+    #[serde(rename = "resolve")]
+    Resolve,
+    /// TCP connection to the port
+    #[serde(rename = "connect")]
+    Connect,
+    /// SSH identification string
+    #[serde(rename = "banner")]
+    Banner,
+    /// Key exchange and host key verification
+    #[serde(rename = "negotiate")]
+    Negotiate,
+    #[serde(rename = "authenticate")]
+    Authenticate,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct ConnectionEvent {
+    pub device: String,
+    #[serde(flatten)]
+    pub state: ConnectionState,
+}
+
+#[derive(Clone, Serialize, Debug)]
+#[serde(tag = "state")]
+pub enum ConnectionState {
+    Connecting,
+    Authenticated,
+    Disconnected { reason: Option<Error> },
+    Reconnecting { reason: Option<Error> },
 }
@@ -6,13 +6,18 @@ use std::time::Duration;
 use r2d2::{HandleError, ManageConnection, Pool};
 
 use crate::conn_pool::{
-    DeviceConnection, DeviceConnectionManager, DeviceConnectionPool, ManagedDeviceConnection,
+    ConnectionCallback, ConnectionEvent, ConnectionState, DeviceConnection,
+    DeviceConnectionManager, DeviceConnectionPool, ManagedDeviceConnection,
 };
 use crate::device_manager::Device;
 use crate::error::Error;
 
 impl DeviceConnectionPool {
-    pub fn new(device: Device, ssh_dir: Option<PathBuf>) -> DeviceConnectionPool {
+    pub fn new(
+        device: Device,
+        ssh_dir: Option<PathBuf>,
+        callback: Arc<Mutex<Option<Box<dyn ConnectionCallback + Send + Sync>>>>,
+    ) -> DeviceConnectionPool {
         let last_error = Arc::<Mutex<Option<Error>>>::default();
         let inner = Pool::<DeviceConnectionManager>::builder()
             .min_idle(Some(0))
@@ -21,7 +26,11 @@ impl DeviceConnectionPool {
             .error_handler(Box::new(DeviceConnectionErrorHandler {
                 last_error: last_error.clone(),
             }))
-            .build_unchecked(DeviceConnectionManager { device, ssh_dir });
+            .build_unchecked(DeviceConnectionManager {
+                device,
+                ssh_dir,
+                callback,
+            });
         return DeviceConnectionPool { inner, last_error };
     }
 
@@ -48,7 +57,25 @@ impl ManageConnection for DeviceConnectionManager {
     type Error = Error;
 
     fn connect(&self) -> Result<Self::Connection, Self::Error> {
-        return DeviceConnection::new(self.device.clone(), self.ssh_dir.as_deref());
+        self.emit(ConnectionState::Connecting);
+        return match DeviceConnection::new(self.device.clone(), self.ssh_dir.as_deref()) {
+            Ok(conn) => {
+                self.emit(ConnectionState::Authenticated);
+                // Trust on first use, so later connections can tell when the key changes
+                if self.device.host_key.is_none() && !self.device.new {
+                    if let Some(callback) = self.callback.lock().unwrap().as_ref() {
+                        callback.host_key_seen(&self.device.name, &conn.host_key);
+                    }
+                }
+                Ok(conn)
+            }
+            Err(e) => {
+                self.emit(ConnectionState::Disconnected {
+                    reason: Some(e.clone()),
+                });
+                Err(e)
+            }
+        };
     }
 
     fn is_valid(&self, _: &mut Self::Connection) -> Result<(), Self::Error> {
@@ -57,12 +84,26 @@ impl ManageConnection for DeviceConnectionManager {
 
     fn has_broken(&self, conn: &mut Self::Connection) -> bool {
         if !conn.is_connected() {
+            self.emit(ConnectionState::Disconnected {
+                reason: Some(Error::Disconnected),
+            });
             return true;
         }
         return conn.last_ok.lock().unwrap().eq(&false);
     }
 }
 
+impl DeviceConnectionManager {
+    fn emit(&self, state: ConnectionState) {
+        if let Some(callback) = self.callback.lock().unwrap().as_ref() {
+            callback.state(ConnectionEvent {
+                device: self.device.name.clone(),
+                state,
+            });
+        }
+    }
+}
+
 #[derive(Debug)]
 struct DeviceConnectionErrorHandler {
     last_error: Arc<Mutex<Option<Error>>>,
@@ -0,0 +1,70 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::conn_pool::DeviceConnection;
+use crate::device_manager::{Device, DeviceCapabilities, DeviceManager};
+use crate::error::Error;
+
+const HOMEBREW_CHANNEL_DIRS: [&str; 2] = [
+    "/media/developer/apps/usr/palm/applications/org.webosbrew.hbchannel",
+    "/var/lib/webosbrew",
+];
+
+impl DeviceCapabilities {
+    /// Probes what the user logged into the device is allowed to do, all in a single command.
+    pub(crate) fn detect(session: &DeviceConnection) -> Result<Self, Error> {
+        let homebrew = HOMEBREW_CHANNEL_DIRS
+            .iter()
+            .map(|dir| format!("-d {dir}"))
+            .collect::<Vec<_>>()
+            .join(" -o ");
+        let command = format!(
+            "id -u; if [ {homebrew} ]; then echo 1; else echo 0; fi; \
+             if [ -w /var/luna ]; then echo 1; else echo 0; fi"
+        );
+        let output = session.exec(&command, None)?;
+        let output = String::from_utf8_lossy(&output);
+        let mut lines = output.lines().map(str::trim);
+        let uid = lines.next().and_then(|line| line.parse::<u32>().ok());
+        let Some(uid) = uid else {
+            return Err(Error::new("Failed to detect device capabilities"));
+        };
+        let homebrew = lines.next() == Some("1");
+        let luna_writable = lines.next() == Some("1");
+        let detected = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        return Ok(Self {
+            root: uid == 0,
+            homebrew,
+            luna_writable,
+            detected,
+        });
+    }
+}
+
+impl Device {
+    /// Whether the device is logged into as root, from the detected capabilities if there are
+    /// any. Root can use the private bus and full service APIs, other users only what Developer
+    /// Mode allows.
+    pub(crate) fn is_rooted(&self) -> bool {
+        return match &self.capabilities {
+            Some(capabilities) => capabilities.root,
+            None => self.username == "root",
+        };
+    }
+}
+
+impl DeviceManager {
+    /// Stores `capabilities` on device `name`, so commands can adapt to them without probing.
+    pub async fn set_capabilities(
+        &self,
+        name: &str,
+        capabilities: DeviceCapabilities,
+    ) -> Result<Device, Error> {
+        return self
+            .modify(name, |device| {
+                device.capabilities = Some(capabilities);
+            })
+            .await;
+    }
+}
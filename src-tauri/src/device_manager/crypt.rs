@@ -0,0 +1,147 @@
+use std::sync::{Arc, Mutex};
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use keyring::Entry;
+use serde::{Deserialize, Serialize};
+
+use crate::app_dirs::GetConfDir;
+use crate::device_manager::io::read;
+use crate::device_manager::DeviceManager;
+use crate::error::Error;
+
+const KEYRING_SERVICE: &str = "webos-dev-manager";
+const KEYRING_USER: &str = "device-store";
+
+/// Devices file content when encrypted. Plaintext files are a JSON array instead, so both can be
+/// told apart without knowing whether encryption is enabled.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Sealed {
+    version: u32,
+    nonce: String,
+    data: String,
+}
+
+impl Sealed {
+    pub(crate) fn seal(plaintext: &[u8], key: &[u8]) -> Result<Self, Error> {
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let data = cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| Error::new("Failed to encrypt device store"))?;
+        return Ok(Self {
+            version: 1,
+            nonce: hex::encode(nonce),
+            data: hex::encode(data),
+        });
+    }
+
+    pub(crate) fn open(&self, key: &[u8]) -> Result<Vec<u8>, Error> {
+        let nonce = hex::decode(&self.nonce).map_err(|_| Error::bad_config())?;
+        let data = hex::decode(&self.data).map_err(|_| Error::bad_config())?;
+        if nonce.len() != 12 {
+            return Err(Error::bad_config());
+        }
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+        return cipher
+            .decrypt(Nonce::from_slice(&nonce), &data[..])
+            .map_err(|_| {
+                Error::new("Failed to decrypt device store. Was the key in the keyring replaced?")
+            });
+    }
+}
+
+/// Device store key, read from the OS keyring on first use
+#[derive(Default, Clone)]
+pub(crate) struct StoreKey {
+    /// `Some(None)` once the keyring was found to have no key, as encryption is disabled
+    key: Arc<Mutex<Option<Option<Vec<u8>>>>>,
+}
+
+impl StoreKey {
+    pub(crate) fn get(&self) -> Result<Option<Vec<u8>>, Error> {
+        let mut cached = self.key.lock().unwrap();
+        if let Some(key) = cached.as_ref() {
+            return Ok(key.clone());
+        }
+        let key = load_key()?;
+        *cached = Some(key.clone());
+        return Ok(key);
+    }
+
+    /// The key, if it was already read from the keyring
+    pub(crate) fn cached(&self) -> Option<Option<Vec<u8>>> {
+        return self.key.lock().unwrap().clone();
+    }
+
+    fn set(&self, key: Option<Vec<u8>>) {
+        *self.key.lock().unwrap() = Some(key);
+    }
+}
+
+/// Returns the device store key from the OS keyring, or `None` if encryption is disabled.
+fn load_key() -> Result<Option<Vec<u8>>, Error> {
+    return match keyring_entry()?.get_password() {
+        Ok(key) => match hex::decode(key) {
+            Ok(key) if key.len() == 32 => Ok(Some(key)),
+            _ => Err(Error::bad_config()),
+        },
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(Error::new(format!("Failed to read key from keyring: {e}"))),
+    };
+}
+
+fn keyring_entry() -> Result<Entry, Error> {
+    return Entry::new(KEYRING_SERVICE, KEYRING_USER)
+        .map_err(|e| Error::new(format!("Keyring is unavailable: {e}")));
+}
+
+impl DeviceManager {
+    /// Whether the devices file is encrypted with a key held in the OS keyring.
+    pub async fn encrypted(&self) -> Result<bool, Error> {
+        let store_key = self.file_key.clone();
+        return tokio::task::spawn_blocking(move || store_key.get().map(|key| key.is_some()))
+            .await
+            .expect("critical failure in DeviceManager::encrypted task");
+    }
+
+    /// Enables or disables encryption of the devices file, and rewrites it accordingly. Enabling
+    /// generates a new key in the OS keyring. Disabling deletes it once the file is rewritten in
+    /// plaintext, so a failed write leaves the file readable.
+    pub async fn set_encrypted(&self, encrypted: bool) -> Result<(), Error> {
+        let devices = read(self.get_conf_dir().as_deref(), &self.file_key).await?;
+        let store_key = self.file_key.clone();
+        let previous = tokio::task::spawn_blocking(move || store_key.get())
+            .await
+            .expect("critical failure in DeviceManager::set_encrypted task")?;
+        if previous.is_some() == encrypted {
+            return Ok(());
+        }
+        if !encrypted {
+            self.file_key.set(None);
+            if let Err(e) = self.save(devices).await {
+                self.file_key.set(previous);
+                return Err(e);
+            }
+            return tokio::task::spawn_blocking(|| -> Result<(), Error> {
+                return match keyring_entry()?.delete_password() {
+                    Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+                    Err(e) => Err(Error::new(format!("Failed to update keyring: {e}"))),
+                };
+            })
+            .await
+            .expect("critical failure in DeviceManager::set_encrypted task");
+        }
+        let key = Aes256Gcm::generate_key(OsRng).to_vec();
+        let stored = key.clone();
+        tokio::task::spawn_blocking(move || -> Result<(), Error> {
+            return keyring_entry()?
+                .set_password(&hex::encode(stored))
+                .map_err(|e| Error::new(format!("Failed to update keyring: {e}")));
+        })
+        .await
+        .expect("critical failure in DeviceManager::set_encrypted task")?;
+        self.file_key.set(Some(key));
+        return self.save(devices).await;
+    }
+}
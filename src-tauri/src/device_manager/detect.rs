@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use crate::device_manager::manager::{DEVMODE_PORT, DEVMODE_USERNAME};
+use crate::device_manager::wake::is_reachable;
+use crate::device_manager::{DetectedSsh, DeviceManager, ExpectedAuth};
+use crate::error::Error;
+
+const DETECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+impl DeviceManager {
+    /// Probes the SSH ports webOS TVs commonly listen on, and returns the settings for each one
+    /// that's open, the most capable first. Rooted TVs run sshd as root on port 22, while the
+    /// Developer Mode app runs a jailed one on port 9922.
+    pub async fn detect_ssh(&self, host: &str) -> Result<Vec<DetectedSsh>, Error> {
+        let host = String::from(host);
+        return tokio::task::spawn_blocking(move || {
+            let candidates = [
+                DetectedSsh {
+                    port: 22,
+                    username: String::from("root"),
+                    auth: ExpectedAuth::Password,
+                },
+                DetectedSsh {
+                    port: DEVMODE_PORT,
+                    username: String::from(DEVMODE_USERNAME),
+                    auth: ExpectedAuth::DevModeKey,
+                },
+            ];
+            let host = host.as_str();
+            let found: Vec<DetectedSsh> = std::thread::scope(|scope| {
+                let probes: Vec<_> = candidates
+                    .into_iter()
+                    .map(|c| {
+                        scope.spawn(move || is_reachable(host, c.port, DETECT_TIMEOUT).then_some(c))
+                    })
+                    .collect();
+                return probes
+                    .into_iter()
+                    .filter_map(|p| p.join().ok().flatten())
+                    .collect();
+            });
+            return Ok(found);
+        })
+        .await
+        .expect("critical failure in DeviceManager::detect_ssh task");
+    }
+}
@@ -1,7 +1,99 @@
-use crate::device_manager::Device;
+use std::time::Duration;
+
+use crate::device_manager::manager::{DEVMODE_PORT, DEVMODE_USERNAME};
+use crate::device_manager::{Device, DeviceKind, Elevation};
+use crate::error::Error;
+use crate::session_manager::command::quote;
+
+/// Password prompt of the elevation command, at the end of its output
+pub(crate) const ELEVATION_PROMPT: &str = r"(?i)password[^\r\n]*:\s*$";
+/// How long to wait for the password prompt of the elevation command
+pub(crate) const ELEVATION_PROMPT_TIMEOUT: Duration = Duration::from_secs(10);
 
 impl Device {
+    /// Device of `kind` with the usual SSH settings for it: webOS OSE images allow root login on
+    /// port 22, and TVs are expected to be in Developer Mode.
+    pub fn preset(kind: DeviceKind, name: &str, host: &str) -> Device {
+        let (port, username) = match kind {
+            DeviceKind::Ose => (22, "root"),
+            DeviceKind::Tv => (DEVMODE_PORT, DEVMODE_USERNAME),
+        };
+        return Device {
+            profile: String::from("ose"),
+            name: String::from(name),
+            host: String::from(host),
+            port,
+            username: String::from(username),
+            kind: Some(kind),
+            ..Default::default()
+        };
+    }
+
+    pub(crate) fn is_ose(&self) -> bool {
+        return self.kind == Some(DeviceKind::Ose);
+    }
+
+    /// Whether the device is a TV logged into through the Developer Mode app's jailed sshd.
+    pub(crate) fn is_devmode(&self) -> bool {
+        return !self.is_ose() && self.username == DEVMODE_USERNAME;
+    }
+
     pub(crate) fn valid_passphrase(&self) -> Option<String> {
         return self.passphrase.clone().filter(|s| !s.is_empty());
     }
+
+    /// Prefixes the command with exports of the device's environment variables. Variables with
+    /// invalid names are left out.
+    pub(crate) fn env_command(&self, command: &str) -> String {
+        let exports: Vec<String> = self
+            .env
+            .iter()
+            .flatten()
+            .filter(|(name, _)| valid_env_name(name))
+            .map(|(name, value)| format!("{name}={}", quote(value)))
+            .collect();
+        if exports.is_empty() {
+            return String::from(command);
+        }
+        return format!("export {}; {command}", exports.join(" "));
+    }
+
+    /// Wraps the command so it runs as root, using the elevation method configured for the device.
+    /// Both prompt with `Password:` on stderr when they need one.
+    pub(crate) fn elevated_command(&self, command: &str) -> Result<String, Error> {
+        return match self.elevation.as_ref().ok_or(Error::Unsupported)? {
+            Elevation::Su => Ok(format!("su - -c {}", quote(command))),
+            Elevation::Sudo => Ok(format!(
+                "sudo -S -p 'Password: ' -i sh -c {}",
+                quote(command)
+            )),
+        };
+    }
+
+    /// Command to start an interactive root shell. Both prompt with `Password:`, `sudo` reads the
+    /// password from stdin only without a terminal, which it would echo to otherwise.
+    pub(crate) fn elevated_shell(&self, has_pty: bool) -> Result<&'static str, Error> {
+        return match self.elevation.as_ref().ok_or(Error::Unsupported)? {
+            Elevation::Su => Ok("su -"),
+            Elevation::Sudo if has_pty => Ok("sudo -p 'Password: ' -i"),
+            Elevation::Sudo => Ok("sudo -S -p 'Password: ' -i"),
+        };
+    }
+
+    /// Input answering the password prompt of the elevation command, if a password is known.
+    pub(crate) fn elevation_input(&self) -> Option<Vec<u8>> {
+        return self
+            .password
+            .as_ref()
+            .filter(|p| !p.is_empty())
+            .map(|p| format!("{p}\n").into_bytes());
+    }
+}
+
+pub(crate) fn valid_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    return chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
 }
@@ -0,0 +1,187 @@
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+
+use crate::device_manager::mdns::mdns_search;
+use crate::device_manager::{DeviceManager, DiscoveredDevice};
+use crate::error::Error;
+
+const SSDP_ADDR: &str = "239.255.255.250:1900";
+/// Advertised by webOS TVs for the second screen (remote control) API
+const SSDP_SEARCH_TARGET: &str = "urn:lge-com:service:webos-second-screen:1";
+/// For reading each device description, so a TV that doesn't answer doesn't hold up the others
+const DESCRIBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+struct SsdpResponse {
+    host: String,
+    location: Option<String>,
+    server: Option<String>,
+}
+
+impl DeviceManager {
+    /// Searches the LAN for webOS TVs with SSDP and mDNS for `timeout`, then reads the name and
+    /// model from each TV's device description.
+    pub async fn discover(&self, timeout: Duration) -> Result<Vec<DiscoveredDevice>, Error> {
+        let ssdp = tokio::task::spawn_blocking(move || ssdp_search(timeout));
+        let mdns = tokio::task::spawn_blocking(move || mdns_search(timeout));
+        let responses = ssdp
+            .await
+            .expect("critical failure in DeviceManager::discover task")?;
+        let mdns = mdns
+            .await
+            .expect("critical failure in DeviceManager::discover task")
+            .unwrap_or_else(|e| {
+                log::warn!("Failed to search with mDNS: {e:?}");
+                Vec::new()
+            });
+        let known = self.list().await?;
+        let client = reqwest::Client::builder()
+            .timeout(DESCRIBE_TIMEOUT)
+            .build()?;
+        let mut found: Vec<(DiscoveredDevice, Option<String>)> = Vec::new();
+        for response in responses {
+            if found.iter().any(|(d, _)| d.host == response.host) {
+                continue;
+            }
+            let device = DiscoveredDevice {
+                existing: known
+                    .iter()
+                    .find(|d| d.host == response.host)
+                    .map(|d| d.name.clone()),
+                host: response.host,
+                name: None,
+                model: None,
+                server: response.server,
+            };
+            found.push((device, response.location));
+        }
+        let descriptions: Vec<_> = found
+            .iter()
+            .map(|(_, location)| {
+                let (client, location) = (client.clone(), location.clone());
+                tauri::async_runtime::spawn(async move {
+                    let location = location?;
+                    let described = describe(&client, &location).await;
+                    if let Err(e) = &described {
+                        log::warn!("Failed to read device description {location}: {e:?}");
+                    }
+                    return described.ok();
+                })
+            })
+            .collect();
+        let mut devices = Vec::new();
+        for ((mut device, _), description) in found.into_iter().zip(descriptions) {
+            if let Ok(Some((name, model))) = description.await {
+                device.name = name;
+                device.model = model;
+            }
+            devices.push(device);
+        }
+        for response in mdns {
+            match devices.iter_mut().find(|d| d.host == response.host) {
+                Some(device) => {
+                    device.name = device.name.take().or(response.name);
+                    device.model = device.model.take().or(response.model);
+                }
+                None => devices.push(DiscoveredDevice {
+                    existing: known
+                        .iter()
+                        .find(|d| d.host == response.host)
+                        .map(|d| d.name.clone()),
+                    host: response.host,
+                    name: response.name,
+                    model: response.model,
+                    server: None,
+                }),
+            }
+        }
+        return Ok(devices);
+    }
+}
+
+fn ssdp_search(timeout: Duration) -> Result<Vec<SsdpResponse>, Error> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    let mx = timeout.as_secs().clamp(1, 5);
+    let request = format!(
+        "M-SEARCH * HTTP/1.1\r\n\
+         HOST: {SSDP_ADDR}\r\n\
+         MAN: \"ssdp:discover\"\r\n\
+         MX: {mx}\r\n\
+         ST: {SSDP_SEARCH_TARGET}\r\n\r\n"
+    );
+    socket.send_to(request.as_bytes(), SSDP_ADDR)?;
+    let deadline = Instant::now() + timeout;
+    let mut responses = Vec::new();
+    let mut buf = [0u8; 2048];
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(left))?;
+        let (len, addr) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                break;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let mut headers = [httparse::EMPTY_HEADER; 32];
+        let mut parsed = httparse::Response::new(&mut headers);
+        if parsed.parse(&buf[..len]).is_err() || parsed.code != Some(200) {
+            continue;
+        }
+        let header = |name: &str| {
+            parsed
+                .headers
+                .iter()
+                .find(|h| h.name.eq_ignore_ascii_case(name))
+                .map(|h| String::from_utf8_lossy(h.value).trim().to_string())
+        };
+        if header("ST").map_or(true, |st| st != SSDP_SEARCH_TARGET) {
+            continue;
+        }
+        responses.push(SsdpResponse {
+            host: addr.ip().to_string(),
+            location: header("LOCATION"),
+            server: header("SERVER"),
+        });
+    }
+    return Ok(responses);
+}
+
+/// Reads the friendly name and model name from the UPnP device description at `location`.
+async fn describe(
+    client: &reqwest::Client,
+    location: &str,
+) -> Result<(Option<String>, Option<String>), Error> {
+    let xml = client
+        .get(location)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let element = |tag: &str| {
+        Regex::new(&format!("<{tag}>([^<]*)</{tag}>"))
+            .unwrap()
+            .captures(&xml)
+            .map(|c| unescape(c[1].trim()))
+    };
+    return Ok((element("friendlyName"), element("modelName")));
+}
+
+fn unescape(text: &str) -> String {
+    return text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+}
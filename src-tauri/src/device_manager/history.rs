@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::app_dirs::GetConfDir;
+use crate::device_manager::{
+    ActivityKind, DevModeRenewal, DeviceActivity, DeviceHistory, DeviceManager,
+};
+
+/// Recent activities kept per device
+const HISTORY_LIMIT: usize = 20;
+
+impl DeviceManager {
+    /// Records that device `name` was just connected to successfully.
+    pub fn record_connected(&self, name: &str) {
+        self.update_history(name, |history, now| history.last_connected = Some(now));
+    }
+
+    /// Records that a shell, command or transfer was just started on device `name`.
+    pub fn record_activity(&self, name: &str, kind: ActivityKind) {
+        self.update_history(name, |history, now| {
+            history
+                .recent
+                .push_front(DeviceActivity { time: now, kind });
+            history.recent.truncate(HISTORY_LIMIT);
+        });
+    }
+
+    /// Records the outcome of extending the Developer Mode session of a device.
+    pub fn record_renewal(&self, renewal: DevModeRenewal) {
+        let name = renewal.device.clone();
+        self.update_history(&name, |history, _| history.last_renewal = Some(renewal));
+    }
+
+    pub fn history(&self) -> HashMap<String, DeviceHistory> {
+        let mut history = self.history.lock().unwrap();
+        return self.load_history(&mut history).clone();
+    }
+
+    fn update_history<F>(&self, name: &str, action: F)
+    where
+        F: FnOnce(&mut DeviceHistory, u64),
+    {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let mut history = self.history.lock().unwrap();
+        let entries = self.load_history(&mut history);
+        action(entries.entry(String::from(name)).or_default(), now);
+        let Some(path) = self.get_conf_dir().map(|d| d.join("device-history.json")) else {
+            return;
+        };
+        // Kept apart from the devices file, which is shared with the CLI tools
+        let result = serde_json::to_vec(&entries)
+            .map_err(std::io::Error::from)
+            .and_then(|data| std::fs::write(&path, data));
+        if let Err(e) = result {
+            log::warn!("Failed to save device history: {e:?}");
+        }
+    }
+
+    fn load_history<'a>(
+        &self,
+        history: &'a mut Option<HashMap<String, DeviceHistory>>,
+    ) -> &'a mut HashMap<String, DeviceHistory> {
+        return history.get_or_insert_with(|| {
+            self.get_conf_dir()
+                .and_then(|d| std::fs::read(d.join("device-history.json")).ok())
+                .and_then(|data| serde_json::from_slice(&data).ok())
+                .unwrap_or_default()
+        });
+    }
+}
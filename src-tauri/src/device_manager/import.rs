@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::app_dirs::{GetConfDir, GetSshDir};
+use crate::device_manager::io::{devices_file_path, read_file};
+use crate::device_manager::{Device, DeviceManager, ImportResult, PrivateKey};
+use crate::error::Error;
+
+/// Bumped when the export format changes incompatibly
+const EXPORT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct DeviceExport {
+    version: u32,
+    devices: Vec<Value>,
+}
+
+impl DeviceManager {
+    /// Imports devices set up with `ares-setup-device` from `path`, or from the webOS TV CLI
+    /// configurations next to ours. Devices with a name that's already taken are reported as
+    /// conflicts instead.
+    pub async fn import_ares(&self, path: Option<&Path>) -> Result<ImportResult, Error> {
+        let own = devices_file_path(self.get_conf_dir().as_deref())?;
+        let sources = match path {
+            Some(path) => vec![path.to_path_buf()],
+            None => self.ares_config_files(),
+        };
+        let mut result = ImportResult::default();
+        for source in sources {
+            if source == own || !source.exists() {
+                continue;
+            }
+            log::info!("Importing devices from {}", source.display());
+            let mut existing = self.list().await?;
+            for mut device in read_file(&source, &self.file_key).await? {
+                if existing.iter().any(|d| d.name == device.name) {
+                    result.conflicts.push(device.name);
+                    continue;
+                }
+                device.default = None;
+                let device = self.add(&device).await?;
+                existing.push(device.clone());
+                result.imported.push(device);
+            }
+        }
+        return Ok(result);
+    }
+
+    /// Writes all devices to `path`. Private keys are embedded, along with passwords and
+    /// passphrases, only with `include_keys`, otherwise they're left out.
+    pub async fn export(&self, path: &Path, include_keys: bool) -> Result<usize, Error> {
+        let ssh_dir = self.get_ssh_dir();
+        let mut devices = Vec::new();
+        for device in self.list().await? {
+            let device = portable(device, include_keys, ssh_dir.as_deref())?;
+            devices.push(serde_json::to_value(device)?);
+        }
+        let count = devices.len();
+        let export = DeviceExport {
+            version: EXPORT_VERSION,
+            devices,
+        };
+        tokio::fs::write(path, serde_json::to_vec_pretty(&export)?).await?;
+        return Ok(count);
+    }
+
+    /// Adds devices exported with [DeviceManager::export]. Devices with a name that's already
+    /// taken are reported as conflicts instead.
+    pub async fn import(&self, path: &Path) -> Result<ImportResult, Error> {
+        let export: DeviceExport = serde_json::from_slice(&tokio::fs::read(path).await?)?;
+        if export.version > EXPORT_VERSION {
+            return Err(Error::new(format!(
+                "Device export version {} is not supported",
+                export.version
+            )));
+        }
+        let mut existing = self.list().await?;
+        let mut result = ImportResult::default();
+        for value in export.devices {
+            let Ok(mut device) = serde_json::from_value::<Device>(value) else {
+                continue;
+            };
+            if existing.iter().any(|d| d.name == device.name) {
+                result.conflicts.push(device.name);
+                continue;
+            }
+            device.default = None;
+            let device = self.add(&device).await?;
+            existing.push(device.clone());
+            result.imported.push(device);
+        }
+        return Ok(result);
+    }
+
+    /// Device lists of the webOS TV CLI, which live next to the OSE one we share
+    fn ares_config_files(&self) -> Vec<PathBuf> {
+        let Some(webos_dir) = self
+            .get_conf_dir()
+            .and_then(|d| d.parent().map(Path::to_path_buf))
+        else {
+            return Vec::new();
+        };
+        return vec![
+            webos_dir.join("tv").join("novacom-devices.json"),
+            webos_dir.join("novacom-devices.json"),
+        ];
+    }
+}
+
+/// Makes the device usable on another machine, by embedding its private key, or by removing all
+/// credentials unless `include_keys` is set.
+pub(super) fn portable(
+    mut device: Device,
+    include_keys: bool,
+    ssh_dir: Option<&Path>,
+) -> Result<Device, Error> {
+    if include_keys {
+        if let Some(key) = &device.private_key {
+            let data = key.content(ssh_dir)?;
+            device.private_key = Some(PrivateKey::Data { data });
+        }
+    } else {
+        device.private_key = None;
+        device.passphrase = None;
+        device.password = None;
+    }
+    return Ok(device);
+}
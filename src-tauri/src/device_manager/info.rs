@@ -0,0 +1,83 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::conn_pool::DeviceConnection;
+use crate::device_manager::{Device, DeviceInfo, DeviceManager};
+use crate::error::Error;
+use crate::session_manager::luna;
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct SystemInfo {
+    model_name: Option<String>,
+    firmware_version: Option<String>,
+    sdk_version: Option<String>,
+    board_type: Option<String>,
+    serial_number: Option<String>,
+    ota_id: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct OsInfo {
+    device_name: Option<String>,
+    webos_release: Option<String>,
+}
+
+impl DeviceInfo {
+    /// Queries the TV system properties, and the OS info backed by nyx. Either may be missing on
+    /// some devices, but not both.
+    pub(crate) fn fetch(session: &DeviceConnection) -> Result<Self, Error> {
+        // OSE has no TV services, and root can use the private bus
+        let public = !session.device.is_ose();
+        let system: Result<SystemInfo, Error> = luna::call(
+            session,
+            "luna://com.webos.service.tv.systemproperty/getSystemInfo",
+            &json!({
+                "keys": [
+                    "modelName",
+                    "firmwareVersion",
+                    "sdkVersion",
+                    "boardType",
+                    "serialNumber",
+                    "otaId"
+                ]
+            }),
+            public,
+        );
+        let os: Result<OsInfo, Error> = luna::call(
+            session,
+            "luna://com.palm.systemservice/osInfo/query",
+            &json!({ "parameters": ["device_name", "webos_release"] }),
+            public,
+        );
+        let (system, os) = match (system, os) {
+            (Err(e), Err(_)) => return Err(e),
+            (system, os) => (system.unwrap_or_default(), os.unwrap_or_default()),
+        };
+        let fetched = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        return Ok(Self {
+            model_name: system.model_name,
+            firmware_version: system.firmware_version,
+            webos_version: os.webos_release.or(system.sdk_version),
+            board_type: system.board_type.or(os.device_name),
+            serial_number: system.serial_number,
+            ota_id: system.ota_id,
+            fetched,
+        });
+    }
+}
+
+impl DeviceManager {
+    /// Stores `info` on device `name`, so it's available without connecting next time.
+    pub async fn set_info(&self, name: &str, info: DeviceInfo) -> Result<Device, Error> {
+        return self
+            .modify(name, |device| {
+                device.info = Some(info);
+            })
+            .await;
+    }
+}
@@ -1,69 +1,113 @@
 use std::fs;
 use std::fs::{create_dir_all, File};
-use std::io::{BufReader, BufWriter, ErrorKind};
+use std::io::{BufReader, BufWriter, ErrorKind, Write};
 use std::path::{Path, PathBuf};
 
 use serde_json::Value;
 
+use crate::device_manager::crypt::{Sealed, StoreKey};
 use crate::device_manager::Device;
 use crate::error::Error;
 
-pub(crate) async fn read(conf_dir: Option<&Path>) -> Result<Vec<Device>, Error> {
-    let conf_dir = conf_dir.map(|conf_dir| conf_dir.to_path_buf());
-    return tokio::task::spawn_blocking(move || -> Result<Vec<Device>, Error> {
-        let path = devices_file_path(conf_dir.as_deref())?;
-        let file = match File::open(path.as_path()) {
-            Ok(file) => file,
-            Err(e) => {
-                return match e.kind() {
-                    ErrorKind::NotFound => Ok(Vec::new()),
-                    _ => Err(e.into()),
-                };
-            }
-        };
-        let reader = BufReader::new(file);
+pub(crate) async fn read(conf_dir: Option<&Path>, key: &StoreKey) -> Result<Vec<Device>, Error> {
+    return read_file(&devices_file_path(conf_dir)?, key).await;
+}
 
-        let raw_list: Vec<Value> = serde_json::from_reader(reader)?;
-        return Ok(raw_list
-            .iter()
-            .filter_map(|v| serde_json::from_value::<Device>(v.clone()).ok())
-            .collect());
-    })
+/// Reads devices from a file in the `novacom-devices.json` format, skipping malformed entries.
+pub(crate) async fn read_file(path: &Path, key: &StoreKey) -> Result<Vec<Device>, Error> {
+    let (path, key) = (path.to_path_buf(), key.clone());
+    return tokio::task::spawn_blocking(move || read_file_blocking(&path, &key))
         .await
         .expect("critical failure in app::io::read task");
 }
 
-pub(crate) async fn write(devices: Vec<Device>, conf_dir: Option<&Path>) -> Result<(), Error> {
+pub(crate) fn read_file_blocking(path: &Path, key: &StoreKey) -> Result<Vec<Device>, Error> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            return match e.kind() {
+                ErrorKind::NotFound => Ok(Vec::new()),
+                _ => Err(e.into()),
+            };
+        }
+    };
+    let reader = BufReader::new(file);
+
+    let raw_list: Vec<Value> = match serde_json::from_reader(reader)? {
+        Value::Array(list) => list,
+        value => {
+            let sealed: Sealed = serde_json::from_value(value)?;
+            let key = key.get()?.ok_or_else(|| {
+                Error::new("Devices file is encrypted, but its key is missing from the keyring")
+            })?;
+            serde_json::from_slice(&sealed.open(&key)?)?
+        }
+    };
+    return Ok(raw_list
+        .iter()
+        .filter_map(|v| serde_json::from_value::<Device>(v.clone()).ok())
+        .collect());
+}
+
+/// Replaces the devices file, encrypting it if it's already encrypted or encryption was enabled.
+/// The content goes to a temporary file first, so a failed write leaves the old file intact.
+pub(crate) async fn write(
+    devices: Vec<Device>,
+    conf_dir: Option<&Path>,
+    key: &StoreKey,
+) -> Result<(), Error> {
     let conf_dir = conf_dir.map(|conf_dir| conf_dir.to_path_buf());
+    let key = key.clone();
     return tokio::task::spawn_blocking(move || -> Result<(), Error> {
         let path = devices_file_path(conf_dir.as_deref())?;
-        let file = match File::create(path.as_path()) {
-            Ok(file) => file,
-            Err(e) => {
-                match e.kind() {
-                    ErrorKind::PermissionDenied => {
-                        fix_devices_json_perm(path.clone())?;
-                    }
-                    ErrorKind::NotFound => {
-                        let parent = path.parent().ok_or_else(|| Error::bad_config())?;
-                        create_dir_all(parent)?;
-                    }
-                    _ => return Err(e.into()),
-                }
-                File::create(path.as_path())?
+        // The keyring is only consulted when the file may need to stay encrypted
+        let key = match key.cached() {
+            Some(key) => key,
+            None if is_sealed(&path) => key.get()?,
+            None => None,
+        };
+        let content = match key {
+            Some(key) => {
+                let sealed = Sealed::seal(&serde_json::to_vec(&devices)?, &key)?;
+                serde_json::to_vec_pretty(&sealed)?
             }
+            None => serde_json::to_vec_pretty(&devices)?,
         };
-        log::info!("make the file writable: {:?}", path);
-        file.metadata()?.permissions().set_readonly(false);
-        let writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(writer, &devices)?;
+        let parent = path.parent().ok_or_else(|| Error::bad_config())?;
+        create_dir_all(parent)?;
+        let temp = path.with_extension("json.tmp");
+        let mut writer = BufWriter::new(File::create(&temp)?);
+        writer.write_all(&content)?;
+        writer
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        if let Err(e) = fs::rename(&temp, &path) {
+            if e.kind() != ErrorKind::PermissionDenied {
+                fs::remove_file(&temp).unwrap_or(());
+                return Err(e.into());
+            }
+            // The old file may be read-only
+            fix_devices_json_perm(path.clone())?;
+            if let Err(e) = fs::rename(&temp, &path) {
+                fs::remove_file(&temp).unwrap_or(());
+                return Err(e.into());
+            }
+        }
         return Ok(());
     })
-        .await
-        .expect("critical failure in app::io::write task");
+    .await
+    .expect("critical failure in app::io::write task");
+}
+
+/// Whether the devices file exists and is encrypted, telling from its first character.
+fn is_sealed(path: &Path) -> bool {
+    return fs::read(path).map_or(false, |data| {
+        data.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{')
+    });
 }
 
-fn devices_file_path(conf_dir: Option<&Path>) -> Result<PathBuf, Error> {
+pub(crate) fn devices_file_path(conf_dir: Option<&Path>) -> Result<PathBuf, Error> {
     return conf_dir
         .map(|conf_dir| conf_dir.join("novacom-devices.json"))
         .ok_or_else(|| Error::bad_config());
@@ -0,0 +1,133 @@
+use std::path::Path;
+
+use rand::rngs::OsRng;
+use ssh_key::private::{Ed25519Keypair, RsaKeypair};
+use ssh_key::LineEnding;
+use uuid::Uuid;
+
+use crate::app_dirs::GetSshDir;
+use crate::device_manager::{Device, DeviceManager, GeneratedKey, KeyAlgorithm, PrivateKey};
+use crate::error::Error;
+use crate::session_manager::command::quote;
+
+const DEFAULT_RSA_BITS: usize = 3072;
+
+impl DeviceManager {
+    /// Generates a key pair for device `name` in the SSH directory, encrypted with `passphrase` if
+    /// given. The device keeps its credentials until the key is deployed to it.
+    pub async fn generate_key(
+        &self,
+        name: &str,
+        algorithm: KeyAlgorithm,
+        bits: Option<usize>,
+        passphrase: Option<String>,
+    ) -> Result<GeneratedKey, Error> {
+        if !self.list().await?.iter().any(|d| d.name == name) {
+            return Err(Error::NotFound);
+        }
+        let passphrase = passphrase.filter(|p| !p.is_empty());
+        let comment = format!("{name}@devman");
+        let (private, public) = tokio::task::spawn_blocking(move || {
+            generate(algorithm, bits, &comment, passphrase.as_deref())
+        })
+        .await
+        .expect("critical failure in DeviceManager::generate_key task")?;
+
+        let key_name = format!("webos_{}", &Uuid::new_v4().simple().to_string()[..10]);
+        let ssh_dir = self.ensure_ssh_dir()?;
+        write_private(&ssh_dir.join(&key_name), &private).await?;
+        tokio::fs::write(
+            ssh_dir.join(format!("{key_name}.pub")),
+            format!("{public}\n"),
+        )
+        .await?;
+        log::info!("Generated key {key_name} for {name}");
+        return Ok(GeneratedKey {
+            name: key_name,
+            public_key: public,
+        });
+    }
+
+    /// Switches device `name` from password authentication to private key file `key_name`. The
+    /// password is kept for devices with an elevation method, which answers its prompt with it.
+    pub async fn use_key(
+        &self,
+        name: &str,
+        key_name: &str,
+        passphrase: Option<String>,
+    ) -> Result<Device, Error> {
+        return self
+            .modify(name, |device| {
+                device.private_key = Some(PrivateKey::Path {
+                    name: String::from(key_name),
+                });
+                device.passphrase = passphrase.filter(|p| !p.is_empty());
+                if device.elevation.is_none() {
+                    device.password = None;
+                }
+            })
+            .await;
+    }
+
+    /// Reads the public key of private key file `key_name` in the SSH directory, from its `.pub`
+    /// file or, for keys in OpenSSH format, the private key file itself.
+    pub fn public_key(&self, key_name: &str) -> Result<String, Error> {
+        let ssh_dir = self.ensure_ssh_dir()?;
+        if let Ok(public) = std::fs::read_to_string(ssh_dir.join(format!("{key_name}.pub"))) {
+            return Ok(String::from(public.trim()));
+        }
+        let private = std::fs::read_to_string(ssh_dir.join(key_name))?;
+        let key = ssh_key::PrivateKey::from_openssh(&private).map_err(key_error)?;
+        return key.public_key().to_openssh().map_err(key_error);
+    }
+}
+
+/// Shell command appending `public_key` to `authorized_keys` unless it's already there, creating
+/// `~/.ssh` with the permissions sshd insists on.
+pub(crate) fn authorize_command(public_key: &str) -> String {
+    let key = quote(public_key);
+    return format!(
+        "umask 077 && mkdir -p ~/.ssh && chmod 700 ~/.ssh && touch ~/.ssh/authorized_keys && \
+         chmod 600 ~/.ssh/authorized_keys && \
+         (grep -qxF {key} ~/.ssh/authorized_keys || echo {key} >> ~/.ssh/authorized_keys)"
+    );
+}
+
+fn generate(
+    algorithm: KeyAlgorithm,
+    bits: Option<usize>,
+    comment: &str,
+    passphrase: Option<&str>,
+) -> Result<(String, String), Error> {
+    let mut key = match algorithm {
+        KeyAlgorithm::Ed25519 => ssh_key::PrivateKey::from(Ed25519Keypair::random(&mut OsRng)),
+        KeyAlgorithm::Rsa => ssh_key::PrivateKey::from(
+            RsaKeypair::random(&mut OsRng, bits.unwrap_or(DEFAULT_RSA_BITS)).map_err(key_error)?,
+        ),
+    };
+    key.set_comment(comment);
+    let public = key.public_key().to_openssh().map_err(key_error)?;
+    if let Some(passphrase) = passphrase {
+        key = key.encrypt(&mut OsRng, passphrase).map_err(key_error)?;
+    }
+    let private = key.to_openssh(LineEnding::LF).map_err(key_error)?;
+    return Ok((private.to_string(), public));
+}
+
+#[cfg(unix)]
+async fn write_private(path: &Path, content: &str) -> Result<(), Error> {
+    use std::os::unix::fs::PermissionsExt;
+    tokio::fs::write(path, content).await?;
+    tokio::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).await?;
+    return Ok(());
+}
+
+#[cfg(not(unix))]
+async fn write_private(path: &Path, content: &str) -> Result<(), Error> {
+    tokio::fs::write(path, content).await?;
+    return Ok(());
+}
+
+fn key_error(e: ssh_key::Error) -> Error {
+    return Error::new(format!("SSH key error: {e}"));
+}
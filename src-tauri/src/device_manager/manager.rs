@@ -4,22 +4,98 @@ use std::path::{Path, PathBuf};
 use libssh_rs::SshKey;
 use tokio::fs::{File, remove_file};
 use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
 
 use crate::app_dirs::{GetConfDir, GetSshDir, SetConfDir, SetSshDir};
 use crate::device_manager::io::{read, write};
-use crate::device_manager::{Device, DeviceManager, PrivateKey};
+use crate::device_manager::{
+    Bookmark, Device, DeviceManager, DeviceSelector, LunaBookmark, PrivateKey,
+};
 use crate::error::Error;
 
+/// SSH port of the Developer Mode app's jailed sshd
+pub(super) const DEVMODE_PORT: u16 = 9922;
+pub(super) const DEVMODE_USERNAME: &str = "prisoner";
+
 impl DeviceManager {
     pub async fn list(&self) -> Result<Vec<Device>, Error> {
-        let devices = read(self.get_conf_dir().as_deref()).await?;
+        let mut devices = read(self.get_conf_dir().as_deref(), &self.file_key).await?;
+        // Devices added before IDs existed get them on first read
+        let mut missing = false;
+        for device in devices.iter_mut().filter(|d| d.id.is_none()) {
+            device.id = Some(Uuid::new_v4().to_string());
+            missing = true;
+        }
+        if missing {
+            if let Err(e) = self.save(devices.clone()).await {
+                log::warn!("Failed to save device IDs: {e:?}");
+            }
+        }
+        // Stable, so devices without an order stay after ordered ones, as they were added
+        devices.sort_by_key(|d| {
+            d.order
+                .as_deref()
+                .and_then(|o| o.parse::<usize>().ok())
+                .unwrap_or(usize::MAX)
+        });
         *self.devices.lock().unwrap() = devices.clone();
         return Ok(devices);
     }
 
+    /// Returns device `name` as last read or written, without reading the devices file.
+    pub fn cached(&self, name: &str) -> Option<Device> {
+        let devices = self.devices.lock().unwrap();
+        return devices.iter().find(|d| d.name == name).cloned();
+    }
+
+    /// Returns the device marked as default, for actions that don't ask which device to use.
+    pub async fn default_device(&self) -> Result<Option<Device>, Error> {
+        return Ok(self
+            .list()
+            .await?
+            .into_iter()
+            .find(|d| d.default.unwrap_or(false)));
+    }
+
+    /// Returns the devices matched by `selector`, failing if a device named in it doesn't exist.
+    pub async fn select(&self, selector: &DeviceSelector) -> Result<Vec<Device>, Error> {
+        let devices = self.list().await?;
+        if let Some(missing) = selector
+            .names
+            .iter()
+            .find(|n| !devices.iter().any(|d| &&d.name == n))
+        {
+            return Err(Error::new(format!("Device {missing} not found")));
+        }
+        return Ok(devices
+            .into_iter()
+            .filter(|d| {
+                selector.names.contains(&d.name)
+                    || d.tags.iter().flatten().any(|t| selector.tags.contains(t))
+            })
+            .collect());
+    }
+
+    /// Puts devices in the order of `names`. Devices not in `names` keep their relative order
+    /// after the named ones.
+    pub async fn reorder(&self, names: &[String]) -> Result<Vec<Device>, Error> {
+        let mut devices = self.list().await?;
+        devices.sort_by_key(|d| {
+            names
+                .iter()
+                .position(|n| n == &d.name)
+                .unwrap_or(names.len())
+        });
+        for (index, device) in devices.iter_mut().enumerate() {
+            device.order = Some(index.to_string());
+        }
+        self.save(devices.clone()).await?;
+        return Ok(devices);
+    }
+
     pub async fn set_default(&self, name: &str) -> Result<Option<Device>, Error> {
         let conf_dir = self.get_conf_dir();
-        let mut devices = read(conf_dir.as_deref()).await?;
+        let mut devices = read(conf_dir.as_deref(), &self.file_key).await?;
         let mut result: Option<Device> = None;
         for device in &mut devices {
             if device.name == name {
@@ -30,45 +106,72 @@ impl DeviceManager {
             }
         }
         log::trace!("{:?}", devices);
-        write(devices, conf_dir.as_deref()).await?;
+        self.save(devices).await?;
         return Ok(result);
     }
 
     pub async fn add(&self, device: &Device) -> Result<Device, Error> {
         let conf_dir = self.get_conf_dir();
+        let mut devices = read(conf_dir.as_deref(), &self.file_key).await?;
+        if devices.iter().any(|d| d.name == device.name) {
+            return Err(Error::Conflict);
+        }
         let mut device = device.clone();
+        if device.id.is_none() || devices.iter().any(|d| d.id == device.id) {
+            device.id = Some(Uuid::new_v4().to_string());
+        }
         if let Some(key) = &device.private_key {
-            match key {
-                PrivateKey::Path { name } => {
-                    let path = Path::new(name);
-                    if path.is_absolute() {
-                        let name = String::from(
-                            pathdiff::diff_paths(path, self.ensure_ssh_dir()?)
-                                .ok_or(Error::NotFound)?
-                                .to_string_lossy(),
-                        );
-                        device.private_key = Some(PrivateKey::Path { name });
-                    }
-                }
-                PrivateKey::Data { data } => {
-                    let name = key.name(device.valid_passphrase())?;
-                    let key_path = self.ensure_ssh_dir()?.join(&name);
-                    let mut file = File::create(key_path).await?;
-                    file.write(data.as_bytes()).await?;
-                    device.private_key = Some(PrivateKey::Path { name });
-                }
-            }
+            device.private_key = Some(self.store_key(key, device.valid_passphrase()).await?);
         }
         log::info!("Save device {}", device.name);
-        let mut devices = read(conf_dir.as_deref()).await?;
         devices.push(device.clone());
-        write(devices.clone(), conf_dir.as_deref()).await?;
+        self.save(devices.clone()).await?;
         return Ok(device);
     }
 
+    /// Sets up device `name` for a TV in Developer Mode, like `ares-setup-device --getkey`. The
+    /// private key is downloaded from the TV's key server with the passphrase shown in the
+    /// Developer Mode app. Updates the device if it exists, or adds it otherwise.
+    pub async fn setup_devmode(
+        &self,
+        name: &str,
+        host: &str,
+        passphrase: &str,
+    ) -> Result<Device, Error> {
+        let data = self.novacom_getkey(host, passphrase).await?;
+        let passphrase = Some(String::from(passphrase)).filter(|p| !p.is_empty());
+        let private_key = self
+            .store_key(&PrivateKey::Data { data }, passphrase.clone())
+            .await?;
+        if self.list().await?.iter().any(|d| d.name == name) {
+            return self
+                .modify(name, |device| {
+                    device.host = String::from(host);
+                    device.port = DEVMODE_PORT;
+                    device.username = String::from(DEVMODE_USERNAME);
+                    device.private_key = Some(private_key);
+                    device.passphrase = passphrase;
+                    device.password = None;
+                })
+                .await;
+        }
+        return self
+            .add(&Device {
+                profile: String::from("ose"),
+                name: String::from(name),
+                host: String::from(host),
+                port: DEVMODE_PORT,
+                username: String::from(DEVMODE_USERNAME),
+                private_key: Some(private_key),
+                passphrase,
+                ..Device::default()
+            })
+            .await;
+    }
+
     pub async fn remove(&self, name: &str, remove_key: bool) -> Result<(), Error> {
         let conf_dir = self.get_conf_dir();
-        let devices = read(conf_dir.as_deref()).await?;
+        let devices = read(conf_dir.as_deref(), &self.file_key).await?;
         let (will_delete, mut will_keep): (Vec<Device>, Vec<Device>) =
             devices.into_iter().partition(|d| d.name == name);
         let mut need_new_default = false;
@@ -84,6 +187,13 @@ impl DeviceManager {
                     if !name.starts_with("webos_") {
                         continue;
                     }
+                    // Cloned devices share the key file
+                    if will_keep.iter().any(|d| match &d.private_key {
+                        Some(PrivateKey::Path { name: n }) => n == &name,
+                        _ => false,
+                    }) {
+                        continue;
+                    }
                     let key_path = self.ensure_ssh_dir()?.join(name);
                     remove_file(key_path).await?;
                 }
@@ -92,10 +202,240 @@ impl DeviceManager {
         if need_new_default && !will_keep.is_empty() {
             will_keep.first_mut().unwrap().default = Some(true);
         }
-        write(will_keep, conf_dir.as_deref()).await?;
+        self.save(will_keep).await?;
         return Ok(());
     }
 
+    /// Adds the device, or updates the port of an existing device with the same name and host,
+    /// keeping its other settings. A device with the same name elsewhere is left alone, and this
+    /// one is added with a unique name instead.
+    pub async fn register(&self, mut device: Device) -> Result<Device, Error> {
+        let devices = self.list().await?;
+        return match devices.iter().find(|d| d.name == device.name) {
+            None => self.add(&device).await,
+            Some(existing) if existing.host == device.host && existing.novacom.is_none() => {
+                self.modify(&device.name, |existing| {
+                    existing.port = device.port;
+                })
+                .await
+            }
+            Some(_) => {
+                device.name = (2..)
+                    .map(|n| format!("{} {n}", device.name))
+                    .find(|name| !devices.iter().any(|d| &d.name == name))
+                    .expect("Ran out of device names");
+                log::info!(
+                    "Register device as {} to keep the existing one",
+                    device.name
+                );
+                self.add(&device).await
+            }
+        };
+    }
+
+    /// Adds a copy of device `name` as `new_name`, with the same credentials and settings. The
+    /// copy connects to `host` and `port` if given, in which case the pinned host key isn't kept.
+    pub async fn clone_device(
+        &self,
+        name: &str,
+        new_name: &str,
+        host: Option<String>,
+        port: Option<u16>,
+    ) -> Result<Device, Error> {
+        let conf_dir = self.get_conf_dir();
+        let mut devices = read(conf_dir.as_deref(), &self.file_key).await?;
+        if devices.iter().any(|d| d.name == new_name) {
+            return Err(Error::Conflict);
+        }
+        let mut device = devices
+            .iter()
+            .find(|d| d.name == name)
+            .ok_or(Error::NotFound)?
+            .clone();
+        device.name = String::from(new_name);
+        device.id = Some(Uuid::new_v4().to_string());
+        device.default = None;
+        device.indelible = None;
+        device.info = None;
+        device.capabilities = None;
+        if host.is_some() || port.is_some() {
+            device.host_key = None;
+            device.mac = None;
+        }
+        if let Some(host) = host {
+            device.host = host;
+        }
+        if let Some(port) = port {
+            device.port = port;
+        }
+        log::info!("Clone device {name} as {new_name}");
+        devices.push(device.clone());
+        self.save(devices).await?;
+        return Ok(device);
+    }
+
+    pub async fn pin_host_key(&self, name: &str, fingerprint: &str) -> Result<Device, Error> {
+        let device = self
+            .modify(name, |device| {
+                device.host_key = Some(fingerprint.to_ascii_lowercase());
+            })
+            .await?;
+        log::info!("Pinned host key of {}", device.name);
+        return Ok(device);
+    }
+
+    /// Pins `fingerprint` as the host key of device `name`, unless it already has one. Returns
+    /// whether it was pinned.
+    pub async fn trust_host_key(&self, name: &str, fingerprint: &str) -> Result<bool, Error> {
+        let mut pinned = false;
+        self.modify(name, |device| {
+            if device.host_key.is_none() {
+                device.host_key = Some(fingerprint.to_ascii_lowercase());
+                pinned = true;
+            }
+        })
+        .await?;
+        if pinned {
+            log::info!("Trusted host key of {name} on first connection");
+        }
+        return Ok(pinned);
+    }
+
+    /// Replaces the notes, display metadata and tags of the device. Empty values are removed.
+    pub async fn set_metadata(
+        &self,
+        name: &str,
+        notes: Option<String>,
+        color: Option<String>,
+        icon: Option<String>,
+        tags: Option<Vec<String>>,
+    ) -> Result<Device, Error> {
+        return self
+            .modify(name, |device| {
+                device.notes = notes.filter(|s| !s.trim().is_empty());
+                device.color = color.filter(|s| !s.trim().is_empty());
+                device.icon = icon.filter(|s| !s.trim().is_empty());
+                device.tags = tags.filter(|t| !t.is_empty());
+            })
+            .await;
+    }
+
+    /// Opts device `name` in or out of renewing its Developer Mode session in the background.
+    pub async fn set_devmode_renew(&self, name: &str, enabled: bool) -> Result<Device, Error> {
+        return self
+            .modify(name, |device| {
+                device.devmode_renew = enabled.then_some(true)
+            })
+            .await;
+    }
+
+    /// Adds the bookmark to the device, replacing any existing bookmark of the same path.
+    pub async fn add_bookmark(&self, name: &str, bookmark: Bookmark) -> Result<Device, Error> {
+        return self
+            .modify(name, |device| {
+                let bookmarks = device.bookmarks.get_or_insert_with(Vec::new);
+                bookmarks.retain(|b| b.path != bookmark.path);
+                bookmarks.push(bookmark);
+            })
+            .await;
+    }
+
+    /// Adds the Luna call bookmark to the device, replacing any existing one of the same name.
+    pub async fn add_luna_bookmark(
+        &self,
+        name: &str,
+        bookmark: LunaBookmark,
+    ) -> Result<Device, Error> {
+        return self
+            .modify(name, |device| {
+                let bookmarks = device.luna_bookmarks.get_or_insert_with(Vec::new);
+                bookmarks.retain(|b| b.name != bookmark.name);
+                bookmarks.push(bookmark);
+            })
+            .await;
+    }
+
+    pub async fn remove_luna_bookmark(
+        &self,
+        name: &str,
+        bookmark_name: &str,
+    ) -> Result<Device, Error> {
+        return self
+            .modify(name, |device| {
+                if let Some(bookmarks) = device.luna_bookmarks.as_mut() {
+                    bookmarks.retain(|b| b.name != bookmark_name);
+                }
+                if device.luna_bookmarks.as_ref().is_some_and(|b| b.is_empty()) {
+                    device.luna_bookmarks = None;
+                }
+            })
+            .await;
+    }
+
+    pub async fn remove_bookmark(&self, name: &str, path: &str) -> Result<Device, Error> {
+        return self
+            .modify(name, |device| {
+                if let Some(bookmarks) = device.bookmarks.as_mut() {
+                    bookmarks.retain(|b| b.path != path);
+                }
+                if device.bookmarks.as_ref().is_some_and(|b| b.is_empty()) {
+                    device.bookmarks = None;
+                }
+            })
+            .await;
+    }
+
+    /// Writes the devices file, remembering its content so the watcher can ignore the change.
+    pub(super) async fn save(&self, devices: Vec<Device>) -> Result<(), Error> {
+        *self.devices.lock().unwrap() = devices.clone();
+        return write(devices, self.get_conf_dir().as_deref(), &self.file_key).await;
+    }
+
+    /// Saves key data into the SSH directory, and makes absolute key paths relative to it.
+    async fn store_key(
+        &self,
+        key: &PrivateKey,
+        passphrase: Option<String>,
+    ) -> Result<PrivateKey, Error> {
+        return match key {
+            PrivateKey::Path { name } => {
+                let path = Path::new(name);
+                if !path.is_absolute() {
+                    return Ok(key.clone());
+                }
+                let name = String::from(
+                    pathdiff::diff_paths(path, self.ensure_ssh_dir()?)
+                        .ok_or(Error::NotFound)?
+                        .to_string_lossy(),
+                );
+                Ok(PrivateKey::Path { name })
+            }
+            PrivateKey::Data { data } => {
+                let name = key.name(passphrase)?;
+                let key_path = self.ensure_ssh_dir()?.join(&name);
+                let mut file = File::create(key_path).await?;
+                file.write(data.as_bytes()).await?;
+                Ok(PrivateKey::Path { name })
+            }
+        };
+    }
+
+    pub(super) async fn modify<F>(&self, name: &str, action: F) -> Result<Device, Error>
+    where
+        F: FnOnce(&mut Device),
+    {
+        let conf_dir = self.get_conf_dir();
+        let mut devices = read(conf_dir.as_deref(), &self.file_key).await?;
+        let device = devices
+            .iter_mut()
+            .find(|d| d.name == name)
+            .ok_or(Error::NotFound)?;
+        action(device);
+        let device = device.clone();
+        self.save(devices).await?;
+        return Ok(device);
+    }
+
     //noinspection HttpUrlsUsage
     pub async fn novacom_getkey(&self, address: &str, passphrase: &str) -> Result<String, Error> {
         let resp = reqwest::get(format!("http://{}:9991/webos_rsa", address))
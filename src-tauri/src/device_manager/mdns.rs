@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+
+const MDNS_ADDR: &str = "224.0.0.251:5353";
+/// Advertised by webOS TVs supporting AirPlay, with the manufacturer and model in TXT records.
+/// The second screen API itself is only advertised with SSDP.
+const MDNS_SERVICE: &str = "_airplay._tcp.local";
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+/// Asks for unicast responses, so they reach our ephemeral port
+const CLASS_IN_UNICAST: u16 = 0x8001;
+
+pub(super) struct MdnsResponse {
+    pub host: String,
+    /// Service instance name, which LG TVs set to the friendly name
+    pub name: Option<String>,
+    pub model: Option<String>,
+}
+
+/// Browses for [MDNS_SERVICE] for `timeout`, keeping instances made by LG.
+pub(super) fn mdns_search(timeout: Duration) -> Result<Vec<MdnsResponse>, Error> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.send_to(&query(MDNS_SERVICE), MDNS_ADDR)?;
+    let deadline = Instant::now() + timeout;
+    let mut responses = Vec::new();
+    let mut buf = [0u8; 9000];
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(left))?;
+        let (len, addr) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                break;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let Some(records) = parse_records(&buf[..len]) else {
+            continue;
+        };
+        for instance in records.ptr.get(MDNS_SERVICE).into_iter().flatten() {
+            let txt = records.txt.get(instance);
+            let value = |key: &str| txt.and_then(|t| t.get(key)).cloned();
+            if !value("manufacturer").map_or(false, |m| m.starts_with("LG")) {
+                continue;
+            }
+            let name = instance.strip_suffix(&format!(".{MDNS_SERVICE}"));
+            responses.push(MdnsResponse {
+                host: addr.ip().to_string(),
+                name: name.map(String::from),
+                model: value("model"),
+            });
+        }
+    }
+    return Ok(responses);
+}
+
+/// DNS query for the PTR records of `name`
+fn query(name: &str) -> Vec<u8> {
+    // ID, flags, one question, no answer, authority or additional records
+    let mut packet = vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+    for label in name.split('.') {
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+    packet.extend_from_slice(&TYPE_PTR.to_be_bytes());
+    packet.extend_from_slice(&CLASS_IN_UNICAST.to_be_bytes());
+    return packet;
+}
+
+#[derive(Default)]
+struct Records {
+    /// PTR targets by owner name
+    ptr: HashMap<String, Vec<String>>,
+    /// `key=value` TXT strings by owner name
+    txt: HashMap<String, HashMap<String, String>>,
+}
+
+/// Collects PTR and TXT records from all sections of a DNS message, or `None` if it's malformed.
+fn parse_records(packet: &[u8]) -> Option<Records> {
+    let count = |at: usize| Some(u16::from_be_bytes([*packet.get(at)?, *packet.get(at + 1)?]));
+    let questions = count(4)?;
+    let records = count(6)? as usize + count(8)? as usize + count(10)? as usize;
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = read_name(packet, pos)?.1 + 4;
+    }
+    let mut result = Records::default();
+    for _ in 0..records {
+        let (owner, next) = read_name(packet, pos)?;
+        let rtype = count(next)?;
+        let rdlength = count(next + 8)? as usize;
+        let rdata = next + 10;
+        let end = rdata + rdlength;
+        if end > packet.len() {
+            return None;
+        }
+        match rtype {
+            TYPE_PTR => {
+                let (target, _) = read_name(packet, rdata)?;
+                result.ptr.entry(owner).or_default().push(target);
+            }
+            TYPE_TXT => {
+                let entries = result.txt.entry(owner).or_default();
+                let mut at = rdata;
+                while at < end {
+                    let len = packet[at] as usize;
+                    let text = packet.get(at + 1..(at + 1 + len).min(end))?;
+                    let text = String::from_utf8_lossy(text);
+                    if let Some((key, value)) = text.split_once('=') {
+                        entries.insert(key.to_ascii_lowercase(), String::from(value));
+                    }
+                    at += 1 + len;
+                }
+            }
+            _ => {}
+        }
+        pos = end;
+    }
+    return Some(result);
+}
+
+/// Reads a possibly compressed name at `pos`, returning it and the position after it.
+fn read_name(packet: &[u8], pos: usize) -> Option<(String, usize)> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut at = pos;
+    let mut end = None;
+    // Each pointer must go backwards, so this also ends pointer loops
+    let mut limit = pos;
+    loop {
+        let len = *packet.get(at)? as usize;
+        if len == 0 {
+            return Some((labels.join("."), end.unwrap_or(at + 1)));
+        }
+        if len & 0xC0 == 0xC0 {
+            let target = (len & 0x3F) << 8 | *packet.get(at + 1)? as usize;
+            if target >= limit {
+                return None;
+            }
+            end.get_or_insert(at + 2);
+            limit = target;
+            at = target;
+            continue;
+        }
+        let label = packet.get(at + 1..at + 1 + len)?;
+        labels.push(String::from_utf8_lossy(label).to_string());
+        at += 1 + len;
+    }
+}
@@ -1,12 +1,31 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
 
 use serde::{Deserialize, Serialize};
 
-mod device;
+use crate::device_manager::crypt::StoreKey;
+
+mod capabilities;
+mod crypt;
+mod detect;
+pub(crate) mod device;
+mod discovery;
+mod history;
+mod import;
+mod info;
 mod io;
+pub(crate) mod keygen;
 mod manager;
+mod mdns;
+pub(crate) mod novacom;
 mod privkey;
+mod share;
+mod status;
+mod validate;
+mod wake;
+mod watch;
 
 #[derive(PartialEq, Eq, Hash)]
 pub struct DeviceSessionToken {
@@ -18,7 +37,85 @@ pub struct DeviceSessionToken {
 pub struct DeviceManager {
     ssh_dir: Mutex<Option<PathBuf>>,
     conf_dir: Mutex<Option<PathBuf>>,
-    devices: Mutex<Vec<Device>>,
+    /// Devices as last read or written by this manager
+    devices: Arc<Mutex<Vec<Device>>>,
+    devices_watcher: Mutex<Option<notify::RecommendedWatcher>>,
+    devices_callback: Arc<Mutex<Option<Box<dyn DevicesCallback + Send + Sync>>>>,
+    /// Loaded on first use
+    history: Mutex<Option<HashMap<String, DeviceHistory>>>,
+    /// Whether each device was reachable when last polled
+    statuses: Arc<Mutex<HashMap<String, bool>>>,
+    /// Set to stop the running status poller
+    status_poll: Mutex<Option<Arc<AtomicBool>>>,
+    status_callback: Arc<Mutex<Option<Box<dyn StatusCallback + Send + Sync>>>>,
+    /// Key of the encrypted devices file, shared with the threads reading it
+    file_key: StoreKey,
+}
+
+pub trait StatusCallback {
+    fn changed(&self, status: DeviceStatus);
+}
+
+pub trait DevicesCallback {
+    fn changed(&self, event: DevicesChanged);
+}
+
+/// Sent when the devices file was changed by another program
+#[derive(Clone, Serialize, Debug)]
+pub struct DevicesChanged {
+    pub devices: Vec<Device>,
+    /// Names of devices that were added, removed or modified, so that unsaved edits to them can
+    /// be flagged as conflicting
+    pub changed: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceHistory {
+    /// Seconds since epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_connected: Option<u64>,
+    /// Newest first
+    #[serde(default)]
+    pub recent: VecDeque<DeviceActivity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_renewal: Option<DevModeRenewal>,
+}
+
+/// Outcome of extending the Developer Mode session of a device
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DevModeRenewal {
+    pub device: String,
+    /// Seconds since epoch
+    pub time: u64,
+    /// As reported by LG, like `1234:56:78`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remaining: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DeviceActivity {
+    /// Seconds since epoch
+    pub time: u64,
+    pub kind: ActivityKind,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub enum ActivityKind {
+    #[serde(rename = "shell")]
+    Shell,
+    #[serde(rename = "exec")]
+    Exec,
+    #[serde(rename = "transfer")]
+    Transfer,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct DeviceStatus {
+    pub name: String,
+    pub online: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -34,7 +131,7 @@ pub enum PrivateKey {
     },
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct Device {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub order: Option<String>,
@@ -42,6 +139,9 @@ pub struct Device {
     pub default: Option<bool>,
     pub profile: String,
     pub name: String,
+    /// Assigned when the device is added, and kept when it's renamed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     pub host: String,
@@ -67,6 +167,240 @@ pub struct Device {
     pub no_port_forwarding: Option<bool>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub indelible: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prewarm: Option<bool>,
+    #[serde(
+    rename = "channelLimit",
+    default,
+    skip_serializing_if = "Option::is_none"
+    )]
+    pub channel_limit: Option<usize>,
+    #[serde(
+    rename = "transferLimit",
+    default,
+    skip_serializing_if = "Option::is_none"
+    )]
+    pub transfer_limit: Option<usize>,
+    #[serde(rename = "hostKey", default, skip_serializing_if = "Option::is_none")]
+    pub host_key: Option<String>,
+    #[serde(
+    rename = "shellProfile",
+    default,
+    skip_serializing_if = "Option::is_none"
+    )]
+    pub shell_profile: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elevation: Option<Elevation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub term: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truecolor: Option<bool>,
+    #[serde(
+    rename = "allowClipboard",
+    default,
+    skip_serializing_if = "Option::is_none"
+    )]
+    pub allow_clipboard: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multiplexer: Option<Multiplexer>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bookmarks: Option<Vec<Bookmark>>,
+    /// For Wake-on-LAN
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mac: Option<String>,
+    /// Cached from the last time it was fetched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub info: Option<DeviceInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// CSS color shown next to the device name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Icon name from the app's icon set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// Defaults to a TV. Not to be confused with `profile`, which is always `ose` so that the
+    /// devices file can be shared with the OSE CLI.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<DeviceKind>,
+    /// For addressing groups of devices at once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    /// NDUID of a device attached to novacomd. SSH then goes through novacom over USB instead of
+    /// the network, to the sshd listening on `port` of the device.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub novacom: Option<String>,
+    /// Exported in shells and commands. Elevation with `su -` or `sudo -i` resets them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<BTreeMap<String, String>>,
+    /// Extend the Developer Mode session in the background before it expires
+    #[serde(
+    rename = "devModeRenew",
+    default,
+    skip_serializing_if = "Option::is_none"
+    )]
+    pub devmode_renew: Option<bool>,
+    /// Detected the last time the device was probed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<DeviceCapabilities>,
+    #[serde(
+    rename = "lunaBookmarks",
+    default,
+    skip_serializing_if = "Option::is_none"
+    )]
+    pub luna_bookmarks: Option<Vec<LunaBookmark>>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct NovacomDevice {
+    /// Port novacomd forwards to the device
+    pub port: u16,
+    pub nduid: String,
+    /// Like `usb` or `emulator`
+    pub connection: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum DeviceKind {
+    /// LG TV, rooted or in Developer Mode
+    #[serde(rename = "tv")]
+    Tv,
+    /// webOS Open Source Edition, like on a Raspberry Pi
+    #[serde(rename = "ose")]
+    Ose,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub firmware_version: Option<String>,
+    /// webOS release, or SDK version if the release is unknown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webos_version: Option<String>,
+    /// SoC name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub board_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial_number: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ota_id: Option<String>,
+    /// Seconds since epoch
+    #[serde(default)]
+    pub fetched: u64,
+}
+
+/// What the user logged into the device can do. Developer Mode users are jailed, while rooted
+/// devices usually have the Homebrew Channel installed.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceCapabilities {
+    pub root: bool,
+    /// Homebrew Channel is installed
+    pub homebrew: bool,
+    /// Luna bus configuration can be modified
+    pub luna_writable: bool,
+    /// Seconds since epoch
+    #[serde(default)]
+    pub detected: u64,
+}
+
+/// Selects devices by name, or by having any of the tags
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct DeviceSelector {
+    #[serde(default)]
+    pub names: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Bookmark {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// Saved Luna call
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LunaBookmark {
+    pub name: String,
+    pub uri: String,
+    /// JSON, with `{{name}}` placeholders filled in when called
+    pub params: String,
+    /// Defaults to `luna-send-pub` unless the device is rooted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public: Option<bool>,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct ImportResult {
+    pub imported: Vec<Device>,
+    /// Names of devices that weren't imported because a device with the same name exists
+    pub conflicts: Vec<String>,
+}
+
+/// A webOS TV found on the LAN
+#[derive(Serialize, Clone, Debug)]
+pub struct DiscoveredDevice {
+    pub host: String,
+    /// Friendly name set on the TV
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// SSDP `SERVER` header, which includes the webOS version
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    /// Name of the device already added with the same host
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub existing: Option<String>,
+}
+
+/// SSH settings for a port found open by [DeviceManager::detect_ssh]
+#[derive(Serialize, Clone, Debug)]
+pub struct DetectedSsh {
+    pub port: u16,
+    pub username: String,
+    pub auth: ExpectedAuth,
+}
+
+#[derive(Serialize, Clone, Copy, Debug)]
+pub enum ExpectedAuth {
+    /// Key downloaded from the Developer Mode key server with the passphrase shown on the TV
+    #[serde(rename = "devmode-key")]
+    DevModeKey,
+    #[serde(rename = "password")]
+    Password,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DeviceShare {
+    /// Text to paste on the other machine
+    pub payload: String,
+    /// The payload as a QR code, in SVG
+    pub qr: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub enum KeyAlgorithm {
+    #[serde(rename = "ed25519")]
+    Ed25519,
+    #[serde(rename = "rsa")]
+    Rsa,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneratedKey {
+    /// File name of the private key in the SSH directory
+    pub name: String,
+    /// Public key in OpenSSH `authorized_keys` format
+    pub public_key: String,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -76,3 +410,19 @@ pub enum DeviceFileTransfer {
     #[serde(rename = "sftp")]
     Sftp,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Elevation {
+    #[serde(rename = "su")]
+    Su,
+    #[serde(rename = "sudo")]
+    Sudo,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Multiplexer {
+    #[serde(rename = "tmux")]
+    Tmux,
+    #[serde(rename = "screen")]
+    Screen,
+}
@@ -0,0 +1,73 @@
+use std::io::{BufRead, BufReader};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use crate::device_manager::wake::is_reachable;
+use crate::device_manager::{DeviceManager, NovacomDevice};
+use crate::error::Error;
+use crate::session_manager::command::quote;
+
+/// novacomd lists attached devices to whoever connects to this port
+const NOVACOMD_ADDR: &str = "127.0.0.1:6968";
+const NOVACOMD_TIMEOUT: Duration = Duration::from_secs(2);
+
+impl DeviceManager {
+    /// Lists devices attached to the local novacomd, over USB or as emulators.
+    pub async fn novacom_devices(&self) -> Result<Vec<NovacomDevice>, Error> {
+        return tokio::task::spawn_blocking(list_devices)
+            .await
+            .expect("critical failure in DeviceManager::novacom_devices task");
+    }
+}
+
+fn list_devices() -> Result<Vec<NovacomDevice>, Error> {
+    let addr = NOVACOMD_ADDR.parse().expect("Illegal novacomd address");
+    let stream = TcpStream::connect_timeout(&addr, NOVACOMD_TIMEOUT).map_err(|e| {
+        return match e.kind() {
+            std::io::ErrorKind::ConnectionRefused => Error::new("novacomd is not running"),
+            _ => e.into(),
+        };
+    })?;
+    stream.set_read_timeout(Some(NOVACOMD_TIMEOUT))?;
+    let mut devices = Vec::new();
+    // Each line is `<port> <nduid> <connection> <device type>`
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [port, nduid, connection, kind, ..] = fields[..] else {
+            continue;
+        };
+        let Ok(port) = port.parse::<u16>() else {
+            continue;
+        };
+        devices.push(NovacomDevice {
+            port,
+            nduid: String::from(nduid),
+            connection: String::from(connection),
+            kind: String::from(kind),
+        });
+    }
+    return Ok(devices);
+}
+
+/// Whether device `nduid` is attached to novacomd, and the port novacomd forwards to it accepts
+/// connections. The device's own host and port aren't reachable from here.
+pub(crate) fn is_attached(nduid: &str, timeout: Duration) -> bool {
+    let Ok(devices) = list_devices() else {
+        return false;
+    };
+    return devices
+        .iter()
+        .find(|d| d.nduid == nduid)
+        .map_or(false, |d| is_reachable("127.0.0.1", d.port, timeout));
+}
+
+/// SSH proxy command that reaches sshd on `port` of device `nduid` through novacomd, by running
+/// `nc` on the device.
+pub(crate) fn proxy_command(nduid: &str, port: u16) -> String {
+    return format!(
+        "novacom -d {} run file:///bin/sh -c {}",
+        quote(nduid),
+        quote(&format!("exec nc 127.0.0.1 {port}"))
+    );
+}
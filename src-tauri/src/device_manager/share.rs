@@ -0,0 +1,82 @@
+use std::io::{Read, Write};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use qrcode::render::svg;
+use qrcode::QrCode;
+use serde::{Deserialize, Serialize};
+
+use crate::app_dirs::GetSshDir;
+use crate::device_manager::import::portable;
+use crate::device_manager::{Device, DeviceManager, DeviceShare};
+use crate::error::Error;
+
+const SHARE_PREFIX: &str = "webosdev:";
+const SHARE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct SharedDevice {
+    version: u32,
+    device: Device,
+}
+
+impl DeviceManager {
+    /// Encodes device `name` into a compact text payload, and a QR code of it, for importing on
+    /// another machine with [Device::from_share]. Secrets are left out unless `include_secrets`
+    /// is set.
+    pub async fn share(&self, name: &str, include_secrets: bool) -> Result<DeviceShare, Error> {
+        let device = self
+            .list()
+            .await?
+            .into_iter()
+            .find(|d| d.name == name)
+            .ok_or(Error::NotFound)?;
+        let mut device = portable(device, include_secrets, self.get_ssh_dir().as_deref())?;
+        device.default = None;
+        device.order = None;
+        let shared = SharedDevice {
+            version: SHARE_VERSION,
+            device,
+        };
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&serde_json::to_vec(&shared)?)?;
+        let payload = format!(
+            "{SHARE_PREFIX}{}",
+            URL_SAFE_NO_PAD.encode(encoder.finish()?)
+        );
+        let qr = QrCode::new(payload.as_bytes())
+            .map_err(|e| Error::new(format!("Device is too large to share as QR code: {e}")))?
+            .render::<svg::Color>()
+            .min_dimensions(256, 256)
+            .build();
+        return Ok(DeviceShare { payload, qr });
+    }
+}
+
+impl Device {
+    /// Decodes a payload made by [DeviceManager::share]. The device isn't added, so that missing
+    /// credentials can be filled in first.
+    pub fn from_share(payload: &str) -> Result<Device, Error> {
+        let invalid = || Error::new("Not a shared device");
+        let data = payload
+            .trim()
+            .strip_prefix(SHARE_PREFIX)
+            .and_then(|p| URL_SAFE_NO_PAD.decode(p).ok())
+            .ok_or_else(invalid)?;
+        let mut json = Vec::new();
+        DeflateDecoder::new(&data[..])
+            .read_to_end(&mut json)
+            .map_err(|_| invalid())?;
+        let shared: SharedDevice = serde_json::from_slice(&json)?;
+        if shared.version > SHARE_VERSION {
+            return Err(Error::new(format!(
+                "Shared device version {} is not supported",
+                shared.version
+            )));
+        }
+        return Ok(shared.device);
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::app_dirs::GetConfDir;
+use crate::device_manager::io::{devices_file_path, read_file_blocking};
+use crate::device_manager::wake::is_device_reachable;
+use crate::device_manager::{DeviceManager, DeviceStatus, StatusCallback};
+use crate::error::Error;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+/// How often the poller checks whether it was stopped while waiting for the next round
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+impl DeviceManager {
+    /// Probes the SSH port of every device each `interval`, calling the status callback whenever
+    /// a device goes online or offline. Replaces any running poller, and `None` just stops it.
+    pub fn poll_status(&self, interval: Option<Duration>) -> Result<(), Error> {
+        if let Some(stop) = self.status_poll.lock().unwrap().take() {
+            stop.store(true, Ordering::Relaxed);
+        }
+        let Some(interval) = interval else {
+            return Ok(());
+        };
+        let path = devices_file_path(self.get_conf_dir().as_deref())?;
+        let stop = Arc::new(AtomicBool::new(false));
+        *self.status_poll.lock().unwrap() = Some(stop.clone());
+        let statuses = self.statuses.clone();
+        let callback = self.status_callback.clone();
+        let store_key = self.file_key.clone();
+        std::thread::spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                let started = Instant::now();
+                match read_file_blocking(&path, &store_key) {
+                    Ok(devices) => {
+                        let online: Vec<(String, bool)> = std::thread::scope(|scope| {
+                            let probes: Vec<_> = devices
+                                .iter()
+                                .map(|d| {
+                                    scope.spawn(|| {
+                                        (d.name.clone(), is_device_reachable(d, PROBE_TIMEOUT))
+                                    })
+                                })
+                                .collect();
+                            return probes.into_iter().filter_map(|p| p.join().ok()).collect();
+                        });
+                        if stop.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        update(&statuses, &callback, online);
+                    }
+                    Err(e) => log::warn!("Failed to read devices for status polling: {e:?}"),
+                }
+                while !stop.load(Ordering::Relaxed) && started.elapsed() < interval {
+                    std::thread::sleep(STOP_CHECK_INTERVAL);
+                }
+            }
+        });
+        return Ok(());
+    }
+
+    /// Last known status of every device, empty unless polling is or was running.
+    pub fn statuses(&self) -> Vec<DeviceStatus> {
+        return self
+            .statuses
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, online)| DeviceStatus {
+                name: name.clone(),
+                online: *online,
+            })
+            .collect();
+    }
+
+    /// Whether the poller last found device `name` offline. Unpolled devices aren't.
+    pub fn is_offline(&self, name: &str) -> bool {
+        return self.statuses.lock().unwrap().get(name) == Some(&false);
+    }
+
+    pub fn set_status_callback(&self, callback: Box<dyn StatusCallback + Send + Sync>) {
+        *self.status_callback.lock().unwrap() = Some(callback);
+    }
+}
+
+fn update(
+    statuses: &Mutex<HashMap<String, bool>>,
+    callback: &Mutex<Option<Box<dyn StatusCallback + Send + Sync>>>,
+    online: Vec<(String, bool)>,
+) {
+    let mut statuses = statuses.lock().unwrap();
+    statuses.retain(|name, _| online.iter().any(|(n, _)| n == name));
+    for (name, online) in online {
+        if statuses.insert(name.clone(), online) == Some(online) {
+            continue;
+        }
+        if let Some(callback) = callback.lock().unwrap().as_ref() {
+            callback.changed(DeviceStatus { name, online });
+        }
+    }
+}
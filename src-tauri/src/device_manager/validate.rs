@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::Path;
+
+use libssh_rs::SshKey;
+use regex::Regex;
+
+use crate::device_manager::device::valid_env_name;
+use crate::device_manager::wake::parse_mac;
+use crate::device_manager::Device;
+use crate::error::Error;
+
+impl Device {
+    /// Checks the settings without connecting, and reports all problems found as
+    /// [Error::Validation], keyed by field name. The name must differ from those of `others`.
+    pub fn validate(&self, others: &[Device], ssh_dir: Option<&Path>) -> Result<(), Error> {
+        let mut fields = BTreeMap::new();
+        if self.name.trim().is_empty() {
+            fields.insert("name", String::from("Name is required"));
+        } else if others.iter().any(|d| d.name == self.name) {
+            fields.insert(
+                "name",
+                format!("A device named {:?} already exists", self.name),
+            );
+        }
+        // Devices reached through novacom don't need an address
+        if self.novacom.is_none() && !valid_host(&self.host) {
+            fields.insert(
+                "host",
+                format!("{:?} is not a valid host name or IP address", self.host),
+            );
+        }
+        if self.port == 0 {
+            fields.insert("port", String::from("Port must be between 1 and 65535"));
+        }
+        if self.username.trim().is_empty() {
+            fields.insert("username", String::from("Username is required"));
+        }
+        if let Some(key) = &self.private_key {
+            match key.content(ssh_dir) {
+                Ok(content) => {
+                    let passphrase = self.valid_passphrase();
+                    if SshKey::from_privkey_base64(&content, passphrase.as_deref()).is_err() {
+                        // The same error is returned for wrong passphrases and malformed keys
+                        if passphrase.is_none() && content.contains("ENCRYPTED") {
+                            fields.insert("passphrase", String::from("Key needs a passphrase"));
+                        } else if passphrase.is_some() {
+                            fields.insert(
+                                "passphrase",
+                                String::from("Passphrase doesn't match the key"),
+                            );
+                        } else {
+                            fields.insert("privateKey", String::from("Not a valid private key"));
+                        }
+                    }
+                }
+                Err(e) => {
+                    fields.insert(
+                        "privateKey",
+                        format!("Can't read key file: {}", message(&e)),
+                    );
+                }
+            }
+        }
+        if let Some(mac) = &self.mac {
+            if let Err(e) = parse_mac(mac) {
+                fields.insert("mac", message(&e));
+            }
+        }
+        if let Some(name) = self
+            .env
+            .iter()
+            .flatten()
+            .map(|(n, _)| n)
+            .find(|n| !valid_env_name(n))
+        {
+            fields.insert("env", format!("{name:?} is not a valid variable name"));
+        }
+        if fields.is_empty() {
+            return Ok(());
+        }
+        return Err(Error::Validation {
+            fields: fields
+                .into_iter()
+                .map(|(field, message)| (String::from(field), message))
+                .collect(),
+        });
+    }
+}
+
+fn valid_host(host: &str) -> bool {
+    if host.parse::<IpAddr>().is_ok() {
+        return true;
+    }
+    let label = Regex::new(r"^[A-Za-z0-9]([A-Za-z0-9-]{0,61}[A-Za-z0-9])?$").unwrap();
+    return !host.is_empty() && host.len() <= 253 && host.split('.').all(|l| label.is_match(l));
+}
+
+fn message(error: &Error) -> String {
+    return match error {
+        Error::Message { message, .. } | Error::IO { message, .. } => message.clone(),
+        e => format!("{e:?}"),
+    };
+}
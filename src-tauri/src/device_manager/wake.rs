@@ -0,0 +1,117 @@
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+use crate::device_manager::novacom::is_attached;
+use crate::device_manager::{Device, DeviceManager};
+use crate::error::Error;
+
+/// Magic packets are sent this many times, as single UDP datagrams easily get lost
+const WAKE_ATTEMPTS: usize = 3;
+const WAKE_INTERVAL: Duration = Duration::from_secs(1);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+impl DeviceManager {
+    /// Sends Wake-on-LAN magic packets to the device, then waits up to `timeout` for its SSH port
+    /// to accept connections. Returns whether it came up in time.
+    pub async fn wake(&self, device: Device, timeout: Duration) -> Result<bool, Error> {
+        let mac = parse_mac(
+            device
+                .mac
+                .as_deref()
+                .ok_or_else(|| Error::new("MAC address of the device is unknown"))?,
+        )?;
+        return tokio::task::spawn_blocking(move || {
+            let deadline = Instant::now() + timeout;
+            let mut attempts = 0;
+            loop {
+                if attempts < WAKE_ATTEMPTS {
+                    send_magic_packet(&mac, &device.host)?;
+                    attempts += 1;
+                }
+                if is_device_reachable(&device, PROBE_TIMEOUT) {
+                    return Ok(true);
+                }
+                if Instant::now() >= deadline {
+                    return Ok(false);
+                }
+                std::thread::sleep(WAKE_INTERVAL);
+            }
+        })
+        .await
+        .expect("critical failure in DeviceManager::wake task");
+    }
+}
+
+/// Whether the device accepts connections within `timeout`, through novacomd for devices attached
+/// to it.
+pub(crate) fn is_device_reachable(device: &Device, timeout: Duration) -> bool {
+    return match &device.novacom {
+        Some(nduid) => is_attached(nduid, timeout),
+        None => is_reachable(&device.host, device.port, timeout),
+    };
+}
+
+/// Whether a TCP connection to `host` and `port` can be established within `timeout`.
+pub(crate) fn is_reachable(host: &str, port: u16, timeout: Duration) -> bool {
+    let Ok(addrs) = (host, port).to_socket_addrs() else {
+        return false;
+    };
+    return addrs
+        .into_iter()
+        .any(|addr| TcpStream::connect_timeout(&addr, timeout).is_ok());
+}
+
+pub(super) fn parse_mac(mac: &str) -> Result<[u8; 6], Error> {
+    let invalid = || Error::new(format!("Invalid MAC address {mac}"));
+    let digits: String = mac.chars().filter(|c| c.is_ascii_hexdigit()).collect();
+    if digits.len() != 12
+        || mac
+            .chars()
+            .any(|c| !c.is_ascii_hexdigit() && !":-.".contains(c))
+    {
+        return Err(invalid());
+    }
+    let mut bytes = [0u8; 6];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    return Ok(bytes);
+}
+
+/// Broadcasts the magic packet on the local network, and also sends it to `host` directly in
+/// case the router forwards it.
+fn send_magic_packet(mac: &[u8; 6], host: &str) -> Result<(), Error> {
+    let mut packet = vec![0xFFu8; 6];
+    for _ in 0..16 {
+        packet.extend_from_slice(mac);
+    }
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.set_broadcast(true)?;
+    socket.send_to(&packet, "255.255.255.255:9")?;
+    if let Err(e) = socket.send_to(&packet, (host, 9)) {
+        log::debug!("Failed to send magic packet to {host}: {e:?}");
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_mac_formats() {
+        let mac = [0xaa, 0xbb, 0xcc, 0x00, 0x11, 0x22];
+        assert_eq!(parse_mac("AA:bb:CC:00:11:22").unwrap(), mac);
+        assert_eq!(parse_mac("aa-bb-cc-00-11-22").unwrap(), mac);
+        assert_eq!(parse_mac("aabb.cc00.1122").unwrap(), mac);
+        assert_eq!(parse_mac("aabbcc001122").unwrap(), mac);
+    }
+
+    #[test]
+    fn rejects_invalid_macs() {
+        assert!(parse_mac("aa:bb:cc:00:11").is_err());
+        assert!(parse_mac("aa:bb:cc:00:11:22:33").is_err());
+        assert!(parse_mac("aa:bb:cc:00:11:zz").is_err());
+        assert!(parse_mac("aa bb cc 00 11 22").is_err());
+    }
+}
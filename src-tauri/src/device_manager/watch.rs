@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use notify::{Event, RecursiveMode, Watcher};
+use serde_json::Value;
+
+use crate::app_dirs::GetConfDir;
+use crate::device_manager::io::{devices_file_path, read_file_blocking};
+use crate::device_manager::{Device, DeviceManager, DevicesCallback, DevicesChanged};
+use crate::error::Error;
+
+/// Tools may write the file in several steps, so events are collected until it's quiet
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+impl DeviceManager {
+    /// Watches the devices file for changes made outside the app, like by `ares-setup-device`,
+    /// and reports them to the callback. Writes made by this manager aren't reported.
+    pub fn watch_file(&self) -> Result<(), Error> {
+        let path = devices_file_path(self.get_conf_dir().as_deref())?;
+        let dir = path
+            .parent()
+            .ok_or_else(|| Error::bad_config())?
+            .to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        let (sender, receiver) = mpsc::channel::<Event>();
+        // The file is often replaced rather than written to, so watch its directory instead
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            if let Ok(event) = event {
+                sender.send(event).unwrap_or(());
+            }
+        })
+        .map_err(|e| Error::new(format!("Failed to watch devices file: {e}")))?;
+        watcher
+            .watch(&dir, RecursiveMode::NonRecursive)
+            .map_err(|e| Error::new(format!("Failed to watch devices file: {e}")))?;
+        let cache = self.devices.clone();
+        let callback = self.devices_callback.clone();
+        let store_key = self.file_key.clone();
+        std::thread::spawn(move || {
+            // Ends when the watcher, and with it the sender, is dropped
+            while let Ok(event) = receiver.recv() {
+                let mut relevant = event.paths.iter().any(|p| p == &path);
+                while let Ok(event) = receiver.recv_timeout(DEBOUNCE) {
+                    relevant |= event.paths.iter().any(|p| p == &path);
+                }
+                if !relevant {
+                    continue;
+                }
+                let devices = match read_file_blocking(&path, &store_key) {
+                    Ok(devices) => devices,
+                    Err(e) => {
+                        log::warn!("Failed to reload devices file: {e:?}");
+                        continue;
+                    }
+                };
+                let changed = {
+                    let mut cache = cache.lock().unwrap();
+                    let changed = changed_names(&cache, &devices);
+                    if !changed.is_empty() {
+                        *cache = devices.clone();
+                    }
+                    changed
+                };
+                if changed.is_empty() {
+                    continue;
+                }
+                log::info!("Devices file changed on disk: {changed:?}");
+                if let Some(callback) = callback.lock().unwrap().as_ref() {
+                    callback.changed(DevicesChanged { devices, changed });
+                }
+            }
+        });
+        *self.devices_watcher.lock().unwrap() = Some(watcher);
+        return Ok(());
+    }
+
+    pub fn set_devices_callback(&self, callback: Box<dyn DevicesCallback + Send + Sync>) {
+        *self.devices_callback.lock().unwrap() = Some(callback);
+    }
+}
+
+/// Names of devices that were added, removed or modified between `old` and `new`.
+fn changed_names(old: &[Device], new: &[Device]) -> Vec<String> {
+    let serialize = |devices: &[Device]| -> HashMap<String, Value> {
+        return devices
+            .iter()
+            .map(|d| {
+                (
+                    d.name.clone(),
+                    serde_json::to_value(d).unwrap_or(Value::Null),
+                )
+            })
+            .collect();
+    };
+    let (old, new) = (serialize(old), serialize(new));
+    let mut changed: Vec<String> = new
+        .iter()
+        .filter(|(name, value)| old.get(*name) != Some(value))
+        .map(|(name, _)| name.clone())
+        .chain(old.keys().filter(|name| !new.contains_key(*name)).cloned())
+        .collect();
+    changed.sort();
+    return changed;
+}
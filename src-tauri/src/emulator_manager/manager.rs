@@ -0,0 +1,102 @@
+use std::path::PathBuf;
+
+use crate::app_dirs::{GetConfDir, SetConfDir};
+use crate::device_manager::{Device, DeviceKind};
+use crate::emulator_manager::{qemu, virtualbox, Emulator, EmulatorBackend, EmulatorManager};
+use crate::error::Error;
+
+impl EmulatorManager {
+    /// Lists VirtualBox emulators, and QEMU images in the `emulators` directory of the
+    /// configuration directory.
+    pub fn list(&self) -> Result<Vec<Emulator>, Error> {
+        let mut emulators = virtualbox::list()?;
+        if let Some(dir) = self.images_dir() {
+            let mut processes = self.qemu.lock().unwrap();
+            // Forget emulators that were shut down from inside
+            processes.retain(|_, (child, _)| child.try_wait().is_ok_and(|s| s.is_none()));
+            for mut emulator in qemu::list(&dir)? {
+                if let Some((_, port)) = processes.get(&emulator.name) {
+                    emulator.running = true;
+                    emulator.ssh_port = Some(*port);
+                }
+                emulators.push(emulator);
+            }
+        }
+        return Ok(emulators);
+    }
+
+    /// Starts the emulator, and returns it with the port SSH is forwarded to.
+    pub fn start(&self, name: &str, headless: bool) -> Result<Emulator, Error> {
+        let emulator = self.find(name)?;
+        if emulator.running {
+            return Ok(emulator);
+        }
+        match emulator.backend {
+            EmulatorBackend::VirtualBox => virtualbox::start(name, headless)?,
+            EmulatorBackend::Qemu => {
+                let image = emulator.image.as_deref().ok_or(Error::NotFound)?;
+                let (child, port) = qemu::start(image, headless)?;
+                self.qemu
+                    .lock()
+                    .unwrap()
+                    .insert(String::from(name), (child, port));
+            }
+        }
+        return self.find(name);
+    }
+
+    /// Shuts the emulator down, or powers it off right away with `force`. QEMU is always killed.
+    pub fn stop(&self, name: &str, force: bool) -> Result<(), Error> {
+        match self.find(name)?.backend {
+            EmulatorBackend::VirtualBox => virtualbox::stop(name, force)?,
+            EmulatorBackend::Qemu => {
+                if let Some((mut child, _)) = self.qemu.lock().unwrap().remove(name) {
+                    child.kill().unwrap_or(());
+                    let _ = child.wait();
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    fn find(&self, name: &str) -> Result<Emulator, Error> {
+        return self
+            .list()?
+            .into_iter()
+            .find(|e| e.name == name)
+            .ok_or(Error::NotFound);
+    }
+
+    fn images_dir(&self) -> Option<PathBuf> {
+        return self.get_conf_dir().map(|d| d.join("emulators"));
+    }
+}
+
+impl Emulator {
+    /// Device for connecting to the running emulator through its forwarded SSH port.
+    pub fn device(&self) -> Result<Device, Error> {
+        let port = self
+            .ssh_port
+            .ok_or_else(|| Error::new("Emulator has no SSH port forwarded"))?;
+        let mut device = Device::preset(self.kind, &self.name, "127.0.0.1");
+        device.port = port;
+        if self.kind == DeviceKind::Tv {
+            // The TV emulator has a dedicated account instead of Developer Mode
+            device.username = String::from("developer");
+        }
+        device.description = Some(String::from("Emulator"));
+        return Ok(device);
+    }
+}
+
+impl GetConfDir for EmulatorManager {
+    fn get_conf_dir(&self) -> Option<PathBuf> {
+        return self.conf_dir.lock().unwrap().clone();
+    }
+}
+
+impl SetConfDir for EmulatorManager {
+    fn set_conf_dir(&self, dir: PathBuf) {
+        *self.conf_dir.lock().unwrap() = Some(dir);
+    }
+}
@@ -0,0 +1,44 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Child;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::device_manager::DeviceKind;
+
+mod manager;
+mod qemu;
+mod virtualbox;
+
+/// Finds, starts and stops local webOS emulators.
+#[derive(Default)]
+pub struct EmulatorManager {
+    conf_dir: Mutex<Option<PathBuf>>,
+    /// QEMU processes started by this app, by emulator name, with their forwarded SSH port
+    qemu: Mutex<HashMap<String, (Child, u16)>>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Emulator {
+    /// VM name for VirtualBox, image file name for QEMU
+    pub name: String,
+    pub backend: EmulatorBackend,
+    /// Disk image, for QEMU
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<PathBuf>,
+    pub running: bool,
+    /// Local port forwarded to the emulator's sshd
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ssh_port: Option<u16>,
+    pub kind: DeviceKind,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum EmulatorBackend {
+    #[serde(rename = "virtualbox")]
+    VirtualBox,
+    #[serde(rename = "qemu")]
+    Qemu,
+}
@@ -0,0 +1,83 @@
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+
+use crate::device_manager::DeviceKind;
+use crate::emulator_manager::{Emulator, EmulatorBackend};
+use crate::error::Error;
+
+/// First local port tried for forwarding SSH, same as the TV emulator uses
+const SSH_PORT_BASE: u16 = 6622;
+const IMAGE_EXTENSIONS: [&str; 4] = ["vmdk", "wic", "qcow2", "img"];
+
+/// Lists webOS OSE disk images in `dir`, like `webos-image-qemux86-64.wic.vmdk`.
+pub(super) fn list(dir: &Path) -> Result<Vec<Emulator>, Error> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+    let mut emulators = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let is_image = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e));
+        if !is_image || !name.to_ascii_lowercase().contains("webos") {
+            continue;
+        }
+        emulators.push(Emulator {
+            name: String::from(name),
+            backend: EmulatorBackend::Qemu,
+            image: Some(path.clone()),
+            running: false,
+            ssh_port: None,
+            kind: DeviceKind::Ose,
+        });
+    }
+    emulators.sort_by(|a, b| a.name.cmp(&b.name));
+    return Ok(emulators);
+}
+
+/// Boots `image`, forwarding a free local port to the guest's sshd. Returns the process and port.
+pub(super) fn start(image: &Path, headless: bool) -> Result<(Child, u16), Error> {
+    let port = free_port()?;
+    let mut command = Command::new(executable());
+    command
+        .args(["-m", "2048", "-smp", "2"])
+        .arg("-drive")
+        .arg(format!("file={},if=virtio", image.display()))
+        .args(["-device", "virtio-net-pci,netdev=net0", "-netdev"])
+        .arg(format!("user,id=net0,hostfwd=tcp:127.0.0.1:{port}-:22"))
+        .args(["-vga", "std", "-usb", "-device", "usb-tablet"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    // QEMU falls back to the next accelerator if one isn't available
+    #[cfg(target_os = "linux")]
+    command.args(["-accel", "kvm"]);
+    #[cfg(target_os = "macos")]
+    command.args(["-accel", "hvf"]);
+    #[cfg(windows)]
+    command.args(["-accel", "whpx"]);
+    command.args(["-accel", "tcg"]);
+    if headless {
+        command.args(["-display", "none"]);
+    }
+    let child = command
+        .spawn()
+        .map_err(|e| Error::new(format!("Failed to start QEMU: {e}")))?;
+    return Ok((child, port));
+}
+
+fn free_port() -> Result<u16, Error> {
+    return (SSH_PORT_BASE..SSH_PORT_BASE + 100)
+        .find(|port| TcpListener::bind(("127.0.0.1", *port)).is_ok())
+        .ok_or_else(|| Error::new("No free port to forward SSH to"));
+}
+
+fn executable() -> PathBuf {
+    return PathBuf::from("qemu-system-x86_64");
+}
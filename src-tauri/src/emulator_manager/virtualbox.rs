@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+use regex::Regex;
+
+use crate::device_manager::DeviceKind;
+use crate::emulator_manager::{Emulator, EmulatorBackend};
+use crate::error::Error;
+
+/// Lists VirtualBox VMs that look like webOS emulators, as set up by the TV SDK or the OSE
+/// emulator scripts.
+pub(super) fn list() -> Result<Vec<Emulator>, Error> {
+    let Ok(all) = vbox_manage(&["list", "vms"]) else {
+        // VirtualBox isn't installed
+        return Ok(Vec::new());
+    };
+    let running = vbox_manage(&["list", "runningvms"])?;
+    let vm_name = Regex::new(r#"^"(.+)" \{[0-9a-f-]+}$"#).unwrap();
+    let names = |output: &str| -> Vec<String> {
+        return output
+            .lines()
+            .filter_map(|l| vm_name.captures(l.trim()))
+            .map(|c| String::from(&c[1]))
+            .collect();
+    };
+    let running = names(&running);
+    let mut emulators = Vec::new();
+    for name in names(&all) {
+        if !name.to_ascii_lowercase().contains("webos") {
+            continue;
+        }
+        emulators.push(Emulator {
+            kind: kind_of(&name),
+            running: running.contains(&name),
+            ssh_port: ssh_port(&name).unwrap_or(None),
+            image: None,
+            backend: EmulatorBackend::VirtualBox,
+            name,
+        });
+    }
+    return Ok(emulators);
+}
+
+pub(super) fn start(name: &str, headless: bool) -> Result<(), Error> {
+    let kind = if headless { "headless" } else { "gui" };
+    vbox_manage(&["startvm", name, "--type", kind])?;
+    return Ok(());
+}
+
+/// Asks the VM to shut down, or powers it off with `force`.
+pub(super) fn stop(name: &str, force: bool) -> Result<(), Error> {
+    let action = if force { "poweroff" } else { "acpipowerbutton" };
+    vbox_manage(&["controlvm", name, action])?;
+    return Ok(());
+}
+
+/// The TV emulator VMs are named like "LG webOS TV Emulator 6.0.0"
+fn kind_of(name: &str) -> DeviceKind {
+    return if name.to_ascii_lowercase().contains("tv") {
+        DeviceKind::Tv
+    } else {
+        DeviceKind::Ose
+    };
+}
+
+/// Finds the NAT rule forwarding a host port to port 22 of the guest.
+fn ssh_port(name: &str) -> Result<Option<u16>, Error> {
+    let info = vbox_manage(&["showvminfo", name, "--machinereadable"])?;
+    // Like Forwarding(0)="ssh,tcp,,6622,,22"
+    let rule = Regex::new(r#"^Forwarding\(\d+\)="[^,]*,tcp,[^,]*,(\d+),[^,]*,22""#).unwrap();
+    return Ok(info
+        .lines()
+        .filter_map(|l| rule.captures(l))
+        .find_map(|c| c[1].parse::<u16>().ok()));
+}
+
+fn vbox_manage(args: &[&str]) -> Result<String, Error> {
+    let output = Command::new(executable()).args(args).output()?;
+    if !output.status.success() {
+        return Err(Error::new(format!(
+            "VBoxManage failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+}
+
+fn executable() -> PathBuf {
+    // The Windows installer doesn't add VirtualBox to PATH
+    #[cfg(windows)]
+    if let Ok(dir) = std::env::var("VBOX_MSI_INSTALL_PATH") {
+        return PathBuf::from(dir).join("VBoxManage.exe");
+    }
+    return PathBuf::from("VBoxManage");
+}
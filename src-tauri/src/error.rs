@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::error::Error as ErrorTrait;
 use std::fmt::{Debug, Display, Formatter};
 use std::io::ErrorKind;
@@ -16,6 +17,12 @@ pub enum Error {
     },
     BadPassphrase,
     BadPrivateKey,
+    Cancelled,
+    /// Risky operations need to be confirmed by calling again
+    ConfirmationRequired {
+        message: String,
+    },
+    Conflict,
     Disconnected,
     ExitStatus {
         message: String,
@@ -34,10 +41,18 @@ pub enum Error {
         message: String,
         unhandled: bool,
     },
+    HostKeyMismatch {
+        expected: String,
+        actual: String,
+    },
     PassphraseRequired,
     NotFound,
     Timeout,
     Unsupported,
+    /// Problems with device settings, by field name
+    Validation {
+        fields: BTreeMap<String, String>,
+    },
 }
 
 impl Error {
@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+
+use serde::Deserialize;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::WebSocket;
+
+use crate::session_manager::PortForward;
+
+mod socket;
+
+/// Buttons the pointer input socket accepts, as named on the remote
+pub const KEYS: [&str; 44] = [
+    "LEFT",
+    "RIGHT",
+    "UP",
+    "DOWN",
+    "ENTER",
+    "BACK",
+    "HOME",
+    "MENU",
+    "EXIT",
+    "INFO",
+    "DASH",
+    "0",
+    "1",
+    "2",
+    "3",
+    "4",
+    "5",
+    "6",
+    "7",
+    "8",
+    "9",
+    "VOLUMEUP",
+    "VOLUMEDOWN",
+    "MUTE",
+    "CHANNELUP",
+    "CHANNELDOWN",
+    "PLAY",
+    "PAUSE",
+    "STOP",
+    "REWIND",
+    "FASTFORWARD",
+    "RECORD",
+    "RED",
+    "GREEN",
+    "YELLOW",
+    "BLUE",
+    "GUIDE",
+    "LIST",
+    "ASTERISK",
+    "CC",
+    "QMENU",
+    "3D_MODE",
+    "AD",
+    "SAP",
+];
+
+/// Pointer input sockets of devices, by device name. Each keeps a port forwarded to the TV's
+/// websocket server while open.
+#[derive(Default)]
+pub struct InputManager {
+    sockets: Mutex<HashMap<String, Arc<Mutex<InputSocket>>>>,
+}
+
+struct InputSocket {
+    socket: WebSocket<MaybeTlsStream<TcpStream>>,
+    forward: PortForward,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PointerEvent {
+    Move {
+        dx: i32,
+        dy: i32,
+        /// Drag, with the button held down
+        #[serde(default)]
+        down: bool,
+    },
+    Click,
+    Scroll {
+        dx: i32,
+        dy: i32,
+    },
+}
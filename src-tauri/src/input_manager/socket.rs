@@ -0,0 +1,114 @@
+use std::sync::{Arc, Mutex};
+
+use serde::Deserialize;
+use serde_json::json;
+use tungstenite::Message;
+
+use crate::device_manager::Device;
+use crate::error::Error;
+use crate::input_manager::{InputManager, InputSocket, PointerEvent, KEYS};
+use crate::session_manager::{luna, SessionManager};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PointerInputSocket {
+    socket_path: String,
+}
+
+impl InputManager {
+    /// Presses remote button `name` on the device.
+    pub fn key(&self, sessions: &SessionManager, device: Device, name: &str) -> Result<(), Error> {
+        if !KEYS.contains(&name) {
+            return Err(Error::new(format!("Unknown key {name}")));
+        }
+        return self.send(sessions, device, &format!("type:button\nname:{name}\n\n"));
+    }
+
+    pub fn pointer(
+        &self,
+        sessions: &SessionManager,
+        device: Device,
+        event: &PointerEvent,
+    ) -> Result<(), Error> {
+        let message = match event {
+            PointerEvent::Move { dx, dy, down } => {
+                format!("type:move\ndx:{dx}\ndy:{dy}\ndown:{}\n\n", *down as u8)
+            }
+            PointerEvent::Click => String::from("type:click\n\n"),
+            PointerEvent::Scroll { dx, dy } => format!("type:scroll\ndx:{dx}\ndy:{dy}\n\n"),
+        };
+        return self.send(sessions, device, &message);
+    }
+
+    /// Closes the input socket of the device, and stops forwarding its port.
+    pub fn disconnect(&self, sessions: &SessionManager, name: &str) {
+        let Some(socket) = self.sockets.lock().unwrap().remove(name) else {
+            return;
+        };
+        let mut socket = socket.lock().unwrap();
+        socket.socket.close(None).unwrap_or(());
+        sessions.unforward(socket.forward.local_port).unwrap_or(());
+    }
+
+    /// Sends `message` through the input socket of the device, opening it first if needed. A
+    /// socket that went stale, e.g. after the TV went to standby, is reopened once.
+    fn send(&self, sessions: &SessionManager, device: Device, message: &str) -> Result<(), Error> {
+        let name = device.name.clone();
+        let socket = self.sockets.lock().unwrap().get(&name).cloned();
+        if let Some(socket) = socket {
+            let sent = socket.lock().unwrap().socket.send(Message::text(message));
+            if sent.is_ok() {
+                return Ok(());
+            }
+            self.disconnect(sessions, &name);
+        }
+        let mut socket = InputSocket::open(sessions, device)?;
+        if let Err(e) = socket.socket.send(Message::text(message)) {
+            sessions.unforward(socket.forward.local_port).unwrap_or(());
+            return Err(Error::new(format!("Failed to send input: {e}")));
+        }
+        self.sockets
+            .lock()
+            .unwrap()
+            .insert(name, Arc::new(Mutex::new(socket)));
+        return Ok(());
+    }
+}
+
+impl InputSocket {
+    /// Asks the network input service for its pointer socket, and connects to it through a
+    /// forwarded port, as it only listens for local connections.
+    fn open(sessions: &SessionManager, device: Device) -> Result<Self, Error> {
+        let response: PointerInputSocket = sessions.with_session(device.clone(), |session| {
+            return luna::call(
+                session,
+                "luna://com.webos.service.networkinput/getPointerInputSocket",
+                &json!({}),
+                !session.device.is_rooted(),
+            );
+        })?;
+        let (port, path) = parse_socket_path(&response.socket_path)
+            .ok_or_else(|| Error::new(format!("Bad input socket path {}", response.socket_path)))?;
+        let forward = sessions.forward(device, port)?;
+        let url = format!("ws://127.0.0.1:{}{path}", forward.local_port);
+        return match tungstenite::connect(url) {
+            Ok((socket, _)) => Ok(Self { socket, forward }),
+            Err(e) => {
+                sessions.unforward(forward.local_port).unwrap_or(());
+                Err(Error::new(format!(
+                    "Failed to connect to input socket: {e}"
+                )))
+            }
+        };
+    }
+}
+
+/// Splits `ws://host:port/path` into the port and the path.
+fn parse_socket_path(socket_path: &str) -> Option<(u16, &str)> {
+    let rest = socket_path
+        .strip_prefix("ws://")
+        .or_else(|| socket_path.strip_prefix("wss://"))?;
+    let (host, path) = rest.split_at(rest.find('/')?);
+    let port = host.rsplit_once(':')?.1.parse().ok()?;
+    return Some((port, path));
+}
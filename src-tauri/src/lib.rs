@@ -11,21 +11,35 @@ use tauri::{AppHandle, Manager, RunEvent, Runtime};
 use tauri::webview::PageLoadEvent;
 
 use crate::app_dirs::{GetConfDir, GetSshDir, SetConfDir, SetSshDir};
-use crate::device_manager::DeviceManager;
+use crate::conn_pool::{ConnectionCallback, ConnectionEvent, ConnectionState};
+use crate::device_manager::{
+    DeviceManager, DeviceStatus, DevicesCallback, DevicesChanged, StatusCallback,
+};
+use crate::emulator_manager::EmulatorManager;
+use crate::plugins::file::QueueRunnerImpl;
 use crate::session_manager::SessionManager;
 use crate::shell_manager::ShellManager;
 use crate::spawn_manager::SpawnManager;
+use crate::transfer_manager::TransferManager;
 
 mod app_dirs;
+mod app_manager;
 mod conn_pool;
 mod device_manager;
+mod emulator_manager;
 mod error;
 mod event_channel;
+mod input_manager;
+mod log_manager;
+mod network_manager;
 mod plugins;
 mod remote_files;
+mod screen_manager;
 mod session_manager;
 mod shell_manager;
 mod spawn_manager;
+mod system_manager;
+mod transfer_manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -47,16 +61,29 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_upload::init())
+        .plugin(tauri_plugin_notification::init())
         .plugin(plugins::device::plugin("device-manager"))
         .plugin(plugins::cmd::plugin("remote-command"))
         .plugin(plugins::shell::plugin("remote-shell"))
         .plugin(plugins::file::plugin("remote-file"))
         .plugin(plugins::devmode::plugin("dev-mode"))
         .plugin(plugins::local_file::plugin("local-file"))
+        .plugin(plugins::app::plugin("app-manager"))
+        .plugin(plugins::batch::plugin("device-batch"))
+        .plugin(plugins::emulator::plugin("emulator"))
+        .plugin(plugins::homebrew::plugin("homebrew"))
+        .plugin(plugins::screen::plugin("screen"))
+        .plugin(plugins::input::plugin("remote-input"))
+        .plugin(plugins::luna::plugin("luna"))
+        .plugin(plugins::system::plugin("system"))
+        .plugin(plugins::logs::plugin("device-logs"))
+        .plugin(plugins::network::plugin("network"))
         .manage(DeviceManager::default())
         .manage(SessionManager::default())
         .manage(SpawnManager::default())
         .manage(ShellManager::default())
+        .manage(TransferManager::default())
+        .manage(EmulatorManager::default())
         .on_page_load(|wnd, payload| {
             if payload.event() == PageLoadEvent::Started {
                 let spawns = wnd.state::<SpawnManager>();
@@ -67,6 +94,12 @@ pub fn run() {
         .and_then(|app| {
             app.run(|app, event| match event {
                 RunEvent::Ready => {
+                    app.state::<SessionManager>()
+                        .set_callback(Box::new(ConnectionEventCb { app: app.clone() }));
+                    app.state::<DeviceManager>()
+                        .set_status_callback(Box::new(DeviceStatusCb { app: app.clone() }));
+                    app.state::<DeviceManager>()
+                        .set_devices_callback(Box::new(DevicesChangedCb { app: app.clone() }));
                     if let Some(ssh_dir) = app.get_ssh_dir() {
                         app.state::<DeviceManager>().set_ssh_dir(ssh_dir.clone());
                         app.state::<SessionManager>().set_ssh_dir(ssh_dir.clone());
@@ -74,7 +107,23 @@ pub fn run() {
                     }
                     if let Some(conf_dir) = app.get_conf_dir() {
                         app.state::<DeviceManager>().set_conf_dir(conf_dir.clone());
+                        if let Err(e) = app.state::<DeviceManager>().watch_file() {
+                            log::warn!("Failed to watch devices file: {e:?}");
+                        }
+                        app.state::<TransferManager>()
+                            .queue
+                            .set_conf_dir(conf_dir.clone());
+                        app.state::<TransferManager>()
+                            .profiles
+                            .set_conf_dir(conf_dir.clone());
+                        app.state::<EmulatorManager>()
+                            .set_conf_dir(conf_dir.clone());
                     }
+                    app.state::<TransferManager>()
+                        .queue
+                        .set_runner(Box::new(QueueRunnerImpl { app: app.clone() }));
+                    tauri::async_runtime::spawn(prewarm_default_device(app.clone()));
+                    plugins::devmode::start_scheduler(app.clone());
                 }
                 _ => {}
             });
@@ -103,6 +152,77 @@ pub fn run() {
     }
 }
 
+async fn prewarm_default_device<R: Runtime>(app: AppHandle<R>) {
+    let Ok(Some(device)) = app.state::<DeviceManager>().default_device().await else {
+        return;
+    };
+    if !device.prewarm.unwrap_or(false) {
+        return;
+    }
+    log::info!("Prewarming connection to {}", device.name);
+    let result = tokio::task::spawn_blocking(move || {
+        return app.state::<SessionManager>().prewarm(device);
+    })
+    .await
+    .expect("critical failure in prewarm task");
+    if let Err(e) = result {
+        log::warn!("Failed to prewarm connection: {e:?}");
+    }
+}
+
+struct ConnectionEventCb<R: Runtime> {
+    app: AppHandle<R>,
+}
+
+impl<R: Runtime> ConnectionCallback for ConnectionEventCb<R> {
+    fn state(&self, event: ConnectionEvent) {
+        if let ConnectionState::Authenticated = event.state {
+            self.app
+                .state::<DeviceManager>()
+                .record_connected(&event.device);
+        }
+        self.app.emit("connection-state", event).unwrap_or(());
+    }
+
+    fn host_key_seen(&self, device: &str, fingerprint: &str) {
+        let app = self.app.clone();
+        let device = String::from(device);
+        let fingerprint = String::from(fingerprint);
+        tauri::async_runtime::spawn(async move {
+            match app
+                .state::<DeviceManager>()
+                .trust_host_key(&device, &fingerprint)
+                .await
+            {
+                // Pooled connections still carry the device without the key
+                Ok(true) => app.state::<SessionManager>().reset(&device),
+                Ok(false) => {}
+                Err(e) => log::warn!("Failed to pin host key of {device}: {e:?}"),
+            }
+        });
+    }
+}
+
+struct DeviceStatusCb<R: Runtime> {
+    app: AppHandle<R>,
+}
+
+impl<R: Runtime> StatusCallback for DeviceStatusCb<R> {
+    fn changed(&self, status: DeviceStatus) {
+        self.app.emit("device-status", status).unwrap_or(());
+    }
+}
+
+struct DevicesChangedCb<R: Runtime> {
+    app: AppHandle<R>,
+}
+
+impl<R: Runtime> DevicesCallback for DevicesChangedCb<R> {
+    fn changed(&self, event: DevicesChanged) {
+        self.app.emit("devices-changed", event).unwrap_or(());
+    }
+}
+
 impl<R: Runtime> GetSshDir for AppHandle<R> {
     fn get_ssh_dir(&self) -> Option<PathBuf> {
         let home: Option<PathBuf>;
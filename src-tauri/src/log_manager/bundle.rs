@@ -0,0 +1,45 @@
+use std::fs::File;
+use std::path::Path;
+
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::transfer_manager::tar_pipe::finish;
+
+/// Collects `/var/log`, including rotated PmLog archives, with the last 50000 journal lines and
+/// the kernel ring buffer exported as text, into a gzipped tarball. Links in a temporary directory
+/// on the device put it all under one root. Only files the user can read are archived, and the
+/// exit status is tar's, except that logs growing while they're read are fine.
+const BUNDLE_COMMAND: &str = "d=$(mktemp -d /tmp/webos-dev-logs.XXXXXX) || exit 1; \
+    ln -s /var/log $d/var-log; \
+    if command -v journalctl >/dev/null 2>&1; then \
+    journalctl --no-pager -o short-iso -n 50000 > $d/journal.txt 2>/dev/null; fi; \
+    dmesg > $d/dmesg.txt 2>/dev/null; \
+    (cd $d && find -L . -type f | while read -r f; do [ -r \"$f\" ] && echo \"$f\"; done) \
+    > $d.list; \
+    tar czhf - -C $d -T $d.list 2> $d.err; s=$?; \
+    [ $s = 1 ] && grep -q 'changed as we read' $d.err && s=0; \
+    rm -rf $d $d.list $d.err; exit $s";
+
+/// Saves the log bundle of the device to local file `target`, and returns its size.
+pub(crate) fn bundle(session: &DeviceConnection, target: &Path) -> Result<u64, Error> {
+    let _permit = session.acquire_channel();
+    let ch = session.new_channel()?;
+    ch.open_session()?;
+    ch.request_exec(BUNDLE_COMMAND)?;
+    let mut file = File::create(target)?;
+    let result = match std::io::copy(&mut ch.stdout(), &mut file) {
+        Ok(0) => {
+            ch.close().unwrap_or(());
+            Err(Error::new("No logs could be collected"))
+        }
+        Ok(size) => finish(ch, BUNDLE_COMMAND).map(|_| size),
+        Err(e) => {
+            ch.close().unwrap_or(());
+            Err(e.into())
+        }
+    };
+    if result.is_err() {
+        std::fs::remove_file(target).unwrap_or(());
+    }
+    return result;
+}
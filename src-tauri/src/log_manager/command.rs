@@ -0,0 +1,324 @@
+use crate::error::Error;
+use crate::log_manager::{LogFilter, LogSource};
+use crate::session_manager::command::quote;
+
+/// Filters PmLogDaemon syslog lines by the priority of their `facility.level` field, and by their
+/// program name as context. Lines without a level only pass when not filtering by context.
+const SYSLOG_FILTER: &str = r#"awk 'BEGIN {
+  split("emerg alert crit err warning notice info debug", names, " ");
+  for (n in names) prio[names[n]] = n - 1;
+  prio["warn"] = 4; prio["error"] = 3; prio["panic"] = 0;
+  max = ENVIRON["LOG_PRIORITY"]; ctx = ENVIRON["LOG_CONTEXT"];
+}
+{
+  level = "";
+  for (i = 1; i < NF; i++) if ($i ~ /^[a-z0-9]+\.[a-z]+$/) {
+    split($i, f, "."); if (f[2] in prio) { level = f[2]; break; }
+  }
+  if (level != "") {
+    if (max != "" && prio[level] > max + 0) next;
+    if (ctx != "") { s = $(i + 1); sub(/\[.*/, "", s); sub(/:.*/, "", s); if (s != ctx) next; }
+  } else if (ctx != "") next;
+  print; fflush();
+}'"#;
+
+/// Filters lines by the `LOG_INCLUDE` and `LOG_EXCLUDE` extended regular expressions. The boot
+/// time of kernel logs always passes. Journal entries are matched by their `MESSAGE` field, still
+/// JSON escaped, rather than their whole JSON.
+const REGEX_FILTER: &str = r#"awk 'BEGIN {
+  inc = ENVIRON["LOG_INCLUDE"]; exc = ENVIRON["LOG_EXCLUDE"]; boot = ENVIRON["LOG_BOOT_MARKER"];
+}
+{
+  if (boot != "" && index($0, boot) == 1) { print; fflush(); next; }
+  s = $0;
+  if (substr(s, 1, 1) == "{") {
+    m = index(s, "\"MESSAGE\":\""); s = m ? substr(s, m + 11) : "";
+    for (i = 1; i <= length(s); i++) {
+      c = substr(s, i, 1); if (c == "\\") i++; else if (c == "\"") break;
+    }
+    s = substr(s, 1, i - 1);
+  }
+  if (inc != "" && s !~ inc) next;
+  if (exc != "" && s ~ exc) next;
+  print; fflush();
+}'"#;
+
+/// Character classes of POSIX bracket expressions
+const CHARACTER_CLASSES: [&str; 12] = [
+    "alnum", "alpha", "blank", "cntrl", "digit", "graph", "lower", "print", "punct", "space",
+    "upper", "xdigit",
+];
+/// Largest repetition count POSIX guarantees, `RE_DUP_MAX`
+const MAX_REPEAT: u32 = 255;
+
+impl LogFilter {
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.priority.is_some_and(|p| p > 7) {
+            return Err(Error::new("Priority must be from 0 to 7"));
+        }
+        for pattern in [&self.include, &self.exclude].into_iter().flatten() {
+            check_ere(pattern).map_err(|e| Error::new(format!("Invalid pattern: {e}")))?;
+        }
+        return Ok(());
+    }
+
+    fn is_syslog_filtered(&self) -> bool {
+        return self.priority.is_some() || self.context.is_some();
+    }
+
+    fn is_regex_filtered(&self) -> bool {
+        return self.include.is_some() || self.exclude.is_some();
+    }
+}
+
+/// Checks that `pattern` is a POSIX extended regular expression, which is all `awk` on the device
+/// is sure to support. Perl extensions like `\d`, lazy quantifiers or lookarounds are refused.
+fn check_ere(pattern: &str) -> Result<(), String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut depth = 0usize;
+    // Whether the last element can be repeated
+    let mut atom = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' => {
+                let Some(&escaped) = chars.get(i + 1) else {
+                    return Err(String::from("Trailing backslash"));
+                };
+                if !r"\.[]()*+?{}|^$/nt".contains(escaped) {
+                    return Err(format!("\\{escaped} is not supported"));
+                }
+                i += 1;
+                atom = true;
+            }
+            '[' => {
+                i = bracket_end(&chars, i)?;
+                atom = true;
+            }
+            '(' => {
+                depth += 1;
+                atom = false;
+            }
+            ')' => {
+                if depth == 0 {
+                    return Err(String::from("Unmatched )"));
+                }
+                if chars[i - 1] == '(' {
+                    return Err(String::from("Empty group"));
+                }
+                depth -= 1;
+                atom = true;
+            }
+            '*' | '+' | '?' | '{' => {
+                if !atom {
+                    return Err(format!("Nothing to repeat before {c}"));
+                }
+                if c == '{' {
+                    i = interval_end(&chars, i)?;
+                }
+                atom = false;
+            }
+            '|' | '^' | '$' => atom = false,
+            _ => atom = true,
+        }
+        i += 1;
+    }
+    if depth > 0 {
+        return Err(String::from("Unmatched ("));
+    }
+    return Ok(());
+}
+
+/// Index of the `]` closing the bracket expression opened at `start`.
+fn bracket_end(chars: &[char], start: usize) -> Result<usize, String> {
+    let mut i = start + 1;
+    if chars.get(i) == Some(&'^') {
+        i += 1;
+    }
+    // A leading `]` is a literal
+    if chars.get(i) == Some(&']') {
+        i += 1;
+    }
+    while let Some(&c) = chars.get(i) {
+        if c == ']' {
+            return Ok(i);
+        }
+        let kind = chars.get(i + 1).copied().filter(|k| ":.=".contains(*k));
+        if let (true, Some(kind)) = (c == '[', kind) {
+            let inner = i + 2;
+            let end = (inner..chars.len().saturating_sub(1))
+                .find(|&j| chars[j] == kind && chars[j + 1] == ']')
+                .ok_or_else(|| format!("Unterminated [{kind}"))?;
+            let name: String = chars[inner..end].iter().collect();
+            if kind == ':' && !CHARACTER_CLASSES.contains(&name.as_str()) {
+                return Err(format!("Unknown character class [:{name}:]"));
+            }
+            i = end + 2;
+            continue;
+        }
+        i += 1;
+    }
+    return Err(String::from("Unmatched ["));
+}
+
+/// Index of the `}` closing the interval opened at `start`, like `{2}`, `{2,}` or `{2,5}`.
+fn interval_end(chars: &[char], start: usize) -> Result<usize, String> {
+    let end = chars[start..]
+        .iter()
+        .position(|c| *c == '}')
+        .map(|offset| start + offset)
+        .ok_or_else(|| String::from("Unmatched {"))?;
+    let interval: String = chars[start + 1..end].iter().collect();
+    let invalid = || format!("Invalid interval {{{interval}}}");
+    let bound = |text: &str| -> Result<u32, String> {
+        if text.is_empty() || !text.chars().all(|c| c.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        return text.parse().map_err(|_| invalid());
+    };
+    let (min, max) = match interval.split_once(',') {
+        Some((min, "")) => (bound(min)?, None),
+        Some((min, max)) => (bound(min)?, Some(bound(max)?)),
+        None => (bound(&interval)?, None),
+    };
+    if min.max(max.unwrap_or(0)) > MAX_REPEAT || max.is_some_and(|max| max < min) {
+        return Err(invalid());
+    }
+    return Ok(end);
+}
+
+/// Prints lines of the kernel ring buffer with their priority as they come, starting with the
+/// last `lines` lines. BusyBox `dmesg` can't follow, so the buffer is polled, keeping the
+/// timestamp of the last line printed and how many lines had it. An empty line every poll ends
+/// the loop once nobody reads the output.
+const KERNEL_COMMAND: &str = r#"last=-1; seen=0;
+while true; do out=$(dmesg -r 2>/dev/null | awk -v last="$last" -v seen="$seen" -v n="$LOG_LINES" '
+BEGIN { max = last; same = seen; }
+{
+  if (!match($0, /\[ *[0-9]+\.[0-9]+\]/)) next;
+  t = substr($0, RSTART + 1, RLENGTH - 2) + 0;
+  if (t < last || (t == last && ++k <= seen)) next;
+  l[++c] = $0;
+  if (t > max) { max = t; same = 0; }
+  same++;
+}
+END {
+  s = (n >= 0 && c > n) ? c - n : 0;
+  for (i = s + 1; i <= c; i++) print l[i];
+  printf "%.6f %d\n", max, same;
+}');
+printf '%s\n' "$out" | sed '$d' || exit; set -- $(printf '%s\n' "$out" | tail -n 1);
+last=$1; seen=$2; LOG_LINES=-1; echo || exit; sleep 1; done"#;
+
+/// Marks the line with the boot time in kernel log output
+pub(super) const BOOT_MARKER: &str = "webos-dev-boot";
+
+/// Command printing the boot time line: wall-clock time, uptime, and the monotonic clock kernel
+/// timestamps use, if `/proc/timer_list` is readable.
+fn boot_command() -> String {
+    return format!(
+        r#"echo "{BOOT_MARKER} $(date +%s) $(cut -d ' ' -f 1 /proc/uptime) \
+        $(awk '/^now at/ {{ printf "%.6f", $3 / 1e9; exit }}' /proc/timer_list 2>/dev/null)""#
+    );
+}
+
+/// Command printing the kernel ring buffer once, like [KERNEL_COMMAND] does when following.
+pub(super) fn kernel_command() -> String {
+    return format!("{}; dmesg -r", boot_command());
+}
+
+/// Command following device logs of `source`, starting with the last `lines` lines. System logs
+/// come from journald where available and `/var/log/messages` written by PmLogDaemon otherwise,
+/// with journal entries printed as JSON, one per line. Filters run on the device, with `awk`
+/// flushing every line where needed, as BusyBox tools would hold them back in their buffers.
+/// Units only apply to journald, priorities and contexts only to system logs, and patterns are
+/// matched against the message of journal entries.
+pub(super) fn follow_command(source: LogSource, lines: u32, filter: &LogFilter) -> String {
+    let mut exports: Vec<String> = Vec::new();
+    let mut command = match source {
+        LogSource::System => system_command(lines, filter, &mut exports),
+        LogSource::Kernel => {
+            exports.push(format!("LOG_BOOT_MARKER={BOOT_MARKER}"));
+            exports.push(format!("LOG_LINES={lines}"));
+            format!("{}; {KERNEL_COMMAND}", boot_command())
+        }
+    };
+    if filter.is_regex_filtered() {
+        if let Some(include) = &filter.include {
+            exports.push(format!("LOG_INCLUDE={}", quote(include)));
+        }
+        if let Some(exclude) = &filter.exclude {
+            exports.push(format!("LOG_EXCLUDE={}", quote(exclude)));
+        }
+        command = format!("{{ {command}; }} | {REGEX_FILTER}");
+    }
+    if exports.is_empty() {
+        return command;
+    }
+    return format!("export {}; {command}", exports.join(" "));
+}
+
+fn system_command(lines: u32, filter: &LogFilter, exports: &mut Vec<String>) -> String {
+    let mut journal = format!("journalctl -f -n {lines} -o json --no-pager");
+    if let Some(priority) = filter.priority {
+        journal.push_str(&format!(" -p {priority}"));
+        exports.push(format!("LOG_PRIORITY={priority}"));
+    }
+    if let Some(unit) = &filter.unit {
+        journal.push_str(&format!(" -u {}", quote(unit)));
+    }
+    if let Some(context) = &filter.context {
+        journal.push_str(&format!(
+            " {}",
+            quote(&format!("SYSLOG_IDENTIFIER={context}"))
+        ));
+        exports.push(format!("LOG_CONTEXT={}", quote(context)));
+    }
+    let mut syslog = format!("tail -n {lines} -F /var/log/messages");
+    if filter.is_syslog_filtered() {
+        syslog.push_str(&format!(" | {SYSLOG_FILTER}"));
+    }
+    return format!("if command -v journalctl >/dev/null 2>&1; then {journal}; else {syslog}; fi");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_extended_regular_expressions() {
+        for pattern in [
+            "error|fail(ed|ure)?",
+            "^[[:alpha:]_]+[0-9]{2,4}$",
+            "[]a-z]+",
+            "[^]]*",
+            r"a\.b\(c\)",
+            "x{3}y{1,}",
+        ] {
+            assert_eq!(check_ere(pattern), Ok(()), "{pattern}");
+        }
+    }
+
+    #[test]
+    fn refuses_other_syntax() {
+        for pattern in [
+            r"\d+",
+            "a+?",
+            "(?i)error",
+            "(?=x)",
+            "*a",
+            "(a",
+            "a)",
+            "()",
+            "[a-z",
+            "[[:word:]]",
+            "a{2,1}",
+            "a{x}",
+            "a{1000}",
+            "a\\",
+        ] {
+            assert!(check_ere(pattern).is_err(), "{pattern}");
+        }
+    }
+}
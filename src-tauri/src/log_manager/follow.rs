@@ -0,0 +1,209 @@
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::device_manager::Device;
+use crate::error::Error;
+use crate::log_manager::command::follow_command;
+use crate::log_manager::parse::{parse_boot, parse_kernel, parse_line};
+use crate::log_manager::{
+    LogBuffer, LogEntry, LogEvent, LogFilter, LogManager, LogSource, LogStream,
+};
+use crate::session_manager::{ProcCallback, SessionManager};
+
+/// Entries kept while waiting to be sent
+const MAX_PENDING: usize = 5000;
+/// Entries are sent in batches at most this often
+const FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+/// Connections lasting this long reset the reconnection backoff
+const STABLE_CONNECTION: Duration = Duration::from_secs(30);
+
+impl LogManager {
+    pub fn open(&self) -> Arc<LogStream> {
+        let stream = Arc::new(LogStream {
+            token: Uuid::new_v4().to_string(),
+            closed: Default::default(),
+            proc: Default::default(),
+        });
+        self.streams
+            .lock()
+            .unwrap()
+            .insert(stream.token.clone(), stream.clone());
+        return stream;
+    }
+
+    /// Stops following, and interrupts the current connection.
+    pub fn close(&self, token: &str) -> Result<(), Error> {
+        let stream = self
+            .streams
+            .lock()
+            .unwrap()
+            .remove(token)
+            .ok_or(Error::NotFound)?;
+        stream.closed.store(true, Ordering::SeqCst);
+        if let Some(proc) = stream.proc.lock().unwrap().as_ref() {
+            proc.interrupt();
+        }
+        return Ok(());
+    }
+
+    /// Follows device logs of `source` from the last `lines` lines until the stream is closed,
+    /// passing batches of entries that pass `filter` to `on_event`. Dropped connections are
+    /// retried with increasing delays, without repeating history.
+    pub fn follow<F>(
+        &self,
+        sessions: &SessionManager,
+        stream: Arc<LogStream>,
+        device: Device,
+        source: LogSource,
+        lines: u32,
+        filter: &LogFilter,
+        on_event: F,
+    ) where
+        F: Fn(LogEvent) + Send + Sync + 'static,
+    {
+        let on_event = Arc::new(on_event);
+        let buffer = Arc::new(LogBuffer::default());
+        let forwarder = {
+            let buffer = buffer.clone();
+            let on_event = on_event.clone();
+            thread::spawn(move || buffer.forward(&*on_event))
+        };
+        let mut lines = lines;
+        let mut attempt = 0;
+        while !stream.closed.load(Ordering::SeqCst) {
+            let proc =
+                Arc::new(sessions.spawn(device.clone(), &follow_command(source, lines, filter)));
+            *stream.proc.lock().unwrap() = Some(proc.clone());
+            // Closed while spawning, so the interrupt would have been missed
+            if stream.closed.load(Ordering::SeqCst) {
+                break;
+            }
+            *proc.callback.lock().unwrap() = Some(Box::new(LogCallback {
+                source,
+                boot: Mutex::default(),
+                partial: Mutex::default(),
+                buffer: buffer.clone(),
+            }));
+            let started = Instant::now();
+            proc.notify_ready();
+            let result = proc.start().and_then(|_| proc.wait_close(sessions));
+            proc.callback.lock().unwrap().take();
+            if stream.closed.load(Ordering::SeqCst) {
+                break;
+            }
+            log::info!("Log stream {} dropped with {result:?}", stream.token);
+            lines = 0;
+            attempt = match started.elapsed() >= STABLE_CONNECTION {
+                true => 1,
+                false => attempt + 1,
+            };
+            on_event(LogEvent::Reconnecting {
+                attempt,
+                error: result.err(),
+            });
+            stream.wait((RECONNECT_DELAY * attempt).min(MAX_RECONNECT_DELAY));
+        }
+        self.streams.lock().unwrap().remove(&stream.token);
+        buffer.finish();
+        forwarder.join().unwrap_or(());
+        on_event(LogEvent::Closed);
+    }
+}
+
+impl LogStream {
+    /// Sleeps for `duration`, or until the stream is closed.
+    fn wait(&self, duration: Duration) {
+        let until = Instant::now() + duration;
+        while !self.closed.load(Ordering::SeqCst) && Instant::now() < until {
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+impl LogBuffer {
+    fn push(&self, entries: Vec<LogEntry>) {
+        let mut state = self.state.lock().unwrap();
+        state.entries.extend(entries);
+        let excess = state.entries.len().saturating_sub(MAX_PENDING);
+        if excess > 0 {
+            state.entries.drain(..excess);
+            state.dropped += excess;
+        }
+        self.changed.notify_one();
+    }
+
+    fn finish(&self) {
+        self.state.lock().unwrap().finished = true;
+        self.changed.notify_one();
+    }
+
+    /// Sends pending entries in batches until finished.
+    fn forward(&self, on_event: &dyn Fn(LogEvent)) {
+        loop {
+            let (entries, dropped, finished) = {
+                let mut state = self.state.lock().unwrap();
+                while state.entries.is_empty() && state.dropped == 0 && !state.finished {
+                    state = self.changed.wait(state).unwrap();
+                }
+                let dropped = std::mem::take(&mut state.dropped);
+                (
+                    state.entries.drain(..).collect::<Vec<_>>(),
+                    dropped,
+                    state.finished,
+                )
+            };
+            if !entries.is_empty() || dropped > 0 {
+                on_event(LogEvent::Entries { entries, dropped });
+            }
+            if finished {
+                return;
+            }
+            thread::sleep(FLUSH_INTERVAL);
+        }
+    }
+}
+
+struct LogCallback {
+    source: LogSource,
+    /// Seconds since epoch, for kernel logs
+    boot: Mutex<Option<f64>>,
+    /// Incomplete last line
+    partial: Mutex<Vec<u8>>,
+    buffer: Arc<LogBuffer>,
+}
+
+impl ProcCallback for LogCallback {
+    fn rx(&self, fd: u32, data: &[u8]) {
+        if fd != 0 {
+            return;
+        }
+        let mut partial = self.partial.lock().unwrap();
+        partial.extend_from_slice(data);
+        let Some(end) = partial.iter().rposition(|b| *b == b'\n') else {
+            return;
+        };
+        let complete: Vec<u8> = partial.drain(..=end).collect();
+        let mut boot = self.boot.lock().unwrap();
+        let entries = String::from_utf8_lossy(&complete)
+            .lines()
+            .filter(|line| !line.is_empty())
+            .filter_map(|line| match self.source {
+                LogSource::System => Some(parse_line(line)),
+                LogSource::Kernel => match parse_boot(line) {
+                    Some(time) => {
+                        *boot = Some(time);
+                        None
+                    }
+                    None => Some(parse_kernel(line, *boot)),
+                },
+            })
+            .collect();
+        self.buffer.push(entries);
+    }
+}
@@ -0,0 +1,17 @@
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::log_manager::command::kernel_command;
+use crate::log_manager::parse::{parse_boot, parse_kernel};
+use crate::log_manager::LogEntry;
+
+/// Reads the kernel ring buffer, with timestamps converted to wall-clock time.
+pub(crate) fn kernel_log(session: &DeviceConnection) -> Result<Vec<LogEntry>, Error> {
+    let output = session.exec(&kernel_command(), None)?;
+    let output = String::from_utf8_lossy(&output);
+    let mut lines = output.lines();
+    let boot = lines.next().and_then(parse_boot);
+    return Ok(lines
+        .filter(|line| !line.is_empty())
+        .map(|line| parse_kernel(line, boot))
+        .collect());
+}
@@ -0,0 +1,109 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Condvar, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::session_manager::Proc;
+
+pub(crate) mod bundle;
+mod command;
+mod follow;
+pub(crate) mod kernel;
+mod parse;
+
+/// Device log streams, by token.
+#[derive(Default)]
+pub struct LogManager {
+    streams: Mutex<HashMap<String, Arc<LogStream>>>,
+}
+
+/// Follows the logs of a device, reconnecting until closed.
+pub struct LogStream {
+    pub token: String,
+    closed: AtomicBool,
+    /// Process of the current connection
+    proc: Mutex<Option<Arc<Proc>>>,
+}
+
+/// Parsed entries waiting to be sent, so a slow receiver doesn't hold back reading from the
+/// device. Once full, the oldest entries are dropped.
+#[derive(Default)]
+struct LogBuffer {
+    state: Mutex<LogBufferState>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct LogBufferState {
+    entries: VecDeque<LogEntry>,
+    dropped: usize,
+    finished: bool,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum LogSource {
+    /// Journal, or PmLogDaemon syslog file
+    #[default]
+    System,
+    /// Kernel ring buffer
+    Kernel,
+}
+
+/// Applied on the device, so filtered out lines aren't transferred at all
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct LogFilter {
+    /// Most verbose syslog priority to show, from 0 for emergencies to 7 for debug messages
+    #[serde(default)]
+    pub priority: Option<u8>,
+    /// Systemd unit, journald only
+    #[serde(default)]
+    pub unit: Option<String>,
+    /// Syslog identifier, or PmLog program name
+    #[serde(default)]
+    pub context: Option<String>,
+    /// Extended regular expression lines must match
+    #[serde(default)]
+    pub include: Option<String>,
+    /// Extended regular expression of lines to leave out
+    #[serde(default)]
+    pub exclude: Option<String>,
+}
+
+/// Line of the journal, of the PmLogDaemon syslog file, or of the kernel ring buffer
+#[derive(Serialize, Clone, Debug)]
+pub struct LogEntry {
+    /// Milliseconds since epoch, when the device reports it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    /// As printed by the device otherwise
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+    /// Syslog priority, from 0 for emergencies to 7 for debug messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u8>,
+    /// Syslog identifier, or program name, or `kernel`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    pub message: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum LogEvent {
+    Entries {
+        entries: Vec<LogEntry>,
+        /// Entries dropped since the previous batch, because they came in too fast
+        dropped: usize,
+    },
+    /// The connection dropped, and will be retried after a delay
+    Reconnecting {
+        attempt: u32,
+        error: Option<Error>,
+    },
+    Closed,
+}
@@ -0,0 +1,255 @@
+use serde::Deserialize;
+
+use crate::log_manager::command::BOOT_MARKER;
+use crate::log_manager::LogEntry;
+
+/// Syslog levels by priority
+const LEVELS: [&str; 8] = [
+    "emerg", "alert", "crit", "err", "warning", "notice", "info", "debug",
+];
+
+/// Fields of `journalctl -o json` output, which are all strings. Fields with binary data are
+/// arrays of bytes instead, and left out.
+#[derive(Deserialize)]
+struct JournalEntry {
+    #[serde(rename = "__REALTIME_TIMESTAMP")]
+    realtime: Option<String>,
+    #[serde(rename = "PRIORITY")]
+    priority: Option<String>,
+    #[serde(rename = "SYSLOG_IDENTIFIER")]
+    identifier: Option<String>,
+    #[serde(rename = "_COMM")]
+    comm: Option<String>,
+    #[serde(rename = "_PID")]
+    pid: Option<String>,
+    #[serde(rename = "MESSAGE", default, deserialize_with = "string_or_none")]
+    message: Option<String>,
+}
+
+/// Parses a line of `journalctl -o json`, or of the syslog file otherwise.
+pub(super) fn parse_line(line: &str) -> LogEntry {
+    if line.starts_with('{') {
+        if let Ok(entry) = serde_json::from_str::<JournalEntry>(line) {
+            return LogEntry {
+                timestamp: entry
+                    .realtime
+                    .and_then(|us| us.parse::<u64>().ok())
+                    .map(|us| us / 1000),
+                time: None,
+                priority: entry.priority.and_then(|p| p.parse().ok()),
+                source: entry.identifier.or(entry.comm),
+                pid: entry.pid.and_then(|pid| pid.parse().ok()),
+                message: entry.message.unwrap_or_default(),
+            };
+        }
+    }
+    return parse_syslog(line);
+}
+
+/// Parses a syslog line like `2024-01-01T00:00:00.000000Z [12] user.info sam[123]: message`.
+/// What comes before `facility.level` is taken as the time. Lines without one are kept whole as
+/// the message.
+fn parse_syslog(line: &str) -> LogEntry {
+    let mut entry = LogEntry {
+        timestamp: None,
+        time: None,
+        priority: None,
+        source: None,
+        pid: None,
+        message: String::from(line),
+    };
+    let mut rest = line;
+    let mut time_end = 0;
+    while let Some(token) = next_token(rest) {
+        let offset = token.as_ptr() as usize - line.as_ptr() as usize;
+        rest = &line[offset + token.len()..];
+        let Some(priority) = parse_level(token) else {
+            time_end = offset + token.len();
+            continue;
+        };
+        entry.priority = Some(priority);
+        entry.time = Some(String::from(line[..time_end].trim())).filter(|t| !t.is_empty());
+        let Some(source) = next_token(rest) else {
+            entry.message = String::new();
+            return entry;
+        };
+        let source_end = source.as_ptr() as usize - line.as_ptr() as usize + source.len();
+        let source = source.trim_end_matches(':');
+        match source.split_once('[') {
+            Some((name, pid)) => {
+                entry.source = Some(String::from(name));
+                entry.pid = pid.trim_end_matches(']').parse().ok();
+            }
+            None => entry.source = Some(String::from(source)),
+        }
+        entry.message = String::from(line[source_end..].trim_start());
+        return entry;
+    }
+    return entry;
+}
+
+fn next_token(text: &str) -> Option<&str> {
+    return text.split_whitespace().next();
+}
+
+/// Priority of a `facility.level` token.
+fn parse_level(token: &str) -> Option<u8> {
+    let (facility, level) = token.split_once('.')?;
+    if facility.is_empty()
+        || !facility
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+    {
+        return None;
+    }
+    let level = match level {
+        "warn" => "warning",
+        "error" => "err",
+        "panic" => "emerg",
+        level => level,
+    };
+    return LEVELS
+        .iter()
+        .position(|l| *l == level)
+        .map(|priority| priority as u8);
+}
+
+fn string_or_none<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    return Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(s) => Some(s),
+        _ => None,
+    });
+}
+
+/// Parses the boot time line printed before kernel logs, into seconds since epoch. Kernel
+/// timestamps stop while suspended, so like `dmesg -T`, the boot time is taken back from the
+/// monotonic clock, which does too. The uptime, which doesn't, is only used without it.
+pub(super) fn parse_boot(line: &str) -> Option<f64> {
+    let mut fields = line.strip_prefix(BOOT_MARKER)?.split_whitespace();
+    let now: f64 = fields.next()?.parse().ok()?;
+    let uptime: f64 = fields.next()?.parse().ok()?;
+    let monotonic: Option<f64> = fields.next().and_then(|clock| clock.parse().ok());
+    return Some(now - monotonic.unwrap_or(uptime));
+}
+
+/// Parses a line of `dmesg -r` like `<6>[   12.345678] message`. With the `boot` time, the
+/// timestamp is converted to wall-clock time.
+pub(super) fn parse_kernel(line: &str, boot: Option<f64>) -> LogEntry {
+    let mut entry = LogEntry {
+        timestamp: None,
+        time: None,
+        priority: None,
+        source: Some(String::from("kernel")),
+        pid: None,
+        message: String::from(line),
+    };
+    let mut rest = line;
+    if let Some((priority, after)) = rest.strip_prefix('<').and_then(|r| r.split_once('>')) {
+        if let Ok(priority) = priority.parse::<u32>() {
+            // Facility is in the upper bits
+            entry.priority = Some((priority & 7) as u8);
+            rest = after;
+        }
+    }
+    if let Some((secs, after)) = rest.strip_prefix('[').and_then(|r| r.split_once(']')) {
+        if let Ok(secs) = secs.trim().parse::<f64>() {
+            match boot {
+                Some(boot) => entry.timestamp = Some(((boot + secs) * 1000.0) as u64),
+                None => entry.time = Some(secs.to_string()),
+            }
+            rest = after;
+        }
+    }
+    entry.message = String::from(rest.strip_prefix(' ').unwrap_or(rest));
+    return entry;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_syslog_line() {
+        let entry =
+            parse_syslog("2024-01-01T00:00:00.000000Z [12] user.info sam[123]: launched app");
+        assert_eq!(
+            entry.time.as_deref(),
+            Some("2024-01-01T00:00:00.000000Z [12]")
+        );
+        assert_eq!(entry.priority, Some(6));
+        assert_eq!(entry.source.as_deref(), Some("sam"));
+        assert_eq!(entry.pid, Some(123));
+        assert_eq!(entry.message, "launched app");
+    }
+
+    #[test]
+    fn keeps_syslog_line_without_level() {
+        let entry = parse_syslog("--- restarted ---");
+        assert_eq!(entry.priority, None);
+        assert_eq!(entry.source, None);
+        assert_eq!(entry.message, "--- restarted ---");
+    }
+
+    #[test]
+    fn parses_levels() {
+        assert_eq!(parse_level("kern.warn"), Some(4));
+        assert_eq!(parse_level("local0.error"), Some(3));
+        assert_eq!(parse_level("user.panic"), Some(0));
+        assert_eq!(parse_level("daemon.debug"), Some(7));
+        assert_eq!(parse_level("User.info"), None);
+        assert_eq!(parse_level(".info"), None);
+        assert_eq!(parse_level("user.verbose"), None);
+        assert_eq!(parse_level("00:00:00.000"), None);
+    }
+
+    #[test]
+    fn parses_kernel_line() {
+        let entry = parse_kernel("<6>[   12.500000] usb 1-1: new device", Some(1000.0));
+        assert_eq!(entry.priority, Some(6));
+        assert_eq!(entry.timestamp, Some(1_012_500));
+        assert_eq!(entry.time, None);
+        assert_eq!(entry.message, "usb 1-1: new device");
+        // Facility is dropped from the priority
+        let entry = parse_kernel("<14>[12.5] message", None);
+        assert_eq!(entry.priority, Some(6));
+        assert_eq!(entry.time.as_deref(), Some("12.5"));
+        assert_eq!(entry.message, "message");
+    }
+
+    #[test]
+    fn parses_boot_time() {
+        assert_eq!(
+            parse_boot("webos-dev-boot 1700000100 100.5 90.500000"),
+            Some(1_700_000_009.5)
+        );
+        assert_eq!(
+            parse_boot("webos-dev-boot 1700000100 100.5 "),
+            Some(1_699_999_999.5)
+        );
+        assert_eq!(parse_boot("<6>[1.0] webos-dev-boot"), None);
+    }
+
+    #[test]
+    fn keeps_kernel_line_without_prefix() {
+        let entry = parse_kernel("continued line", Some(1000.0));
+        assert_eq!(entry.priority, None);
+        assert_eq!(entry.timestamp, None);
+        assert_eq!(entry.message, "continued line");
+    }
+
+    #[test]
+    fn parses_journal_line() {
+        let entry = parse_line(concat!(
+            r#"{"__REALTIME_TIMESTAMP":"1700000000123456","PRIORITY":"3","#,
+            r#""SYSLOG_IDENTIFIER":"sam","_PID":"42","MESSAGE":[1,2]}"#
+        ));
+        assert_eq!(entry.timestamp, Some(1_700_000_000_123));
+        assert_eq!(entry.priority, Some(3));
+        assert_eq!(entry.source.as_deref(), Some("sam"));
+        assert_eq!(entry.pid, Some(42));
+        assert_eq!(entry.message, "");
+    }
+}
@@ -0,0 +1,80 @@
+use crate::error::Error;
+use crate::session_manager::command::quote;
+
+/// Hops `traceroute` probes by default
+pub const DEFAULT_MAX_HOPS: u32 = 30;
+
+/// Seconds to wait for each reply
+const REPLY_TIMEOUT_SECS: u32 = 2;
+
+/// Command pinging `target` `count` times, or until stopped without `count`.
+pub(crate) fn ping_command(target: &str, count: Option<u32>) -> Result<String, Error> {
+    validate_target(target)?;
+    let count = match count {
+        Some(0) => return Err(Error::new("Count must be positive")),
+        Some(count) => format!(" -c {count}"),
+        None => String::new(),
+    };
+    return Ok(format!(
+        "{}ping{count} -W {REPLY_TIMEOUT_SECS} {} 2>&1",
+        require("ping"),
+        quote(target)
+    ));
+}
+
+/// Command tracing the route to `target` over at most `max_hops` hops, without resolving the
+/// addresses of hops, which would take longer than the trace itself on networks without reverse
+/// DNS.
+pub(crate) fn traceroute_command(target: &str, max_hops: u32) -> Result<String, Error> {
+    validate_target(target)?;
+    if !(1..=255).contains(&max_hops) {
+        return Err(Error::new("Hops must be from 1 to 255"));
+    }
+    return Ok(format!(
+        "{}traceroute -n -m {max_hops} -w {REPLY_TIMEOUT_SECS} {} 2>&1",
+        require("traceroute"),
+        quote(target)
+    ));
+}
+
+/// Command resolving `name` with `server`, or the resolver of the device by default. Devices
+/// without `nslookup` resolve with `getent`, which can't query other servers.
+pub(crate) fn lookup_command(name: &str, server: Option<&str>) -> Result<String, Error> {
+    validate_target(name)?;
+    let server = match server {
+        Some(server) => {
+            validate_target(server)?;
+            format!(" {}", quote(server))
+        }
+        None => String::new(),
+    };
+    let name = quote(name);
+    let fallback = match server.is_empty() {
+        true => format!("getent ahosts {name}"),
+        false => String::from("echo 'nslookup is not available' >&2; exit 127"),
+    };
+    return Ok(format!(
+        "if command -v nslookup >/dev/null 2>&1; then nslookup {name}{server} 2>&1; \
+         else {fallback}; fi"
+    ));
+}
+
+/// Host names and addresses only, so targets can't pass options to the tools.
+fn validate_target(target: &str) -> Result<(), Error> {
+    let valid = !target.is_empty()
+        && !target.starts_with('-')
+        && target
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | ':' | '-' | '_' | '%'));
+    if !valid {
+        return Err(Error::new(format!("Invalid host {target}")));
+    }
+    return Ok(());
+}
+
+/// Fails with a readable message when `tool` isn't available on the device.
+fn require(tool: &str) -> String {
+    return format!(
+        "command -v {tool} >/dev/null 2>&1 || {{ echo '{tool} is not available' >&2; exit 127; }}; "
+    );
+}
@@ -0,0 +1,56 @@
+use serde::Serialize;
+
+use crate::system_manager::NetworkAddress;
+
+pub(crate) mod diagnostics;
+pub(crate) mod status;
+
+/// Network configuration and connectivity of the device
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkStatus {
+    pub interfaces: Vec<NetworkInterface>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_route: Option<DefaultRoute>,
+    pub dns_servers: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wifi: Option<WifiStatus>,
+    /// Whether the connection manager of the device sees the internet
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub internet: Option<bool>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct NetworkInterface {
+    pub name: String,
+    /// Operational state, like `UP` or `DOWN`
+    pub state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mac: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mtu: Option<u32>,
+    pub wireless: bool,
+    pub addresses: Vec<NetworkAddress>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DefaultRoute {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gateway: Option<String>,
+    pub interface: String,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct WifiStatus {
+    pub interface: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ssid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bssid: Option<String>,
+    /// In MHz
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency: Option<u32>,
+    /// In dBm
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signal: Option<i32>,
+}
@@ -0,0 +1,223 @@
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::network_manager::{DefaultRoute, NetworkInterface, NetworkStatus, WifiStatus};
+use crate::session_manager::luna;
+use crate::system_manager::info::parse_addresses;
+
+const SECTION: &str = "--- webos-dev-section ---";
+
+#[derive(Deserialize, Default)]
+struct ConnectionStatus {
+    #[serde(rename = "isInternetConnectionAvailable")]
+    internet: Option<bool>,
+    wired: Option<Connection>,
+    wifi: Option<Connection>,
+}
+
+#[derive(Deserialize, Default)]
+struct Connection {
+    state: Option<String>,
+    ssid: Option<String>,
+    dns1: Option<String>,
+    dns2: Option<String>,
+    dns3: Option<String>,
+}
+
+/// Collects interfaces, the default route, DNS servers and Wi-Fi link of the device with `ip` and
+/// `iw`, completed by the connection manager where the device has one. The connection manager
+/// runs its own DNS proxy on some devices, so its servers come before those of `resolv.conf`.
+pub(crate) fn network_status(session: &DeviceConnection) -> Result<NetworkStatus, Error> {
+    let command = [
+        "ip -o link show 2>/dev/null",
+        "ip -o addr show 2>/dev/null",
+        "ip route show default 2>/dev/null",
+        "cat /etc/resolv.conf 2>/dev/null",
+        "for d in /sys/class/net/*/wireless; do [ -d $d ] && basename ${d%/*}; done",
+        "for d in /sys/class/net/*/wireless; do iw dev $(basename ${d%/*}) link; done 2>/dev/null",
+        "cat /proc/net/wireless 2>/dev/null; true",
+    ]
+    .join(&format!("; echo '{SECTION}'; "));
+    let output = session.exec(&command, None)?;
+    let output = String::from_utf8_lossy(&output);
+    let sections: Vec<&str> = output.split(&format!("{SECTION}\n")).collect();
+    let section = |index: usize| sections.get(index).copied().unwrap_or("");
+    let wireless: Vec<&str> = section(4).split_whitespace().collect();
+    let mut addresses = parse_addresses(section(1));
+    let interfaces = parse_links(section(0))
+        .into_iter()
+        .map(|mut interface| {
+            interface.wireless = wireless.contains(&interface.name.as_str());
+            (interface.addresses, addresses) = addresses
+                .drain(..)
+                .partition(|address| address.interface == interface.name);
+            return interface;
+        })
+        .collect();
+    let mut wifi = parse_iw_link(section(5));
+    if let Some(wifi) = &mut wifi {
+        if wifi.signal.is_none() {
+            wifi.signal = parse_proc_wireless(section(6), &wifi.interface);
+        }
+    }
+    let mut dns_servers: Vec<String> = Vec::new();
+    let status = match luna::call::<ConnectionStatus>(
+        session,
+        "luna://com.palm.connectionmanager/getStatus",
+        &json!({}),
+        true,
+    ) {
+        Ok(status) => status,
+        Err(e) => {
+            log::debug!("Connection manager is unavailable: {e:?}");
+            ConnectionStatus::default()
+        }
+    };
+    for connection in [&status.wired, &status.wifi].into_iter().flatten() {
+        if connection.state.as_deref() != Some("connected") {
+            continue;
+        }
+        let servers = [&connection.dns1, &connection.dns2, &connection.dns3];
+        dns_servers.extend(servers.into_iter().flatten().cloned());
+    }
+    for dns in parse_resolv_conf(section(3)) {
+        if !dns_servers.contains(&dns) {
+            dns_servers.push(dns);
+        }
+    }
+    // iw is missing on most TVs, the connection manager knows the SSID
+    if let Some(ssid) = status.wifi.as_ref().and_then(|wifi| wifi.ssid.clone()) {
+        match &mut wifi {
+            Some(wifi) => {
+                wifi.ssid.get_or_insert(ssid);
+            }
+            None => {
+                let interface = wireless.first().copied().unwrap_or_default();
+                wifi = Some(WifiStatus {
+                    interface: String::from(interface),
+                    ssid: Some(ssid),
+                    signal: parse_proc_wireless(section(6), interface),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+    return Ok(NetworkStatus {
+        interfaces,
+        default_route: parse_default_route(section(2)),
+        dns_servers,
+        wifi,
+        internet: status.internet,
+    });
+}
+
+/// Parses `ip -o link`, one interface per line like
+/// `2: eth0: <BROADCAST,UP> mtu 1500 state UP \    link/ether 00:11:22:33:44:55`.
+fn parse_links(links: &str) -> Vec<NetworkInterface> {
+    return links
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace().skip(1);
+            let name = fields.next()?.trim_end_matches(':');
+            let name = name.split('@').next().unwrap_or(name);
+            let mut interface = NetworkInterface {
+                name: String::from(name),
+                state: String::from("UNKNOWN"),
+                mac: None,
+                mtu: None,
+                wireless: false,
+                addresses: Vec::new(),
+            };
+            while let Some(field) = fields.next() {
+                match field {
+                    "mtu" => interface.mtu = fields.next().and_then(|mtu| mtu.parse().ok()),
+                    "state" => interface.state = String::from(fields.next()?),
+                    "link/ether" => interface.mac = fields.next().map(String::from),
+                    _ => {}
+                }
+            }
+            return Some(interface);
+        })
+        .collect();
+}
+
+/// Parses `ip route show default`, like `default via 192.168.0.1 dev wlan0 proto dhcp`.
+fn parse_default_route(routes: &str) -> Option<DefaultRoute> {
+    let line = routes.lines().find(|line| line.starts_with("default"))?;
+    let mut gateway = None;
+    let mut interface = None;
+    let mut fields = line.split_whitespace();
+    while let Some(field) = fields.next() {
+        match field {
+            "via" => gateway = fields.next().map(String::from),
+            "dev" => interface = fields.next().map(String::from),
+            _ => {}
+        }
+    }
+    return Some(DefaultRoute {
+        gateway,
+        interface: interface?,
+    });
+}
+
+fn parse_resolv_conf(resolv_conf: &str) -> Vec<String> {
+    return resolv_conf
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            if fields.next()? != "nameserver" {
+                return None;
+            }
+            return fields.next().map(String::from);
+        })
+        .collect();
+}
+
+/// Parses `iw dev <interface> link` of the first connected interface, like
+/// `Connected to 00:11:22:33:44:55 (on wlan0)` followed by `SSID`, `freq` and `signal` lines.
+fn parse_iw_link(link: &str) -> Option<WifiStatus> {
+    let mut lines = link
+        .lines()
+        .skip_while(|line| !line.starts_with("Connected to"));
+    let mut fields = lines.next()?.split_whitespace().skip(2);
+    let bssid = fields.next().map(String::from);
+    let interface = fields.nth(1)?.trim_end_matches(')');
+    let mut wifi = WifiStatus {
+        interface: String::from(interface),
+        bssid,
+        ..Default::default()
+    };
+    for line in lines.take_while(|line| !line.starts_with("Connected to")) {
+        let Some((key, value)) = line.trim().split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key {
+            "SSID" => wifi.ssid = Some(String::from(value)),
+            "freq" => wifi.frequency = value.split('.').next().and_then(|f| f.parse().ok()),
+            "signal" => wifi.signal = value.split_whitespace().next().and_then(|s| s.parse().ok()),
+            _ => {}
+        }
+    }
+    return Some(wifi);
+}
+
+/// Reads the signal level of `interface` in dBm from `/proc/net/wireless`, where it's the fourth
+/// field, like `wlan0: 0000   60.  -50.  -256`.
+fn parse_proc_wireless(wireless: &str, interface: &str) -> Option<i32> {
+    return wireless.lines().find_map(|line| {
+        let (name, values) = line.split_once(':')?;
+        if name.trim() != interface {
+            return None;
+        }
+        let level: f64 = values
+            .split_whitespace()
+            .nth(2)?
+            .trim_end_matches('.')
+            .parse()
+            .ok()?;
+        return Some(level as i32);
+    });
+}
@@ -0,0 +1,329 @@
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use tauri::ipc::Channel;
+use tauri::plugin::{Builder, TauriPlugin};
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::app_dirs::GetConfDir;
+use crate::app_manager::debug;
+use crate::app_manager::inspect::compare_versions;
+use crate::app_manager::install::install_file_with_progress;
+use crate::app_manager::{
+    backup as app_backup, details as app_details, inspect as ipk_inspect, ipk, lifecycle,
+    list as apps, logs as app_logs, package as app_package,
+};
+use crate::app_manager::{
+    AppBackup, AppDetails, InstalledApp, IpkInfo, NodeInspector, WebInspector,
+};
+use crate::device_manager::{ActivityKind, Device, DeviceManager};
+use crate::error::Error;
+use crate::plugins::cmd::spawn_proc;
+use crate::session_manager::SessionManager;
+use crate::transfer_manager::TransferManager;
+
+/// Lists apps installed on the device. Without root, only apps installed in developer mode are
+/// listed.
+#[tauri::command]
+async fn list<R: Runtime>(app: AppHandle<R>, device: Device) -> Result<Vec<InstalledApp>, Error> {
+    return app
+        .state::<SessionManager>()
+        .run(device, |session| apps::list(session))
+        .await;
+}
+
+/// Reads `appinfo.json` of app `id`. With `icon`, the app icon is included as a data URL.
+#[tauri::command]
+async fn details<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    id: String,
+    icon: Option<bool>,
+) -> Result<AppDetails, Error> {
+    let icon = icon.unwrap_or(false);
+    return app
+        .state::<SessionManager>()
+        .run(device, move |session| {
+            app_details::details(session, &id, icon)
+        })
+        .await;
+}
+
+/// Launches app `id` with optional launch `params`, which must be a JSON object.
+#[tauri::command]
+async fn launch<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    id: String,
+    params: Option<Value>,
+) -> Result<(), Error> {
+    return app
+        .state::<SessionManager>()
+        .run(device, move |session| {
+            lifecycle::launch(session, &id, params.clone())
+        })
+        .await;
+}
+
+/// Closes app `id` if it's running.
+#[tauri::command]
+async fn close<R: Runtime>(app: AppHandle<R>, device: Device, id: String) -> Result<(), Error> {
+    return app
+        .state::<SessionManager>()
+        .run(device, move |session| lifecycle::close(session, &id))
+        .await;
+}
+
+/// Reads what's inside local IPK file `package`. With `device`, the version already installed on it
+/// is included, and whether installing would be a downgrade.
+#[tauri::command]
+async fn inspect<R: Runtime>(
+    app: AppHandle<R>,
+    package: String,
+    device: Option<Device>,
+) -> Result<IpkInfo, Error> {
+    let mut info = tokio::task::spawn_blocking(move || ipk_inspect::inspect(Path::new(&package)))
+        .await
+        .expect("critical failure in app::inspect task")?;
+    let Some(device) = device else {
+        return Ok(info);
+    };
+    let installed = match app
+        .state::<SessionManager>()
+        .run(device, |session| apps::list(session))
+        .await
+    {
+        Ok(installed) => installed,
+        Err(Error::Unsupported) => return Ok(info),
+        Err(e) => return Err(e),
+    };
+    info.installed_version = installed
+        .into_iter()
+        .find(|app| app.id == info.package)
+        .and_then(|app| app.version);
+    if let Some(installed) = info.installed_version.as_deref() {
+        info.downgrade = compare_versions(&info.version, installed) == Ordering::Less;
+    }
+    return Ok(info);
+}
+
+/// Relaunches app `id`, forwards the web inspector port of the device to this machine, and returns
+/// the DevTools URL for the app. Use `unforward` of `remote-command` to stop the forward.
+#[tauri::command]
+async fn web_inspect<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    id: String,
+) -> Result<WebInspector, Error> {
+    let sessions = app.state::<SessionManager>();
+    let launch_id = id.clone();
+    let port = sessions
+        .run(device.clone(), move |session| {
+            debug::debug_app(session, &launch_id)
+        })
+        .await?;
+    let forward = sessions.forward(device, port)?;
+    return debug::web_inspector(forward, &id).await;
+}
+
+/// Restarts JS service `id` with the Node inspector enabled, forwards the inspector port to this
+/// machine, and returns the target for `chrome://inspect`. Use `unforward` of `remote-command` to
+/// stop the forward.
+#[tauri::command]
+async fn debug_service<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    id: String,
+) -> Result<NodeInspector, Error> {
+    let sessions = app.state::<SessionManager>();
+    let port = sessions
+        .run(device.clone(), move |session| {
+            debug::debug_service(session, &id)
+        })
+        .await?;
+    let forward = sessions.forward(device, port)?;
+    return debug::node_inspector(forward).await;
+}
+
+/// Installs local IPK file `package`. Upload progress is sent to `on_progress` like for file
+/// transfers, then each [InstallStatus](crate::app_manager::InstallStatus) to `on_status`.
+/// Failures carry the installer's reason. Fails with `ConfirmationRequired` unless `confirmed`
+/// when the developer mount is low on space.
+#[tauri::command]
+async fn install<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    package: String,
+    transfer_id: Option<String>,
+    confirmed: Option<bool>,
+    on_progress: Channel,
+    on_status: Channel,
+) -> Result<(), Error> {
+    return tokio::task::spawn_blocking(move || {
+        let sessions = app.state::<SessionManager>();
+        let transfers = app.state::<TransferManager>();
+        let transfer = transfers.begin(transfer_id);
+        app.state::<DeviceManager>()
+            .record_activity(&device.name, ActivityKind::Transfer);
+        let source = Path::new(&package);
+        let confirmed = confirmed.unwrap_or(false);
+        let result = sessions.with_session(device, |session| {
+            transfer.restart();
+            return install_file_with_progress(
+                session,
+                source,
+                &transfer,
+                &on_progress,
+                confirmed,
+                |s| {
+                    return on_status
+                        .send(s)
+                        .map_err(|e| Error::new(format!("Failed to send status: {e}")));
+                },
+            );
+        });
+        transfers.finish(&transfer);
+        return result;
+    })
+    .await
+    .expect("critical failure in app::install task");
+}
+
+/// Builds IPK file `target` from the app in local directory `app_dir`, and services in
+/// `service_dirs`, and returns what's inside it.
+#[tauri::command]
+async fn package(
+    app_dir: String,
+    service_dirs: Option<Vec<String>>,
+    target: String,
+) -> Result<IpkInfo, Error> {
+    return tokio::task::spawn_blocking(move || {
+        let service_dirs = service_dirs.unwrap_or_default();
+        let service_dirs: Vec<&Path> = service_dirs.iter().map(Path::new).collect();
+        let target = Path::new(&target);
+        app_package::package(Path::new(&app_dir), &service_dirs, target)?;
+        return ipk_inspect::inspect(target);
+    })
+    .await
+    .expect("critical failure in app::package task");
+}
+
+/// Repackages app `id`, installed in developer mode, into IPK file `target` on this machine.
+#[tauri::command]
+async fn repackage<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    id: String,
+    target: String,
+    transfer_id: Option<String>,
+    on_progress: Channel,
+) -> Result<(), Error> {
+    return tokio::task::spawn_blocking(move || {
+        let sessions = app.state::<SessionManager>();
+        let transfers = app.state::<TransferManager>();
+        let transfer = transfers.begin(transfer_id);
+        let target = Path::new(&target);
+        let result = sessions.with_session(device, |session| {
+            transfer.restart();
+            return ipk::extract(session, &transfer, &id, target, &on_progress);
+        });
+        transfers.finish(&transfer);
+        return result;
+    })
+    .await
+    .expect("critical failure in app::repackage task");
+}
+
+/// Follows device logs, sending lines mentioning app or service `id` to `on_data`. Like `spawn` of
+/// `remote-command`, returns the token of the process for stopping it.
+#[tauri::command]
+async fn logs<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    id: String,
+    on_data: Channel,
+) -> Result<String, Error> {
+    let command = app_logs::follow_command(&id)?;
+    let sessions = app.state::<SessionManager>();
+    return Ok(spawn_proc(
+        app.clone(),
+        &sessions,
+        device,
+        &command,
+        true,
+        Some(on_data),
+    ));
+}
+
+/// Closes app `id` and archives its data on the device into a new backup. `paths` replaces the
+/// default data locations.
+#[tauri::command]
+async fn backup<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    id: String,
+    paths: Option<Vec<String>>,
+) -> Result<AppBackup, Error> {
+    let dir = backups_dir(&app)?;
+    return app
+        .state::<SessionManager>()
+        .run(device, move |session| {
+            app_backup::backup(session, &id, paths.as_deref(), &dir)
+        })
+        .await;
+}
+
+/// Closes the app of backup file `path`, one of those listed by `backups`, and restores its data on
+/// the device.
+#[tauri::command]
+async fn restore<R: Runtime>(app: AppHandle<R>, device: Device, path: String) -> Result<(), Error> {
+    let dir = backups_dir(&app)?;
+    return app
+        .state::<SessionManager>()
+        .run(device, move |session| {
+            app_backup::restore(session, Path::new(&path), &dir)
+        })
+        .await;
+}
+
+/// Lists backups, of app `id` if given, newest first.
+#[tauri::command]
+async fn backups<R: Runtime>(
+    app: AppHandle<R>,
+    id: Option<String>,
+) -> Result<Vec<AppBackup>, Error> {
+    let dir = backups_dir(&app)?;
+    return tokio::task::spawn_blocking(move || app_backup::backups(&dir, id.as_deref()))
+        .await
+        .expect("critical failure in app::backups task");
+}
+
+fn backups_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, Error> {
+    return app
+        .get_conf_dir()
+        .map(|dir| dir.join("app-backups"))
+        .ok_or_else(Error::bad_config);
+}
+
+/// Initializes the plugin.
+pub fn plugin<R: Runtime>(name: &'static str) -> TauriPlugin<R> {
+    Builder::new(name)
+        .invoke_handler(tauri::generate_handler![
+            list,
+            details,
+            launch,
+            close,
+            inspect,
+            install,
+            package,
+            repackage,
+            web_inspect,
+            debug_service,
+            backup,
+            restore,
+            backups,
+            logs
+        ])
+        .build()
+}
@@ -0,0 +1,106 @@
+use std::path::Path;
+
+use tauri::ipc::Channel;
+use tauri::plugin::{Builder, TauriPlugin};
+use tauri::{AppHandle, Manager, Runtime, State};
+
+use crate::app_manager::install::install_file;
+use crate::app_manager::DeviceInstallStatus;
+use crate::device_manager::{DeviceManager, DeviceSelector};
+use crate::error::Error;
+use crate::session_manager::{BatchResult, SessionManager};
+
+/// Commands mostly wait for the devices, so more can run at once than transfers
+const DEFAULT_EXEC_PARALLELISM: usize = 8;
+/// Uploading and installing is mostly bound by the network, so few at a time is enough
+const DEFAULT_PUSH_PARALLELISM: usize = 4;
+const DEFAULT_INSTALL_PARALLELISM: usize = 4;
+
+/// Runs the command on every selected device, on at most `parallel` of them at once, returning
+/// stdout of each.
+#[tauri::command]
+async fn exec<R: Runtime>(
+    app: AppHandle<R>,
+    manager: State<'_, DeviceManager>,
+    target: DeviceSelector,
+    command: String,
+    stdin: Option<Vec<u8>>,
+    parallel: Option<usize>,
+) -> Result<Vec<BatchResult<Vec<u8>>>, Error> {
+    let devices = manager.select(&target).await?;
+    let parallel = parallel.unwrap_or(DEFAULT_EXEC_PARALLELISM);
+    return Ok(tokio::task::spawn_blocking(move || {
+        let sessions = app.state::<SessionManager>();
+        return sessions.batch_limited(devices, parallel, |session| {
+            let command = session.device.env_command(&command);
+            return session.exec(&command, stdin.as_deref());
+        });
+    })
+    .await
+    .expect("critical failure in batch::exec task"));
+}
+
+/// Uploads local file `source` to `path` on every selected device, on at most `parallel` of them
+/// at once.
+#[tauri::command]
+async fn push<R: Runtime>(
+    app: AppHandle<R>,
+    manager: State<'_, DeviceManager>,
+    target: DeviceSelector,
+    source: String,
+    path: String,
+    parallel: Option<usize>,
+) -> Result<Vec<BatchResult<()>>, Error> {
+    let devices = manager.select(&target).await?;
+    let parallel = parallel.unwrap_or(DEFAULT_PUSH_PARALLELISM);
+    return Ok(tokio::task::spawn_blocking(move || {
+        let sessions = app.state::<SessionManager>();
+        return sessions.batch_limited(devices, parallel, |session| {
+            return session.push_file(Path::new(&source), &path);
+        });
+    })
+    .await
+    .expect("critical failure in batch::push task"));
+}
+
+/// Installs local IPK file `package` on every selected device, on at most `parallel` of them at
+/// once. Each change of a device's installation status is sent to `on_status`, as
+/// [DeviceInstallStatus]. Devices low on space for developer apps fail with
+/// `ConfirmationRequired` unless `confirmed`.
+#[tauri::command]
+async fn install<R: Runtime>(
+    app: AppHandle<R>,
+    manager: State<'_, DeviceManager>,
+    target: DeviceSelector,
+    package: String,
+    parallel: Option<usize>,
+    confirmed: Option<bool>,
+    on_status: Channel,
+) -> Result<Vec<BatchResult<()>>, Error> {
+    let devices = manager.select(&target).await?;
+    let parallel = parallel.unwrap_or(DEFAULT_INSTALL_PARALLELISM);
+    let confirmed = confirmed.unwrap_or(false);
+    return Ok(tokio::task::spawn_blocking(move || {
+        let sessions = app.state::<SessionManager>();
+        return sessions.batch_limited(devices, parallel, |session| {
+            return install_file(session, Path::new(&package), confirmed, |status| {
+                let status = DeviceInstallStatus {
+                    device: session.device.name.clone(),
+                    status,
+                };
+                // Reporting is best effort, it shouldn't fail the installation
+                on_status.send(status).unwrap_or(());
+                return Ok(());
+            });
+        });
+    })
+    .await
+    .expect("critical failure in batch::install task"));
+}
+
+/// Initializes the plugin.
+pub fn plugin<R: Runtime>(name: &'static str) -> TauriPlugin<R> {
+    Builder::new(name)
+        .invoke_handler(tauri::generate_handler![exec, push, install])
+        .build()
+}
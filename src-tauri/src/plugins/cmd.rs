@@ -1,16 +1,16 @@
 use serde::Deserialize;
-use std::io::{Read, Write};
 use std::sync::Arc;
 
 use tauri::{
+    ipc::{Channel, Response},
     plugin::{Builder, TauriPlugin},
     AppHandle, Manager, Runtime, State,
 };
 
-use crate::device_manager::Device;
+use crate::device_manager::{ActivityKind, Device, DeviceManager};
 use crate::error::Error;
 use crate::event_channel::{EventChannel, EventHandler};
-use crate::session_manager::{Proc, ProcCallback, ProcData, SessionManager};
+use crate::session_manager::{PortForward, Proc, ProcCallback, ProcData, SessionManager};
 use crate::spawn_manager::SpawnManager;
 
 #[tauri::command]
@@ -19,34 +19,23 @@ async fn exec<R: Runtime>(
     device: Device,
     command: String,
     stdin: Option<Vec<u8>>,
+    elevate: Option<bool>,
 ) -> Result<Vec<u8>, Error> {
+    app.state::<DeviceManager>()
+        .record_activity(&device.name, ActivityKind::Exec);
+    let elevate = elevate.unwrap_or(false);
+    let command = match elevate {
+        true => device.elevated_command(&command)?,
+        false => command,
+    };
     return tokio::task::spawn_blocking(move || {
         let sessions = app.state::<SessionManager>();
+        if !elevate {
+            return sessions.exec(device, &command, stdin.as_deref());
+        }
+        let command = device.env_command(&command);
         return sessions.with_session(device, |session| {
-            let ch = session.new_channel()?;
-            ch.open_session()?;
-            ch.request_exec(&command)?;
-            if let Some(stdin) = stdin.clone() {
-                ch.stdin().write_all(&stdin)?;
-                ch.send_eof()?;
-            }
-            let mut buf = Vec::<u8>::new();
-            ch.stdout().read_to_end(&mut buf)?;
-            let mut stderr = Vec::<u8>::new();
-            ch.stderr().read_to_end(&mut stderr)?;
-            let exit_code = ch.get_exit_status().unwrap_or(0);
-            ch.close()?;
-            session.mark_last_ok();
-            if exit_code != 0 {
-                return Err(Error::ExitStatus {
-                    message: format!(""),
-                    command: command.clone(),
-                    exit_code,
-                    stderr,
-                    unhandled: true,
-                });
-            }
-            return Ok(buf);
+            return session.exec_elevated(&command, stdin.as_deref());
         });
     })
     .await
@@ -60,13 +49,56 @@ async fn spawn<R: Runtime>(
     device: Device,
     command: String,
     managed: Option<bool>,
+    on_data: Option<Channel>,
 ) -> Result<String, Error> {
+    app.state::<DeviceManager>()
+        .record_activity(&device.name, ActivityKind::Exec);
+    return Ok(spawn_proc(
+        app,
+        &sessions,
+        device,
+        &command,
+        managed.unwrap_or(true),
+        on_data,
+    ));
+}
+
+/// Starts `command` like [spawn] does, and returns the token of its event channel.
+pub(crate) fn spawn_proc<R: Runtime>(
+    app: AppHandle<R>,
+    sessions: &SessionManager,
+    device: Device,
+    command: &str,
+    managed: bool,
+    on_data: Option<Channel>,
+) -> String {
     let channel = EventChannel::<R, ProcEventHandler>::new(app.clone(), "shell-proc");
     let token = channel.token();
-    let proc = Arc::new(sessions.spawn(device, &command));
+    let proc = Arc::new(sessions.spawn(device, command));
     channel.listen(ProcEventHandler { proc: proc.clone() });
-    tokio::task::spawn_blocking(move || proc_worker(app, proc, channel, managed.unwrap_or(true)));
-    return Ok(token);
+    tokio::task::spawn_blocking(move || proc_worker(app, proc, channel, managed, on_data));
+    return token;
+}
+
+/// Forwards a free local port to `port` on the device, and returns it.
+#[tauri::command]
+async fn forward(
+    sessions: State<'_, SessionManager>,
+    device: Device,
+    port: u16,
+) -> Result<PortForward, Error> {
+    return sessions.forward(device, port);
+}
+
+/// Stops forwarding local port `local_port`.
+#[tauri::command]
+async fn unforward(sessions: State<'_, SessionManager>, local_port: u16) -> Result<(), Error> {
+    return sessions.unforward(local_port);
+}
+
+#[tauri::command]
+async fn forwards(sessions: State<'_, SessionManager>) -> Result<Vec<PortForward>, Error> {
+    return Ok(sessions.forwards());
 }
 
 fn proc_worker<R: Runtime>(
@@ -74,6 +106,7 @@ fn proc_worker<R: Runtime>(
     proc: Arc<Proc>,
     channel: EventChannel<R, ProcEventHandler>,
     managed: bool,
+    output: Option<Channel>,
 ) -> Result<(), Error> {
     let spawns = app.state::<SpawnManager>();
     let channel = Arc::new(channel);
@@ -82,6 +115,7 @@ fn proc_worker<R: Runtime>(
     }
     *proc.callback.lock().unwrap() = Some(Box::new(ProcCallbackImpl {
         channel: channel.clone(),
+        output,
     }));
     proc.start()?;
     match proc.wait_close(&app.state::<SessionManager>()) {
@@ -104,6 +138,8 @@ struct ProcEventHandler {
 
 struct ProcCallbackImpl<R: Runtime> {
     channel: Arc<EventChannel<R, ProcEventHandler>>,
+    /// Receives raw output when set, each message starts with the fd byte
+    output: Option<Channel>,
 }
 
 #[derive(Deserialize)]
@@ -113,6 +149,13 @@ struct TxPayload {
 
 impl<R: Runtime> ProcCallback for ProcCallbackImpl<R> {
     fn rx(&self, fd: u32, data: &[u8]) {
+        if let Some(output) = &self.output {
+            let mut body = Vec::with_capacity(data.len() + 1);
+            body.push(fd as u8);
+            body.extend_from_slice(data);
+            output.send(Response::new(body)).unwrap_or(());
+            return;
+        }
         self.channel.rx(ProcData {
             fd,
             data: Vec::<u8>::from(data),
@@ -143,6 +186,8 @@ impl EventHandler for ProcEventHandler {
 /// Initializes the plugin.
 pub fn plugin<R: Runtime>(name: &'static str) -> TauriPlugin<R> {
     Builder::new(name)
-        .invoke_handler(tauri::generate_handler![exec, spawn])
+        .invoke_handler(tauri::generate_handler![
+            exec, spawn, forward, unforward, forwards
+        ])
         .build()
 }
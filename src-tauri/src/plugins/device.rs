@@ -1,12 +1,27 @@
-use tauri::{AppHandle, State};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager, State};
 use tauri::{
     plugin::{Builder, TauriPlugin},
     Runtime,
 };
 
-use crate::device_manager::{Device, DeviceManager};
+use crate::device_manager::keygen::authorize_command;
+use crate::device_manager::{
+    Bookmark, DetectedSsh, Device, DeviceCapabilities, DeviceHistory, DeviceInfo, DeviceKind,
+    DeviceManager, DeviceShare, DeviceStatus, DiscoveredDevice, GeneratedKey, ImportResult,
+    KeyAlgorithm, NovacomDevice, PrivateKey,
+};
 use crate::error::Error;
 use crate::app_dirs::GetSshDir;
+use crate::conn_pool::{DeviceConnection, Diagnosis};
+use crate::session_manager::SessionManager;
+
+const DISCOVER_TIMEOUT_MS: u64 = 3000;
+/// TVs can take a while to start sshd after waking up
+const WAKE_TIMEOUT_MS: u64 = 60000;
 
 #[tauri::command]
 async fn list(manager: State<'_, DeviceManager>) -> Result<Vec<Device>, Error> {
@@ -21,11 +36,47 @@ async fn set_default(
     return manager.set_default(&name).await;
 }
 
+#[tauri::command]
+async fn get_default(manager: State<'_, DeviceManager>) -> Result<Option<Device>, Error> {
+    return manager.default_device().await;
+}
+
+/// Orders devices as in `names`, returning the reordered list.
+#[tauri::command]
+async fn reorder(
+    manager: State<'_, DeviceManager>,
+    names: Vec<String>,
+) -> Result<Vec<Device>, Error> {
+    return manager.reorder(&names).await;
+}
+
+/// Returns a new device of `kind` with the usual defaults filled in, without adding it.
+#[tauri::command]
+async fn preset(kind: DeviceKind, name: String, address: String) -> Result<Device, Error> {
+    return Ok(Device::preset(kind, &name, &address));
+}
+
 #[tauri::command]
 async fn add(manager: State<'_, DeviceManager>, device: Device) -> Result<Device, Error> {
+    let others = manager.list().await?;
+    device.validate(&others, manager.get_ssh_dir().as_deref())?;
     return manager.add(&device).await;
 }
 
+/// Checks the device settings without connecting. Fails with [Error::Validation] listing the
+/// problem of each invalid field. When editing, `original` is the name of the device being edited,
+/// which the new name may keep.
+#[tauri::command]
+async fn validate(
+    manager: State<'_, DeviceManager>,
+    device: Device,
+    original: Option<String>,
+) -> Result<(), Error> {
+    let mut others = manager.list().await?;
+    others.retain(|d| Some(&d.name) != original.as_ref());
+    return device.validate(&others, manager.get_ssh_dir().as_deref());
+}
+
 #[tauri::command]
 async fn remove(
     manager: State<'_, DeviceManager>,
@@ -35,6 +86,18 @@ async fn remove(
     return manager.remove(&name, remove_key).await;
 }
 
+/// Adds a copy of device `name` named `new_name`, optionally with a different address.
+#[tauri::command]
+async fn clone(
+    manager: State<'_, DeviceManager>,
+    name: String,
+    new_name: String,
+    host: Option<String>,
+    port: Option<u16>,
+) -> Result<Device, Error> {
+    return manager.clone_device(&name, &new_name, host, port).await;
+}
+
 #[tauri::command]
 async fn novacom_getkey(
     manager: State<'_, DeviceManager>,
@@ -46,6 +109,18 @@ async fn novacom_getkey(
         .await;
 }
 
+/// Downloads the key of a TV in Developer Mode at `address`, and adds or updates device `name`
+/// to log in with it.
+#[tauri::command]
+async fn devmode_setup(
+    manager: State<'_, DeviceManager>,
+    name: String,
+    address: String,
+    passphrase: String,
+) -> Result<Device, Error> {
+    return manager.setup_devmode(&name, &address, &passphrase).await;
+}
+
 #[tauri::command]
 async fn localkey_verify(
     manager: State<'_, DeviceManager>,
@@ -65,17 +140,338 @@ async fn privkey_read<R: Runtime>(app: AppHandle<R>, device: Device) -> Result<S
         .content(app.get_ssh_dir().as_deref())?);
 }
 
+#[tauri::command]
+async fn host_key_read(device: Device) -> Result<String, Error> {
+    return tokio::task::spawn_blocking(move || DeviceConnection::host_key(&device))
+        .await
+        .expect("critical failure in device::host_key_read task");
+}
+
+#[tauri::command]
+async fn host_key_pin<R: Runtime>(
+    app: AppHandle<R>,
+    manager: State<'_, DeviceManager>,
+    device: Device,
+    fingerprint: String,
+) -> Result<Device, Error> {
+    let name = device.name.clone();
+    let actual = tokio::task::spawn_blocking(move || DeviceConnection::host_key(&device))
+        .await
+        .expect("critical failure in device::host_key_pin task")?;
+    if !actual.eq_ignore_ascii_case(&fingerprint) {
+        return Err(Error::HostKeyMismatch {
+            expected: fingerprint,
+            actual,
+        });
+    }
+    let device = manager.pin_host_key(&name, &actual).await?;
+    app.state::<SessionManager>().reset(&name);
+    return Ok(device);
+}
+
+/// Checks each stage of connecting to the device, and reports which one failed.
+#[tauri::command]
+async fn diagnose<R: Runtime>(app: AppHandle<R>, device: Device) -> Result<Diagnosis, Error> {
+    let ssh_dir = app.get_ssh_dir();
+    return Ok(tokio::task::spawn_blocking(move || {
+        DeviceConnection::diagnose(&device, ssh_dir.as_deref())
+    })
+    .await
+    .expect("critical failure in device::diagnose task"));
+}
+
+#[tauri::command]
+async fn metadata_set(
+    manager: State<'_, DeviceManager>,
+    name: String,
+    notes: Option<String>,
+    color: Option<String>,
+    icon: Option<String>,
+    tags: Option<Vec<String>>,
+) -> Result<Device, Error> {
+    return manager.set_metadata(&name, notes, color, icon, tags).await;
+}
+
+#[tauri::command]
+async fn bookmark_add(
+    manager: State<'_, DeviceManager>,
+    name: String,
+    bookmark: Bookmark,
+) -> Result<Device, Error> {
+    return manager.add_bookmark(&name, bookmark).await;
+}
+
+#[tauri::command]
+async fn bookmark_remove(
+    manager: State<'_, DeviceManager>,
+    name: String,
+    path: String,
+) -> Result<Device, Error> {
+    return manager.remove_bookmark(&name, &path).await;
+}
+
+/// Finds which SSH port and user to use for a TV at `address`.
+#[tauri::command]
+async fn detect(
+    manager: State<'_, DeviceManager>,
+    address: String,
+) -> Result<Vec<DetectedSsh>, Error> {
+    return manager.detect_ssh(&address).await;
+}
+
+/// Scans the LAN for webOS TVs for `timeout` milliseconds.
+#[tauri::command]
+async fn discover(
+    manager: State<'_, DeviceManager>,
+    timeout: Option<u64>,
+) -> Result<Vec<DiscoveredDevice>, Error> {
+    let timeout = Duration::from_millis(timeout.unwrap_or(DISCOVER_TIMEOUT_MS));
+    return manager.discover(timeout).await;
+}
+
+/// Lists devices attached over USB, or emulators, known to the local novacomd.
+#[tauri::command]
+async fn novacom_list(manager: State<'_, DeviceManager>) -> Result<Vec<NovacomDevice>, Error> {
+    return manager.novacom_devices().await;
+}
+
+/// Imports devices from an `ares-setup-device` configuration at `path`, or the default locations.
+#[tauri::command]
+async fn import_ares(
+    manager: State<'_, DeviceManager>,
+    path: Option<String>,
+) -> Result<ImportResult, Error> {
+    return manager.import_ares(path.as_deref().map(Path::new)).await;
+}
+
+/// Exports all devices to file `path`, returning how many were written. With `include_keys`,
+/// the file contains private keys and passwords, so it must be kept safe.
+#[tauri::command]
+async fn export(
+    manager: State<'_, DeviceManager>,
+    path: String,
+    include_keys: Option<bool>,
+) -> Result<usize, Error> {
+    return manager
+        .export(Path::new(&path), include_keys.unwrap_or(false))
+        .await;
+}
+
+/// Imports devices from a file written by [export], skipping names that already exist.
+#[tauri::command]
+async fn import(manager: State<'_, DeviceManager>, path: String) -> Result<ImportResult, Error> {
+    return manager.import(Path::new(&path)).await;
+}
+
+/// Encodes device `name` for [share_decode] on another machine. With `include_secrets`, the
+/// payload contains the private key and passwords, so it must only be shared privately.
+#[tauri::command]
+async fn share(
+    manager: State<'_, DeviceManager>,
+    name: String,
+    include_secrets: Option<bool>,
+) -> Result<DeviceShare, Error> {
+    return manager.share(&name, include_secrets.unwrap_or(false)).await;
+}
+
+#[tauri::command]
+async fn share_decode(payload: String) -> Result<Device, Error> {
+    return Device::from_share(&payload);
+}
+
+/// Generates a key pair for device `name`. The device only switches to it once [key_deploy] has
+/// installed the public key on it.
+#[tauri::command]
+async fn keygen(
+    manager: State<'_, DeviceManager>,
+    name: String,
+    algorithm: KeyAlgorithm,
+    bits: Option<usize>,
+    passphrase: Option<String>,
+) -> Result<GeneratedKey, Error> {
+    return manager
+        .generate_key(&name, algorithm, bits, passphrase)
+        .await;
+}
+
+/// Appends the public key of private key file `key` in the SSH directory to the device's
+/// `authorized_keys`, logging in with its current credentials, then switches the device to the key
+/// once logging in with it works. Otherwise the device keeps its credentials.
+#[tauri::command]
+async fn key_deploy<R: Runtime>(
+    app: AppHandle<R>,
+    manager: State<'_, DeviceManager>,
+    device: Device,
+    key: String,
+    passphrase: Option<String>,
+) -> Result<Device, Error> {
+    let command = authorize_command(&manager.public_key(&key)?);
+    let name = device.name.clone();
+    let mut keyed = device.clone();
+    keyed.private_key = Some(PrivateKey::Path { name: key.clone() });
+    keyed.passphrase = passphrase.clone().filter(|p| !p.is_empty());
+    keyed.password = None;
+    let sessions = app.state::<SessionManager>();
+    sessions
+        .run(device, move |session| session.exec(&command, None))
+        .await?;
+    let ssh_dir = app.get_ssh_dir();
+    tokio::task::spawn_blocking(move || {
+        return DeviceConnection::new(keyed, ssh_dir.as_deref()).map(|_| ());
+    })
+    .await
+    .expect("critical failure in device::key_deploy task")
+    .map_err(|e| {
+        return match e {
+            Error::Authorization { message } => Error::Authorization {
+                message: format!("The key was added, but logging in with it failed: {message}"),
+            },
+            e => e,
+        };
+    })?;
+    let device = manager.use_key(&name, &key, passphrase).await?;
+    sessions.reset(&name);
+    return Ok(device);
+}
+
+/// Wakes the device with Wake-on-LAN, returning whether it became reachable within `timeout`
+/// milliseconds.
+#[tauri::command]
+async fn wake(
+    manager: State<'_, DeviceManager>,
+    device: Device,
+    timeout: Option<u64>,
+) -> Result<bool, Error> {
+    let timeout = Duration::from_millis(timeout.unwrap_or(WAKE_TIMEOUT_MS));
+    return manager.wake(device, timeout).await;
+}
+
+/// Starts probing devices every `interval` milliseconds, emitting `device-status` when one goes
+/// online or offline. Stops polling without `interval`.
+#[tauri::command]
+async fn status_poll(
+    manager: State<'_, DeviceManager>,
+    interval: Option<u64>,
+) -> Result<(), Error> {
+    return manager.poll_status(interval.map(Duration::from_millis));
+}
+
+#[tauri::command]
+async fn status_list(manager: State<'_, DeviceManager>) -> Result<Vec<DeviceStatus>, Error> {
+    return Ok(manager.statuses());
+}
+
+/// Returns information about the device, from the cache unless there's none or `refresh` is set.
+/// Fetched information is cached on the device.
+#[tauri::command]
+async fn info<R: Runtime>(
+    app: AppHandle<R>,
+    manager: State<'_, DeviceManager>,
+    device: Device,
+    refresh: Option<bool>,
+) -> Result<DeviceInfo, Error> {
+    let name = device.name.clone();
+    if !refresh.unwrap_or(false) {
+        let cached = manager.list().await?.into_iter().find(|d| d.name == name);
+        if let Some(info) = cached.and_then(|d| d.info) {
+            return Ok(info);
+        }
+    }
+    let info = app
+        .state::<SessionManager>()
+        .run(device, |session| DeviceInfo::fetch(session))
+        .await?;
+    manager.set_info(&name, info.clone()).await?;
+    return Ok(info);
+}
+
+/// Returns what the user logged into the device can do, from the cache unless there's none or
+/// `refresh` is set. Detected capabilities are cached on the device.
+#[tauri::command]
+async fn capabilities<R: Runtime>(
+    app: AppHandle<R>,
+    manager: State<'_, DeviceManager>,
+    device: Device,
+    refresh: Option<bool>,
+) -> Result<DeviceCapabilities, Error> {
+    let name = device.name.clone();
+    if !refresh.unwrap_or(false) {
+        let cached = manager.list().await?.into_iter().find(|d| d.name == name);
+        if let Some(capabilities) = cached.and_then(|d| d.capabilities) {
+            return Ok(capabilities);
+        }
+    }
+    let capabilities = app
+        .state::<SessionManager>()
+        .run(device, |session| DeviceCapabilities::detect(session))
+        .await?;
+    manager
+        .set_capabilities(&name, capabilities.clone())
+        .await?;
+    return Ok(capabilities);
+}
+
+#[tauri::command]
+async fn encryption_get(manager: State<'_, DeviceManager>) -> Result<bool, Error> {
+    return manager.encrypted().await;
+}
+
+/// Encrypts the devices file with a key kept in the OS keyring, or decrypts it back to plaintext.
+/// Note that `ares-*` tools can't read an encrypted file.
+#[tauri::command]
+async fn encryption_set(manager: State<'_, DeviceManager>, enabled: bool) -> Result<(), Error> {
+    return manager.set_encrypted(enabled).await;
+}
+
+/// Returns when each device was last connected to, and what it was recently used for.
+#[tauri::command]
+async fn history(
+    manager: State<'_, DeviceManager>,
+) -> Result<HashMap<String, DeviceHistory>, Error> {
+    return Ok(manager.history());
+}
+
 /// Initializes the plugin.
 pub fn plugin<R: Runtime>(name: &'static str) -> TauriPlugin<R> {
     Builder::new(name)
         .invoke_handler(tauri::generate_handler![
             list,
             set_default,
+            get_default,
+            reorder,
+            preset,
             add,
+            validate,
             remove,
+            clone,
             novacom_getkey,
+            devmode_setup,
             localkey_verify,
             privkey_read,
+            host_key_read,
+            host_key_pin,
+            diagnose,
+            metadata_set,
+            bookmark_add,
+            bookmark_remove,
+            discover,
+            detect,
+            novacom_list,
+            import_ares,
+            export,
+            import,
+            share,
+            share_decode,
+            keygen,
+            key_deploy,
+            wake,
+            status_poll,
+            status_list,
+            info,
+            capabilities,
+            encryption_get,
+            encryption_set,
+            history,
         ])
         .build()
 }
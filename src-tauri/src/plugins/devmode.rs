@@ -1,19 +1,54 @@
-use std::io::Read;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use regex::Regex;
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use tauri::plugin::{Builder, TauriPlugin};
-use tauri::{AppHandle, Manager, Runtime};
+use tauri::{AppHandle, Manager, Runtime, State};
+use tauri_plugin_notification::NotificationExt;
 
-use crate::device_manager::Device;
+use crate::device_manager::{DevModeRenewal, Device, DeviceManager};
 use crate::error::Error;
 use crate::session_manager::SessionManager;
 
+/// Renewed well ahead of the 50 hours a session lasts
+const RENEWAL_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+const RENEWAL_RETRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const RENEWAL_CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const DEFAULT_EXPIRY_THRESHOLD: Duration = Duration::from_secs(24 * 60 * 60);
+
 #[derive(Serialize)]
 pub struct DevModeStatus {
     token: Option<String>,
     remaining: Option<String>,
+    /// [DevModeStatus::remaining] in seconds
+    #[serde(rename = "remainingSecs", skip_serializing_if = "Option::is_none")]
+    remaining_secs: Option<u64>,
+}
+
+/// Remaining time of a device's Developer Mode session, emitted as `devmode-countdown`
+#[derive(Serialize, Clone)]
+pub struct DevModeCountdown {
+    device: String,
+    #[serde(rename = "remainingSecs")]
+    remaining_secs: u64,
+}
+
+/// State of the expiry watch, which notifies once per device when its session runs low
+pub struct DevModeWatch {
+    threshold: Mutex<Duration>,
+    notified: Mutex<HashSet<String>>,
+}
+
+impl Default for DevModeWatch {
+    fn default() -> Self {
+        return Self {
+            threshold: Mutex::new(DEFAULT_EXPIRY_THRESHOLD),
+            notified: Mutex::default(),
+        };
+    }
 }
 
 #[derive(Deserialize)]
@@ -27,7 +62,7 @@ struct DevModeSession {
 
 #[tauri::command]
 async fn token<R: Runtime>(app: AppHandle<R>, device: Device) -> Result<String, Error> {
-    if device.username != "prisoner" {
+    if !device.is_devmode() {
         return Err(Error::Unsupported);
     }
     if let Some(token) = valid_token(app, device).await? {
@@ -39,49 +74,267 @@ async fn token<R: Runtime>(app: AppHandle<R>, device: Device) -> Result<String,
 #[tauri::command]
 async fn status<R: Runtime>(app: AppHandle<R>, device: Device) -> Result<DevModeStatus, Error> {
     if let Some(token) = valid_token(app, device).await? {
-        let resp = reqwest::get(
-            Url::parse_with_params(
-                "https://developer.lge.com/secure/CheckDevModeSession.dev",
-                &[("sessionToken", &token)],
-            )
-            .expect("Illegal HTTP URL"),
-        )
-        .await?
-        .error_for_status()?;
-        let session = resp.json::<DevModeSession>().await?;
+        let session = devmode_session("CheckDevModeSession", &token).await?;
         if session.result == "success" {
+            let remaining = session.error_msg.unwrap_or(String::from(""));
             return Ok(DevModeStatus {
                 token: Some(token),
-                remaining: Some(session.error_msg.unwrap_or(String::from(""))),
+                remaining_secs: parse_remaining(&remaining).map(|d| d.as_secs()),
+                remaining: Some(remaining),
             });
         }
         return Ok(DevModeStatus {
             token: Some(token),
             remaining: None,
+            remaining_secs: None,
         });
     }
     return Ok(DevModeStatus {
         token: None,
         remaining: None,
+        remaining_secs: None,
     });
 }
 
+/// Whether the scheduler renews `device`: it has opted in, and isn't known to be offline.
+fn scheduled(manager: &DeviceManager, device: &Device) -> bool {
+    return device.devmode_renew.unwrap_or(false)
+        && device.is_devmode()
+        && !manager.is_offline(&device.name);
+}
+
+/// Resets the Developer Mode session timer of the device, and returns the new remaining time.
+#[tauri::command]
+async fn extend<R: Runtime>(app: AppHandle<R>, device: Device) -> Result<DevModeStatus, Error> {
+    if !device.is_devmode() {
+        return Err(Error::Unsupported);
+    }
+    let name = device.name.clone();
+    let token = valid_token(app.clone(), device)
+        .await?
+        .ok_or(Error::Unsupported)?;
+    let result = reset_session(&token).await;
+    app.state::<DeviceManager>()
+        .record_renewal(renewal(name, &result));
+    let remaining = result?;
+    return Ok(DevModeStatus {
+        token: Some(token),
+        remaining_secs: parse_remaining(&remaining).map(|d| d.as_secs()),
+        remaining: Some(remaining),
+    });
+}
+
+/// Sets how little remaining time of a Developer Mode session triggers a notification.
+#[tauri::command]
+async fn expiry_threshold_set<R: Runtime>(app: AppHandle<R>, hours: u64) -> Result<(), Error> {
+    let watch = app.state::<DevModeWatch>();
+    *watch.threshold.lock().unwrap() = Duration::from_secs(hours * 60 * 60);
+    // Devices above the new threshold may be notified again
+    watch.notified.lock().unwrap().clear();
+    return Ok(());
+}
+
+/// Opts device `name` in or out of extending its Developer Mode session in the background.
+#[tauri::command]
+async fn renew_set(
+    manager: State<'_, DeviceManager>,
+    name: String,
+    enabled: bool,
+) -> Result<Device, Error> {
+    return manager.set_devmode_renew(&name, enabled).await;
+}
+
+/// Extends Developer Mode sessions of devices that opted in, whenever the last renewal is older
+/// than [RENEWAL_INTERVAL]. Each outcome is recorded in the device history, and emitted as
+/// `devmode-renewal`. Remaining time of every Developer Mode device is emitted as
+/// `devmode-countdown`, with a notification when it drops below the threshold. Devices the status
+/// poller found offline are skipped.
+pub fn start_scheduler<R: Runtime>(app: AppHandle<R>) {
+    std::thread::spawn(move || loop {
+        tauri::async_runtime::block_on(async {
+            renew_due(&app).await;
+            check_expiry(&app).await;
+        });
+        std::thread::sleep(RENEWAL_CHECK_INTERVAL);
+    });
+}
+
+async fn check_expiry<R: Runtime>(app: &AppHandle<R>) {
+    let manager = app.state::<DeviceManager>();
+    let Ok(devices) = manager.list().await else {
+        return;
+    };
+    for device in devices
+        .into_iter()
+        .filter(|d| d.is_devmode() && !manager.is_offline(&d.name))
+    {
+        let name = device.name.clone();
+        let remaining = match valid_token(app.clone(), device).await {
+            Ok(Some(token)) => match devmode_session("CheckDevModeSession", &token).await {
+                Ok(session) if session.result == "success" => {
+                    session.error_msg.as_deref().and_then(parse_remaining)
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        // Devices can be offline, there's nothing to report for them then
+        let Some(remaining) = remaining else {
+            continue;
+        };
+        app.emit(
+            "devmode-countdown",
+            DevModeCountdown {
+                device: name.clone(),
+                remaining_secs: remaining.as_secs(),
+            },
+        )
+        .unwrap_or(());
+        let watch = app.state::<DevModeWatch>();
+        let threshold = *watch.threshold.lock().unwrap();
+        let mut notified = watch.notified.lock().unwrap();
+        if remaining >= threshold {
+            notified.remove(&name);
+            continue;
+        }
+        if !notified.insert(name.clone()) {
+            continue;
+        }
+        let hours = remaining.as_secs() / 3600;
+        let result = app
+            .notification()
+            .builder()
+            .title("Developer Mode is expiring")
+            .body(format!(
+                "Developer Mode session of {name} expires in {hours} hours"
+            ))
+            .show();
+        if let Err(e) = result {
+            log::warn!("Failed to show notification: {e:?}");
+        }
+    }
+}
+
+async fn renew_due<R: Runtime>(app: &AppHandle<R>) {
+    let manager = app.state::<DeviceManager>();
+    let devices = match manager.list().await {
+        Ok(devices) => devices,
+        Err(e) => {
+            log::warn!("Failed to read devices for Developer Mode renewal: {e:?}");
+            return;
+        }
+    };
+    let history = manager.history();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    for device in devices {
+        if !scheduled(&manager, &device) {
+            continue;
+        }
+        let last = history
+            .get(&device.name)
+            .and_then(|h| h.last_renewal.as_ref());
+        let due = match last {
+            Some(last) if last.error.is_none() => {
+                now.saturating_sub(last.time) >= RENEWAL_INTERVAL.as_secs()
+            }
+            Some(last) => now.saturating_sub(last.time) >= RENEWAL_RETRY_INTERVAL.as_secs(),
+            None => true,
+        };
+        if !due {
+            continue;
+        }
+        let renewal = renew(app.clone(), device).await;
+        match &renewal.error {
+            Some(e) => log::warn!("Failed to renew Developer Mode of {}: {e}", renewal.device),
+            None => log::info!("Renewed Developer Mode of {}", renewal.device),
+        }
+        manager.record_renewal(renewal.clone());
+        app.emit("devmode-renewal", renewal).unwrap_or(());
+    }
+}
+
+/// Resets the Developer Mode session timer of the device. Failures are reported in the result.
+async fn renew<R: Runtime>(app: AppHandle<R>, device: Device) -> DevModeRenewal {
+    let name = device.name.clone();
+    let result = match valid_token(app, device).await {
+        Ok(Some(token)) => reset_session(&token).await,
+        Ok(None) => Err(Error::new("Developer Mode token is not available")),
+        Err(e) => Err(e),
+    };
+    return renewal(name, &result);
+}
+
+fn renewal(device: String, result: &Result<String, Error>) -> DevModeRenewal {
+    let (remaining, error) = match result {
+        Ok(remaining) => (Some(remaining.clone()), None),
+        Err(Error::Message { message, .. }) => (None, Some(message.clone())),
+        Err(e) => (None, Some(format!("{e:?}"))),
+    };
+    return DevModeRenewal {
+        device,
+        time: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        remaining,
+        error,
+    };
+}
+
+/// Resets the timer of the session of `token`, and returns the new remaining time.
+async fn reset_session(token: &str) -> Result<String, Error> {
+    let session = devmode_session("ResetDevModeSession", token).await?;
+    if session.result != "success" {
+        let reason = session
+            .error_msg
+            .or(session.error_code)
+            .unwrap_or(session.result);
+        return Err(Error::new(format!(
+            "Failed to extend Developer Mode session: {reason}"
+        )));
+    }
+    return Ok(session.error_msg.unwrap_or_default());
+}
+
+/// Parses remaining time of a session, like `49:59:59`.
+fn parse_remaining(remaining: &str) -> Option<Duration> {
+    let parts: Vec<u64> = remaining
+        .trim()
+        .split(':')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<u64>>>()?;
+    let [hours, minutes, seconds] = parts[..] else {
+        return None;
+    };
+    return Some(Duration::from_secs(hours * 3600 + minutes * 60 + seconds));
+}
+
+/// Calls Developer Mode session `endpoint` of LG, like `CheckDevModeSession`, with `token`.
+async fn devmode_session(endpoint: &str, token: &str) -> Result<DevModeSession, Error> {
+    let url = Url::parse_with_params(
+        &format!("https://developer.lge.com/secure/{endpoint}.dev"),
+        &[("sessionToken", token)],
+    )
+    .expect("Illegal HTTP URL");
+    let resp = reqwest::get(url).await?.error_for_status()?;
+    return Ok(resp.json::<DevModeSession>().await?);
+}
+
+/// Reads the Developer Mode token of the device through the shared [SessionManager], so it uses
+/// pooled connections like everything else, and falls back to `cat` where SFTP is unavailable.
 async fn valid_token<R: Runtime>(
     app: AppHandle<R>,
     device: Device,
 ) -> Result<Option<String>, Error> {
-    let data = tokio::task::spawn_blocking(move || {
-        let sessions = app.state::<SessionManager>();
-        return sessions.with_session(device, |session| {
-            let sftp = session.sftp()?;
-            let mut ch = sftp.open("/var/luna/preferences/devmode_enabled", 0, 0)?;
-            let mut data = Vec::<u8>::new();
-            ch.read_to_end(&mut data)?;
-            return Ok::<Vec<u8>, Error>(data);
-        });
-    })
-    .await
-    .unwrap()?;
+    // There's no Developer Mode app on OSE
+    if device.is_ose() {
+        return Ok(None);
+    }
+    let data = app
+        .state::<SessionManager>()
+        .sftp_read(device, "/var/luna/preferences/devmode_enabled")
+        .await?;
     let token = match String::from_utf8(data) {
         Ok(token) => token,
         Err(e) => {
@@ -103,6 +356,36 @@ async fn valid_token<R: Runtime>(
 /// Initializes the plugin.
 pub fn plugin<R: Runtime>(name: &'static str) -> TauriPlugin<R> {
     Builder::new(name)
-        .invoke_handler(tauri::generate_handler![status, token])
+        .invoke_handler(tauri::generate_handler![
+            status,
+            token,
+            extend,
+            renew_set,
+            expiry_threshold_set
+        ])
+        .setup(|app, _api| {
+            app.manage(DevModeWatch::default());
+            return Ok(());
+        })
         .build()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_remaining_time() {
+        assert_eq!(
+            parse_remaining("49:59:59"),
+            Some(Duration::from_secs(49 * 3600 + 59 * 60 + 59))
+        );
+        assert_eq!(
+            parse_remaining(" 00:01:00\n"),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(parse_remaining("01:00"), None);
+        assert_eq!(parse_remaining("aa:bb:cc"), None);
+        assert_eq!(parse_remaining(""), None);
+    }
+}
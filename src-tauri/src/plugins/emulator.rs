@@ -0,0 +1,57 @@
+use tauri::plugin::{Builder, TauriPlugin};
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::device_manager::{Device, DeviceManager};
+use crate::emulator_manager::{Emulator, EmulatorManager};
+use crate::error::Error;
+
+#[tauri::command]
+async fn list<R: Runtime>(app: AppHandle<R>) -> Result<Vec<Emulator>, Error> {
+    return tokio::task::spawn_blocking(move || app.state::<EmulatorManager>().list())
+        .await
+        .expect("critical failure in emulator::list task");
+}
+
+/// Starts the emulator, and adds or updates a device with its name for connecting to it. If a
+/// device elsewhere has that name, the emulator's device gets a unique one.
+#[tauri::command]
+async fn start<R: Runtime>(
+    app: AppHandle<R>,
+    name: String,
+    headless: Option<bool>,
+) -> Result<Device, Error> {
+    let handle = app.clone();
+    let emulator = tokio::task::spawn_blocking(move || {
+        return handle
+            .state::<EmulatorManager>()
+            .start(&name, headless.unwrap_or(false));
+    })
+    .await
+    .expect("critical failure in emulator::start task")?;
+    return app
+        .state::<DeviceManager>()
+        .register(emulator.device()?)
+        .await;
+}
+
+#[tauri::command]
+async fn stop<R: Runtime>(
+    app: AppHandle<R>,
+    name: String,
+    force: Option<bool>,
+) -> Result<(), Error> {
+    return tokio::task::spawn_blocking(move || {
+        return app
+            .state::<EmulatorManager>()
+            .stop(&name, force.unwrap_or(false));
+    })
+    .await
+    .expect("critical failure in emulator::stop task");
+}
+
+/// Initializes the plugin.
+pub fn plugin<R: Runtime>(name: &'static str) -> TauriPlugin<R> {
+    Builder::new(name)
+        .invoke_handler(tauri::generate_handler![list, start, stop])
+        .build()
+}
@@ -1,26 +1,35 @@
 use std::env::temp_dir;
 use std::fs::File;
-use std::io::{Read, Write};
-use std::path::Path;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
 
 use flate2::read::GzDecoder;
 use serde::Serialize;
-use tauri::ipc::Channel;
+use tauri::ipc::{Channel, InvokeBody};
 use tauri::plugin::{Builder, TauriPlugin};
 use tauri::{AppHandle, Manager, Runtime};
 use uuid::Uuid;
 
-use crate::device_manager::Device;
+use crate::device_manager::{ActivityKind, Device, DeviceManager};
 use crate::error::Error;
+use crate::remote_files::archive;
+use crate::remote_files::mounts::MountPoint;
+use crate::remote_files::search::SearchQuery;
 use crate::remote_files::serve;
-use crate::remote_files::{FileItem, PermInfo};
+use crate::remote_files::trash::{self, TrashItem};
+use crate::remote_files::usage::DiskUsage;
+use crate::remote_files::FileItem;
+use crate::session_manager::command::quote;
 use crate::session_manager::SessionManager;
+use crate::transfer_manager::preserve;
+use crate::transfer_manager::remote_fs::RemoteFs;
+use crate::transfer_manager::watch::deploy;
+use crate::transfer_manager::{
+    Exclude, QueueItem, QueueRunner, RemoteEdit, RemoteEditInfo, RemoteEditSynced, SyncOptions,
+    SyncProfile, SyncSummary, Transfer, TransferDirection, TransferManager, WatchSynced,
+};
 
-#[derive(Clone, Serialize)]
-struct CopyProgress {
-    copied: usize,
-    total: usize,
-}
+const SEARCH_MAX_RESULTS: usize = 1000;
 
 #[tauri::command]
 async fn ls<R: Runtime>(
@@ -32,21 +41,146 @@ async fn ls<R: Runtime>(
         return Err(Error::new("Absolute path required"));
     }
     log::info!("ls {}", path);
-    return tokio::task::spawn_blocking(move || {
-        let sessions = app.state::<SessionManager>();
-        return sessions.with_session(device, |session| {
-            let sftp = session.sftp()?;
-            let entries = sftp.read_dir(&path)?;
-            let user = session.user.as_ref();
-            return Ok(entries
-                .iter()
-                .filter(|entry| entry.name() != Some(".") && entry.name() != Some(".."))
-                .map(|entry| FileItem::new(entry, None, user.map(|u| PermInfo::from(entry, &u))))
-                .collect());
-        });
-    })
-    .await
-    .expect("critical failure in file::ls task");
+    return app
+        .state::<SessionManager>()
+        .sftp_read_dir(device, &path)
+        .await;
+}
+
+#[tauri::command]
+async fn stat<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    path: String,
+) -> Result<FileItem, Error> {
+    if !path.starts_with("/") {
+        return Err(Error::new("Absolute path required"));
+    }
+    return app.state::<SessionManager>().sftp_stat(device, &path).await;
+}
+
+#[tauri::command]
+async fn readlink<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    path: String,
+) -> Result<String, Error> {
+    if !path.starts_with("/") {
+        return Err(Error::new("Absolute path required"));
+    }
+    return app
+        .state::<SessionManager>()
+        .sftp_read_link(device, &path)
+        .await;
+}
+
+/// Runs `find`, and `grep` if the query has a content pattern, under `path`, sending each match
+/// to `on_match` as soon as it's found. Returns the number of matches.
+#[tauri::command]
+async fn search<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    path: String,
+    query: SearchQuery,
+    max_results: Option<usize>,
+    on_match: Channel,
+) -> Result<usize, Error> {
+    if !path.starts_with("/") {
+        return Err(Error::new("Absolute path required"));
+    }
+    let command = query.command(&path);
+    let max_results = max_results.unwrap_or(SEARCH_MAX_RESULTS);
+    return app
+        .state::<SessionManager>()
+        .run(device, move |session| {
+            let _permit = session.acquire_channel();
+            let ch = session.new_channel()?;
+            ch.open_session()?;
+            ch.request_exec(&command)?;
+            let mut count = 0;
+            for line in BufReader::new(ch.stdout()).lines() {
+                let Some(found) = query.parse(&line?) else {
+                    continue;
+                };
+                count += 1;
+                if on_match.send(found).is_err() || count >= max_results {
+                    break;
+                }
+            }
+            ch.close()?;
+            return Ok(count);
+        })
+        .await;
+}
+
+/// Returns the sizes of directories under `path`, down to `depth` levels.
+#[tauri::command]
+async fn disk_usage<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    path: String,
+    depth: Option<u32>,
+) -> Result<DiskUsage, Error> {
+    if !path.starts_with("/") {
+        return Err(Error::new("Absolute path required"));
+    }
+    let root = match path.trim_end_matches('/') {
+        "" => String::from("/"),
+        trimmed => String::from(trimmed),
+    };
+    let command = DiskUsage::command(&root, depth.unwrap_or(1));
+    let output = app
+        .state::<SessionManager>()
+        .run(device, move |session| session.exec(&command, None))
+        .await?;
+    return DiskUsage::parse(&root, &String::from_utf8_lossy(&output)).ok_or(Error::NotFound);
+}
+
+/// Lists mounted filesystems with their space, flagging USB drives, the one apps are installed to
+/// in developer mode, and those running out of space.
+#[tauri::command]
+async fn mounts<R: Runtime>(app: AppHandle<R>, device: Device) -> Result<Vec<MountPoint>, Error> {
+    let output = app
+        .state::<SessionManager>()
+        .run(device, |session| session.exec(&MountPoint::command(), None))
+        .await?;
+    return Ok(MountPoint::parse(&String::from_utf8_lossy(&output)));
+}
+
+/// Archives `path` on the device into `archive`, gzipped if it's named `.tar.gz` or `.tgz`.
+#[tauri::command]
+async fn archive_create<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    path: String,
+    archive: String,
+) -> Result<(), Error> {
+    if !path.starts_with("/") || !archive.starts_with("/") {
+        return Err(Error::new("Absolute path required"));
+    }
+    let command = archive::create_command(&path, &archive);
+    app.state::<SessionManager>()
+        .run(device, move |session| session.exec(&command, None))
+        .await?;
+    return Ok(());
+}
+
+/// Extracts `archive` on the device into directory `target`.
+#[tauri::command]
+async fn archive_extract<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    archive: String,
+    target: String,
+) -> Result<(), Error> {
+    if !archive.starts_with("/") || !target.starts_with("/") {
+        return Err(Error::new("Absolute path required"));
+    }
+    let command = archive::extract_command(&archive, &target);
+    app.state::<SessionManager>()
+        .run(device, move |session| session.exec(&command, None))
+        .await?;
+    return Ok(());
 }
 
 #[tauri::command]
@@ -56,27 +190,19 @@ async fn read<R: Runtime>(
     path: String,
     encoding: Option<String>,
 ) -> Result<Vec<u8>, Error> {
-    return tokio::task::spawn_blocking(move || {
-        let sessions = app.state::<SessionManager>();
-        return sessions.with_session(device, |session| {
-            let sftp = session.sftp()?;
-            let mut file = sftp.open(&path, 0 /*O_RDONLY*/, 0)?;
-            let mut buf = Vec::<u8>::new();
-            if let Some(encoding) = &encoding {
-                if encoding == "gzip" {
-                    let mut decoder = GzDecoder::new(&mut file);
-                    decoder.read_to_end(&mut buf)?;
-                } else {
-                    return Err(Error::new(format!("Unsupported encoding {}", encoding)));
-                }
-            } else {
-                file.read_to_end(&mut buf)?;
-            }
-            return Ok(buf);
-        });
-    })
-    .await
-    .expect("critical failure in file::read task");
+    let data = app
+        .state::<SessionManager>()
+        .sftp_read(device, &path)
+        .await?;
+    let Some(encoding) = &encoding else {
+        return Ok(data);
+    };
+    if encoding != "gzip" {
+        return Err(Error::new(format!("Unsupported encoding {}", encoding)));
+    }
+    let mut buf = Vec::<u8>::new();
+    GzDecoder::new(&data[..]).read_to_end(&mut buf)?;
+    return Ok(buf);
 }
 
 #[tauri::command]
@@ -86,57 +212,247 @@ async fn write<R: Runtime>(
     path: String,
     content: Vec<u8>,
 ) -> Result<(), Error> {
-    return tokio::task::spawn_blocking(move || {
-        let sessions = app.state::<SessionManager>();
-        return Ok(sessions.with_session(device, |session| {
-            let sftp = session.sftp()?;
-            let mut file =
-                sftp.open(&path, libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC, 0o644)?;
-            file.write_all(&content)?;
-            return Ok(());
-        })?);
-    })
-    .await
-    .expect("critical failure in file::write task");
+    return app
+        .state::<SessionManager>()
+        .sftp_write(device, &path, content)
+        .await;
+}
+
+#[tauri::command]
+async fn rename<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    from: String,
+    to: String,
+) -> Result<(), Error> {
+    return app
+        .state::<SessionManager>()
+        .sftp_rename(device, &from, &to)
+        .await;
+}
+
+/// Returns the removed paths, children before their parents. With `dry_run`, nothing is removed
+/// and the paths that would be are returned. With `trash`, only `path` itself is moved.
+#[tauri::command]
+async fn remove<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    path: String,
+    recursive: Option<bool>,
+    trash: Option<bool>,
+    dry_run: Option<bool>,
+) -> Result<Vec<String>, Error> {
+    if !path.starts_with("/") || path.trim_end_matches('/').is_empty() {
+        return Err(Error::new("Absolute path required"));
+    }
+    let dry_run = dry_run.unwrap_or(false);
+    if trash.unwrap_or(false) {
+        if !dry_run {
+            let trash_path = path.clone();
+            app.state::<SessionManager>()
+                .run(device, move |session| trash::trash(session, &trash_path))
+                .await?;
+        }
+        return Ok(vec![path]);
+    }
+    return app
+        .state::<SessionManager>()
+        .sftp_remove(device, &path, recursive.unwrap_or(false), dry_run)
+        .await;
+}
+
+#[tauri::command]
+async fn trash_list<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+) -> Result<Vec<TrashItem>, Error> {
+    return app
+        .state::<SessionManager>()
+        .run(device, |session| trash::list(session))
+        .await;
+}
+
+#[tauri::command]
+async fn trash_restore<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    id: String,
+) -> Result<TrashItem, Error> {
+    return app
+        .state::<SessionManager>()
+        .run(device, move |session| trash::restore(session, &id))
+        .await;
+}
+
+#[tauri::command]
+async fn trash_empty<R: Runtime>(app: AppHandle<R>, device: Device) -> Result<(), Error> {
+    return app
+        .state::<SessionManager>()
+        .run(device, |session| trash::empty(session))
+        .await;
+}
+
+#[tauri::command]
+async fn mkdir<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    path: String,
+    parents: Option<bool>,
+) -> Result<(), Error> {
+    return app
+        .state::<SessionManager>()
+        .sftp_mkdir(device, &path, parents.unwrap_or(false))
+        .await;
+}
+
+#[tauri::command]
+async fn chmod<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    path: String,
+    mode: u32,
+) -> Result<(), Error> {
+    return app
+        .state::<SessionManager>()
+        .sftp_chmod(device, &path, mode)
+        .await;
+}
+
+#[tauri::command]
+async fn chown<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    path: String,
+    uid: u32,
+    gid: u32,
+) -> Result<(), Error> {
+    return app
+        .state::<SessionManager>()
+        .sftp_chown(device, &path, uid, gid)
+        .await;
 }
 
+#[tauri::command]
+async fn touch<R: Runtime>(app: AppHandle<R>, device: Device, path: String) -> Result<(), Error> {
+    return app
+        .state::<SessionManager>()
+        .sftp_touch(device, &path)
+        .await;
+}
+
+/// With `resume`, partial content is kept in a `.part` file and continued by the next attempt.
+/// Devices without SFTP can't resume. With `preserve`, permission bits and modification time are
+/// carried over. `rate_limit` caps the transfer at that many bytes per second, on top of the
+/// limit set with [set_rate_limit].
 #[tauri::command]
 async fn get<R: Runtime>(
     app: AppHandle<R>,
     device: Device,
     path: String,
     target: String,
+    transfer_id: Option<String>,
+    rate_limit: Option<usize>,
+    resume: Option<bool>,
+    preserve: Option<bool>,
     on_progress: Channel,
 ) -> Result<(), Error> {
+    let resume = resume.unwrap_or(false);
+    let preserve = preserve.unwrap_or(false);
     return tokio::task::spawn_blocking(move || {
         let sessions = app.state::<SessionManager>();
-        let on_progress = on_progress.clone();
-        return sessions.with_session(device, move |session| {
+        let transfers = app.state::<TransferManager>();
+        let transfer = transfers.begin(transfer_id);
+        app.state::<DeviceManager>()
+            .record_activity(&device.name, ActivityKind::Transfer);
+        transfer.set_limit(rate_limit);
+        let result = sessions.with_session(device, |session| {
+            transfer.restart();
+            let target = Path::new(&target);
+            if session.use_stream() {
+                if resume {
+                    return Err(Error::Unsupported);
+                }
+                transfer.download_stream(session, &path, target, &on_progress)?;
+                if preserve {
+                    preserve::to_local_exec(session, &path, target)?;
+                }
+                return Ok(());
+            }
             let sftp = session.sftp()?;
-            let mut sfile = sftp.open(&path, 0, 0)?;
-            let mut file = File::create(target.clone())?;
-            let size = sfile.metadata()?.len().unwrap_or_default() as usize;
-            copy(&mut sfile, &mut file, size, &on_progress)?;
+            if resume {
+                transfer.download_resumable(session, &sftp, &path, target, &on_progress)?;
+            } else {
+                let mut sfile = sftp.open(&path, 0, 0)?;
+                let mut file = File::create(target)?;
+                let size = sfile.metadata()?.len().unwrap_or_default() as usize;
+                transfer.expect(size);
+                transfer.copy(&mut sfile, &mut file, &on_progress)?;
+            }
+            if preserve {
+                preserve::to_local(&sftp.metadata(&path)?, target)?;
+            }
             return Ok(());
         });
+        transfers.finish(&transfer);
+        if !resume && result == Err(Error::Cancelled) {
+            std::fs::remove_file(&target).unwrap_or(());
+        }
+        return result;
     })
     .await
     .expect("critical failure in file::get task");
 }
 
+/// See [get] for `resume`, `preserve` and `rate_limit`.
 #[tauri::command]
 async fn put<R: Runtime>(
     app: AppHandle<R>,
     device: Device,
     path: String,
     source: String,
+    transfer_id: Option<String>,
+    rate_limit: Option<usize>,
+    resume: Option<bool>,
+    preserve: Option<bool>,
     on_progress: Channel,
 ) -> Result<(), Error> {
+    let resume = resume.unwrap_or(false);
+    let preserve = preserve.unwrap_or(false);
     return tokio::task::spawn_blocking(move || {
         let sessions = app.state::<SessionManager>();
-        let on_progress = on_progress.clone();
-        return sessions.with_session(device, move |session| {
+        let transfers = app.state::<TransferManager>();
+        let transfer = transfers.begin(transfer_id);
+        app.state::<DeviceManager>()
+            .record_activity(&device.name, ActivityKind::Transfer);
+        transfer.set_limit(rate_limit);
+        let result = sessions.with_session(device, |session| {
+            transfer.restart();
+            if session.use_stream() {
+                if resume {
+                    return Err(Error::Unsupported);
+                }
+                let source = Path::new(&source);
+                let copied = transfer.upload_stream(session, source, &path, &on_progress);
+                if copied == Err(Error::Cancelled) {
+                    session
+                        .exec(&format!("rm -f -- {}", quote(&path)), None)
+                        .unwrap_or_default();
+                }
+                copied?;
+                if preserve {
+                    preserve::to_remote_exec(session, &path, source)?;
+                }
+                return Ok(());
+            }
             let sftp = session.sftp()?;
+            if resume {
+                let source = Path::new(&source);
+                transfer.upload_resumable(session, &sftp, source, &path, &on_progress)?;
+                if preserve {
+                    preserve::to_remote(&sftp, &path, source)?;
+                }
+                return Ok(());
+            }
             let mut sfile = sftp
                 .open(&path, libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC, 0o644)
                 .map_err(|e| {
@@ -162,44 +478,461 @@ async fn put<R: Runtime>(
                 unhandled: true,
             })?;
             let size = file.metadata().unwrap().len() as usize;
-            copy(&mut file, &mut sfile, size, &on_progress)?;
+            transfer.expect(size);
+            let copied = transfer.copy(&mut file, &mut sfile, &on_progress);
+            if copied == Err(Error::Cancelled) {
+                sftp.remove_file(&path).unwrap_or(());
+            }
+            copied?;
+            drop(sfile);
+            if preserve {
+                preserve::to_remote(&sftp, &path, Path::new(&source))?;
+            }
             return Ok(());
         });
+        transfers.finish(&transfer);
+        return result;
     })
     .await
     .expect("critical failure in file::put task");
 }
 
-fn copy<R: ?Sized, W: ?Sized>(
-    reader: &mut R,
-    writer: &mut W,
-    total: usize,
-    progress: &Channel,
-) -> std::io::Result<usize>
-where
-    R: Read,
-    W: Write,
-{
-    let mut buf = [0; 8192];
-    let mut copied: usize = 0;
-    loop {
-        let bytes = reader.read(&mut buf)?;
-        if bytes == 0 {
-            break;
+/// Downloads the directory tree at `path` into local directory `target`. Symlinks are skipped
+/// unless `follow_links` is set. With `tar`, the tree is streamed as a single archive instead of
+/// file by file, which is much faster for many small files. `preserve` carries over permission
+/// bits and modification times of files, which tar mode always does. Devices without SFTP always
+/// use tar mode. Paths matching any of the `.gitignore`-style `exclude` patterns are skipped.
+#[tauri::command]
+async fn get_dir<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    path: String,
+    target: String,
+    follow_links: Option<bool>,
+    tar: Option<bool>,
+    preserve: Option<bool>,
+    exclude: Option<Vec<String>>,
+    transfer_id: Option<String>,
+    rate_limit: Option<usize>,
+    on_progress: Channel,
+) -> Result<(), Error> {
+    let exclude = Exclude::new(&exclude.unwrap_or_default())?;
+    return tokio::task::spawn_blocking(move || {
+        let sessions = app.state::<SessionManager>();
+        let transfers = app.state::<TransferManager>();
+        let transfer = transfers.begin(transfer_id);
+        app.state::<DeviceManager>()
+            .record_activity(&device.name, ActivityKind::Transfer);
+        transfer.set_limit(rate_limit);
+        let follow_links = follow_links.unwrap_or(false);
+        let target = Path::new(&target);
+        let result = sessions.with_session(device, |session| {
+            transfer.restart();
+            if tar.unwrap_or(false) || session.use_stream() {
+                return transfer.download_tar(
+                    session,
+                    &path,
+                    target,
+                    follow_links,
+                    &exclude,
+                    &on_progress,
+                );
+            }
+            let sftp = session.sftp()?;
+            let preserve = preserve.unwrap_or(false);
+            return transfer.download_tree(
+                &sftp,
+                &path,
+                target,
+                follow_links,
+                preserve,
+                &exclude,
+                &on_progress,
+            );
+        });
+        transfers.finish(&transfer);
+        return result;
+    })
+    .await
+    .expect("critical failure in file::get_dir task");
+}
+
+/// Downloads the directory at `path` into a single gzipped tar file `target`, which is much faster
+/// than [get_dir] for large trees such as `/var/log`.
+#[tauri::command]
+async fn get_archive<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    path: String,
+    target: String,
+    follow_links: Option<bool>,
+    transfer_id: Option<String>,
+    rate_limit: Option<usize>,
+    on_progress: Channel,
+) -> Result<(), Error> {
+    if !path.starts_with("/") {
+        return Err(Error::new("Absolute path required"));
+    }
+    return tokio::task::spawn_blocking(move || {
+        let sessions = app.state::<SessionManager>();
+        let transfers = app.state::<TransferManager>();
+        let transfer = transfers.begin(transfer_id);
+        app.state::<DeviceManager>()
+            .record_activity(&device.name, ActivityKind::Transfer);
+        transfer.set_limit(rate_limit);
+        let follow_links = follow_links.unwrap_or(false);
+        let target = Path::new(&target);
+        let result = sessions.with_session(device, |session| {
+            transfer.restart();
+            return transfer.download_archive(session, &path, target, follow_links, &on_progress);
+        });
+        transfers.finish(&transfer);
+        return result;
+    })
+    .await
+    .expect("critical failure in file::get_archive task");
+}
+
+/// Uploads local directory tree `source` to `path` on the device. See [get_dir] for
+/// `follow_links`, `tar`, `preserve` and `exclude`.
+#[tauri::command]
+async fn put_dir<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    path: String,
+    source: String,
+    follow_links: Option<bool>,
+    tar: Option<bool>,
+    preserve: Option<bool>,
+    exclude: Option<Vec<String>>,
+    transfer_id: Option<String>,
+    rate_limit: Option<usize>,
+    on_progress: Channel,
+) -> Result<(), Error> {
+    let exclude = Exclude::new(&exclude.unwrap_or_default())?;
+    return tokio::task::spawn_blocking(move || {
+        let sessions = app.state::<SessionManager>();
+        let transfers = app.state::<TransferManager>();
+        let transfer = transfers.begin(transfer_id);
+        app.state::<DeviceManager>()
+            .record_activity(&device.name, ActivityKind::Transfer);
+        transfer.set_limit(rate_limit);
+        let follow_links = follow_links.unwrap_or(false);
+        let source = Path::new(&source);
+        let result = sessions.with_session(device, |session| {
+            transfer.restart();
+            if tar.unwrap_or(false) || session.use_stream() {
+                return transfer.upload_tar(
+                    session,
+                    source,
+                    &path,
+                    follow_links,
+                    &exclude,
+                    &on_progress,
+                );
+            }
+            let sftp = session.sftp()?;
+            let preserve = preserve.unwrap_or(false);
+            return transfer.upload_tree(
+                &sftp,
+                source,
+                &path,
+                follow_links,
+                preserve,
+                &exclude,
+                &on_progress,
+            );
+        });
+        transfers.finish(&transfer);
+        return result;
+    })
+    .await
+    .expect("critical failure in file::put_dir task");
+}
+
+/// Uploads only the files of local directory `source` that changed since they were last synced to
+/// `path`. Without `options`, those saved in sync profile `profile` are used.
+#[tauri::command]
+async fn sync<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    path: String,
+    source: String,
+    options: Option<SyncOptions>,
+    profile: Option<String>,
+    transfer_id: Option<String>,
+    rate_limit: Option<usize>,
+    on_progress: Channel,
+) -> Result<SyncSummary, Error> {
+    return tokio::task::spawn_blocking(move || {
+        let sessions = app.state::<SessionManager>();
+        let transfers = app.state::<TransferManager>();
+        let options = match (options, profile) {
+            (Some(options), _) => options,
+            (None, Some(profile)) => {
+                transfers
+                    .profiles
+                    .get(&profile)
+                    .ok_or(Error::NotFound)?
+                    .options
+            }
+            (None, None) => SyncOptions::default(),
+        };
+        let transfer = transfers.begin(transfer_id);
+        app.state::<DeviceManager>()
+            .record_activity(&device.name, ActivityKind::Transfer);
+        transfer.set_limit(rate_limit);
+        let source = Path::new(&source);
+        let result = sessions.with_session(device, |session| {
+            transfer.restart();
+            let fs = RemoteFs::new(session)?;
+            return transfer.sync_up(session, &fs, source, &path, &options, &on_progress);
+        });
+        transfers.finish(&transfer);
+        return result;
+    })
+    .await
+    .expect("critical failure in file::sync task");
+}
+
+#[tauri::command]
+async fn sync_profiles<R: Runtime>(app: AppHandle<R>) -> Result<Vec<SyncProfile>, Error> {
+    return Ok(app.state::<TransferManager>().profiles.list());
+}
+
+/// Saves the profile, replacing the one with the same name.
+#[tauri::command]
+async fn sync_profile_save<R: Runtime>(
+    app: AppHandle<R>,
+    profile: SyncProfile,
+) -> Result<(), Error> {
+    Exclude::new(&profile.options.exclude)?;
+    return app.state::<TransferManager>().profiles.save(profile);
+}
+
+#[tauri::command]
+async fn sync_profile_remove<R: Runtime>(app: AppHandle<R>, name: String) -> Result<(), Error> {
+    return app.state::<TransferManager>().profiles.remove(&name);
+}
+
+/// Pushes every change in local directory `source` to `path` on the device, until [unwatch] is
+/// called. Emits `watch-synced` for each deployed path. Changes to paths matching `exclude` are
+/// ignored.
+#[tauri::command]
+async fn watch<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    path: String,
+    source: String,
+    exclude: Option<Vec<String>>,
+) -> Result<String, Error> {
+    let transfers = app.state::<TransferManager>();
+    let handle = app.clone();
+    let root = PathBuf::from(&source);
+    let exclude = Exclude::new(&exclude.unwrap_or_default())?;
+    return transfers.watch(Path::new(&source), move |id, mut changed| {
+        changed.retain(|p| !exclude.matches(p, root.join(p).is_dir()));
+        if changed.is_empty() {
+            return;
         }
-        writer.write_all(&buf[..bytes])?;
-        copied += bytes;
-        progress.send(CopyProgress { copied, total }).map_err(|e| {
-            return match e {
-                tauri::Error::Io(e) => e,
-                e => std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("Failed to send progress: {e}"),
-                ),
-            };
-        })?;
+        let sessions = handle.state::<SessionManager>();
+        let result = sessions.with_session(device.clone(), |session| {
+            let fs = RemoteFs::new(session)?;
+            for changed_path in &changed {
+                let (removed, error) = match deploy(&fs, &root, &path, changed_path) {
+                    Ok(removed) => (removed, None),
+                    Err(e) => (false, Some(e)),
+                };
+                let payload = WatchSynced {
+                    id: String::from(id),
+                    path: changed_path.clone(),
+                    removed,
+                    error,
+                };
+                handle.emit("watch-synced", payload).unwrap_or(());
+            }
+            return Ok(());
+        });
+        if let Err(e) = result {
+            for changed_path in changed {
+                let payload = WatchSynced {
+                    id: String::from(id),
+                    path: changed_path,
+                    removed: false,
+                    error: Some(e.clone()),
+                };
+                handle.emit("watch-synced", payload).unwrap_or(());
+            }
+        }
+    });
+}
+
+#[tauri::command]
+async fn unwatch<R: Runtime>(app: AppHandle<R>, id: String) -> Result<(), Error> {
+    if !app.state::<TransferManager>().unwatch(&id) {
+        return Err(Error::NotFound);
+    }
+    return Ok(());
+}
+
+/// Downloads `path` to a temporary file for editing with a local application. Every save of the
+/// local copy is written back to the device, emitting `edit-synced`.
+#[tauri::command]
+async fn edit_open<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    path: String,
+) -> Result<RemoteEditInfo, Error> {
+    let id = Uuid::new_v4().to_string();
+    let dir = temp_dir().join(format!("webos-dev-edit-{id}"));
+    std::fs::create_dir_all(&dir)?;
+    let name = Path::new(&path)
+        .file_name()
+        .map_or(String::from("file"), |s| s.to_string_lossy().to_string());
+    let local = dir.join(name);
+    let edit = {
+        let (id, device, path, local) = (id.clone(), device.clone(), path.clone(), local.clone());
+        app.state::<SessionManager>()
+            .run(device.clone(), move |session| {
+                let fs = RemoteFs::new(session)?;
+                return RemoteEdit::open(
+                    &fs,
+                    id.clone(),
+                    device.clone(),
+                    path.clone(),
+                    local.clone(),
+                );
+            })
+            .await?
+    };
+    let transfers = app.state::<TransferManager>();
+    let edit = transfers.add_edit(edit);
+    let handle = app.clone();
+    let watched = edit.clone();
+    let watch = transfers.watch(&dir, move |_, _| {
+        let sessions = handle.state::<SessionManager>();
+        let result = sessions.with_session(watched.device.clone(), |session| {
+            return watched.write_back(&RemoteFs::new(session)?, false);
+        });
+        let payload = RemoteEditSynced {
+            id: watched.id.clone(),
+            error: result.err(),
+        };
+        handle.emit("edit-synced", payload).unwrap_or(());
+    })?;
+    edit.set_watch(Some(watch));
+    return Ok(RemoteEditInfo {
+        id,
+        local_path: local.to_string_lossy().to_string(),
+    });
+}
+
+/// Writes the local copy back to the device. With `force`, overwrites the remote file even if it
+/// changed since it was opened.
+#[tauri::command]
+async fn edit_save<R: Runtime>(
+    app: AppHandle<R>,
+    id: String,
+    force: Option<bool>,
+) -> Result<(), Error> {
+    let edit = app
+        .state::<TransferManager>()
+        .edit(&id)
+        .ok_or(Error::NotFound)?;
+    let device = edit.device.clone();
+    return app
+        .state::<SessionManager>()
+        .run(device, move |session| {
+            return edit.write_back(&RemoteFs::new(session)?, force.unwrap_or(false));
+        })
+        .await;
+}
+
+#[tauri::command]
+async fn edit_close<R: Runtime>(app: AppHandle<R>, id: String) -> Result<(), Error> {
+    let transfers = app.state::<TransferManager>();
+    let edit = transfers.remove_edit(&id).ok_or(Error::NotFound)?;
+    if let Some(watch) = edit.set_watch(None) {
+        transfers.unwatch(&watch);
+    }
+    if let Some(dir) = edit.local.parent() {
+        std::fs::remove_dir_all(dir).unwrap_or(());
     }
-    Ok(copied)
+    return Ok(());
+}
+
+/// Adds a transfer to the queue, which runs it once its device has a free transfer slot.
+#[tauri::command]
+async fn queue_add<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    direction: TransferDirection,
+    source: String,
+    target: String,
+) -> Result<QueueItem, Error> {
+    let queue = app.state::<TransferManager>().queue.clone();
+    return Ok(queue.add(&device.name, direction, &source, &target));
+}
+
+#[tauri::command]
+async fn queue_list<R: Runtime>(app: AppHandle<R>) -> Result<Vec<QueueItem>, Error> {
+    return Ok(app.state::<TransferManager>().queue.list());
+}
+
+#[tauri::command]
+async fn queue_pause<R: Runtime>(app: AppHandle<R>, id: String) -> Result<(), Error> {
+    return app.state::<TransferManager>().queue.pause(&id);
+}
+
+#[tauri::command]
+async fn queue_resume<R: Runtime>(app: AppHandle<R>, id: String) -> Result<(), Error> {
+    return app.state::<TransferManager>().queue.resume(&id);
+}
+
+#[tauri::command]
+async fn queue_cancel<R: Runtime>(app: AppHandle<R>, id: String) -> Result<(), Error> {
+    return app.state::<TransferManager>().queue.cancel(&id);
+}
+
+#[tauri::command]
+async fn queue_reorder<R: Runtime>(
+    app: AppHandle<R>,
+    id: String,
+    index: usize,
+) -> Result<(), Error> {
+    return app.state::<TransferManager>().queue.reorder(&id, index);
+}
+
+#[tauri::command]
+async fn queue_clear<R: Runtime>(app: AppHandle<R>) -> Result<(), Error> {
+    app.state::<TransferManager>().queue.clear_done();
+    return Ok(());
+}
+
+/// Limits transfer `transfer_id` to `limit` bytes per second, or all transfers combined if it's
+/// absent. A `limit` of `None` removes the limit.
+#[tauri::command]
+async fn set_rate_limit<R: Runtime>(
+    app: AppHandle<R>,
+    limit: Option<usize>,
+    transfer_id: Option<String>,
+) -> Result<(), Error> {
+    if !app
+        .state::<TransferManager>()
+        .set_limit(transfer_id.as_deref(), limit)
+    {
+        return Err(Error::NotFound);
+    }
+    return Ok(());
+}
+
+/// Cancels a running transfer started with the given `transfer_id`.
+#[tauri::command]
+async fn cancel<R: Runtime>(app: AppHandle<R>, transfer_id: String) -> Result<(), Error> {
+    if !app.state::<TransferManager>().cancel(&transfer_id) {
+        return Err(Error::NotFound);
+    }
+    return Ok(());
 }
 
 #[tauri::command]
@@ -219,7 +952,18 @@ async fn get_temp<R: Runtime>(
             .to_str()
             .expect(&format!("Bad temp_path {:?}", temp_path)),
     );
-    get(app, device, path, target.clone(), on_progress).await?;
+    get(
+        app,
+        device,
+        path,
+        target.clone(),
+        None,
+        None,
+        None,
+        None,
+        on_progress,
+    )
+    .await?;
     return Ok(target);
 }
 
@@ -232,10 +976,122 @@ async fn serve<R: Runtime>(
     return serve::exec(app, device, path).await;
 }
 
+/// Runs queued transfers, resumable so pausing keeps the partial content.
+pub(crate) struct QueueRunnerImpl<R: Runtime> {
+    pub app: AppHandle<R>,
+}
+
+#[derive(Clone, Serialize)]
+struct QueueProgress {
+    id: String,
+    progress: serde_json::Value,
+}
+
+impl<R: Runtime> QueueRunner for QueueRunnerImpl<R> {
+    fn run(&self, item: &QueueItem, transfer: &Transfer) -> Result<(), Error> {
+        let app = self.app.clone();
+        let id = item.id.clone();
+        let progress = Channel::new(move |body| {
+            if let InvokeBody::Json(progress) = body {
+                let payload = QueueProgress {
+                    id: id.clone(),
+                    progress,
+                };
+                app.emit("transfer-queue-progress", payload).unwrap_or(());
+            }
+            return Ok(());
+        });
+        let devices = self.app.state::<DeviceManager>();
+        let device = tauri::async_runtime::block_on(devices.list())?
+            .into_iter()
+            .find(|d| d.name == item.device)
+            .ok_or_else(|| Error::new(format!("Device {} not found", item.device)))?;
+        let sessions = self.app.state::<SessionManager>();
+        return sessions.with_session(device, |session| {
+            transfer.restart();
+            if session.use_stream() {
+                return match item.direction {
+                    TransferDirection::Download => {
+                        let target = Path::new(&item.target);
+                        transfer.download_stream(session, &item.source, target, &progress)
+                    }
+                    TransferDirection::Upload => {
+                        let source = Path::new(&item.source);
+                        transfer.upload_stream(session, source, &item.target, &progress)
+                    }
+                };
+            }
+            let sftp = session.sftp()?;
+            return match item.direction {
+                TransferDirection::Download => {
+                    let target = Path::new(&item.target);
+                    transfer.download_resumable(session, &sftp, &item.source, target, &progress)
+                }
+                TransferDirection::Upload => {
+                    let source = Path::new(&item.source);
+                    transfer.upload_resumable(session, &sftp, source, &item.target, &progress)
+                }
+            };
+        });
+    }
+
+    fn transfer_limit(&self, name: &str) -> Option<usize> {
+        let devices = self.app.state::<DeviceManager>();
+        return devices.cached(name)?.transfer_limit;
+    }
+
+    fn updated(&self, items: Vec<QueueItem>) {
+        self.app.emit("transfer-queue", items).unwrap_or(());
+    }
+}
+
 pub fn plugin<R: Runtime>(name: &'static str) -> TauriPlugin<R> {
     Builder::new(name)
         .invoke_handler(tauri::generate_handler![
-            ls, read, write, get, put, get_temp, serve
+            ls,
+            stat,
+            readlink,
+            search,
+            disk_usage,
+            mounts,
+            archive_create,
+            archive_extract,
+            read,
+            write,
+            rename,
+            remove,
+            trash_list,
+            trash_restore,
+            trash_empty,
+            mkdir,
+            chmod,
+            chown,
+            touch,
+            get,
+            put,
+            get_dir,
+            get_archive,
+            put_dir,
+            sync,
+            sync_profiles,
+            sync_profile_save,
+            sync_profile_remove,
+            watch,
+            unwatch,
+            edit_open,
+            edit_save,
+            edit_close,
+            queue_add,
+            queue_list,
+            queue_pause,
+            queue_resume,
+            queue_cancel,
+            queue_reorder,
+            queue_clear,
+            set_rate_limit,
+            cancel,
+            get_temp,
+            serve
         ])
         .build()
 }
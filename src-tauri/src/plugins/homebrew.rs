@@ -0,0 +1,125 @@
+use std::path::PathBuf;
+
+use tauri::ipc::Channel;
+use tauri::plugin::{Builder, TauriPlugin};
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::app_manager::homebrew::{self, DEFAULT_REPO};
+use crate::app_manager::install::install_file;
+use crate::app_manager::{list as apps, AppUpdate, InstallStatus, RepoPackage};
+use crate::device_manager::Device;
+use crate::error::Error;
+use crate::session_manager::SessionManager;
+
+/// Lists packages of repository index `repo`, or the Homebrew Channel's by default. With
+/// `device`, packages are compared against apps installed on it.
+#[tauri::command]
+async fn packages<R: Runtime>(
+    app: AppHandle<R>,
+    device: Option<Device>,
+    repo: Option<String>,
+) -> Result<Vec<RepoPackage>, Error> {
+    let mut packages = homebrew::packages(repo.as_deref().unwrap_or(DEFAULT_REPO)).await?;
+    if let Some(device) = device {
+        let installed = app
+            .state::<SessionManager>()
+            .run(device, |session| apps::list(session))
+            .await?;
+        homebrew::compare_installed(&mut packages, &installed);
+    }
+    return Ok(packages);
+}
+
+/// Lists apps installed on the device that have a newer version in repository index `repo`, or
+/// in one of `manifests` given as URLs. Without `repo`, the Homebrew Channel's is used, unless
+/// `no_repo` is set.
+#[tauri::command]
+async fn updates<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    repo: Option<String>,
+    no_repo: Option<bool>,
+    manifests: Option<Vec<String>>,
+) -> Result<Vec<AppUpdate>, Error> {
+    let installed = app
+        .state::<SessionManager>()
+        .run(device, |session| apps::list(session))
+        .await?;
+    let repo = match no_repo.unwrap_or(false) {
+        true => None,
+        false => Some(repo.as_deref().unwrap_or(DEFAULT_REPO)),
+    };
+    return homebrew::updates(&installed, repo, &manifests.unwrap_or_default()).await;
+}
+
+/// Downloads package `id` from repository index `repo`, or the Homebrew Channel's by default, and
+/// installs or updates it on the device. With `manifest_url`, the package is taken from that
+/// manifest instead of the repository. Each [InstallStatus] is sent to `on_status`. Fails with
+/// `ConfirmationRequired` unless `confirmed` when the developer mount is low on space.
+#[tauri::command]
+async fn install<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    id: String,
+    repo: Option<String>,
+    manifest_url: Option<String>,
+    confirmed: Option<bool>,
+    on_status: Channel,
+) -> Result<(), Error> {
+    let send = move |status: InstallStatus| {
+        return on_status
+            .send(status)
+            .map_err(|e| Error::new(format!("Failed to send status: {e}")));
+    };
+    let manifest = match manifest_url {
+        Some(url) => homebrew::fetch_manifest(&url).await?,
+        None => {
+            let packages = homebrew::packages(repo.as_deref().unwrap_or(DEFAULT_REPO)).await?;
+            let package = packages
+                .into_iter()
+                .find(|package| package.id == id)
+                .ok_or(Error::NotFound)?;
+            homebrew::manifest(&package).await?
+        }
+    };
+    if manifest.id != id {
+        return Err(Error::new(format!(
+            "Manifest is for {}, not {id}",
+            manifest.id
+        )));
+    }
+    if manifest.root_required.required() && !device.is_rooted() {
+        return Err(Error::new(format!("{id} requires a rooted device")));
+    }
+    send(InstallStatus::Downloading)?;
+    let path = homebrew::download(&manifest).await?;
+    let confirmed = confirmed.unwrap_or(false);
+    let result = install_downloaded(app, device, path.clone(), confirmed, send).await;
+    tokio::fs::remove_file(&path).await.unwrap_or(());
+    return result;
+}
+
+async fn install_downloaded<R: Runtime, F>(
+    app: AppHandle<R>,
+    device: Device,
+    path: PathBuf,
+    confirmed: bool,
+    on_status: F,
+) -> Result<(), Error>
+where
+    F: Fn(InstallStatus) -> Result<(), Error> + Send + Sync + 'static,
+{
+    return app
+        .state::<SessionManager>()
+        .run(device, move |session| {
+            install_file(session, &path, confirmed, &on_status)
+        })
+        .await;
+}
+
+/// Initializes the plugin.
+pub fn plugin<R: Runtime>(name: &'static str) -> TauriPlugin<R> {
+    Builder::new(name)
+        .invoke_handler(tauri::generate_handler![packages, updates, install])
+        .build()
+}
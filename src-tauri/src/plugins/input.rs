@@ -0,0 +1,53 @@
+use tauri::plugin::{Builder, TauriPlugin};
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::device_manager::Device;
+use crate::error::Error;
+use crate::input_manager::{InputManager, PointerEvent};
+use crate::session_manager::SessionManager;
+
+/// Presses remote button `name`, one of [crate::input_manager::KEYS], on the device.
+#[tauri::command]
+async fn key<R: Runtime>(app: AppHandle<R>, device: Device, name: String) -> Result<(), Error> {
+    return tokio::task::spawn_blocking(move || {
+        return app
+            .state::<InputManager>()
+            .key(&app.state::<SessionManager>(), device, &name);
+    })
+    .await
+    .expect("critical failure in input::key task");
+}
+
+#[tauri::command]
+async fn pointer<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    event: PointerEvent,
+) -> Result<(), Error> {
+    return tokio::task::spawn_blocking(move || {
+        return app
+            .state::<InputManager>()
+            .pointer(&app.state::<SessionManager>(), device, &event);
+    })
+    .await
+    .expect("critical failure in input::pointer task");
+}
+
+/// Closes the input connection to the device. Sending input again reopens it.
+#[tauri::command]
+async fn disconnect<R: Runtime>(app: AppHandle<R>, device: Device) -> Result<(), Error> {
+    app.state::<InputManager>()
+        .disconnect(&app.state::<SessionManager>(), &device.name);
+    return Ok(());
+}
+
+/// Initializes the plugin.
+pub fn plugin<R: Runtime>(name: &'static str) -> TauriPlugin<R> {
+    Builder::new(name)
+        .invoke_handler(tauri::generate_handler![key, pointer, disconnect])
+        .setup(|app, _api| {
+            app.manage(InputManager::default());
+            return Ok(());
+        })
+        .build()
+}
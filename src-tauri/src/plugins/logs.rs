@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use tauri::ipc::Channel;
+use tauri::plugin::{Builder, TauriPlugin};
+use tauri::{AppHandle, Manager, Runtime, State};
+
+use crate::device_manager::Device;
+use crate::error::Error;
+use crate::log_manager::{
+    bundle, kernel as kernel_log, LogEntry, LogFilter, LogManager, LogSource,
+};
+use crate::session_manager::SessionManager;
+
+/// Lines of history shown before following, by default
+const DEFAULT_HISTORY_LINES: u32 = 100;
+
+/// Follows the journal of the device, or PmLogDaemon's syslog file where there's no journald,
+/// or the kernel ring buffer with `source`, and returns the stream token. Only lines passing
+/// `filter` are sent, and each [crate::log_manager::LogEvent] is sent to `on_event`.
+#[tauri::command]
+async fn follow<R: Runtime>(
+    app: AppHandle<R>,
+    logs: State<'_, LogManager>,
+    device: Device,
+    source: Option<LogSource>,
+    lines: Option<u32>,
+    filter: Option<LogFilter>,
+    on_event: Channel,
+) -> Result<String, Error> {
+    let filter = filter.unwrap_or_default();
+    filter.validate()?;
+    let stream = logs.open();
+    let token = stream.token.clone();
+    tokio::task::spawn_blocking(move || {
+        app.state::<LogManager>().follow(
+            &app.state::<SessionManager>(),
+            stream,
+            device,
+            source.unwrap_or_default(),
+            lines.unwrap_or(DEFAULT_HISTORY_LINES),
+            &filter,
+            move |event| on_event.send(event).unwrap_or(()),
+        );
+    });
+    return Ok(token);
+}
+
+#[tauri::command]
+async fn stop(logs: State<'_, LogManager>, token: String) -> Result<(), Error> {
+    return logs.close(&token);
+}
+
+/// Reads the kernel ring buffer of the device, with timestamps converted to wall-clock time.
+#[tauri::command]
+async fn kernel<R: Runtime>(app: AppHandle<R>, device: Device) -> Result<Vec<LogEntry>, Error> {
+    return app
+        .state::<SessionManager>()
+        .run(device, |session| kernel_log::kernel_log(session))
+        .await;
+}
+
+/// Collects device logs, the journal and kernel messages into a gzipped tarball saved to local
+/// file `target`, and returns its size.
+#[tauri::command]
+async fn export<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    target: String,
+) -> Result<u64, Error> {
+    return app
+        .state::<SessionManager>()
+        .run(device, move |session| {
+            bundle::bundle(session, Path::new(&target))
+        })
+        .await;
+}
+
+/// Initializes the plugin.
+pub fn plugin<R: Runtime>(name: &'static str) -> TauriPlugin<R> {
+    Builder::new(name)
+        .invoke_handler(tauri::generate_handler![follow, stop, kernel, export])
+        .setup(|app, _api| {
+            app.manage(LogManager::default());
+            return Ok(());
+        })
+        .build()
+}
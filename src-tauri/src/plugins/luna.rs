@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+use tauri::ipc::Channel;
+use tauri::plugin::{Builder, TauriPlugin};
+use tauri::{AppHandle, Manager, Runtime, State};
+
+use crate::device_manager::{ActivityKind, Device, DeviceManager, LunaBookmark};
+use crate::error::Error;
+use crate::plugins::cmd::spawn_proc;
+use crate::session_manager::{luna, SessionManager};
+
+/// Calls Luna service method `uri` with `params`, and returns the response. Unless `public` says
+/// otherwise, `luna-send-pub` is used if the device isn't rooted.
+#[tauri::command]
+async fn call<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    uri: String,
+    params: Option<Value>,
+    public: Option<bool>,
+) -> Result<Value, Error> {
+    app.state::<DeviceManager>()
+        .record_activity(&device.name, ActivityKind::Exec);
+    let public = public.unwrap_or(!device.is_rooted());
+    let params = params.unwrap_or(Value::Object(Default::default()));
+    return app
+        .state::<SessionManager>()
+        .run(device, move |session| {
+            luna::call(session, &uri, &params, public)
+        })
+        .await;
+}
+
+/// Subscribes to Luna service method `uri` like [call] does, and returns the token of the
+/// process. Responses are sent to `on_data` as they come, one per line.
+#[tauri::command]
+async fn subscribe<R: Runtime>(
+    app: AppHandle<R>,
+    sessions: State<'_, SessionManager>,
+    device: Device,
+    uri: String,
+    params: Option<Value>,
+    public: Option<bool>,
+    on_data: Channel,
+) -> Result<String, Error> {
+    app.state::<DeviceManager>()
+        .record_activity(&device.name, ActivityKind::Exec);
+    let public = public.unwrap_or(!device.is_rooted());
+    let params = params.unwrap_or(Value::Object(Default::default()));
+    let command = luna::command(&uri, &params, public, true);
+    return Ok(spawn_proc(
+        app,
+        &sessions,
+        device,
+        &command,
+        true,
+        Some(on_data),
+    ));
+}
+
+/// Calls the Luna bookmark of the device named `bookmark`, with its parameter template filled in
+/// with `vars`.
+#[tauri::command]
+async fn bookmark_call<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    bookmark: String,
+    vars: Option<HashMap<String, String>>,
+) -> Result<Value, Error> {
+    let bookmark = device
+        .luna_bookmarks
+        .iter()
+        .flatten()
+        .find(|b| b.name == bookmark)
+        .cloned()
+        .ok_or(Error::NotFound)?;
+    let params = luna::render(&bookmark.params, &vars.unwrap_or_default())?;
+    return call(app, device, bookmark.uri, Some(params), bookmark.public).await;
+}
+
+#[tauri::command]
+async fn bookmark_add(
+    manager: State<'_, DeviceManager>,
+    name: String,
+    bookmark: LunaBookmark,
+) -> Result<Device, Error> {
+    return manager.add_luna_bookmark(&name, bookmark).await;
+}
+
+#[tauri::command]
+async fn bookmark_remove(
+    manager: State<'_, DeviceManager>,
+    name: String,
+    bookmark: String,
+) -> Result<Device, Error> {
+    return manager.remove_luna_bookmark(&name, &bookmark).await;
+}
+
+/// Initializes the plugin.
+pub fn plugin<R: Runtime>(name: &'static str) -> TauriPlugin<R> {
+    Builder::new(name)
+        .invoke_handler(tauri::generate_handler![
+            call,
+            subscribe,
+            bookmark_call,
+            bookmark_add,
+            bookmark_remove
+        ])
+        .build()
+}
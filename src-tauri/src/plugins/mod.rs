@@ -1,6 +1,16 @@
+pub mod app;
+pub mod batch;
 pub mod cmd;
 pub mod device;
 pub mod devmode;
+pub mod emulator;
 pub mod file;
+pub mod homebrew;
+pub mod input;
 pub mod local_file;
+pub mod logs;
+pub mod luna;
+pub mod network;
+pub mod screen;
 pub mod shell;
+pub mod system;
@@ -0,0 +1,82 @@
+use tauri::ipc::Channel;
+use tauri::plugin::{Builder, TauriPlugin};
+use tauri::{AppHandle, Manager, Runtime, State};
+
+use crate::device_manager::{ActivityKind, Device, DeviceManager};
+use crate::error::Error;
+use crate::network_manager::diagnostics::{self, DEFAULT_MAX_HOPS};
+use crate::network_manager::{status, NetworkStatus};
+use crate::plugins::cmd::spawn_proc;
+use crate::session_manager::SessionManager;
+
+/// Reports interfaces, the default route, DNS servers and Wi-Fi link of the device.
+#[tauri::command]
+async fn status<R: Runtime>(app: AppHandle<R>, device: Device) -> Result<NetworkStatus, Error> {
+    return app
+        .state::<SessionManager>()
+        .run(device, |session| status::network_status(session))
+        .await;
+}
+
+/// Pings `target` from the device `count` times, or until stopped without `count`. Like `spawn` of
+/// `remote-command`, output is sent to `on_data` as it comes, and the token of the process is
+/// returned for stopping it.
+#[tauri::command]
+async fn ping<R: Runtime>(
+    app: AppHandle<R>,
+    sessions: State<'_, SessionManager>,
+    device: Device,
+    target: String,
+    count: Option<u32>,
+    on_data: Channel,
+) -> Result<String, Error> {
+    let command = diagnostics::ping_command(&target, count)?;
+    return Ok(run_diagnostic(app, &sessions, device, &command, on_data));
+}
+
+/// Traces the route from the device to `target`, streaming output like [ping].
+#[tauri::command]
+async fn traceroute<R: Runtime>(
+    app: AppHandle<R>,
+    sessions: State<'_, SessionManager>,
+    device: Device,
+    target: String,
+    max_hops: Option<u32>,
+    on_data: Channel,
+) -> Result<String, Error> {
+    let command = diagnostics::traceroute_command(&target, max_hops.unwrap_or(DEFAULT_MAX_HOPS))?;
+    return Ok(run_diagnostic(app, &sessions, device, &command, on_data));
+}
+
+/// Resolves `name` on the device, with DNS `server` if given, streaming output like [ping].
+#[tauri::command]
+async fn lookup<R: Runtime>(
+    app: AppHandle<R>,
+    sessions: State<'_, SessionManager>,
+    device: Device,
+    name: String,
+    server: Option<String>,
+    on_data: Channel,
+) -> Result<String, Error> {
+    let command = diagnostics::lookup_command(&name, server.as_deref())?;
+    return Ok(run_diagnostic(app, &sessions, device, &command, on_data));
+}
+
+fn run_diagnostic<R: Runtime>(
+    app: AppHandle<R>,
+    sessions: &SessionManager,
+    device: Device,
+    command: &str,
+    on_data: Channel,
+) -> String {
+    app.state::<DeviceManager>()
+        .record_activity(&device.name, ActivityKind::Exec);
+    return spawn_proc(app, sessions, device, command, true, Some(on_data));
+}
+
+/// Initializes the plugin.
+pub fn plugin<R: Runtime>(name: &'static str) -> TauriPlugin<R> {
+    Builder::new(name)
+        .invoke_handler(tauri::generate_handler![status, ping, traceroute, lookup])
+        .build()
+}
@@ -0,0 +1,104 @@
+use std::env::temp_dir;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use tauri::ipc::{Channel, Response};
+use tauri::plugin::{Builder, TauriPlugin};
+use tauri::{AppHandle, Manager, Runtime, State};
+use uuid::Uuid;
+
+use crate::device_manager::Device;
+use crate::error::Error;
+use crate::screen_manager::{capture, ScreenManager, ScreenStreamClosed};
+use crate::session_manager::SessionManager;
+
+const DEFAULT_STREAM_INTERVAL_MS: u64 = 1000;
+
+/// Captures the device screen as PNG, saves it to local file `target`, or a temporary file by
+/// default, and returns its path.
+#[tauri::command]
+async fn capture<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    target: Option<String>,
+) -> Result<String, Error> {
+    let data = app
+        .state::<SessionManager>()
+        .run(device, |session| capture::capture(session))
+        .await?;
+    let target = match target {
+        Some(target) => target,
+        None => temp_dir()
+            .join(format!("webos-dev-capture-{}.png", Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned(),
+    };
+    tokio::fs::write(&target, data).await?;
+    return Ok(target);
+}
+
+/// Starts capturing the device screen every `interval_ms`, sending each PNG frame to `on_frame`,
+/// and returns the stream token. When the stream ends, `screen-stream-closed` is emitted.
+#[tauri::command]
+async fn stream_start<R: Runtime>(
+    app: AppHandle<R>,
+    screens: State<'_, ScreenManager>,
+    device: Device,
+    interval_ms: Option<u64>,
+    on_frame: Channel,
+) -> Result<String, Error> {
+    let interval = Duration::from_millis(interval_ms.unwrap_or(DEFAULT_STREAM_INTERVAL_MS));
+    let stream = screens.open(interval);
+    let token = stream.token.clone();
+    tokio::task::spawn_blocking(move || {
+        let result = app
+            .state::<SessionManager>()
+            .with_session(device, |session| {
+                return stream.run(session, |frame| {
+                    return on_frame
+                        .send(Response::new(frame))
+                        .map_err(|e| Error::new(format!("Failed to send frame: {e}")));
+                });
+            });
+        app.state::<ScreenManager>()
+            .close(&stream.token)
+            .unwrap_or(());
+        let closed = ScreenStreamClosed {
+            token: stream.token.clone(),
+            error: result.err(),
+        };
+        app.emit("screen-stream-closed", closed).unwrap_or(());
+    });
+    return Ok(token);
+}
+
+#[tauri::command]
+async fn stream_stop(screens: State<'_, ScreenManager>, token: String) -> Result<(), Error> {
+    return screens.close(&token);
+}
+
+/// Saves frames of the stream to local directory `dir` from now on, or stops saving without it.
+#[tauri::command]
+async fn stream_record(
+    screens: State<'_, ScreenManager>,
+    token: String,
+    dir: Option<String>,
+) -> Result<(), Error> {
+    return screens.record(&token, dir.map(PathBuf::from));
+}
+
+/// Initializes the plugin.
+pub fn plugin<R: Runtime>(name: &'static str) -> TauriPlugin<R> {
+    Builder::new(name)
+        .invoke_handler(tauri::generate_handler![
+            capture,
+            stream_start,
+            stream_stop,
+            stream_record
+        ])
+        .setup(|app, _api| {
+            app.manage(ScreenManager::default());
+            return Ok(());
+        })
+        .build()
+}
@@ -1,10 +1,18 @@
+use std::path::PathBuf;
+
+use tauri::ipc::{Channel, Response};
 use tauri::plugin::{Builder, TauriPlugin};
 use tauri::{AppHandle, Manager, Runtime, State};
 
-use crate::device_manager::Device;
+use crate::device_manager::{ActivityKind, Device, DeviceManager};
 use crate::error::Error;
+use crate::session_manager::SessionManager;
+use crate::shell_manager::persist;
+use crate::shell_manager::zmodem::{ZmodemDirection, ZmodemProgress};
 use crate::shell_manager::{
-    ShellCallback, ShellData, ShellInfo, ShellManager, ShellScreen, ShellToken,
+    ShellCallback, ShellClipboard, ShellCloseReason, ShellClosed, ShellData, ShellInfo,
+    ShellManager, ShellOptions, ShellPending, ShellScreen, ShellSearchMatch, ShellToken,
+    ShellZmodem, ShellZmodemProgress,
 };
 
 #[tauri::command]
@@ -15,16 +23,63 @@ fn open<R: Runtime>(
     cols: u16,
     rows: u16,
     dumb: Option<bool>,
+    elevate: Option<bool>,
+    term: Option<String>,
+    truecolor: Option<bool>,
+    session: Option<String>,
 ) -> Result<ShellInfo, Error> {
-    let shell = manager.open(device, rows, cols, dumb.unwrap_or(false));
+    if let Some(name) = &session {
+        if !persist::valid_name(name) {
+            return Err(Error::new(format!("Invalid session name {name}")));
+        }
+    }
+    let options = ShellOptions {
+        wants_pty: !dumb.unwrap_or(false),
+        elevate: elevate.unwrap_or(false),
+        term: term
+            .or_else(|| device.term.clone())
+            .unwrap_or_else(|| String::from("xterm-256color")),
+        truecolor: truecolor.or(device.truecolor).unwrap_or(false),
+        session,
+    };
+    if options.elevate && device.elevation.is_none() {
+        return Err(Error::Unsupported);
+    }
+    app.state::<DeviceManager>()
+        .record_activity(&device.name, ActivityKind::Shell);
+    let shell = manager.open(device, rows, cols, options);
     *shell.callback.lock().unwrap() = Some(Box::new(PluginShellCb::<R> {
         token: shell.token.clone(),
         app: app.clone(),
+        output: None,
     }));
     app.emit("shell-opened", &shell.token).unwrap_or(());
     return Ok(shell.info());
 }
 
+/// Sends output of the shell to `on_data` as raw bytes instead of `shell-rx` events. Each message
+/// starts with the fd byte, followed by the data.
+#[tauri::command]
+async fn attach<R: Runtime>(
+    app: AppHandle<R>,
+    manager: State<'_, ShellManager>,
+    token: ShellToken,
+    on_data: Channel,
+) -> Result<ShellInfo, Error> {
+    let shell = manager.find(&token).ok_or(Error::NotFound)?;
+    let mut callback = shell.callback.lock().unwrap();
+    if callback.is_none() {
+        return Err(Error::Disconnected);
+    }
+    *callback = Some(Box::new(PluginShellCb::<R> {
+        token: shell.token.clone(),
+        app: app.clone(),
+        output: Some(on_data),
+    }));
+    drop(callback);
+    return Ok(shell.info());
+}
+
 #[tauri::command]
 async fn close<R: Runtime>(
     app: AppHandle<R>,
@@ -63,6 +118,84 @@ async fn screen(
     return shell.screen(cols);
 }
 
+#[tauri::command]
+async fn search(
+    manager: State<'_, ShellManager>,
+    token: ShellToken,
+    pattern: String,
+    limit: Option<usize>,
+) -> Result<Vec<ShellSearchMatch>, Error> {
+    let shell = manager.find(&token).ok_or(Error::NotFound)?;
+    return shell.search(&pattern, limit.unwrap_or(100));
+}
+
+#[tauri::command]
+async fn rename<R: Runtime>(
+    app: AppHandle<R>,
+    manager: State<'_, ShellManager>,
+    token: ShellToken,
+    title: Option<String>,
+) -> Result<ShellInfo, Error> {
+    let shell = manager.find(&token).ok_or(Error::NotFound)?;
+    shell.rename(title);
+    app.emit("shells-updated", manager.list()).unwrap_or(());
+    return Ok(shell.info());
+}
+
+#[tauri::command]
+async fn scrollback(manager: State<'_, ShellManager>, token: ShellToken) -> Result<Vec<u8>, Error> {
+    let shell = manager.find(&token).ok_or(Error::NotFound)?;
+    return Ok(shell.scrollback());
+}
+
+#[tauri::command]
+async fn zmodem_receive(
+    manager: State<'_, ShellManager>,
+    token: ShellToken,
+    dir: PathBuf,
+) -> Result<(), Error> {
+    let shell = manager.find(&token).ok_or(Error::NotFound)?;
+    return shell.zmodem_receive(&dir);
+}
+
+#[tauri::command]
+async fn zmodem_send(
+    manager: State<'_, ShellManager>,
+    token: ShellToken,
+    paths: Vec<PathBuf>,
+) -> Result<(), Error> {
+    let shell = manager.find(&token).ok_or(Error::NotFound)?;
+    return shell.zmodem_send(&paths);
+}
+
+#[tauri::command]
+async fn zmodem_end(manager: State<'_, ShellManager>, token: ShellToken) -> Result<(), Error> {
+    let shell = manager.find(&token).ok_or(Error::NotFound)?;
+    shell.zmodem_end();
+    return Ok(());
+}
+
+#[tauri::command]
+async fn focus(manager: State<'_, ShellManager>, token: Option<ShellToken>) -> Result<(), Error> {
+    manager.focus(token.as_ref());
+    return Ok(());
+}
+
+/// Lists multiplexer sessions left on the device, which can be reattached with `open`.
+#[tauri::command]
+async fn sessions<R: Runtime>(app: AppHandle<R>, device: Device) -> Result<Vec<String>, Error> {
+    return tokio::task::spawn_blocking(move || {
+        let sessions = app.state::<SessionManager>();
+        let output = sessions.exec(device.clone(), persist::list_command(&device), None)?;
+        return Ok(persist::parse_list(
+            &device,
+            &String::from_utf8_lossy(&output),
+        ));
+    })
+    .await
+    .expect("critical failure in shell::sessions task");
+}
+
 #[tauri::command]
 async fn list(manager: State<'_, ShellManager>) -> Result<Vec<ShellInfo>, Error> {
     return Ok(manager.list());
@@ -71,7 +204,21 @@ async fn list(manager: State<'_, ShellManager>) -> Result<Vec<ShellInfo>, Error>
 pub fn plugin<R: Runtime>(name: &'static str) -> TauriPlugin<R> {
     Builder::new(name)
         .invoke_handler(tauri::generate_handler![
-            open, close, write, resize, screen, list
+            open,
+            attach,
+            close,
+            write,
+            resize,
+            screen,
+            scrollback,
+            search,
+            rename,
+            zmodem_receive,
+            zmodem_send,
+            zmodem_end,
+            focus,
+            sessions,
+            list
         ])
         .build()
 }
@@ -79,6 +226,7 @@ pub fn plugin<R: Runtime>(name: &'static str) -> TauriPlugin<R> {
 struct PluginShellCb<R: Runtime> {
     token: ShellToken,
     app: AppHandle<R>,
+    output: Option<Channel>,
 }
 
 impl<R: Runtime> ShellCallback for PluginShellCb<R> {
@@ -87,6 +235,13 @@ impl<R: Runtime> ShellCallback for PluginShellCb<R> {
     }
 
     fn rx(&self, fd: u32, data: &[u8]) {
+        if let Some(output) = &self.output {
+            let mut body = Vec::with_capacity(data.len() + 1);
+            body.push(fd as u8);
+            body.extend_from_slice(data);
+            output.send(Response::new(body)).unwrap_or(());
+            return;
+        }
         let payload = ShellData {
             token: self.token.clone(),
             data: Vec::from(data),
@@ -95,8 +250,54 @@ impl<R: Runtime> ShellCallback for PluginShellCb<R> {
         self.app.emit("shell-rx", payload).unwrap_or(());
     }
 
-    fn closed(&self, removed: bool) {
+    fn pending(&self, bytes: usize) {
+        let payload = ShellPending {
+            token: self.token.clone(),
+            pending: bytes,
+        };
+        self.app.emit("shell-pending", payload).unwrap_or(());
+    }
+
+    fn clipboard(&self, selection: String, data: String) {
+        let payload = ShellClipboard {
+            token: self.token.clone(),
+            selection,
+            data,
+        };
+        self.app.emit("shell-clipboard", payload).unwrap_or(());
+    }
+
+    fn zmodem(&self, direction: Option<ZmodemDirection>) {
+        let payload = ShellZmodem {
+            token: self.token.clone(),
+            direction,
+        };
+        self.app.emit("shell-zmodem", payload).unwrap_or(());
+    }
+
+    fn zmodem_progress(&self, progress: ZmodemProgress) {
+        let payload = ShellZmodemProgress {
+            token: self.token.clone(),
+            progress,
+        };
+        self.app
+            .emit("shell-zmodem-progress", payload)
+            .unwrap_or(());
+    }
+
+    fn activity(&self) {
+        self.app
+            .emit("shell-activity", self.token.clone())
+            .unwrap_or(());
+    }
+
+    fn closed(&self, reason: ShellCloseReason, removed: bool) {
         let shells = self.app.state::<ShellManager>();
+        let payload = ShellClosed {
+            token: self.token.clone(),
+            reason,
+        };
+        self.app.emit("shell-closed", payload).unwrap_or(());
         if removed {
             self.app
                 .emit("shell-removed", self.token.clone())
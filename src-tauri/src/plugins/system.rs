@@ -0,0 +1,265 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tauri::ipc::Channel;
+use tauri::plugin::{Builder, TauriPlugin};
+use tauri::{AppHandle, Manager, Runtime, State};
+
+use crate::app_dirs::GetConfDir;
+use crate::device_manager::Device;
+use crate::error::Error;
+use crate::session_manager::SessionManager;
+use crate::spawn_manager::SpawnManager;
+use crate::system_manager::{
+    cores, crashes, info, metrics, processes as procs, services as svcs, CoreDownload, CoreDump,
+    CrashReport, ProcessInfo, ResourceSample, ServiceAction, SystemInfo, SystemManager,
+    SystemService,
+};
+
+const DEFAULT_MONITOR_INTERVAL_SECS: u64 = 2;
+const DEFAULT_MONITOR_TOP: usize = 10;
+
+/// Collects an overview of the device: versions, uptime, load, memory, storage and addresses.
+#[tauri::command]
+async fn system_info<R: Runtime>(app: AppHandle<R>, device: Device) -> Result<SystemInfo, Error> {
+    return app
+        .state::<SessionManager>()
+        .run(device, |session| info::system_info(session))
+        .await;
+}
+
+#[tauri::command]
+async fn processes<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+) -> Result<Vec<ProcessInfo>, Error> {
+    return app
+        .state::<SessionManager>()
+        .run(device, |session| procs::processes(session))
+        .await;
+}
+
+/// Sends `signal`, `TERM` by default, to process `pid` on the device.
+#[tauri::command]
+async fn kill<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    pid: u32,
+    signal: Option<String>,
+) -> Result<(), Error> {
+    let signal = signal.unwrap_or_else(|| String::from("TERM"));
+    return app
+        .state::<SessionManager>()
+        .run(device, move |session| procs::kill(session, pid, &signal))
+        .await;
+}
+
+/// Lists RDX and crash reports on the device, newest first.
+#[tauri::command]
+async fn crash_reports<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+) -> Result<Vec<CrashReport>, Error> {
+    return app
+        .state::<SessionManager>()
+        .run(device, |session| crashes::crash_reports(session))
+        .await;
+}
+
+/// Downloads crash reports at `paths` into local directory `dir`, and returns the local paths.
+#[tauri::command]
+async fn crash_download<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    paths: Vec<String>,
+    dir: String,
+) -> Result<Vec<String>, Error> {
+    return app
+        .state::<SessionManager>()
+        .run(device, move |session| {
+            crashes::download_crash_reports(session, &paths, Path::new(&dir))
+        })
+        .await;
+}
+
+/// Lists core dumps on the device, newest first, only of `binary` if given. It can be a path,
+/// an app or service ID, or a process name.
+#[tauri::command]
+async fn core_dumps<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    binary: Option<String>,
+) -> Result<Vec<CoreDump>, Error> {
+    return app
+        .state::<SessionManager>()
+        .run(device, move |session| {
+            cores::core_dumps(session, binary.as_deref())
+        })
+        .await;
+}
+
+/// Downloads core dump `path` compressed into local directory `dir`, recording the path of the
+/// crashed binary, looked up by `binary` or the process name.
+#[tauri::command]
+async fn core_download<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    path: String,
+    binary: Option<String>,
+    dir: String,
+) -> Result<CoreDownload, Error> {
+    return app
+        .state::<SessionManager>()
+        .run(device, move |session| {
+            cores::download_core_dump(session, &path, binary.as_deref(), Path::new(&dir))
+        })
+        .await;
+}
+
+/// Lists system services of the device, from systemd or upstart. Only for rooted or OSE devices.
+#[tauri::command]
+async fn services<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+) -> Result<Vec<SystemService>, Error> {
+    if !device.is_rooted() && !device.is_ose() {
+        return Err(Error::Unsupported);
+    }
+    return app
+        .state::<SessionManager>()
+        .run(device, |session| svcs::services(session))
+        .await;
+}
+
+/// Starts, stops or restarts service `name`. Stopping or restarting risky services fails with
+/// `ConfirmationRequired` unless `confirmed`.
+#[tauri::command]
+async fn service_control<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    name: String,
+    action: ServiceAction,
+    confirmed: Option<bool>,
+) -> Result<(), Error> {
+    if !device.is_rooted() && !device.is_ose() {
+        return Err(Error::Unsupported);
+    }
+    let confirmed = confirmed.unwrap_or(false);
+    return app
+        .state::<SessionManager>()
+        .run(device, move |session| {
+            svcs::control(session, &name, action, confirmed)
+        })
+        .await;
+}
+
+/// Starts sampling CPU, memory, load and the `top` processes using most CPU every
+/// `interval_secs`, sending each [ResourceSample] to `on_sample`. With `record`, samples are also
+/// added to the metrics history of the device. Returns the monitor token.
+#[tauri::command]
+async fn monitor_start<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    interval_secs: Option<u64>,
+    top: Option<usize>,
+    record: Option<bool>,
+    on_sample: Channel,
+) -> Result<String, Error> {
+    let interval = Duration::from_secs(interval_secs.unwrap_or(DEFAULT_MONITOR_INTERVAL_SECS));
+    let history = match record.unwrap_or(false) {
+        true => Some((metrics_dir(&app)?, device_id(&device)?, device.name.clone())),
+        false => None,
+    };
+    let (token, proc) =
+        app.state::<SystemManager>()
+            .monitor(&app.state::<SessionManager>(), device, interval);
+    app.state::<SpawnManager>().add_proc(proc.clone());
+    let monitor = token.clone();
+    tokio::task::spawn_blocking(move || {
+        let result = app.state::<SystemManager>().run_monitor(
+            &app.state::<SessionManager>(),
+            &monitor,
+            proc,
+            top.unwrap_or(DEFAULT_MONITOR_TOP),
+            move |sample: ResourceSample| {
+                if let Some((dir, id, name)) = &history {
+                    if let Err(e) = metrics::record(dir, id, &sample) {
+                        log::warn!("Failed to record metrics of {name}: {e:?}");
+                    }
+                }
+                on_sample.send(sample).unwrap_or(());
+            },
+        );
+        if let Err(e) = result {
+            log::warn!("Resource monitor {monitor} closed with {e:?}");
+        }
+    });
+    return Ok(token);
+}
+
+#[tauri::command]
+async fn monitor_stop(systems: State<'_, SystemManager>, token: String) -> Result<(), Error> {
+    return systems.stop_monitor(&token);
+}
+
+/// Reads the metrics history of `device` between `from` and `to`, in milliseconds since epoch.
+/// With `points`, samples are averaged down to at most that many.
+#[tauri::command]
+async fn metrics_query<R: Runtime>(
+    app: AppHandle<R>,
+    device: Device,
+    from: Option<u64>,
+    to: Option<u64>,
+    points: Option<usize>,
+) -> Result<Vec<ResourceSample>, Error> {
+    let dir = metrics_dir(&app)?;
+    let id = device_id(&device)?;
+    return tokio::task::spawn_blocking(move || metrics::query(&dir, &id, from, to, points))
+        .await
+        .expect("critical failure in system::metrics_query task");
+}
+
+#[tauri::command]
+async fn metrics_clear<R: Runtime>(app: AppHandle<R>, device: Device) -> Result<(), Error> {
+    return metrics::clear(&metrics_dir(&app)?, &device_id(&device)?);
+}
+
+/// Metrics are kept by device ID, so they survive renaming the device
+fn device_id(device: &Device) -> Result<String, Error> {
+    return device
+        .id
+        .clone()
+        .ok_or_else(|| Error::new(format!("Device {} has no ID", device.name)));
+}
+
+fn metrics_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, Error> {
+    return app
+        .get_conf_dir()
+        .map(|dir| dir.join("metrics"))
+        .ok_or_else(Error::bad_config);
+}
+
+/// Initializes the plugin.
+pub fn plugin<R: Runtime>(name: &'static str) -> TauriPlugin<R> {
+    Builder::new(name)
+        .invoke_handler(tauri::generate_handler![
+            system_info,
+            processes,
+            kill,
+            crash_reports,
+            crash_download,
+            core_dumps,
+            core_download,
+            services,
+            service_control,
+            monitor_start,
+            monitor_stop,
+            metrics_query,
+            metrics_clear
+        ])
+        .setup(|app, _api| {
+            app.manage(SystemManager::default());
+            return Ok(());
+        })
+        .build()
+}
@@ -0,0 +1,39 @@
+use crate::session_manager::command::quote;
+
+/// `tar` command archiving `path` into `archive`, with entries relative to the parent of `path`,
+/// or to `path` itself if it's the root directory.
+/// Compresses with gzip if `archive` is named `.tar.gz` or `.tgz`.
+pub(crate) fn create_command(path: &str, archive: &str) -> String {
+    let path = path.trim_end_matches('/');
+    let (parent, name) = match path.rsplit_once('/') {
+        // The root directory itself
+        None if path.is_empty() => ("/", "."),
+        Some(("", name)) => ("/", name),
+        Some((parent, name)) => (parent, name),
+        None => (".", path),
+    };
+    return format!(
+        "tar -c{}f {} -C {} {}",
+        compression(archive),
+        quote(archive),
+        quote(parent),
+        quote(name)
+    );
+}
+
+/// `tar` command extracting `archive` into directory `target`, creating it if needed.
+pub(crate) fn extract_command(archive: &str, target: &str) -> String {
+    let target = quote(target);
+    return format!(
+        "mkdir -p {target} && tar -x{}f {} -C {target}",
+        compression(archive),
+        quote(archive)
+    );
+}
+
+fn compression(archive: &str) -> &'static str {
+    if archive.ends_with(".tar.gz") || archive.ends_with(".tgz") {
+        return "z";
+    }
+    return "";
+}
@@ -0,0 +1,139 @@
+use crate::conn_pool::DeviceConnectionUserInfo;
+use crate::remote_files::{FileItem, LinkInfo, PermInfo};
+use crate::session_manager::command::quote;
+
+/// Prints `E <mode in hex> <size> <mtime> <uid> <gid> <user> <group> <name>` for path `$f`, and for
+/// symlinks `L <0, or 1 if broken> <target>` after it
+const STAT_SCRIPT: &str = "stat -c 'E %f %s %Y %u %g %U %G %n' -- \"$f\" && \
+    if [ -L \"$f\" ]; then [ -e \"$f\" ]; printf 'L %s %s\\n' $? \"$(readlink -- \"$f\")\"; fi";
+
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+
+/// File status printed by shell commands, for devices without SFTP
+#[derive(Debug)]
+pub(crate) struct ExecStat {
+    pub name: String,
+    /// File type and permission bits
+    pub mode: u32,
+    pub size: u64,
+    /// Seconds since epoch
+    pub mtime: u64,
+    pub uid: u32,
+    pub gid: u32,
+    pub user: String,
+    pub group: String,
+    pub link: Option<LinkInfo>,
+}
+
+impl ExecStat {
+    /// Command printing the status of `path`. Symlinks are followed with `follow`, otherwise
+    /// they're reported along with their target.
+    pub(crate) fn command(path: &str, follow: bool) -> String {
+        if follow {
+            return format!("stat -L -c 'E %f %s %Y %u %g %U %G %n' -- {}", quote(path));
+        }
+        return format!("f={}; {STAT_SCRIPT}", quote(path));
+    }
+
+    /// Command printing the status of entries in directory `path`, without `.` and `..`.
+    pub(crate) fn list_command(path: &str) -> String {
+        return format!(
+            "cd -- {} && for f in .* *; do case \"$f\" in .|..) continue;; esac; \
+             [ -e \"$f\" ] || [ -L \"$f\" ] || continue; {STAT_SCRIPT}; done; true",
+            quote(path)
+        );
+    }
+
+    /// Command printing the status of everything below directory `path`, named relative to it.
+    pub(crate) fn walk_command(path: &str) -> String {
+        return format!(
+            "cd -- {} && find . -mindepth 1 | while IFS= read -r f; do {STAT_SCRIPT}; done",
+            quote(path)
+        );
+    }
+
+    /// Parses output of the commands above.
+    pub(crate) fn parse(output: &str) -> Vec<ExecStat> {
+        let mut stats: Vec<ExecStat> = Vec::new();
+        for line in output.lines() {
+            if let Some(link) = line.strip_prefix("L ") {
+                let (broken, target) = link.split_once(' ').unwrap_or((link, ""));
+                if let Some(stat) = stats.last_mut() {
+                    stat.link = Some(LinkInfo {
+                        target: Some(String::from(target)),
+                        broken: Some(broken != "0"),
+                    });
+                }
+                continue;
+            }
+            let Some(fields) = line.strip_prefix("E ") else {
+                continue;
+            };
+            let fields: Vec<&str> = fields.splitn(8, ' ').collect();
+            let [mode, size, mtime, uid, gid, user, group, name] = fields[..] else {
+                continue;
+            };
+            let (Ok(mode), Ok(size), Ok(mtime), Ok(uid), Ok(gid)) = (
+                u32::from_str_radix(mode, 16),
+                size.parse(),
+                mtime.parse(),
+                uid.parse(),
+                gid.parse(),
+            ) else {
+                continue;
+            };
+            stats.push(ExecStat {
+                name: String::from(name.strip_prefix("./").unwrap_or(name)),
+                mode,
+                size,
+                mtime,
+                uid,
+                gid,
+                user: String::from(user),
+                group: String::from(group),
+                link: None,
+            });
+        }
+        return stats;
+    }
+
+    pub(crate) fn is_dir(&self) -> bool {
+        return self.mode & S_IFMT == S_IFDIR;
+    }
+
+    pub(crate) fn is_file(&self) -> bool {
+        return self.mode & S_IFMT == S_IFREG;
+    }
+
+    fn abbrev_type(&self) -> char {
+        return match self.mode & S_IFMT {
+            S_IFDIR => 'd',
+            S_IFREG => '-',
+            S_IFLNK => 'l',
+            _ => 'b',
+        };
+    }
+}
+
+impl FileItem {
+    pub(crate) fn from_exec(
+        name: &str,
+        stat: ExecStat,
+        user: Option<&DeviceConnectionUserInfo>,
+    ) -> Self {
+        return FileItem {
+            filename: String::from(name),
+            r#type: format!("{}", stat.abbrev_type()),
+            mode: unix_mode::to_string(stat.mode),
+            user: Some(stat.user),
+            group: Some(stat.group),
+            size: stat.size as usize,
+            mtime: stat.mtime as f64,
+            access: user.map(|u| PermInfo::from_mode(stat.mode, stat.uid, stat.gid, u)),
+            link: stat.link,
+        };
+    }
+}
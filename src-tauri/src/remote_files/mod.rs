@@ -1,7 +1,13 @@
 use serde::{Deserialize, Serialize};
 
+pub(crate) mod archive;
+pub(crate) mod exec;
+pub(crate) mod mounts;
+pub(crate) mod search;
 pub(crate) mod serve;
 mod sftp;
+pub(crate) mod trash;
+pub(crate) mod usage;
 
 #[derive(Serialize, Clone, Debug)]
 pub struct FileItem {
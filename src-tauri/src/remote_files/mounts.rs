@@ -0,0 +1,163 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::app_manager::APPS_ROOT;
+
+const SECTION: &str = "--- webos-dev-section ---";
+
+/// Where TVs and OSE mount USB drives, for devices without sysfs links to tell
+const EXTERNAL_ROOTS: [&str; 3] = ["/tmp/usb/", "/media/usb", "/run/media/"];
+
+/// Less free space than this is low, or less than 5% of the filesystem
+const LOW_SPACE_BYTES: u64 = 200 * 1024 * 1024;
+
+/// Mounted filesystem, sizes in bytes
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MountPoint {
+    pub source: String,
+    pub mount: String,
+    pub fs_type: String,
+    pub read_only: bool,
+    pub size: u64,
+    pub used: u64,
+    pub available: u64,
+    /// On a USB drive
+    pub external: bool,
+    /// Holds apps installed in developer mode
+    pub developer: bool,
+    pub low_space: bool,
+}
+
+impl MountPoint {
+    /// Command printing mounts, their sizes, and block devices on USB.
+    pub(crate) fn command() -> String {
+        return [
+            "cat /proc/mounts",
+            "df -P -k 2>/dev/null",
+            "for b in /sys/class/block/*; do case $(readlink -f $b) in */usb*) echo ${b##*/};; \
+             esac; done; true",
+        ]
+        .join(&format!("; echo '{SECTION}'; "));
+    }
+
+    /// Parses output of [MountPoint::command], skipping pseudo filesystems without size.
+    pub(crate) fn parse(output: &str) -> Vec<MountPoint> {
+        let sections: Vec<&str> = output.split(&format!("{SECTION}\n")).collect();
+        let section = |index: usize| sections.get(index).copied().unwrap_or("");
+        let usb: HashSet<&str> = section(2).split_whitespace().collect();
+        let mut mounts: Vec<MountPoint> = Vec::new();
+        for line in section(0).lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [source, mount, fs_type, options, ..] = fields[..] else {
+                continue;
+            };
+            let source = unescape(source);
+            let mount = unescape(mount);
+            let Some((size, used, available)) = df_sizes(section(1), &mount) else {
+                continue;
+            };
+            let external = source
+                .strip_prefix("/dev/")
+                .is_some_and(|name| usb.contains(name))
+                || EXTERNAL_ROOTS.iter().any(|root| mount.starts_with(root));
+            // Later mounts hide earlier ones on the same path
+            mounts.retain(|m| m.mount != mount);
+            mounts.push(MountPoint {
+                source,
+                mount,
+                fs_type: String::from(fs_type),
+                read_only: options.split(',').any(|option| option == "ro"),
+                size,
+                used,
+                available,
+                external,
+                developer: false,
+                low_space: available < LOW_SPACE_BYTES || available * 20 < size,
+            });
+        }
+        let developer = mounts
+            .iter_mut()
+            .filter(|m| m.mount == "/" || APPS_ROOT.starts_with(&format!("{}/", m.mount)))
+            .max_by_key(|m| m.mount.len());
+        if let Some(developer) = developer {
+            developer.developer = true;
+        }
+        return mounts;
+    }
+}
+
+/// Finds sizes of `mount` in POSIX output of `df -k`, where mount points are the last field.
+fn df_sizes(df: &str, mount: &str) -> Option<(u64, u64, u64)> {
+    return df.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 6 || fields[5..].join(" ") != mount {
+            return None;
+        }
+        let kbytes = |index: usize| fields[index].parse::<u64>().ok().map(|k| k * 1024);
+        let size = kbytes(1).filter(|size| *size > 0)?;
+        return Some((size, kbytes(2)?, kbytes(3)?));
+    });
+}
+
+/// Decodes octal escapes of `/proc/mounts`, like `\040` for spaces.
+fn unescape(field: &str) -> String {
+    let mut result = String::new();
+    let mut rest = field;
+    while let Some(index) = rest.find('\\') {
+        result.push_str(&rest[..index]);
+        let code = rest.get(index + 1..index + 4);
+        match code.and_then(|code| u8::from_str_radix(code, 8).ok()) {
+            Some(byte) => {
+                result.push(byte as char);
+                rest = &rest[index + 4..];
+            }
+            None => {
+                result.push('\\');
+                rest = &rest[index + 1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_mounts_with_sizes() {
+        let output = [
+            "/dev/root / ext4 ro,relatime 0 0\n\
+             proc /proc proc rw 0 0\n\
+             /dev/mmcblk0p20 /media/developer ext4 rw 0 0\n\
+             /dev/sda1 /tmp/usb/sda/my\\040drive vfat rw 0 0\n",
+            "Filesystem 1024-blocks Used Available Capacity Mounted on\n\
+             /dev/root 1000000 900000 100000 90% /\n\
+             /dev/mmcblk0p20 4000000 3900000 100000 98% /media/developer\n\
+             /dev/sda1 8000000 0 8000000 0% /tmp/usb/sda/my drive\n",
+            "sda1\n",
+        ]
+        .join(&format!("{SECTION}\n"));
+        let mounts = MountPoint::parse(&output);
+        let paths: Vec<&str> = mounts.iter().map(|m| m.mount.as_str()).collect();
+        assert_eq!(paths, ["/", "/media/developer", "/tmp/usb/sda/my drive"]);
+        assert!(mounts[0].read_only);
+        assert!(!mounts[0].developer);
+        assert!(mounts[1].developer);
+        assert!(mounts[1].low_space);
+        assert_eq!(mounts[1].available, 100000 * 1024);
+        assert!(mounts[2].external);
+        assert!(!mounts[2].low_space);
+    }
+
+    #[test]
+    fn unescapes_octal() {
+        assert_eq!(unescape("/media/my\\040drive"), "/media/my drive");
+        assert_eq!(unescape("tab\\011end"), "tab\tend");
+        assert_eq!(unescape("odd\\x"), "odd\\x");
+        assert_eq!(unescape("trailing\\"), "trailing\\");
+    }
+}
@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+use crate::session_manager::command::quote;
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchQuery {
+    /// Shell pattern matched against file names
+    pub name: Option<String>,
+    /// Basic regular expression matched against file contents
+    pub content: Option<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// Only files modified within this many days
+    pub modified_within: Option<u32>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SearchMatch {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+impl SearchQuery {
+    pub(crate) fn command(&self, path: &str) -> String {
+        let mut command = format!("find {} -type f", quote(path));
+        if let Some(name) = &self.name {
+            command.push_str(&format!(" -name {}", quote(name)));
+        }
+        if let Some(min_size) = self.min_size {
+            command.push_str(&format!(" -size +{}c", min_size.saturating_sub(1)));
+        }
+        if let Some(max_size) = self.max_size {
+            command.push_str(&format!(" -size -{}c", max_size + 1));
+        }
+        if let Some(days) = self.modified_within {
+            command.push_str(&format!(" -mtime -{days}"));
+        }
+        if let Some(content) = &self.content {
+            command.push_str(&format!(" -exec grep -n -H -e {} {{}} +", quote(content)));
+        }
+        // Unreadable directories are expected, and not worth failing the search for
+        command.push_str(" 2>/dev/null");
+        return command;
+    }
+
+    pub(crate) fn parse(&self, line: &str) -> Option<SearchMatch> {
+        if line.is_empty() {
+            return None;
+        }
+        if self.content.is_none() {
+            return Some(SearchMatch {
+                path: String::from(line),
+                line: None,
+                text: None,
+            });
+        }
+        let mut parts = line.splitn(3, ':');
+        let path = parts.next()?;
+        let number = parts.next()?.parse::<u32>().ok()?;
+        return Some(SearchMatch {
+            path: String::from(path),
+            line: Some(number),
+            text: parts.next().map(String::from),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_name_matches() {
+        let query = SearchQuery::default();
+        let found = query.parse("/media/internal/a:b.txt").unwrap();
+        assert_eq!(found.path, "/media/internal/a:b.txt");
+        assert_eq!(found.line, None);
+        assert!(query.parse("").is_none());
+    }
+
+    #[test]
+    fn parses_content_matches() {
+        let query = SearchQuery {
+            content: Some(String::from("foo")),
+            ..SearchQuery::default()
+        };
+        let found = query.parse("/etc/hosts:12:foo: bar").unwrap();
+        assert_eq!(found.path, "/etc/hosts");
+        assert_eq!(found.line, Some(12));
+        assert_eq!(found.text.as_deref(), Some("foo: bar"));
+        assert!(query.parse("/etc/hosts:x:foo").is_none());
+        assert!(query.parse("Binary file matches").is_none());
+    }
+}
@@ -1,7 +1,7 @@
 use std::time::UNIX_EPOCH;
 
 use crate::conn_pool::DeviceConnectionUserInfo;
-use libssh_rs::{FileType, Metadata};
+use libssh_rs::{FileType, Metadata, Sftp};
 
 use crate::remote_files::{FileItem, LinkInfo, PermInfo};
 
@@ -13,8 +13,17 @@ impl From<&Metadata> for FileItem {
 
 impl FileItem {
     pub(crate) fn new(stat: &Metadata, link: Option<LinkInfo>, access: Option<PermInfo>) -> Self {
+        return Self::named(stat.name().unwrap(), stat, link, access);
+    }
+
+    pub(crate) fn named(
+        name: &str,
+        stat: &Metadata,
+        link: Option<LinkInfo>,
+        access: Option<PermInfo>,
+    ) -> Self {
         return FileItem {
-            filename: String::from(stat.name().unwrap()),
+            filename: String::from(name),
             r#type: format!(
                 "{}",
                 abbrev_type(stat.file_type().unwrap_or(FileType::Unknown))
@@ -35,10 +44,28 @@ impl FileItem {
     }
 }
 
+impl LinkInfo {
+    pub(crate) fn resolve(sftp: &Sftp, path: &str) -> Self {
+        return LinkInfo {
+            target: sftp.read_link(path).ok(),
+            broken: Some(sftp.metadata(path).is_err()),
+        };
+    }
+}
+
 impl PermInfo {
     pub fn from(stat: &Metadata, user: &DeviceConnectionUserInfo) -> Self {
-        let perms = stat.permissions().unwrap_or(0);
-        if user.uid.id == stat.uid().unwrap_or(0) {
+        return Self::from_mode(
+            stat.permissions().unwrap_or(0),
+            stat.uid().unwrap_or(0),
+            stat.gid().unwrap_or(0),
+            user,
+        );
+    }
+
+    /// Access of `user` to a file with permission bits `perms`, owned by `uid` and `gid`.
+    pub fn from_mode(perms: u32, uid: u32, gid: u32, user: &DeviceConnectionUserInfo) -> Self {
+        if user.uid.id == uid {
             return PermInfo {
                 read: (perms & 0o400) != 0,
                 write: (perms & 0o200) != 0,
@@ -46,7 +73,7 @@ impl PermInfo {
             };
         }
         for group in &user.groups {
-            if group.id == stat.gid().unwrap_or(0) {
+            if group.id == gid {
                 return PermInfo {
                     read: (perms & 0o040) != 0,
                     write: (perms & 0o020) != 0,
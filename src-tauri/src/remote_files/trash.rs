@@ -0,0 +1,175 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::remote_files::mounts::MountPoint;
+use crate::session_manager::command::quote;
+
+/// Laid out like the freedesktop.org trash, with `files` and `info` subdirectories. Used for paths
+/// on the developer filesystem, and for others whose filesystem has no writable trash.
+const DEFAULT_TRASH_DIR: &str = "/media/developer/.trash";
+
+/// Trash at the top of other filesystems, so trashing doesn't copy between filesystems
+const TRASH_NAME: &str = ".trash";
+
+#[derive(Serialize, Clone, Debug)]
+pub struct TrashItem {
+    pub id: String,
+    /// Where the file was before it was trashed
+    pub path: String,
+    /// Seconds since epoch
+    pub deleted: u64,
+    /// Trash directory holding the item
+    #[serde(skip)]
+    trash: String,
+}
+
+/// Moves `path` into the trash of its filesystem. `mv` is used instead of SFTP rename, as the file
+/// may be on another filesystem than the trash, if its own has none.
+pub(crate) fn trash(session: &DeviceConnection, path: &str) -> Result<TrashItem, Error> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let deleted = now.as_secs();
+    let name = path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or("file")
+        .replace("..", "_");
+    let id = format!("{}-{name}", now.as_millis());
+    let mounts = mounts(session)?;
+    let candidates = [trash_dir(&mounts, path), String::from(DEFAULT_TRASH_DIR)];
+    let info = format!("[Trash Info]\nPath={path}\nDeletionDate={deleted}\n");
+    let output = session.exec(
+        &format!(
+            "for t in {} {}; do mkdir -p \"$t/files\" \"$t/info\" 2>/dev/null && break; done && \
+             mv -- {} \"$t/files/\"{id} && printf '%s' {} > \"$t/info/\"{id}.trashinfo && \
+             echo \"$t\"",
+            quote(&candidates[0]),
+            quote(&candidates[1]),
+            quote(path),
+            quote(&info),
+            id = quote(&id),
+        ),
+        None,
+    )?;
+    let trash = String::from_utf8_lossy(&output).trim_end().to_string();
+    return Ok(TrashItem {
+        id,
+        path: String::from(path),
+        deleted,
+        trash,
+    });
+}
+
+/// Lists items in the trash of every filesystem, most recently trashed first.
+pub(crate) fn list(session: &DeviceConnection) -> Result<Vec<TrashItem>, Error> {
+    let dirs = trash_dirs(&mounts(session)?);
+    let output = session.exec(
+        &format!(
+            "for t in {dirs}; do for f in \"$t\"/info/*.trashinfo; do [ -f \"$f\" ] && \
+             printf 'Trash=%s\\nId=%s\\n' \"$t\" \"${{f##*/}}\" && cat \"$f\" && echo; \
+             done; done; true"
+        ),
+        None,
+    )?;
+    let mut items = parse_list(&String::from_utf8_lossy(&output));
+    items.sort_by(|a, b| b.deleted.cmp(&a.deleted));
+    return Ok(items);
+}
+
+/// Moves the item back to where it was trashed from, failing if something exists there now.
+pub(crate) fn restore(session: &DeviceConnection, id: &str) -> Result<TrashItem, Error> {
+    if id.is_empty() || id.contains('/') || id.contains("..") {
+        return Err(Error::new(format!("Bad trash item {id}")));
+    }
+    let item = list(session)?
+        .into_iter()
+        .find(|item| item.id == id)
+        .ok_or(Error::NotFound)?;
+    let target = quote(&item.path);
+    session.exec(
+        &format!(
+            "[ ! -e {target} ] && mv -- {} {target} && rm -f {}",
+            quote(&format!("{}/files/{id}", item.trash)),
+            quote(&format!("{}/info/{id}.trashinfo", item.trash)),
+        ),
+        None,
+    )?;
+    return Ok(item);
+}
+
+/// Removes the trash of every filesystem.
+pub(crate) fn empty(session: &DeviceConnection) -> Result<(), Error> {
+    let dirs = trash_dirs(&mounts(session)?);
+    session.exec(
+        &format!(
+            "rc=0; for t in {dirs}; do if [ -d \"$t/info\" ]; then rm -rf -- \"$t\" || rc=1; fi; \
+             done; exit $rc"
+        ),
+        None,
+    )?;
+    return Ok(());
+}
+
+fn mounts(session: &DeviceConnection) -> Result<Vec<MountPoint>, Error> {
+    let output = session.exec(&MountPoint::command(), None)?;
+    return Ok(MountPoint::parse(&String::from_utf8_lossy(&output)));
+}
+
+/// Trash directory of the filesystem holding `path`.
+fn trash_dir(mounts: &[MountPoint], path: &str) -> String {
+    let mount = mounts
+        .iter()
+        .filter(|m| m.mount == "/" || path.starts_with(&format!("{}/", m.mount)))
+        .max_by_key(|m| m.mount.len());
+    return match mount {
+        Some(mount) if !mount.developer && !mount.read_only => {
+            format!("{}/{TRASH_NAME}", mount.mount.trim_end_matches('/'))
+        }
+        _ => String::from(DEFAULT_TRASH_DIR),
+    };
+}
+
+/// Quoted trash directories of all filesystems, for shell loops.
+fn trash_dirs(mounts: &[MountPoint]) -> String {
+    let mut dirs = vec![String::from(DEFAULT_TRASH_DIR)];
+    for mount in mounts.iter().filter(|m| !m.developer && !m.read_only) {
+        let dir = format!("{}/{TRASH_NAME}", mount.mount.trim_end_matches('/'));
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    return dirs.iter().map(|d| quote(d)).collect::<Vec<_>>().join(" ");
+}
+
+/// Parses the info files printed by [list], each following `Trash=` and `Id=` lines.
+fn parse_list(output: &str) -> Vec<TrashItem> {
+    let mut items: Vec<TrashItem> = Vec::new();
+    for line in output.lines() {
+        if let Some(trash) = line.strip_prefix("Trash=") {
+            items.push(TrashItem {
+                id: String::new(),
+                path: String::new(),
+                deleted: 0,
+                trash: String::from(trash),
+            });
+            continue;
+        }
+        let Some(item) = items.last_mut() else {
+            continue;
+        };
+        if let Some(value) = line.strip_prefix("Id=") {
+            item.id = String::from(value.strip_suffix(".trashinfo").unwrap_or(value));
+        } else if let Some(value) = line.strip_prefix("Path=") {
+            item.path = String::from(value);
+        } else if let Some(value) = line.strip_prefix("DeletionDate=") {
+            item.deleted = value.parse().unwrap_or(0);
+        }
+    }
+    items.retain(|item| !item.id.is_empty() && !item.path.is_empty());
+    return items;
+}
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::session_manager::command::quote;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DiskUsage {
+    pub path: String,
+    /// Size in bytes, as counted by `du` in 1 KiB blocks
+    pub size: u64,
+    /// Sorted by size, largest first
+    pub children: Vec<DiskUsage>,
+}
+
+impl DiskUsage {
+    /// `du` command listing directories under `path` down to `depth`. Unreadable directories
+    /// don't fail the command as long as `path` itself exists.
+    pub(crate) fn command(path: &str, depth: u32) -> String {
+        let path = quote(path);
+        return format!("du -k -d {depth} {path} 2>/dev/null || [ -e {path} ]");
+    }
+
+    pub(crate) fn parse(root: &str, output: &str) -> Option<DiskUsage> {
+        let mut nodes: HashMap<String, DiskUsage> = HashMap::new();
+        for line in output.lines() {
+            let Some((size, path)) = line.split_once('\t') else {
+                continue;
+            };
+            let Ok(size) = size.trim().parse::<u64>() else {
+                continue;
+            };
+            nodes.insert(
+                String::from(path),
+                DiskUsage {
+                    path: String::from(path),
+                    size: size * 1024,
+                    children: Vec::new(),
+                },
+            );
+        }
+        let mut paths: Vec<String> = nodes.keys().filter(|p| *p != root).cloned().collect();
+        // Deepest first, so every node is complete before it's attached to its parent
+        paths.sort_by_key(|p| std::cmp::Reverse(p.matches('/').count()));
+        for path in paths {
+            let parent = match path.rfind('/') {
+                Some(0) => String::from("/"),
+                Some(index) => String::from(&path[..index]),
+                None => continue,
+            };
+            if !nodes.contains_key(&parent) {
+                continue;
+            }
+            let mut node = nodes.remove(&path).unwrap();
+            node.children.sort_by(|a, b| b.size.cmp(&a.size));
+            nodes.get_mut(&parent).unwrap().children.push(node);
+        }
+        let mut root = nodes.remove(root)?;
+        root.children.sort_by(|a, b| b.size.cmp(&a.size));
+        return Some(root);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_tree_largest_first() {
+        let output = "4\t/a/b/c\n8\t/a/b\n2\t/a/d\nbogus line\n20\t/a\n";
+        let root = DiskUsage::parse("/a", output).unwrap();
+        assert_eq!(root.size, 20 * 1024);
+        let children: Vec<&str> = root.children.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(children, ["/a/b", "/a/d"]);
+        assert_eq!(root.children[0].children[0].path, "/a/b/c");
+        assert!(root.children[1].children.is_empty());
+    }
+
+    #[test]
+    fn attaches_to_filesystem_root() {
+        let root = DiskUsage::parse("/", "1\t/tmp\n5\t/\n").unwrap();
+        assert_eq!(root.children[0].path, "/tmp");
+    }
+
+    #[test]
+    fn needs_root() {
+        assert!(DiskUsage::parse("/a", "4\t/a/b\n").is_none());
+    }
+}
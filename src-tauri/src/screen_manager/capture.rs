@@ -0,0 +1,45 @@
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::session_manager::command::quote;
+use crate::session_manager::luna;
+
+/// Captures the screen into a PNG on the device, and returns its contents. The file on the device
+/// is removed afterwards.
+pub(crate) fn capture(session: &DeviceConnection) -> Result<Vec<u8>, Error> {
+    let path = format!("/tmp/webos-dev-capture-{}.png", Uuid::new_v4());
+    let result = capture_to(session, &path)
+        .and_then(|_| session.exec(&format!("cat {}", quote(&path)), None));
+    session
+        .exec(&format!("rm -f {}", quote(&path)), None)
+        .unwrap_or_default();
+    let data = result?;
+    if data.is_empty() {
+        return Err(Error::new("Screen capture is empty"));
+    }
+    return Ok(data);
+}
+
+/// TVs have a capture service that includes the video layer, OSE can only capture what the
+/// compositor draws.
+fn capture_to(session: &DeviceConnection, path: &str) -> Result<(), Error> {
+    let public = !session.device.is_rooted();
+    if session.device.is_ose() {
+        luna::call::<Value>(
+            session,
+            "luna://com.webos.surfacemanager/captureCompositorOutput",
+            &json!({ "output": path, "format": "PNG" }),
+            public,
+        )?;
+    } else {
+        luna::call::<Value>(
+            session,
+            "luna://com.webos.service.capture/executeOneShot",
+            &json!({ "path": path, "method": "DISPLAY", "format": "PNG" }),
+            public,
+        )?;
+    }
+    return Ok(());
+}
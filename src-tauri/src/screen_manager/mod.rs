@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::error::Error;
+
+pub(crate) mod capture;
+mod stream;
+
+/// Screen capture streams, by token.
+#[derive(Default)]
+pub struct ScreenManager {
+    streams: Mutex<HashMap<String, Arc<ScreenStream>>>,
+}
+
+/// Captures the screen periodically, as a low-rate live preview.
+pub struct ScreenStream {
+    pub token: String,
+    interval: Duration,
+    closed: AtomicBool,
+    /// Where frames are saved, while recording
+    recording: Mutex<Option<Recording>>,
+}
+
+struct Recording {
+    dir: PathBuf,
+    frames: usize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ScreenStreamClosed {
+    pub token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Error>,
+}
@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::screen_manager::capture::capture;
+use crate::screen_manager::{Recording, ScreenManager, ScreenStream};
+
+/// Capturing takes a while on the device, faster than this it would just lag behind
+const MIN_INTERVAL: Duration = Duration::from_millis(500);
+const MAX_INTERVAL: Duration = Duration::from_secs(60);
+
+impl ScreenManager {
+    /// Creates a stream capturing a frame every `interval`, clamped to what's practical.
+    pub fn open(&self, interval: Duration) -> Arc<ScreenStream> {
+        let stream = Arc::new(ScreenStream {
+            token: Uuid::new_v4().to_string(),
+            interval: interval.clamp(MIN_INTERVAL, MAX_INTERVAL),
+            closed: Default::default(),
+            recording: Default::default(),
+        });
+        self.streams
+            .lock()
+            .unwrap()
+            .insert(stream.token.clone(), stream.clone());
+        return stream;
+    }
+
+    /// Stops the stream after the frame being captured.
+    pub fn close(&self, token: &str) -> Result<(), Error> {
+        let stream = self
+            .streams
+            .lock()
+            .unwrap()
+            .remove(token)
+            .ok_or(Error::NotFound)?;
+        stream.closed.store(true, Ordering::SeqCst);
+        return Ok(());
+    }
+
+    /// Starts saving frames of the stream as numbered PNGs in `dir`, or stops if there's none.
+    pub fn record(&self, token: &str, dir: Option<PathBuf>) -> Result<(), Error> {
+        let stream = self
+            .streams
+            .lock()
+            .unwrap()
+            .get(token)
+            .cloned()
+            .ok_or(Error::NotFound)?;
+        if let Some(dir) = &dir {
+            std::fs::create_dir_all(dir)?;
+        }
+        *stream.recording.lock().unwrap() = dir.map(|dir| Recording { dir, frames: 0 });
+        return Ok(());
+    }
+}
+
+impl ScreenStream {
+    /// Captures frames until the stream is closed, or capturing fails. Each frame is saved if
+    /// recording, and passed to `on_frame`.
+    pub fn run<F>(&self, session: &DeviceConnection, on_frame: F) -> Result<(), Error>
+    where
+        F: Fn(Vec<u8>) -> Result<(), Error>,
+    {
+        while !self.closed.load(Ordering::SeqCst) {
+            let started = Instant::now();
+            let frame = capture(session)?;
+            if let Some(recording) = self.recording.lock().unwrap().as_mut() {
+                recording.frames += 1;
+                let path = recording
+                    .dir
+                    .join(format!("frame-{:06}.png", recording.frames));
+                std::fs::write(path, &frame)?;
+            }
+            on_frame(frame)?;
+            if let Some(wait) = self.interval.checked_sub(started.elapsed()) {
+                std::thread::sleep(wait);
+            }
+        }
+        return Ok(());
+    }
+}
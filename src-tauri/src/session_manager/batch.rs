@@ -0,0 +1,80 @@
+use std::fs::File;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::conn_pool::{DeviceConnection, ManagedDeviceConnection};
+use crate::device_manager::Device;
+use crate::error::Error;
+use crate::session_manager::{BatchResult, SessionManager};
+use crate::transfer_manager::remote_fs::RemoteFs;
+
+impl SessionManager {
+    /// Runs the action on at most `parallelism` devices at once, and collects the result of each,
+    /// in the order of `devices`. One device failing doesn't stop the others.
+    pub fn batch_limited<T, F>(
+        &self,
+        devices: Vec<Device>,
+        parallelism: usize,
+        action: F,
+    ) -> Vec<BatchResult<T>>
+    where
+        T: Send,
+        F: Fn(&ManagedDeviceConnection) -> Result<T, Error> + Sync,
+    {
+        let names: Vec<String> = devices.iter().map(|device| device.name.clone()).collect();
+        let queue = Mutex::new(devices.into_iter().enumerate());
+        let results = Mutex::new(Vec::<(usize, Result<T, Error>)>::new());
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..parallelism.clamp(1, names.len().max(1)))
+                .map(|_| {
+                    return scope.spawn(|| loop {
+                        let Some((index, device)) = queue.lock().unwrap().next() else {
+                            break;
+                        };
+                        let result = self.with_session(device, &action);
+                        results.lock().unwrap().push((index, result));
+                    });
+                })
+                .collect();
+            // A panicking worker only loses its current device, the others keep going
+            for worker in workers {
+                worker.join().unwrap_or(());
+            }
+        });
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|(index, _)| *index);
+        let mut results = results.into_iter().peekable();
+        return names
+            .into_iter()
+            .enumerate()
+            .map(|(index, device)| {
+                let result = match results.next_if(|(i, _)| *i == index) {
+                    Some((_, result)) => result,
+                    None => Err(Error::new("Batch worker panicked")),
+                };
+                return match result {
+                    Ok(result) => BatchResult {
+                        device,
+                        result: Some(result),
+                        error: None,
+                    },
+                    Err(e) => BatchResult {
+                        device,
+                        result: None,
+                        error: Some(e),
+                    },
+                };
+            })
+            .collect();
+    }
+}
+
+impl DeviceConnection {
+    /// Uploads local file `source` to `target`, without progress reporting.
+    pub(crate) fn push_file(&self, source: &Path, target: &str) -> Result<(), Error> {
+        let mut file = File::open(source)?;
+        let mut remote = RemoteFs::new(self)?.create(target)?;
+        std::io::copy(&mut file, &mut remote)?;
+        return remote.finish();
+    }
+}
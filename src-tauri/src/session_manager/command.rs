@@ -0,0 +1,16 @@
+/// Quotes an argument for POSIX shells on the device.
+pub(crate) fn quote(arg: &str) -> String {
+    return format!("'{}'", arg.replace('\'', "'\\''"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_arguments() {
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("a b$c"), "'a b$c'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+    }
+}
@@ -0,0 +1,176 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use libssh_rs::Channel;
+
+use crate::conn_pool::{DeviceConnection, DeviceConnectionPool, ManagedDeviceConnection};
+use crate::device_manager::Device;
+use crate::error::Error;
+use crate::session_manager::{PortForward, SessionManager};
+
+/// Bounds of how long a tunnel waits for the device while idle. Reads hold the connection, which
+/// other tunnels share, so this stays short.
+const TUNNEL_MIN_WAIT: Duration = Duration::from_millis(5);
+const TUNNEL_MAX_WAIT: Duration = Duration::from_millis(50);
+
+impl SessionManager {
+    /// Listens on a free local port, and forwards connections to it to `remote_port` on the
+    /// device, until stopped with [SessionManager::unforward].
+    pub fn forward(&self, device: Device, remote_port: u16) -> Result<PortForward, Error> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))?;
+        listener.set_nonblocking(true)?;
+        let forward = PortForward {
+            device: device.name.clone(),
+            local_port: listener.local_addr()?.port(),
+            remote_port,
+        };
+        let stopped = Arc::new(AtomicBool::new(false));
+        let pool = self.pool(device);
+        {
+            let stopped = stopped.clone();
+            thread::spawn(move || accept(listener, pool, remote_port, stopped));
+        }
+        log::info!("Forwarding {forward:?}");
+        self.forwards
+            .lock()
+            .expect("Failed to lock SessionManager::forwards")
+            .insert(forward.local_port, (forward.clone(), stopped));
+        return Ok(forward);
+    }
+
+    /// Stops forwarding `local_port`. Connections already made through it are closed too.
+    pub fn unforward(&self, local_port: u16) -> Result<(), Error> {
+        let (forward, stopped) = self
+            .forwards
+            .lock()
+            .expect("Failed to lock SessionManager::forwards")
+            .remove(&local_port)
+            .ok_or(Error::NotFound)?;
+        stopped.store(true, Ordering::Relaxed);
+        log::info!("Stopped forwarding {forward:?}");
+        return Ok(());
+    }
+
+    pub fn forwards(&self) -> Vec<PortForward> {
+        return self
+            .forwards
+            .lock()
+            .expect("Failed to lock SessionManager::forwards")
+            .values()
+            .map(|(forward, _)| forward.clone())
+            .collect();
+    }
+}
+
+/// Accepts connections until stopped. All of them are tunneled through the same connection, so
+/// clients opening many at once don't use up the pool.
+fn accept(listener: TcpListener, pool: DeviceConnectionPool, port: u16, stopped: Arc<AtomicBool>) {
+    let mut session: Option<Arc<ManagedDeviceConnection>> = None;
+    while !stopped.load(Ordering::Relaxed) {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(100));
+                continue;
+            }
+            Err(e) => {
+                log::warn!("Failed to accept connection to forward: {e:?}");
+                break;
+            }
+        };
+        let conn = match session.as_ref() {
+            Some(conn) => conn.clone(),
+            None => match pool.get() {
+                Ok(conn) => session.insert(Arc::new(conn)).clone(),
+                Err(e) => {
+                    log::warn!("Failed to connect for forwarding port {port}: {e:?}");
+                    continue;
+                }
+            },
+        };
+        let stopped = stopped.clone();
+        thread::spawn(move || match tunnel(stream, &conn, port, &stopped) {
+            Ok(()) => conn.mark_last_ok(),
+            Err(e) => log::warn!("Forwarded connection to port {port} failed: {e:?}"),
+        });
+        // Reconnect for the next one if this connection broke
+        if session.as_ref().is_some_and(|conn| !conn.is_connected()) {
+            session = None;
+        }
+    }
+}
+
+/// Copies data both ways between `stream` and `port` on the device, until either side closes.
+/// The local side is read on its own thread, so the device side only waits in reads, backing off
+/// while the tunnel is idle.
+fn tunnel(
+    stream: TcpStream,
+    session: &DeviceConnection,
+    port: u16,
+    stopped: &AtomicBool,
+) -> Result<(), Error> {
+    let local_port = stream.local_addr()?.port();
+    // Accepted streams may inherit non-blocking mode from the listener
+    stream.set_nonblocking(false)?;
+    // Forwarded channels don't count against sshd's session limit, and clients like DevTools keep
+    // some open for as long as they run, so they don't take a channel permit
+    let ch = session.new_channel()?;
+    ch.open_forward("127.0.0.1", port, "127.0.0.1", local_port)?;
+    let (tx, rx) = mpsc::channel::<Vec<u8>>();
+    {
+        let mut stream = stream.try_clone()?;
+        thread::spawn(move || {
+            let mut buf = [0; 8192];
+            while let Ok(size) = stream.read(&mut buf) {
+                if size == 0 || tx.send(buf[..size].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    let result = pump(&ch, &stream, rx, stopped);
+    // Also ends the reading thread
+    stream.shutdown(Shutdown::Both).unwrap_or(());
+    ch.close().unwrap_or(());
+    return result;
+}
+
+/// Writes what the reading thread sends to the device, and what the device sends to `stream`.
+fn pump(
+    ch: &Channel,
+    mut stream: &TcpStream,
+    rx: Receiver<Vec<u8>>,
+    stopped: &AtomicBool,
+) -> Result<(), Error> {
+    let mut buf = [0; 8192];
+    let mut wait = TUNNEL_MIN_WAIT;
+    while !ch.is_closed() && !ch.is_eof() && !stopped.load(Ordering::Relaxed) {
+        let mut active = false;
+        loop {
+            match rx.try_recv() {
+                Ok(data) => {
+                    ch.stdin().write_all(&data)?;
+                    active = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Ok(()),
+            }
+        }
+        let size = ch.read_timeout(&mut buf, false, Some(wait))?;
+        if size > 0 {
+            stream.write_all(&buf[..size])?;
+            active = true;
+        }
+        wait = if active {
+            TUNNEL_MIN_WAIT
+        } else {
+            (wait * 2).min(TUNNEL_MAX_WAIT)
+        };
+    }
+    return Ok(());
+}
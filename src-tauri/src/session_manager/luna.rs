@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use regex::{Captures, Regex};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::session_manager::command::quote;
+
+/// Calls a Luna service method once with `luna-send`, or `luna-send-pub` if `public`, which is
+/// all that's allowed for the Developer Mode user. Responses with `returnValue: false` are errors.
+pub(crate) fn call<T: DeserializeOwned>(
+    session: &DeviceConnection,
+    uri: &str,
+    params: &Value,
+    public: bool,
+) -> Result<T, Error> {
+    let command = command(uri, params, public, false);
+    let output = match session.exec(&command, None) {
+        Ok(output) => output,
+        Err(Error::ExitStatus { exit_code: 127, .. }) => return Err(Error::Unsupported),
+        Err(e) => return Err(e),
+    };
+    let response: Value = serde_json::from_slice(&output)
+        .map_err(|_| Error::new(format!("Bad response from {uri}")))?;
+    if response.get("returnValue") == Some(&Value::Bool(false)) {
+        let text = response
+            .get("errorText")
+            .and_then(Value::as_str)
+            .unwrap_or("Unknown error");
+        return Err(Error::new(format!("{uri} failed: {text}")));
+    }
+    return Ok(serde_json::from_value(response)?);
+}
+
+/// Command calling a Luna service method, the way [call] does. With `subscribe`, responses keep
+/// coming one per line until the command is interrupted.
+pub(crate) fn command(uri: &str, params: &Value, public: bool, subscribe: bool) -> String {
+    let send = if public { "luna-send-pub" } else { "luna-send" };
+    let count = if subscribe { "-i" } else { "-n 1" };
+    return format!(
+        "{send} {count} {} {}",
+        quote(uri),
+        quote(&params.to_string())
+    );
+}
+
+/// Fills `{{name}}` placeholders of JSON `template` with `vars`, and parses the result. Values
+/// are inserted as is, so strings need to be quoted in the template.
+pub(crate) fn render(template: &str, vars: &HashMap<String, String>) -> Result<Value, Error> {
+    let placeholder = Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*}}").unwrap();
+    let mut missing: Vec<String> = Vec::new();
+    let rendered = placeholder.replace_all(template, |caps: &Captures| {
+        return match vars.get(&caps[1]) {
+            Some(value) => value.clone(),
+            None => {
+                missing.push(String::from(&caps[1]));
+                String::new()
+            }
+        };
+    });
+    if !missing.is_empty() {
+        return Err(Error::new(format!(
+            "Missing values for {}",
+            missing.join(", ")
+        )));
+    }
+    return serde_json::from_str(&rendered)
+        .map_err(|e| Error::new(format!("Bad parameters after filling in the template: {e}")));
+}
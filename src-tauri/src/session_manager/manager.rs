@@ -1,7 +1,10 @@
 use std::path::PathBuf;
 use std::sync::{Arc, Condvar, Mutex};
 
-use crate::conn_pool::{DeviceConnectionPool, ManagedDeviceConnection};
+use crate::conn_pool::{
+    ConnectionCallback, ConnectionEvent, ConnectionState, DeviceConnectionPool,
+    ManagedDeviceConnection,
+};
 use crate::device_manager::Device;
 use crate::error::Error;
 use crate::session_manager::{Proc, SessionManager};
@@ -16,20 +19,42 @@ impl SessionManager {
     where
         F: Fn(&ManagedDeviceConnection) -> Result<T, Error>,
     {
+        let name = device.name.clone();
+        return with_pool(&name, &self.pool(device), &self.callback, action);
+    }
+
+    /// Async version of [SessionManager::with_session], running the action on a blocking thread.
+    pub async fn run<T, F>(&self, device: Device, action: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: Fn(&ManagedDeviceConnection) -> Result<T, Error> + Send + 'static,
+    {
+        let name = device.name.clone();
         let pool = self.pool(device);
-        loop {
-            let session = pool.get()?;
-            return match action(&session) {
-                Ok(ret) => {
-                    session.mark_last_ok();
-                    Ok(ret)
-                }
-                Err(Error::Disconnected) => {
-                    continue;
-                }
-                Err(e) => Err(e),
-            };
-        }
+        let callback = self.callback.clone();
+        return tokio::task::spawn_blocking(move || with_pool(&name, &pool, &callback, action))
+            .await
+            .expect("critical failure in SessionManager::run task");
+    }
+
+    /// Runs the command on the device, and returns its stdout. Non-zero exit codes are reported
+    /// as [Error::ExitStatus].
+    pub fn exec(
+        &self,
+        device: Device,
+        command: &str,
+        stdin: Option<&[u8]>,
+    ) -> Result<Vec<u8>, Error> {
+        let command = device.env_command(command);
+        return self.with_session(device, |session| session.exec(&command, stdin));
+    }
+
+    /// Establishes a connection and returns it to the pool, so the next call for this device
+    /// doesn't have to wait for the SSH handshake.
+    pub fn prewarm(&self, device: Device) -> Result<(), Error> {
+        let session = self.session(device)?;
+        session.mark_last_ok();
+        return Ok(());
     }
 
     pub fn spawn(&self, device: Device, command: &str) -> Proc {
@@ -43,9 +68,21 @@ impl SessionManager {
         };
     }
 
-    fn pool(&self, device: Device) -> DeviceConnectionPool {
+    /// Drops the pooled connections of a device, so the next session picks up its new settings.
+    pub fn reset(&self, name: &str) {
+        self.pools
+            .lock()
+            .expect("Failed to lock SessionManager::pools")
+            .remove(name);
+    }
+
+    pub fn set_callback(&self, callback: Box<dyn ConnectionCallback + Send + Sync>) {
+        *self.callback.lock().unwrap() = Some(callback);
+    }
+
+    pub(super) fn pool(&self, device: Device) -> DeviceConnectionPool {
         if device.new {
-            return DeviceConnectionPool::new(device, self.get_ssh_dir());
+            return DeviceConnectionPool::new(device, self.get_ssh_dir(), self.callback.clone());
         }
         if let Some(p) = self
             .pools
@@ -56,7 +93,7 @@ impl SessionManager {
             return p.clone();
         }
         let key = device.name.clone();
-        let pool = DeviceConnectionPool::new(device, self.get_ssh_dir());
+        let pool = DeviceConnectionPool::new(device, self.get_ssh_dir(), self.callback.clone());
         self.pools
             .lock()
             .expect("Failed to lock SessionManager::pools")
@@ -65,6 +102,38 @@ impl SessionManager {
     }
 }
 
+fn with_pool<T, F>(
+    name: &str,
+    pool: &DeviceConnectionPool,
+    callback: &Mutex<Option<Box<dyn ConnectionCallback + Send + Sync>>>,
+    action: F,
+) -> Result<T, Error>
+where
+    F: Fn(&ManagedDeviceConnection) -> Result<T, Error>,
+{
+    loop {
+        let session = pool.get()?;
+        return match action(&session) {
+            Ok(ret) => {
+                session.mark_last_ok();
+                Ok(ret)
+            }
+            Err(Error::Disconnected) => {
+                if let Some(callback) = callback.lock().unwrap().as_ref() {
+                    callback.state(ConnectionEvent {
+                        device: String::from(name),
+                        state: ConnectionState::Reconnecting {
+                            reason: Some(Error::Disconnected),
+                        },
+                    });
+                }
+                continue;
+            }
+            Err(e) => Err(e),
+        };
+    }
+}
+
 impl GetSshDir for SessionManager {
     fn get_ssh_dir(&self) -> Option<PathBuf> {
         return self.ssh_dir.lock().unwrap().clone();
@@ -1,20 +1,30 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::mpsc::Sender;
+use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Condvar, Mutex};
 
 use serde::Serialize;
 
-use crate::conn_pool::DeviceConnectionPool;
+use crate::conn_pool::{ConnectionCallback, DeviceConnectionPool};
 use crate::device_manager::Device;
+use crate::error::Error;
 
+mod batch;
+pub(crate) mod command;
+mod forward;
+pub(crate) mod luna;
 mod manager;
 mod proc;
+mod sftp;
 
 #[derive(Default)]
 pub struct SessionManager {
     ssh_dir: Mutex<Option<PathBuf>>,
     pools: Mutex<HashMap<String, DeviceConnectionPool>>,
+    callback: Arc<Mutex<Option<Box<dyn ConnectionCallback + Send + Sync>>>>,
+    /// Active port forwards by local port, with the flag for stopping them
+    forwards: Mutex<HashMap<u16, (PortForward, Arc<AtomicBool>)>>,
 }
 
 pub struct Proc {
@@ -26,6 +36,25 @@ pub struct Proc {
     pub(crate) interrupted: Mutex<bool>,
 }
 
+/// Outcome of [SessionManager::batch] for one device
+#[derive(Clone, Serialize)]
+pub struct BatchResult<T> {
+    pub device: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Error>,
+}
+
+/// Local port forwarded to a port on the device
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortForward {
+    pub device: String,
+    pub local_port: u16,
+    pub remote_port: u16,
+}
+
 #[derive(Clone, Serialize)]
 pub struct ProcData {
     pub fd: u32,
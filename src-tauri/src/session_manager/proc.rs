@@ -5,7 +5,7 @@ use std::time::Duration;
 
 use libssh_rs::Channel;
 
-use crate::conn_pool::ManagedDeviceConnection;
+use crate::conn_pool::{ChannelPermit, ManagedDeviceConnection};
 use crate::error::Error;
 use crate::session_manager::{Proc, SessionManager};
 
@@ -58,8 +58,10 @@ impl Proc {
         let (sender, receiver) = channel::<Vec<u8>>();
         *self.sender.lock().unwrap() = Some(sender);
         let channel: Channel;
+        let _permit: ChannelPermit;
         loop {
             let conn = sessions.session(self.device.clone())?;
+            let permit = conn.acquire_channel();
             let open = || {
                 let ch = conn.new_channel()?;
                 ch.open_session()?;
@@ -69,13 +71,14 @@ impl Proc {
                 Ok(ch) => {
                     session = conn;
                     channel = ch;
+                    _permit = permit;
                     break;
                 }
                 Err(Error::Disconnected) => continue,
                 Err(e) => return Err(e),
             };
         }
-        channel.request_exec(&self.command)?;
+        channel.request_exec(&self.device.env_command(&self.command))?;
         let mut buf = [0; 8192];
         let mut interrupted = false;
         while !channel.is_closed() {
@@ -0,0 +1,373 @@
+use std::io::{Read, Write};
+use std::time::SystemTime;
+
+use libssh_rs::{FileType, SetAttributes, Sftp};
+
+use crate::conn_pool::DeviceConnection;
+use crate::device_manager::Device;
+use crate::error::Error;
+use crate::remote_files::exec::ExecStat;
+use crate::remote_files::{FileItem, LinkInfo, PermInfo};
+use crate::session_manager::command::quote;
+use crate::session_manager::SessionManager;
+
+impl SessionManager {
+    pub async fn sftp_read(&self, device: Device, path: &str) -> Result<Vec<u8>, Error> {
+        let path = String::from(path);
+        return self
+            .run(device, move |session| {
+                if session.use_stream() {
+                    return session.exec(&format!("cat {}", quote(&path)), None);
+                }
+                let sftp = session.sftp()?;
+                let mut file = sftp.open(&path, 0 /*O_RDONLY*/, 0)?;
+                let mut buf = Vec::<u8>::new();
+                file.read_to_end(&mut buf)?;
+                return Ok(buf);
+            })
+            .await;
+    }
+
+    pub async fn sftp_write(
+        &self,
+        device: Device,
+        path: &str,
+        content: Vec<u8>,
+    ) -> Result<(), Error> {
+        let path = String::from(path);
+        return self
+            .run(device, move |session| {
+                if session.use_stream() {
+                    session.exec(&format!("cat > {}", quote(&path)), Some(&content[..]))?;
+                    return Ok(());
+                }
+                let sftp = session.sftp()?;
+                let mut file =
+                    sftp.open(&path, libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC, 0o644)?;
+                file.write_all(&content)?;
+                return Ok(());
+            })
+            .await;
+    }
+
+    /// Stats `path` without following a trailing symlink, so links are reported as such along with
+    /// their target.
+    pub async fn sftp_stat(&self, device: Device, path: &str) -> Result<FileItem, Error> {
+        let path = String::from(path);
+        return self
+            .run(device, move |session| {
+                let user = session.user.as_ref();
+                if session.use_stream() {
+                    let output = session.exec(&ExecStat::command(&path, false), None)?;
+                    let stat = ExecStat::parse(&String::from_utf8_lossy(&output))
+                        .into_iter()
+                        .next()
+                        .ok_or(Error::NotFound)?;
+                    return Ok(FileItem::from_exec(base_name(&path), stat, user));
+                }
+                let sftp = session.sftp()?;
+                let stat = sftp.symlink_metadata(&path)?;
+                let link = (stat.file_type() == Some(FileType::Symlink))
+                    .then(|| LinkInfo::resolve(&sftp, &path));
+                return Ok(FileItem::named(
+                    base_name(&path),
+                    &stat,
+                    link,
+                    user.map(|u| PermInfo::from(&stat, &u)),
+                ));
+            })
+            .await;
+    }
+
+    pub async fn sftp_read_dir(&self, device: Device, path: &str) -> Result<Vec<FileItem>, Error> {
+        let path = String::from(path);
+        return self
+            .run(device, move |session| {
+                let user = session.user.as_ref();
+                if session.use_stream() {
+                    let output = session.exec(&ExecStat::list_command(&path), None)?;
+                    return Ok(ExecStat::parse(&String::from_utf8_lossy(&output))
+                        .into_iter()
+                        .map(|stat| FileItem::from_exec(&stat.name.clone(), stat, user))
+                        .collect());
+                }
+                let sftp = session.sftp()?;
+                let entries = sftp.read_dir(&path)?;
+                return Ok(entries
+                    .iter()
+                    .filter(|entry| entry.name() != Some(".") && entry.name() != Some(".."))
+                    .map(|entry| {
+                        let link = (entry.file_type() == Some(FileType::Symlink)).then(|| {
+                            let name = entry.name().unwrap_or_default();
+                            LinkInfo::resolve(&sftp, &child_path(&path, name))
+                        });
+                        FileItem::new(entry, link, user.map(|u| PermInfo::from(entry, &u)))
+                    })
+                    .collect());
+            })
+            .await;
+    }
+
+    pub async fn sftp_read_link(&self, device: Device, path: &str) -> Result<String, Error> {
+        let path = String::from(path);
+        return self
+            .run(device, move |session| {
+                if session.use_stream() {
+                    let output = session.exec(&format!("readlink -- {}", quote(&path)), None)?;
+                    return Ok(String::from_utf8_lossy(&output)
+                        .trim_end_matches('\n')
+                        .to_string());
+                }
+                let sftp = session.sftp()?;
+                return Ok(sftp.read_link(&path)?);
+            })
+            .await;
+    }
+
+    pub async fn sftp_rename(&self, device: Device, from: &str, to: &str) -> Result<(), Error> {
+        let (from, to) = (String::from(from), String::from(to));
+        return self
+            .run(device, move |session| {
+                if session.use_stream() {
+                    // Fails like SFTP rename, instead of replacing the target
+                    let to = quote(&to);
+                    session.exec(
+                        &format!(
+                            "if [ -e {to} ] || [ -L {to} ]; then echo \"File exists\" >&2; \
+                             exit 1; fi; mv -- {} {to}",
+                            quote(&from)
+                        ),
+                        None,
+                    )?;
+                    return Ok(());
+                }
+                return Ok(session.sftp()?.rename(&from, &to)?);
+            })
+            .await;
+    }
+
+    /// Removes the file or directory, returning the removed paths with children before their
+    /// parents. With `dry_run`, only returns the paths that would be removed.
+    pub async fn sftp_remove(
+        &self,
+        device: Device,
+        path: &str,
+        recursive: bool,
+        dry_run: bool,
+    ) -> Result<Vec<String>, Error> {
+        let path = String::from(path);
+        return self
+            .run(device, move |session| {
+                if session.use_stream() {
+                    return remove_exec(session, &path, recursive, dry_run);
+                }
+                let sftp = session.sftp()?;
+                let stat = sftp.symlink_metadata(&path)?;
+                let mut removed = Vec::new();
+                if stat.file_type() == Some(FileType::Directory) && recursive {
+                    remove_tree(&sftp, &path, dry_run, &mut removed)?;
+                    return Ok(removed);
+                }
+                if !dry_run {
+                    if stat.file_type() == Some(FileType::Directory) {
+                        sftp.remove_dir(&path)?;
+                    } else {
+                        sftp.remove_file(&path)?;
+                    }
+                }
+                removed.push(path);
+                return Ok(removed);
+            })
+            .await;
+    }
+
+    /// Creates the directory, and its missing ancestors if `parents` is set.
+    pub async fn sftp_mkdir(&self, device: Device, path: &str, parents: bool) -> Result<(), Error> {
+        let path = String::from(path);
+        return self
+            .run(device, move |session| {
+                if session.use_stream() {
+                    let flag = if parents { "-p " } else { "" };
+                    session.exec(&format!("mkdir {flag}-- {}", quote(&path)), None)?;
+                    return Ok(());
+                }
+                let sftp = session.sftp()?;
+                if !parents {
+                    return Ok(sftp.create_dir(&path, 0o755)?);
+                }
+                let mut current = String::new();
+                for segment in path.split('/').filter(|s| !s.is_empty()) {
+                    current = format!("{current}/{segment}");
+                    let is_dir = sftp
+                        .metadata(&current)
+                        .map_or(false, |s| s.file_type() == Some(FileType::Directory));
+                    if !is_dir {
+                        sftp.create_dir(&current, 0o755)?;
+                    }
+                }
+                return Ok(());
+            })
+            .await;
+    }
+
+    pub async fn sftp_chmod(&self, device: Device, path: &str, mode: u32) -> Result<(), Error> {
+        return self
+            .set_attributes(
+                device,
+                path,
+                SetAttributes {
+                    size: None,
+                    uid_gid: None,
+                    permissions: Some(mode),
+                    atime_mtime: None,
+                },
+            )
+            .await;
+    }
+
+    pub async fn sftp_chown(
+        &self,
+        device: Device,
+        path: &str,
+        uid: u32,
+        gid: u32,
+    ) -> Result<(), Error> {
+        return self
+            .set_attributes(
+                device,
+                path,
+                SetAttributes {
+                    size: None,
+                    uid_gid: Some((uid, gid)),
+                    permissions: None,
+                    atime_mtime: None,
+                },
+            )
+            .await;
+    }
+
+    /// Creates an empty file if `path` doesn't exist, and updates its access and modification time.
+    pub async fn sftp_touch(&self, device: Device, path: &str) -> Result<(), Error> {
+        let path = String::from(path);
+        return self
+            .run(device, move |session| {
+                if session.use_stream() {
+                    session.exec(&format!("touch -- {}", quote(&path)), None)?;
+                    return Ok(());
+                }
+                let sftp = session.sftp()?;
+                drop(sftp.open(&path, libc::O_WRONLY | libc::O_CREAT, 0o644)?);
+                let now = SystemTime::now();
+                sftp.set_metadata(
+                    &path,
+                    &SetAttributes {
+                        size: None,
+                        uid_gid: None,
+                        permissions: None,
+                        atime_mtime: Some((now, now)),
+                    },
+                )?;
+                return Ok(());
+            })
+            .await;
+    }
+
+    async fn set_attributes(
+        &self,
+        device: Device,
+        path: &str,
+        attrs: SetAttributes,
+    ) -> Result<(), Error> {
+        let path = String::from(path);
+        return self
+            .run(device, move |session| {
+                if session.use_stream() {
+                    let command = match (attrs.permissions, attrs.uid_gid) {
+                        (Some(mode), _) => format!("chmod {:o} -- {}", mode & 0o7777, quote(&path)),
+                        (None, Some((uid, gid))) => {
+                            format!("chown {uid}:{gid} -- {}", quote(&path))
+                        }
+                        (None, None) => return Ok(()),
+                    };
+                    session.exec(&command, None)?;
+                    return Ok(());
+                }
+                return Ok(session.sftp()?.set_metadata(&path, &attrs)?);
+            })
+            .await;
+    }
+}
+
+/// Removes `path` with shell commands, for devices without SFTP. `find -depth` lists children
+/// before their parents, like [remove_tree].
+fn remove_exec(
+    session: &DeviceConnection,
+    path: &str,
+    recursive: bool,
+    dry_run: bool,
+) -> Result<Vec<String>, Error> {
+    let output = session.exec(&ExecStat::command(path, false), None)?;
+    let stat = ExecStat::parse(&String::from_utf8_lossy(&output))
+        .into_iter()
+        .next()
+        .ok_or(Error::NotFound)?;
+    let quoted = quote(path);
+    if stat.is_dir() && recursive {
+        let output = session.exec(&format!("find {quoted} -depth"), None)?;
+        let removed = String::from_utf8_lossy(&output)
+            .lines()
+            .map(String::from)
+            .collect();
+        if !dry_run {
+            session.exec(&format!("rm -rf -- {quoted}"), None)?;
+        }
+        return Ok(removed);
+    }
+    if !dry_run {
+        let command = if stat.is_dir() { "rmdir" } else { "rm -f" };
+        session.exec(&format!("{command} -- {quoted}"), None)?;
+    }
+    return Ok(vec![String::from(path)]);
+}
+
+fn remove_tree(
+    sftp: &Sftp,
+    path: &str,
+    dry_run: bool,
+    removed: &mut Vec<String>,
+) -> Result<(), Error> {
+    for entry in sftp.read_dir(path)? {
+        let Some(name) = entry.name() else {
+            continue;
+        };
+        if name == "." || name == ".." {
+            continue;
+        }
+        let child = child_path(path, name);
+        if entry.file_type() == Some(FileType::Directory) {
+            remove_tree(sftp, &child, dry_run, removed)?;
+            continue;
+        }
+        if !dry_run {
+            sftp.remove_file(&child)?;
+        }
+        removed.push(child);
+    }
+    if !dry_run {
+        sftp.remove_dir(path)?;
+    }
+    removed.push(String::from(path));
+    return Ok(());
+}
+
+fn child_path(dir: &str, name: &str) -> String {
+    return format!("{}/{}", dir.trim_end_matches('/'), name);
+}
+
+fn base_name(path: &str) -> &str {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        return "/";
+    }
+    return trimmed.rsplit('/').next().unwrap_or(trimmed);
+}
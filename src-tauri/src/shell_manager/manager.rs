@@ -4,14 +4,14 @@ use std::sync::Arc;
 use crate::app_dirs::{GetSshDir, SetSshDir};
 use crate::device_manager::Device;
 use crate::error::Error;
-use crate::shell_manager::{Shell, ShellInfo, ShellManager, ShellToken};
+use crate::shell_manager::{Shell, ShellInfo, ShellManager, ShellOptions, ShellToken};
 
 impl ShellManager {
-    pub fn open(&self, device: Device, rows: u16, cols: u16, dumb: bool) -> Arc<Shell> {
+    pub fn open(&self, device: Device, rows: u16, cols: u16, options: ShellOptions) -> Arc<Shell> {
         let shell = Arc::new(Shell::new(
             device,
             self.get_ssh_dir().as_deref(),
-            !dumb,
+            options,
             rows,
             cols,
             self.shells.clone(),
@@ -36,6 +36,12 @@ impl ShellManager {
         return Ok(());
     }
 
+    pub fn focus(&self, token: Option<&ShellToken>) {
+        for (key, shell) in self.shells.lock().unwrap().iter() {
+            shell.set_focused(Some(key) == token);
+        }
+    }
+
     pub fn list(&self) -> Vec<ShellInfo> {
         let mut list: Vec<ShellInfo> = self
             .shells
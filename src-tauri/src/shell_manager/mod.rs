@@ -1,7 +1,8 @@
+use std::collections::VecDeque;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::sync::mpsc::Sender;
-use std::time::Instant;
+use std::time::{Instant, SystemTime};
 
 use serde::Serialize;
 use uuid::Uuid;
@@ -10,10 +11,14 @@ use vt100::Parser;
 use crate::device_manager::Device;
 use crate::error::Error;
 use crate::shell_manager::shell::ShellsMap;
+use crate::shell_manager::zmodem::{ZmodemDirection, ZmodemProgress, ZmodemSession};
 
 pub(crate) mod manager;
+mod osc52;
+pub(crate) mod persist;
 pub(crate) mod shell;
 pub(crate) mod token;
+pub(crate) mod zmodem;
 
 #[derive(Default)]
 pub struct ShellManager {
@@ -26,18 +31,42 @@ pub struct Shell {
     created_at: Instant,
     device: Device,
     ssh_dir: Option<PathBuf>,
+    options: ShellOptions,
     pub(crate) has_pty: Mutex<Option<bool>>,
+    pub(crate) custom_title: Mutex<Option<String>>,
     pub(crate) closed: Mutex<Option<ShellState>>,
     pub(crate) sender: Mutex<Option<Sender<ShellMessage>>>,
     pub(crate) callback: Mutex<Option<Box<dyn ShellCallback + Send + Sync>>>,
     pub(crate) parser: Mutex<Parser>,
+    pub(crate) scrollback: Mutex<VecDeque<u8>>,
+    pub(crate) pending: Mutex<usize>,
+    pub(crate) zmodem: Mutex<Option<ZmodemDirection>>,
+    pub(crate) last_input: Mutex<Option<SystemTime>>,
+    pub(crate) last_output: Mutex<Option<SystemTime>>,
+    pub(crate) focused: Mutex<bool>,
+    pub(crate) unseen_output: Mutex<bool>,
     pub(crate) shells: Arc<Mutex<ShellsMap>>,
 }
 
+#[derive(Clone, Debug)]
+pub struct ShellOptions {
+    pub wants_pty: bool,
+    pub elevate: bool,
+    pub term: String,
+    pub truecolor: bool,
+    /// Name of the multiplexer session to run the shell in, so it survives disconnections
+    pub session: Option<String>,
+}
+
 pub trait ShellCallback {
     fn info(&self, info: ShellInfo);
     fn rx(&self, fd: u32, data: &[u8]);
-    fn closed(&self, removed: bool);
+    fn pending(&self, bytes: usize);
+    fn clipboard(&self, selection: String, data: String);
+    fn zmodem(&self, direction: Option<ZmodemDirection>);
+    fn zmodem_progress(&self, progress: ZmodemProgress);
+    fn activity(&self);
+    fn closed(&self, reason: ShellCloseReason, removed: bool);
 }
 
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
@@ -50,6 +79,16 @@ pub struct ShellInfo {
     pub state: ShellState,
     #[serde(rename = "hasPty", skip_serializing_if = "Option::is_none")]
     pub has_pty: Option<bool>,
+    /// Milliseconds since UNIX epoch
+    #[serde(rename = "lastInput")]
+    pub last_input: Option<u64>,
+    /// Milliseconds since UNIX epoch
+    #[serde(rename = "lastOutput")]
+    pub last_output: Option<u64>,
+    #[serde(rename = "unseenOutput")]
+    pub unseen_output: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
     #[serde(skip_serializing)]
     created_at: Instant,
 }
@@ -61,6 +100,40 @@ pub struct ShellData {
     pub data: Vec<u8>,
 }
 
+#[derive(Clone, Serialize, Debug)]
+pub struct ShellPending {
+    pub token: ShellToken,
+    pub pending: usize,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct ShellClipboard {
+    pub token: ShellToken,
+    pub selection: String,
+    pub data: String,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct ShellZmodem {
+    pub token: ShellToken,
+    pub direction: Option<ZmodemDirection>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct ShellZmodemProgress {
+    pub token: ShellToken,
+    #[serde(flatten)]
+    pub progress: ZmodemProgress,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct ShellSearchMatch {
+    /// Offset of the match in the scrollback buffer
+    pub offset: usize,
+    pub length: usize,
+    pub snippet: String,
+}
+
 #[derive(Clone, Serialize, Debug)]
 pub struct ShellScreen {
     rows: Option<Vec<Vec<u8>>>,
@@ -71,9 +144,38 @@ pub struct ShellScreen {
 pub(crate) enum ShellMessage {
     Data(Vec<u8>),
     Resize { rows: u16, cols: u16 },
+    /// Accepts the ZMODEM transfer the device started, `init` is sent to it first
+    ZmodemStart {
+        session: Box<ZmodemSession>,
+        init: Vec<u8>,
+    },
+    ZmodemCancel,
     Close,
 }
 
+#[derive(Clone, Serialize, Debug)]
+pub struct ShellClosed {
+    pub token: ShellToken,
+    #[serde(flatten)]
+    pub reason: ShellCloseReason,
+}
+
+#[derive(Clone, Serialize, Debug)]
+#[serde(tag = "reason")]
+pub enum ShellCloseReason {
+    /// The remote process exited with a status code
+    Exited {
+        #[serde(rename = "exitCode")]
+        exit_code: i32,
+    },
+    /// The remote process was terminated by a signal
+    Signaled,
+    /// The shell was closed by the user
+    Closed,
+    /// The connection was lost or couldn't be established
+    Disconnected { error: Error },
+}
+
 #[derive(Clone, Serialize, Debug)]
 #[serde(tag = "which")]
 pub enum ShellState {
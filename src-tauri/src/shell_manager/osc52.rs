@@ -0,0 +1,78 @@
+const PREFIX: &[u8] = b"\x1b]52;";
+const MAX_PAYLOAD: usize = 1024 * 1024;
+
+/// Picks OSC 52 clipboard sequences out of terminal output, which may be split across reads.
+#[derive(Default)]
+pub(crate) struct Osc52Scanner {
+    matched: usize,
+    seq: Option<Vec<u8>>,
+    esc: bool,
+    overflow: bool,
+}
+
+pub(crate) struct Osc52Clipboard {
+    pub selection: String,
+    /// Base64 encoded clipboard content, as sent by the remote program
+    pub data: String,
+}
+
+impl Osc52Scanner {
+    pub(crate) fn feed(&mut self, data: &[u8]) -> Vec<Osc52Clipboard> {
+        let mut found = Vec::new();
+        for &b in data {
+            let Some(seq) = self.seq.as_mut() else {
+                if b == PREFIX[self.matched] {
+                    self.matched += 1;
+                    if self.matched == PREFIX.len() {
+                        self.matched = 0;
+                        self.seq = Some(Vec::new());
+                    }
+                } else {
+                    self.matched = if b == PREFIX[0] { 1 } else { 0 };
+                }
+                continue;
+            };
+            if self.esc {
+                self.esc = false;
+                if b == b'\\' {
+                    found.extend(self.finish());
+                } else {
+                    self.reset();
+                }
+            } else if b == 0x07 {
+                found.extend(self.finish());
+            } else if b == 0x1b {
+                self.esc = true;
+            } else if seq.len() < MAX_PAYLOAD {
+                seq.push(b);
+            } else {
+                self.overflow = true;
+            }
+        }
+        return found;
+    }
+
+    fn finish(&mut self) -> Option<Osc52Clipboard> {
+        let seq = self.seq.take()?;
+        if std::mem::take(&mut self.overflow) {
+            log::warn!("Ignoring OSC 52 sequence larger than {MAX_PAYLOAD} bytes");
+            return None;
+        }
+        let seq = String::from_utf8(seq).ok()?;
+        let (selection, data) = seq.split_once(';')?;
+        // Clipboard queries are never answered, remote programs shouldn't read the host clipboard
+        if data == "?" {
+            return None;
+        }
+        return Some(Osc52Clipboard {
+            selection: String::from(selection),
+            data: String::from(data),
+        });
+    }
+
+    fn reset(&mut self) {
+        self.seq = None;
+        self.esc = false;
+        self.overflow = false;
+    }
+}
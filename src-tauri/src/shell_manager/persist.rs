@@ -0,0 +1,64 @@
+use std::io::Read;
+
+use libssh_rs::Session;
+
+use crate::device_manager::{Device, Multiplexer};
+use crate::error::Error;
+use crate::session_manager::command::quote;
+
+/// Prefix of multiplexer sessions created by this app, to tell them apart from user's own ones
+const SESSION_PREFIX: &str = "devman-";
+
+pub(crate) fn attach_command(device: &Device, name: &str) -> String {
+    let name = quote(&format!("{SESSION_PREFIX}{name}"));
+    return match multiplexer(device) {
+        Multiplexer::Tmux => format!("tmux new-session -A -s {name}"),
+        Multiplexer::Screen => format!("screen -D -R -S {name}"),
+    };
+}
+
+pub(crate) fn list_command(device: &Device) -> &'static str {
+    return match multiplexer(device) {
+        Multiplexer::Tmux => "tmux list-sessions -F '#{session_name}' 2>/dev/null || true",
+        Multiplexer::Screen => "screen -ls 2>/dev/null || true",
+    };
+}
+
+/// Extracts names of the sessions created by this app from the output of [list_command].
+pub(crate) fn parse_list(device: &Device, output: &str) -> Vec<String> {
+    let names = output.lines().filter_map(|line| match multiplexer(device) {
+        Multiplexer::Tmux => Some(line.trim()),
+        // Lines look like `\t12345.name\t(Detached)`
+        Multiplexer::Screen => line
+            .split_whitespace()
+            .next()
+            .and_then(|s| s.split_once('.'))
+            .map(|(_, name)| name),
+    });
+    return names
+        .filter_map(|name| name.strip_prefix(SESSION_PREFIX))
+        .map(String::from)
+        .collect();
+}
+
+pub(crate) fn list(session: &Session, device: &Device) -> Result<Vec<String>, Error> {
+    let ch = session.new_channel()?;
+    ch.open_session()?;
+    ch.request_exec(list_command(device))?;
+    let mut buf = String::new();
+    ch.stdout().read_to_string(&mut buf)?;
+    ch.close()?;
+    return Ok(parse_list(device, &buf));
+}
+
+/// Whether the name can be used for a session without quoting surprises in multiplexers.
+pub(crate) fn valid_name(name: &str) -> bool {
+    return !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+}
+
+fn multiplexer(device: &Device) -> Multiplexer {
+    return device.multiplexer.clone().unwrap_or(Multiplexer::Tmux);
+}
@@ -1,25 +1,61 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fmt::{Debug, Formatter};
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::channel;
 use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use libssh_rs::Error::RequestDenied;
+use regex::bytes::Regex;
 use vt100::Parser;
 
 use crate::conn_pool::DeviceConnection;
+use crate::device_manager::device::{ELEVATION_PROMPT, ELEVATION_PROMPT_TIMEOUT};
 use crate::device_manager::Device;
 use crate::error::Error;
-use crate::shell_manager::{Shell, ShellInfo, ShellMessage, ShellScreen, ShellState, ShellToken};
+use crate::shell_manager::osc52::Osc52Scanner;
+use crate::shell_manager::persist;
+use crate::shell_manager::zmodem::{
+    ZmodemDetector, ZmodemDirection, ZmodemSession, CANCEL as ZMODEM_CANCEL,
+};
+use crate::shell_manager::{
+    Shell, ShellCloseReason, ShellInfo, ShellMessage, ShellOptions, ShellScreen, ShellSearchMatch,
+    ShellState, ShellToken,
+};
 
 pub(crate) type ShellsMap = HashMap<ShellToken, Arc<Shell>>;
 
+const SCROLLBACK_CAPACITY: usize = 256 * 1024;
+const WRITE_CHUNK_SIZE: usize = 1024;
+const SEARCH_SNIPPET_CONTEXT: usize = 40;
+/// ZMODEM transfers are cancelled when the device sends nothing for this long
+const ZMODEM_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+const ZMODEM_PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Password for the elevation command, held back with the startup profile until it prompts
+struct PendingElevation {
+    password: Vec<u8>,
+    profile: Option<Vec<u8>>,
+    /// Last output, where the prompt is looked for
+    tail: Vec<u8>,
+    deadline: Instant,
+}
+
 impl Shell {
+    /// Queues data to the shell. Large writes are split into chunks, so output keeps being read
+    /// between them and the channel window can catch up.
     pub fn write(&self, data: &[u8]) -> Result<(), Error> {
-        return self.queue_message(ShellMessage::Data(Vec::from(data)));
+        *self.last_input.lock().unwrap() = Some(SystemTime::now());
+        *self.pending.lock().unwrap() += data.len();
+        for chunk in data.chunks(WRITE_CHUNK_SIZE) {
+            if let Err(e) = self.queue_message(ShellMessage::Data(Vec::from(chunk))) {
+                *self.pending.lock().unwrap() = 0;
+                return Err(e);
+            }
+        }
+        return Ok(());
     }
 
     pub fn resize(&self, rows: u16, cols: u16) -> Result<(), Error> {
@@ -61,6 +97,82 @@ impl Shell {
         });
     }
 
+    /// Returns the most recent output of this shell, for reattaching terminals.
+    pub fn scrollback(&self) -> Vec<u8> {
+        return self.scrollback.lock().unwrap().iter().copied().collect();
+    }
+
+    /// Searches the scrollback buffer for the pattern, returning at most `limit` matches.
+    pub fn search(&self, pattern: &str, limit: usize) -> Result<Vec<ShellSearchMatch>, Error> {
+        let regex = Regex::new(pattern).map_err(|e| Error::new(format!("Invalid pattern: {e}")))?;
+        let mut scrollback = self.scrollback.lock().unwrap();
+        let haystack: &[u8] = scrollback.make_contiguous();
+        return Ok(regex
+            .find_iter(haystack)
+            .take(limit)
+            .map(|m| {
+                let start = m.start().saturating_sub(SEARCH_SNIPPET_CONTEXT);
+                let end = (m.end() + SEARCH_SNIPPET_CONTEXT).min(haystack.len());
+                ShellSearchMatch {
+                    offset: m.start(),
+                    length: m.len(),
+                    snippet: String::from_utf8_lossy(&haystack[start..end]).into_owned(),
+                }
+            })
+            .collect());
+    }
+
+    /// Sets a user supplied title for this shell, or resets to the default one with `None`.
+    pub fn rename(&self, title: Option<String>) {
+        *self.custom_title.lock().unwrap() = title.filter(|t| !t.trim().is_empty());
+    }
+
+    /// Receives the files `sz` on the device is sending into local directory `dir`.
+    pub fn zmodem_receive(&self, dir: &Path) -> Result<(), Error> {
+        self.zmodem_waiting(ZmodemDirection::Download)?;
+        let (session, init) = ZmodemSession::receive(dir)?;
+        return self.queue_message(ShellMessage::ZmodemStart {
+            session: Box::new(session),
+            init,
+        });
+    }
+
+    /// Sends local files to `rz` waiting on the device.
+    pub fn zmodem_send(&self, paths: &[PathBuf]) -> Result<(), Error> {
+        self.zmodem_waiting(ZmodemDirection::Upload)?;
+        let (session, init) = ZmodemSession::send(paths)?;
+        return self.queue_message(ShellMessage::ZmodemStart {
+            session: Box::new(session),
+            init,
+        });
+    }
+
+    /// Cancels the ZMODEM transfer, or declines it if it wasn't accepted yet.
+    pub fn zmodem_end(&self) {
+        if self.zmodem.lock().unwrap().is_some() {
+            if let Err(e) = self.queue_message(ShellMessage::ZmodemCancel) {
+                log::warn!("{self:?} failed to cancel ZMODEM transfer: {e:?}");
+                self.zmodem_finished();
+            }
+        }
+    }
+
+    fn zmodem_waiting(&self, direction: ZmodemDirection) -> Result<(), Error> {
+        if *self.zmodem.lock().unwrap() != Some(direction) {
+            return Err(Error::new("No ZMODEM transfer is waiting"));
+        }
+        return Ok(());
+    }
+
+    fn zmodem_finished(&self) {
+        if self.zmodem.lock().unwrap().take().is_some() {
+            log::info!("{self:?} ZMODEM transfer ended");
+            if let Some(callback) = self.callback.lock().unwrap().as_ref() {
+                callback.zmodem(None);
+            }
+        }
+    }
+
     pub fn close(&self) -> Result<(), Error> {
         self.queue_message(ShellMessage::Close)?;
         return Ok(());
@@ -79,14 +191,27 @@ impl Shell {
             title: self.title(),
             has_pty: self.has_pty.lock().unwrap().clone(),
             state,
+            last_input: self.last_input.lock().unwrap().map(epoch_millis),
+            last_output: self.last_output.lock().unwrap().map(epoch_millis),
+            unseen_output: self.unseen_output.lock().unwrap().clone(),
+            session: self.options.session.clone(),
             created_at: self.created_at,
         };
     }
 
+    /// Marks whether this shell is the one the user is looking at. Output of unfocused shells
+    /// is reported once with an activity event, until the shell gets focused again.
+    pub fn set_focused(&self, focused: bool) {
+        *self.focused.lock().unwrap() = focused;
+        if focused {
+            *self.unseen_output.lock().unwrap() = false;
+        }
+    }
+
     pub(crate) fn new(
         device: Device,
         ssh_dir: Option<&Path>,
-        wants_pty: bool,
+        options: ShellOptions,
         rows: u16,
         cols: u16,
         shells: Arc<Mutex<ShellsMap>>,
@@ -96,17 +221,47 @@ impl Shell {
             created_at: Instant::now(),
             device,
             ssh_dir: ssh_dir.map(|p| p.to_path_buf()),
-            has_pty: Mutex::new(if !wants_pty { Some(false) } else { None }),
+            has_pty: Mutex::new((!options.wants_pty).then_some(false)),
+            options,
+            custom_title: Mutex::default(),
             closed: Mutex::default(),
             sender: Mutex::default(),
             callback: Mutex::new(None),
             parser: Mutex::new(Parser::new(rows, cols, 1000)),
+            scrollback: Mutex::new(VecDeque::new()),
+            pending: Mutex::new(0),
+            zmodem: Mutex::default(),
+            last_input: Mutex::default(),
+            last_output: Mutex::default(),
+            // Only output after the frontend moves focus elsewhere counts as background activity
+            focused: Mutex::new(true),
+            unseen_output: Mutex::new(false),
             shells,
         };
         log::info!("{shell:?} created: rows={rows}, cols={cols}");
         return shell;
     }
 
+    fn record(&self, data: &[u8]) {
+        *self.last_output.lock().unwrap() = Some(SystemTime::now());
+        if !*self.focused.lock().unwrap() {
+            let mut unseen = self.unseen_output.lock().unwrap();
+            if !*unseen {
+                *unseen = true;
+                drop(unseen);
+                if let Some(callback) = self.callback.lock().unwrap().as_ref() {
+                    callback.activity();
+                }
+            }
+        }
+        let mut scrollback = self.scrollback.lock().unwrap();
+        scrollback.extend(data);
+        if scrollback.len() > SCROLLBACK_CAPACITY {
+            let overflow = scrollback.len() - SCROLLBACK_CAPACITY;
+            scrollback.drain(..overflow);
+        }
+    }
+
     fn process(&self, data: &[u8]) -> bool {
         if !self.has_pty.lock().unwrap().unwrap_or(false) {
             return false;
@@ -118,12 +273,15 @@ impl Shell {
     }
 
     fn title(&self) -> String {
+        if let Some(title) = self.custom_title.lock().unwrap().as_ref() {
+            return title.clone();
+        }
         let guard = self.parser.lock().unwrap();
         let title = guard.screen().title();
         if title.is_empty() {
-            return format!("{}@{}", self.device.username, self.device.host);
+            return self.device.name.clone();
         }
-        return String::from(title);
+        return format!("{} - {}", self.device.name, title);
     }
 
     fn queue_message(&self, message: ShellMessage) -> Result<(), Error> {
@@ -135,7 +293,7 @@ impl Shell {
         return Err(Error::Disconnected);
     }
 
-    fn worker(&self) -> Result<i32, Error> {
+    fn worker(&self) -> Result<ShellCloseReason, Error> {
         let (sender, receiver) = channel::<ShellMessage>();
         let connection = DeviceConnection::new(self.device.clone(), self.ssh_dir.as_deref())?;
         let channel = connection.new_channel()?;
@@ -143,7 +301,7 @@ impl Shell {
         let (rows, cols) = self.parser.lock().unwrap().screen().size();
         let mut has_pty = false;
         if self.has_pty.lock().unwrap().unwrap_or(true) {
-            match channel.request_pty("xterm", cols as u32, rows as u32) {
+            match channel.request_pty(&self.options.term, cols as u32, rows as u32) {
                 Ok(_) => {
                     *self.has_pty.lock().unwrap() = {
                         has_pty = true;
@@ -157,33 +315,185 @@ impl Shell {
                 e => e?,
             }
         }
-        channel.request_shell()?;
+        if has_pty && self.options.truecolor {
+            if let Err(e) = channel.request_env("COLORTERM", "truecolor") {
+                log::warn!("{self:?} failed to set COLORTERM {e:?}");
+            }
+        }
+        let mut fresh = true;
+        if let Some(name) = &self.options.session {
+            fresh = !persist::list(&connection, &self.device)?.contains(name);
+            log::info!("{self:?} attaching to session {name}, fresh={fresh}");
+            let command = persist::attach_command(&self.device, name);
+            channel.request_exec(&self.device.env_command(&command))?;
+        } else if self.device.env.as_ref().is_some_and(|env| !env.is_empty()) {
+            // sshd usually doesn't accept environment requests, so start the login shell ourselves
+            channel.request_exec(&self.device.env_command("exec \"${SHELL:-/bin/sh}\" -l"))?;
+        } else {
+            channel.request_shell()?;
+        }
+        let profile = self
+            .device
+            .shell_profile
+            .as_ref()
+            .filter(|p| fresh && !p.is_empty())
+            .map(|profile| {
+                let mut profile = profile.clone().into_bytes();
+                if !profile.ends_with(b"\n") {
+                    profile.push(b'\n');
+                }
+                return profile;
+            });
+        let mut elevation: Option<PendingElevation> = None;
+        if fresh && self.options.elevate {
+            log::debug!("{self:?} elevating to root");
+            let command = self.device.elevated_shell(has_pty)?;
+            channel
+                .stdin()
+                .write_all(format!("{command}\n").as_bytes())?;
+            // Written blindly, the password could be echoed, or end up in the shell history
+            elevation = self
+                .device
+                .elevation_input()
+                .map(|password| PendingElevation {
+                    password,
+                    profile: profile.clone(),
+                    tail: Vec::new(),
+                    deadline: Instant::now() + ELEVATION_PROMPT_TIMEOUT,
+                });
+        }
+        if let Some(profile) = profile.filter(|_| elevation.is_none()) {
+            log::debug!("{self:?} running startup profile");
+            channel.stdin().write_all(&profile)?;
+        }
+        let prompt = Regex::new(ELEVATION_PROMPT).unwrap();
         *self.sender.lock().unwrap() = Some(sender);
         if let Some(callback) = self.callback.lock().unwrap().as_ref() {
             callback.info(self.info());
         }
         let mut buf = [0; 8192];
+        let mut flowing = false;
+        let mut clipboard = self
+            .device
+            .allow_clipboard
+            .unwrap_or(false)
+            .then(Osc52Scanner::default);
+        let mut zmodem = ZmodemDetector::default();
+        let mut transfer: Option<ZmodemSession> = None;
+        let mut progress_at = Instant::now();
         while !channel.is_closed() {
             if let Ok(msg) = receiver.recv_timeout(Duration::from_micros(1)) {
                 match msg {
                     ShellMessage::Data(d) => {
                         channel.stdin().write_all(&d)?;
+                        let pending = {
+                            let mut pending = self.pending.lock().unwrap();
+                            *pending = pending.saturating_sub(d.len());
+                            *pending
+                        };
+                        if pending > 0 || flowing {
+                            flowing = pending > 0;
+                            if let Some(callback) = self.callback.lock().unwrap().as_ref() {
+                                callback.pending(pending);
+                            }
+                        }
                     }
                     ShellMessage::Resize { rows, cols } => {
                         channel.change_pty_size(cols as u32, rows as u32)?;
                     }
+                    ShellMessage::ZmodemStart { session, init } => {
+                        log::info!("{self:?} ZMODEM transfer accepted");
+                        channel.stdin().write_all(&init)?;
+                        transfer = Some(*session);
+                    }
+                    ShellMessage::ZmodemCancel => {
+                        if transfer.take().is_some() {
+                            log::info!("{self:?} ZMODEM transfer cancelled");
+                        }
+                        channel.stdin().write_all(ZMODEM_CANCEL)?;
+                        self.zmodem_finished();
+                    }
                     ShellMessage::Close => {
                         channel.close()?;
-                        break;
+                        return Ok(ShellCloseReason::Closed);
                     }
                 }
             }
             let size = channel.read_timeout(&mut buf, false, Some(Duration::from_micros(5)))?;
-            if size != 0 {
+            if let Some(pending) = elevation.as_mut() {
+                pending.tail.extend_from_slice(&buf[..size]);
+                let overflow = pending.tail.len().saturating_sub(256);
+                pending.tail.drain(..overflow);
+                let prompted = prompt.is_match(&pending.tail);
+                if prompted || Instant::now() > pending.deadline {
+                    if prompted {
+                        channel.stdin().write_all(&pending.password)?;
+                    } else {
+                        log::warn!("{self:?} elevation didn't prompt for password");
+                    }
+                    if let Some(profile) = pending.profile.as_ref() {
+                        log::debug!("{self:?} running startup profile");
+                        channel.stdin().write_all(profile)?;
+                    }
+                    elevation = None;
+                }
+            }
+            let mut leftover = Vec::new();
+            let mut data = &buf[..size];
+            if let Some(session) = transfer.as_mut() {
+                let result = session
+                    .feed(data)
+                    .and_then(|out| Ok([out, session.poll()?].concat()));
+                let error = match result {
+                    Ok(out) => {
+                        channel.stdin().write_all(&out)?;
+                        (session.idle() > ZMODEM_IDLE_TIMEOUT)
+                            .then(|| Error::new("The device stopped responding"))
+                    }
+                    Err(e) => Some(e),
+                };
+                if let Some(e) = &error {
+                    log::warn!("{self:?} ZMODEM transfer failed: {e:?}");
+                    channel.stdin().write_all(ZMODEM_CANCEL)?;
+                }
+                let ended = error.is_some() || session.is_done();
+                if ended || progress_at.elapsed() > ZMODEM_PROGRESS_INTERVAL {
+                    progress_at = Instant::now();
+                    let mut progress = session.progress().clone();
+                    progress.error = error.map(|e| e.to_string());
+                    if let Some(callback) = self.callback.lock().unwrap().as_ref() {
+                        callback.zmodem_progress(progress);
+                    }
+                }
+                if ended {
+                    leftover = session.take_leftover();
+                    transfer = None;
+                    self.zmodem_finished();
+                }
+                data = &leftover;
+            } else if self.zmodem.lock().unwrap().is_some() {
+                // Protocol data until the transfer is accepted, which would only garble the screen
+                data = &[];
+            }
+            if !data.is_empty() {
+                let zmodem_started = zmodem.feed(data);
+                if let Some(direction) = zmodem_started {
+                    log::info!("{self:?} ZMODEM transfer started: {direction:?}");
+                    *self.zmodem.lock().unwrap() = Some(direction);
+                }
+                self.record(data);
                 if let Some(callback) = self.callback.lock().unwrap().as_ref() {
-                    callback.rx(0, &buf[..size]);
+                    callback.rx(0, data);
+                    if let Some(scanner) = clipboard.as_mut() {
+                        for item in scanner.feed(data) {
+                            callback.clipboard(item.selection, item.data);
+                        }
+                    }
+                    if let Some(direction) = zmodem_started {
+                        callback.zmodem(Some(direction));
+                    }
                 }
-                if self.process(&buf[..size]) {
+                if self.process(data) {
                     if let Some(callback) = self.callback.lock().unwrap().as_ref() {
                         callback.info(self.info());
                     }
@@ -191,29 +501,42 @@ impl Shell {
             }
             if !has_pty {
                 let size = channel.read_timeout(&mut buf, true, Some(Duration::from_micros(5)))?;
+                // Without a terminal, prompts go to stderr
+                if let Some(pending) = elevation.as_mut() {
+                    pending.tail.extend_from_slice(&buf[..size]);
+                }
                 if size != 0 {
+                    self.record(&buf[..size]);
                     if let Some(callback) = self.callback.lock().unwrap().as_ref() {
                         callback.rx(1, &buf[..size]);
                     }
                 }
             }
         }
-        return Ok(channel.get_exit_status().unwrap_or(0));
+        return Ok(match channel.get_exit_status() {
+            Some(exit_code) => ShellCloseReason::Exited { exit_code },
+            // No exit status is sent when the process is killed by a signal
+            None => ShellCloseReason::Signaled,
+        });
     }
 
-    fn closed(&self, result: Result<i32, Error>) -> bool {
-        *self.closed.lock().unwrap() = Some(match &result {
-            Ok(code) => ShellState::Exited {
-                return_code: code.clone(),
+    fn closed(&self, reason: ShellCloseReason) -> bool {
+        *self.closed.lock().unwrap() = Some(match &reason {
+            ShellCloseReason::Exited { exit_code } => ShellState::Exited {
+                return_code: *exit_code,
+            },
+            ShellCloseReason::Signaled => ShellState::Exited { return_code: -1 },
+            ShellCloseReason::Closed => ShellState::Exited { return_code: 0 },
+            ShellCloseReason::Disconnected { error } => ShellState::Error {
+                error: error.clone(),
             },
-            Err(e) => ShellState::Error { error: e.clone() },
         });
         if let Some(callback) = self.callback.lock().unwrap().take() {
-            let removed = result.map_or(false, |v| v == 0);
+            let removed = reason.is_clean();
             if !removed {
                 callback.info(self.info());
             }
-            callback.closed(removed);
+            callback.closed(reason, removed);
             return true;
         }
         return false;
@@ -222,18 +545,37 @@ impl Shell {
     pub(crate) fn thread(shell: Arc<Shell>) -> JoinHandle<()> {
         log::info!("Starting thread for {shell:?}");
         return std::thread::spawn(move || {
-            let result = shell.worker();
-            log::info!("{shell:?} worker exited with {result:?}");
-            if let Ok(0) = result {
+            let reason = shell
+                .worker()
+                .unwrap_or_else(|error| ShellCloseReason::Disconnected { error });
+            log::info!("{shell:?} worker exited with {reason:?}");
+            if reason.is_clean() {
                 if shell.shells.lock().unwrap().remove(&shell.token).is_some() {
                     log::info!("Removed {shell:?}");
                 }
             }
-            shell.closed(result);
+            shell.closed(reason);
         });
     }
 }
 
+impl ShellCloseReason {
+    /// Whether the shell ended normally, and doesn't need to be kept around for the user to see
+    fn is_clean(&self) -> bool {
+        return matches!(
+            self,
+            ShellCloseReason::Exited { exit_code: 0 } | ShellCloseReason::Closed
+        );
+    }
+}
+
+fn epoch_millis(time: SystemTime) -> u64 {
+    return time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+}
+
 impl Debug for Shell {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
@@ -0,0 +1,933 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::error::Error;
+
+/// Start of a ZMODEM hex header, followed by the frame type
+const HEADER: &[u8] = b"**\x18B0";
+
+/// Aborts the transfer on the other side, and erases the cancel characters from its terminal
+pub(crate) const CANCEL: &[u8] =
+    b"\x18\x18\x18\x18\x18\x18\x18\x18\x08\x08\x08\x08\x08\x08\x08\x08";
+
+const ZPAD: u8 = b'*';
+const ZDLE: u8 = 0x18;
+const ZBIN: u8 = b'A';
+const ZHEX: u8 = b'B';
+const ZBIN32: u8 = b'C';
+
+const ZRQINIT: u8 = 0;
+const ZRINIT: u8 = 1;
+const ZSINIT: u8 = 2;
+const ZACK: u8 = 3;
+const ZFILE: u8 = 4;
+const ZSKIP: u8 = 5;
+const ZNAK: u8 = 6;
+const ZABORT: u8 = 7;
+const ZFIN: u8 = 8;
+const ZRPOS: u8 = 9;
+const ZDATA: u8 = 10;
+const ZEOF: u8 = 11;
+const ZFERR: u8 = 12;
+const ZCHALLENGE: u8 = 14;
+const ZCAN: u8 = 16;
+const ZFREECNT: u8 = 17;
+const ZCOMMAND: u8 = 18;
+
+/// Ends of data subpackets: end of frame, go on, go on and acknowledge, wait for acknowledgement
+const ZCRCE: u8 = b'h';
+const ZCRCG: u8 = b'i';
+const ZCRCQ: u8 = b'j';
+const ZCRCW: u8 = b'k';
+const ZRUB0: u8 = b'l';
+const ZRUB1: u8 = b'm';
+
+/// Full duplex, overlapped I/O and 32-bit CRC, as the receiver
+const RECEIVER_FLAGS: u8 = 0x01 | 0x02 | 0x20;
+/// Binary conversion of sent files
+const ZCBIN: u8 = 1;
+
+const SUBPACKET_SIZE: usize = 1024;
+/// Subpackets sent before asking the receiver for an acknowledgement
+const ACK_INTERVAL: u64 = 16;
+/// Largest subpacket accepted, lrzsz sends up to 8 KiB
+const MAX_SUBPACKET_SIZE: usize = 16 * 1024;
+/// Output produced by one [ZmodemSession::poll] while sending
+const POLL_SIZE: usize = 16 * 1024;
+
+#[derive(Clone, Copy, Serialize, Debug, PartialEq)]
+pub enum ZmodemDirection {
+    /// Remote `sz` is sending a file (ZRQINIT)
+    Download,
+    /// Remote `rz` is waiting for a file (ZRINIT)
+    Upload,
+}
+
+/// Looks for ZMODEM session start headers in terminal output, which may be split across reads.
+#[derive(Default)]
+pub(crate) struct ZmodemDetector {
+    matched: usize,
+}
+
+impl ZmodemDetector {
+    pub(crate) fn feed(&mut self, data: &[u8]) -> Option<ZmodemDirection> {
+        for &b in data {
+            if self.matched == HEADER.len() {
+                self.matched = 0;
+                match b {
+                    b'0' => return Some(ZmodemDirection::Download),
+                    b'1' => return Some(ZmodemDirection::Upload),
+                    _ => {}
+                }
+            }
+            if b == HEADER[self.matched] {
+                self.matched += 1;
+            } else if b == ZPAD {
+                // Only a mismatch right after `**` leaves `**` seen, otherwise this starts over
+                self.matched = if self.matched == 2 { 2 } else { 1 };
+            } else {
+                self.matched = 0;
+            }
+        }
+        return None;
+    }
+}
+
+/// State of a transfer, reported to the frontend
+#[derive(Clone, Serialize, Debug, Default)]
+pub struct ZmodemProgress {
+    /// Name of the file being transferred
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Bytes of the file transferred
+    pub transferred: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// Local paths of received files, or names of sent files
+    pub completed: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// ZMODEM transfer through the shell channel. Incoming data is given to [ZmodemSession::feed],
+/// and what it returns, or [ZmodemSession::poll] returns while sending, goes to the device.
+pub(crate) struct ZmodemSession {
+    parser: Parser,
+    role: Role,
+    progress: ZmodemProgress,
+    done: bool,
+    last_input: Instant,
+}
+
+enum Role {
+    Receive(Receiver),
+    Send(Sender),
+}
+
+impl ZmodemSession {
+    /// Starts receiving files sent by `sz` into local directory `dir`.
+    pub(crate) fn receive(dir: &Path) -> Result<(ZmodemSession, Vec<u8>), Error> {
+        std::fs::create_dir_all(dir)?;
+        let session = ZmodemSession {
+            parser: Parser::default(),
+            role: Role::Receive(Receiver {
+                dir: dir.to_path_buf(),
+                file: None,
+                expect: Expect::Header,
+            }),
+            progress: ZmodemProgress::default(),
+            done: false,
+            last_input: Instant::now(),
+        };
+        return Ok((session, receiver_init()));
+    }
+
+    /// Starts sending local files `paths` to `rz`, which is waiting for them.
+    pub(crate) fn send(paths: &[PathBuf]) -> Result<(ZmodemSession, Vec<u8>), Error> {
+        if paths.is_empty() {
+            return Err(Error::new("No files to send"));
+        }
+        for path in paths {
+            if !path.is_file() {
+                return Err(Error::new(format!("{} is not a file", path.display())));
+            }
+        }
+        let session = ZmodemSession {
+            parser: Parser::default(),
+            role: Role::Send(Sender {
+                files: paths.iter().cloned().collect(),
+                file: None,
+                state: SendState::Init,
+            }),
+            progress: ZmodemProgress::default(),
+            done: false,
+            last_input: Instant::now(),
+        };
+        return Ok((session, hex_header(ZRQINIT, [0; 4])));
+    }
+
+    /// Handles data from the device, and returns the response.
+    pub(crate) fn feed(&mut self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut out = Vec::new();
+        if data.is_empty() || self.done {
+            return Ok(out);
+        }
+        self.last_input = Instant::now();
+        self.parser.push(data);
+        while !self.done {
+            let Some(frame) = self.parser.next() else {
+                break;
+            };
+            match &mut self.role {
+                Role::Receive(_) => self.receive_frame(frame, &mut out)?,
+                Role::Send(_) => self.send_frame(frame, &mut out)?,
+            }
+        }
+        return Ok(out);
+    }
+
+    /// Returns more file data to send, if the receiver is ready for it.
+    pub(crate) fn poll(&mut self) -> Result<Vec<u8>, Error> {
+        let mut out = Vec::new();
+        let Role::Send(sender) = &mut self.role else {
+            return Ok(out);
+        };
+        let Some(file) = sender.file.as_mut() else {
+            return Ok(out);
+        };
+        while sender.state == SendState::Streaming && out.len() < POLL_SIZE {
+            let mut data = Vec::with_capacity(SUBPACKET_SIZE);
+            (&mut file.file)
+                .take(SUBPACKET_SIZE as u64)
+                .read_to_end(&mut data)?;
+            file.offset += data.len() as u64;
+            file.packets += 1;
+            if data.len() < SUBPACKET_SIZE {
+                out.extend(subpacket(&data, ZCRCE));
+                out.extend(bin_header(ZEOF, position(file.offset)));
+                sender.state = SendState::WaitEof;
+            } else if file.packets % ACK_INTERVAL == 0 {
+                out.extend(subpacket(&data, ZCRCQ));
+            } else {
+                out.extend(subpacket(&data, ZCRCG));
+            }
+            self.progress.transferred = file.offset;
+        }
+        return Ok(out);
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        return self.done;
+    }
+
+    /// Time since the device last sent anything
+    pub(crate) fn idle(&self) -> Duration {
+        return self.last_input.elapsed();
+    }
+
+    pub(crate) fn progress(&self) -> &ZmodemProgress {
+        return &self.progress;
+    }
+
+    /// Output of the device after the transfer ended, which belongs to the terminal again. The
+    /// `OO` that `sz` ends with is dropped.
+    pub(crate) fn take_leftover(&mut self) -> Vec<u8> {
+        let leftover = std::mem::take(&mut self.parser.buf);
+        let start = leftover.iter().take(2).take_while(|b| **b == b'O').count();
+        return leftover[start..].to_vec();
+    }
+
+    fn receive_frame(&mut self, frame: Frame, out: &mut Vec<u8>) -> Result<(), Error> {
+        let Role::Receive(receiver) = &mut self.role else {
+            return Ok(());
+        };
+        match frame {
+            Frame::Cancel => return Err(Error::new("Transfer cancelled by the device")),
+            Frame::BadCrc { subpacket } => {
+                // Bad headers are ignored, the sender times out and repeats them
+                if subpacket {
+                    receiver.expect = Expect::Header;
+                    self.parser.subpacket = None;
+                    let offset = receiver.file.as_ref().map_or(0, |f| f.offset);
+                    out.extend(hex_header(ZRPOS, position(offset)));
+                }
+            }
+            Frame::Header { kind, data, crc32 } => match kind {
+                ZRQINIT => out.extend(receiver_init()),
+                ZSINIT => {
+                    receiver.expect = Expect::Init;
+                    self.parser.subpacket = Some(crc32);
+                }
+                ZFILE => {
+                    receiver.expect = Expect::FileInfo;
+                    self.parser.subpacket = Some(crc32);
+                }
+                ZDATA => {
+                    let offset = receiver.file.as_ref().map(|f| f.offset);
+                    let discard = offset != Some(u32::from_le_bytes(data) as u64);
+                    if discard {
+                        out.extend(hex_header(ZRPOS, position(offset.unwrap_or(0))));
+                    }
+                    receiver.expect = Expect::Data { discard };
+                    self.parser.subpacket = Some(crc32);
+                }
+                ZEOF => match receiver.file.take() {
+                    Some(file) if file.offset == u32::from_le_bytes(data) as u64 => {
+                        file.file.sync_all()?;
+                        log::info!("ZMODEM received {}", file.path.display());
+                        self.progress
+                            .completed
+                            .push(file.path.to_string_lossy().to_string());
+                        self.progress.file = None;
+                        out.extend(receiver_init());
+                    }
+                    // Data still in flight, the sender goes back to our position
+                    Some(file) => receiver.file = Some(file),
+                    None => out.extend(receiver_init()),
+                },
+                ZFIN => {
+                    out.extend(hex_header(ZFIN, [0; 4]));
+                    self.done = true;
+                }
+                ZFREECNT => out.extend(hex_header(ZACK, [0; 4])),
+                ZCHALLENGE => out.extend(hex_header(ZACK, data)),
+                ZCOMMAND => return Err(Error::new("Remote commands are not supported")),
+                ZCAN | ZABORT | ZFERR => return Err(Error::new("Transfer aborted by the device")),
+                _ => {}
+            },
+            Frame::Subpacket { data, end } => {
+                match receiver.expect {
+                    Expect::Init => out.extend(hex_header(ZACK, [0; 4])),
+                    Expect::FileInfo => {
+                        let (file, size) = receiver.open(&data)?;
+                        self.progress.file = Some(file.name.clone());
+                        self.progress.size = size;
+                        self.progress.transferred = 0;
+                        receiver.file = Some(file);
+                        out.extend(hex_header(ZRPOS, position(0)));
+                    }
+                    Expect::Data { discard: false } => {
+                        if let Some(file) = receiver.file.as_mut() {
+                            file.file.write_all(&data)?;
+                            file.offset += data.len() as u64;
+                            self.progress.transferred = file.offset;
+                            if end == ZCRCQ || end == ZCRCW {
+                                out.extend(hex_header(ZACK, position(file.offset)));
+                            }
+                        }
+                    }
+                    Expect::Data { discard: true } | Expect::Header => {}
+                }
+                if end == ZCRCE || end == ZCRCW {
+                    receiver.expect = Expect::Header;
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    fn send_frame(&mut self, frame: Frame, out: &mut Vec<u8>) -> Result<(), Error> {
+        let Role::Send(sender) = &mut self.role else {
+            return Ok(());
+        };
+        let Frame::Header { kind, data, .. } = frame else {
+            if let Frame::Cancel = frame {
+                return Err(Error::new("Transfer cancelled by the device"));
+            }
+            return Ok(());
+        };
+        match (kind, sender.state) {
+            (ZRINIT, SendState::Init) => self.next_file(out)?,
+            // The receiver didn't get the file header
+            (ZNAK, SendState::WaitPosition) => {
+                if let Some(file) = sender.file.as_ref() {
+                    out.extend(file_header(file));
+                }
+            }
+            (ZRINIT, SendState::WaitEof) => {
+                if let Some(file) = sender.file.take() {
+                    self.progress.completed.push(file.name);
+                }
+                self.next_file(out)?;
+            }
+            (ZRPOS, SendState::WaitPosition | SendState::Streaming | SendState::WaitEof) => {
+                if let Some(file) = sender.file.as_mut() {
+                    let offset = u32::from_le_bytes(data) as u64;
+                    file.file.seek(SeekFrom::Start(offset))?;
+                    file.offset = offset;
+                    out.extend(bin_header(ZDATA, position(offset)));
+                    sender.state = SendState::Streaming;
+                }
+            }
+            (ZSKIP, SendState::WaitPosition | SendState::Streaming | SendState::WaitEof) => {
+                sender.file = None;
+                self.next_file(out)?;
+            }
+            (ZFIN, SendState::WaitFinish) => {
+                out.extend(b"OO");
+                self.done = true;
+            }
+            (ZCAN | ZABORT | ZFERR, _) => return Err(Error::new("Transfer aborted by the device")),
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    /// Offers the next file to the receiver, or ends the session when all were sent.
+    fn next_file(&mut self, out: &mut Vec<u8>) -> Result<(), Error> {
+        let Role::Send(sender) = &mut self.role else {
+            return Ok(());
+        };
+        let Some(path) = sender.files.pop_front() else {
+            sender.state = SendState::WaitFinish;
+            self.progress.file = None;
+            out.extend(hex_header(ZFIN, [0; 4]));
+            return Ok(());
+        };
+        let file = File::open(&path)?;
+        let metadata = file.metadata()?;
+        let outgoing = Outgoing {
+            file,
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            size: metadata.len(),
+            mtime: metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_secs()),
+            offset: 0,
+            packets: 0,
+        };
+        out.extend(file_header(&outgoing));
+        self.progress.file = Some(outgoing.name.clone());
+        self.progress.size = Some(outgoing.size);
+        self.progress.transferred = 0;
+        sender.file = Some(outgoing);
+        sender.state = SendState::WaitPosition;
+        return Ok(());
+    }
+}
+
+struct Receiver {
+    dir: PathBuf,
+    file: Option<Incoming>,
+    expect: Expect,
+}
+
+#[derive(Clone, Copy)]
+enum Expect {
+    Header,
+    Init,
+    FileInfo,
+    Data { discard: bool },
+}
+
+struct Incoming {
+    file: File,
+    path: PathBuf,
+    name: String,
+    offset: u64,
+}
+
+impl Receiver {
+    /// Creates the file described by a ZFILE subpacket, `name\0size mtime mode ...`. Only the
+    /// base name is used, and existing files are kept, with a number appended to the new one.
+    fn open(&self, info: &[u8]) -> Result<(Incoming, Option<u64>), Error> {
+        let mut fields = info.splitn(2, |b| *b == 0);
+        let name = String::from_utf8_lossy(fields.next().unwrap_or_default()).to_string();
+        let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+        if name.is_empty() || name == "." || name == ".." {
+            return Err(Error::new(format!("Bad file name {name:?}")));
+        }
+        let size = fields
+            .next()
+            .map(|rest| String::from_utf8_lossy(rest).to_string())
+            .and_then(|rest| rest.split([' ', '\0']).next()?.parse::<u64>().ok());
+        let mut path = self.dir.join(name);
+        let mut copy = 0;
+        while path.exists() {
+            copy += 1;
+            path = self.dir.join(format!("{name}.{copy}"));
+        }
+        let file = Incoming {
+            file: File::create(&path)?,
+            path,
+            name: String::from(name),
+            offset: 0,
+        };
+        return Ok((file, size));
+    }
+}
+
+struct Sender {
+    files: VecDeque<PathBuf>,
+    file: Option<Outgoing>,
+    state: SendState,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum SendState {
+    /// Waiting for the receiver to be ready
+    Init,
+    /// File offered, waiting for the position to start from
+    WaitPosition,
+    Streaming,
+    /// End of file sent, waiting for the receiver to be ready again
+    WaitEof,
+    WaitFinish,
+}
+
+struct Outgoing {
+    file: File,
+    name: String,
+    size: u64,
+    /// Seconds since epoch
+    mtime: u64,
+    offset: u64,
+    packets: u64,
+}
+
+#[derive(Debug, PartialEq)]
+enum Frame {
+    Header {
+        kind: u8,
+        data: [u8; 4],
+        crc32: bool,
+    },
+    Subpacket {
+        data: Vec<u8>,
+        end: u8,
+    },
+    BadCrc {
+        subpacket: bool,
+    },
+    Cancel,
+}
+
+/// Splits incoming data into headers and data subpackets, skipping anything else.
+#[derive(Default)]
+struct Parser {
+    buf: Vec<u8>,
+    /// Whether subpackets come next, and with 32-bit CRC, otherwise a header is looked for
+    subpacket: Option<bool>,
+}
+
+impl Parser {
+    fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    fn next(&mut self) -> Option<Frame> {
+        if let Some(index) = self.buf.windows(5).position(|w| w == [ZDLE; 5]) {
+            self.buf.drain(..index + 5);
+            return Some(Frame::Cancel);
+        }
+        return match self.subpacket {
+            Some(crc32) => self.next_subpacket(crc32),
+            None => self.next_header(),
+        };
+    }
+
+    fn next_header(&mut self) -> Option<Frame> {
+        loop {
+            let Some(start) = self.buf.iter().position(|b| *b == ZPAD) else {
+                self.buf.clear();
+                return None;
+            };
+            self.buf.drain(..start);
+            let pads = self.buf.iter().take_while(|b| **b == ZPAD).count();
+            if self.buf.len() < pads + 2 {
+                return None;
+            }
+            if self.buf[pads] != ZDLE {
+                self.buf.drain(..pads);
+                continue;
+            }
+            let body = pads + 2;
+            let parsed = match self.buf[pads + 1] {
+                ZHEX => parse_hex_header(&self.buf[body..]),
+                ZBIN => parse_bin_header(&self.buf[body..], false),
+                ZBIN32 => parse_bin_header(&self.buf[body..], true),
+                _ => Some(None),
+            };
+            return match parsed {
+                None => None,
+                Some(None) => {
+                    self.buf.drain(..pads);
+                    continue;
+                }
+                Some(Some((frame, len))) => {
+                    self.buf.drain(..body + len);
+                    Some(frame)
+                }
+            };
+        }
+    }
+
+    fn next_subpacket(&mut self, crc32: bool) -> Option<Frame> {
+        let mut data = Vec::new();
+        let mut index = 0;
+        let end = loop {
+            if data.len() > MAX_SUBPACKET_SIZE {
+                return Some(self.bad_subpacket(index));
+            }
+            let b = *self.buf.get(index)?;
+            index += 1;
+            if b != ZDLE {
+                // Flow control characters are never data, those are escaped
+                if !matches!(b, 0x11 | 0x13 | 0x91 | 0x93) {
+                    data.push(b);
+                }
+                continue;
+            }
+            let escaped = *self.buf.get(index)?;
+            index += 1;
+            match escaped {
+                ZCRCE | ZCRCG | ZCRCQ | ZCRCW => break escaped,
+                _ => match unescape(escaped) {
+                    Some(b) => data.push(b),
+                    None => return Some(self.bad_subpacket(index)),
+                },
+            }
+        };
+        let (crc, len) = match unescape_bytes(&self.buf[index..], if crc32 { 4 } else { 2 }) {
+            Ok(Some(crc)) => crc,
+            Ok(None) => return None,
+            Err(_) => return Some(self.bad_subpacket(index)),
+        };
+        self.buf.drain(..index + len);
+        let valid = match crc32 {
+            true => crc.as_slice() == crc32_of(&[&data, &[end]]).to_le_bytes(),
+            false => crc.as_slice() == crc16_of(&[&data, &[end]]).to_be_bytes(),
+        };
+        if !valid {
+            self.subpacket = None;
+            return Some(Frame::BadCrc { subpacket: true });
+        }
+        if end == ZCRCE || end == ZCRCW {
+            self.subpacket = None;
+        }
+        return Some(Frame::Subpacket { data, end });
+    }
+
+    fn bad_subpacket(&mut self, index: usize) -> Frame {
+        self.buf.drain(..index);
+        self.subpacket = None;
+        return Frame::BadCrc { subpacket: true };
+    }
+}
+
+/// Parses a hex header after `ZDLE B`. Returns `None` if incomplete, and `Some(None)` if invalid.
+fn parse_hex_header(buf: &[u8]) -> Option<Option<(Frame, usize)>> {
+    if buf.len() < 14 {
+        return None;
+    }
+    let Ok(hex) = std::str::from_utf8(&buf[..14]) else {
+        return Some(None);
+    };
+    let Ok(bytes) = hex::decode(hex) else {
+        return Some(None);
+    };
+    // Followed by CR, LF and XON, with the high bit set on some
+    let trailer = buf[14..]
+        .iter()
+        .take(3)
+        .take_while(|b| matches!(*b & 0x7f, b'\r' | b'\n' | 0x11))
+        .count();
+    let len = 14 + trailer;
+    if crc16_of(&[&bytes[..5]]).to_be_bytes() != bytes[5..] {
+        return Some(Some((Frame::BadCrc { subpacket: false }, len)));
+    }
+    let frame = Frame::Header {
+        kind: bytes[0],
+        data: [bytes[1], bytes[2], bytes[3], bytes[4]],
+        crc32: false,
+    };
+    return Some(Some((frame, len)));
+}
+
+/// Parses a binary header after `ZDLE A` or `ZDLE C`, like [parse_hex_header].
+fn parse_bin_header(buf: &[u8], crc32: bool) -> Option<Option<(Frame, usize)>> {
+    let bytes = match unescape_bytes(buf, if crc32 { 9 } else { 7 }) {
+        Ok(Some(bytes)) => bytes,
+        Ok(None) => return None,
+        Err(_) => return Some(None),
+    };
+    let (bytes, len) = bytes;
+    let valid = match crc32 {
+        true => bytes[5..] == crc32_of(&[&bytes[..5]]).to_le_bytes(),
+        false => bytes[5..] == crc16_of(&[&bytes[..5]]).to_be_bytes(),
+    };
+    if !valid {
+        return Some(Some((Frame::BadCrc { subpacket: false }, len)));
+    }
+    let frame = Frame::Header {
+        kind: bytes[0],
+        data: [bytes[1], bytes[2], bytes[3], bytes[4]],
+        crc32,
+    };
+    return Some(Some((frame, len)));
+}
+
+/// Decodes `count` ZDLE-escaped bytes, and returns them with the length they took.
+fn unescape_bytes(buf: &[u8], count: usize) -> Result<Option<(Vec<u8>, usize)>, ()> {
+    let mut bytes = Vec::with_capacity(count);
+    let mut index = 0;
+    while bytes.len() < count {
+        let Some(&b) = buf.get(index) else {
+            return Ok(None);
+        };
+        index += 1;
+        if b != ZDLE {
+            bytes.push(b);
+            continue;
+        }
+        let Some(&escaped) = buf.get(index) else {
+            return Ok(None);
+        };
+        index += 1;
+        bytes.push(unescape(escaped).ok_or(())?);
+    }
+    return Ok(Some((bytes, index)));
+}
+
+fn unescape(escaped: u8) -> Option<u8> {
+    return match escaped {
+        ZRUB0 => Some(0x7f),
+        ZRUB1 => Some(0xff),
+        b if b & 0x60 == 0x40 => Some(b ^ 0x40),
+        _ => None,
+    };
+}
+
+fn escape(out: &mut Vec<u8>, data: &[u8]) {
+    for &b in data {
+        match b {
+            ZDLE | 0x10 | 0x11 | 0x13 | 0x90 | 0x91 | 0x93 => {
+                out.push(ZDLE);
+                out.push(b ^ 0x40);
+            }
+            _ => out.push(b),
+        }
+    }
+}
+
+fn position(offset: u64) -> [u8; 4] {
+    return (offset as u32).to_le_bytes();
+}
+
+fn receiver_init() -> Vec<u8> {
+    return hex_header(ZRINIT, [0, 0, 0, RECEIVER_FLAGS]);
+}
+
+fn file_header(file: &Outgoing) -> Vec<u8> {
+    let mut out = bin_header(ZFILE, [0, 0, 0, ZCBIN]);
+    let info = format!(
+        "{}\0{} {:o} 0 0 1 {}\0",
+        file.name, file.size, file.mtime, file.size
+    );
+    out.extend(subpacket(info.as_bytes(), ZCRCW));
+    return out;
+}
+
+fn hex_header(kind: u8, data: [u8; 4]) -> Vec<u8> {
+    let mut frame = vec![kind];
+    frame.extend(data);
+    let crc = crc16_of(&[&frame]);
+    let mut out = vec![ZPAD, ZPAD, ZDLE, ZHEX];
+    out.extend(hex::encode([&frame[..], &crc.to_be_bytes()].concat()).as_bytes());
+    out.extend(b"\r\x8a");
+    if kind != ZFIN && kind != ZACK {
+        out.push(0x11);
+    }
+    return out;
+}
+
+/// Binary header with 16-bit CRC, which every receiver accepts
+fn bin_header(kind: u8, data: [u8; 4]) -> Vec<u8> {
+    let mut frame = vec![kind];
+    frame.extend(data);
+    let crc = crc16_of(&[&frame]);
+    let mut out = vec![ZPAD, ZDLE, ZBIN];
+    escape(&mut out, &frame);
+    escape(&mut out, &crc.to_be_bytes());
+    return out;
+}
+
+/// Data subpacket with 16-bit CRC
+fn subpacket(data: &[u8], end: u8) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + 8);
+    escape(&mut out, data);
+    out.push(ZDLE);
+    out.push(end);
+    escape(&mut out, &crc16_of(&[data, &[end]]).to_be_bytes());
+    if end == ZCRCW {
+        out.push(0x11);
+    }
+    return out;
+}
+
+/// CRC-16/XMODEM
+fn crc16_of(parts: &[&[u8]]) -> u16 {
+    let mut crc: u16 = 0;
+    for &b in parts.iter().flat_map(|part| part.iter()) {
+        crc ^= (b as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    return crc;
+}
+
+/// CRC-32 as in Ethernet and zlib
+fn crc32_of(parts: &[&[u8]]) -> u32 {
+    let mut crc: u32 = 0xffff_ffff;
+    for &b in parts.iter().flat_map(|part| part.iter()) {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    return !crc;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_start_headers() {
+        let mut detector = ZmodemDetector::default();
+        assert_eq!(
+            detector.feed(b"$ sz log\r\n**\x18B00000"),
+            Some(ZmodemDirection::Download)
+        );
+        let mut detector = ZmodemDetector::default();
+        assert_eq!(detector.feed(b"rz ready **\x18B0"), None);
+        assert_eq!(detector.feed(b"100000"), Some(ZmodemDirection::Upload));
+    }
+
+    #[test]
+    fn detects_after_extra_pads() {
+        let mut detector = ZmodemDetector::default();
+        assert_eq!(
+            detector.feed(b"***\x18B00"),
+            Some(ZmodemDirection::Download)
+        );
+        let mut detector = ZmodemDetector::default();
+        assert_eq!(detector.feed(b"**\x18*\x18B00"), None);
+        let mut detector = ZmodemDetector::default();
+        assert_eq!(detector.feed(b"*\x18*"), None);
+        assert_eq!(detector.feed(b"*\x18B01"), Some(ZmodemDirection::Upload));
+    }
+
+    #[test]
+    fn crcs_match_reference_values() {
+        assert_eq!(crc16_of(&[b"123456789"]), 0x31c3);
+        assert_eq!(crc32_of(&[b"123", b"456789"]), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn parses_hex_header() {
+        let mut parser = Parser::default();
+        parser.push(&hex_header(ZRPOS, position(1234)));
+        let frame = parser.next();
+        assert_eq!(
+            frame,
+            Some(Frame::Header {
+                kind: ZRPOS,
+                data: position(1234),
+                crc32: false
+            })
+        );
+        assert!(parser.buf.is_empty());
+    }
+
+    #[test]
+    fn parses_crc32_bin_header() {
+        let frame = [ZDATA, 0x18, 0x11, 0, 0];
+        let mut encoded = vec![ZPAD, ZDLE, ZBIN32];
+        escape(&mut encoded, &frame);
+        escape(&mut encoded, &crc32_of(&[&frame]).to_le_bytes());
+        let mut parser = Parser::default();
+        parser.push(b"noise");
+        parser.push(&encoded);
+        let expected = Frame::Header {
+            kind: ZDATA,
+            data: [0x18, 0x11, 0, 0],
+            crc32: true,
+        };
+        assert_eq!(parser.next(), Some(expected));
+    }
+
+    #[test]
+    fn parses_split_subpackets() {
+        let mut parser = Parser::default();
+        parser.subpacket = Some(false);
+        let data: Vec<u8> = (0..=255).collect();
+        let encoded = subpacket(&data, ZCRCW);
+        parser.push(&encoded[..100]);
+        assert_eq!(parser.next(), None);
+        parser.push(&encoded[100..]);
+        assert_eq!(parser.next(), Some(Frame::Subpacket { data, end: ZCRCW }));
+        assert_eq!(parser.subpacket, None);
+    }
+
+    #[test]
+    fn reports_corrupted_subpackets() {
+        let mut parser = Parser::default();
+        parser.subpacket = Some(false);
+        let mut encoded = subpacket(b"hello", ZCRCG);
+        encoded[0] = b'j';
+        parser.push(&encoded);
+        assert_eq!(parser.next(), Some(Frame::BadCrc { subpacket: true }));
+    }
+
+    #[test]
+    fn transfers_files_between_sessions() {
+        let base = std::env::temp_dir().join(format!("zmodem-test-{}", std::process::id()));
+        let source = base.join("source");
+        let target = base.join("target");
+        std::fs::create_dir_all(&source).unwrap();
+        let big: Vec<u8> = (0..100_000u32).map(|i| (i * 7 % 256) as u8).collect();
+        std::fs::write(source.join("big.bin"), &big).unwrap();
+        std::fs::write(source.join("empty.txt"), b"").unwrap();
+        std::fs::write(source.join("exact.bin"), vec![0x18; SUBPACKET_SIZE]).unwrap();
+        let paths = ["big.bin", "empty.txt", "exact.bin"].map(|name| source.join(name));
+        let (mut receiver, mut to_sender) = ZmodemSession::receive(&target).unwrap();
+        let (mut sender, mut to_receiver) = ZmodemSession::send(&paths).unwrap();
+        for _ in 0..10_000 {
+            if sender.is_done() && receiver.is_done() {
+                break;
+            }
+            to_receiver.extend(sender.feed(&std::mem::take(&mut to_sender)).unwrap());
+            to_receiver.extend(sender.poll().unwrap());
+            to_sender.extend(receiver.feed(&std::mem::take(&mut to_receiver)).unwrap());
+        }
+        assert!(sender.is_done() && receiver.is_done());
+        assert_eq!(receiver.progress().completed.len(), 3);
+        assert_eq!(std::fs::read(target.join("big.bin")).unwrap(), big);
+        assert_eq!(std::fs::read(target.join("empty.txt")).unwrap(), b"");
+        assert_eq!(
+            std::fs::read(target.join("exact.bin")).unwrap(),
+            vec![0x18; SUBPACKET_SIZE]
+        );
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -0,0 +1,253 @@
+use std::path::Path;
+
+use crate::app_manager::APPS_ROOT;
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::session_manager::command::quote;
+use crate::system_manager::crashes::fetch;
+use crate::system_manager::{CoreDownload, CoreDump};
+
+/// Searched besides the directory of the kernel core pattern
+const CORE_DIRS: [&str; 3] = ["/var/lib/systemd/coredump", "/var/crash", "/tmp"];
+
+/// Core files already compressed by systemd-coredump
+const COMPRESSED: [&str; 4] = [".zst", ".lz4", ".xz", ".gz"];
+
+/// Separators of fields in core file names
+const SEPARATORS: [char; 2] = ['.', '-'];
+/// Kernel limit of PIDs, larger numbers in core file names are something else like a timestamp
+const PID_MAX: u32 = 4_194_304;
+/// Process names are truncated to this many bytes by the kernel
+const COMM_LENGTH: usize = 15;
+
+/// Lists core dumps on the device, newest first. With `binary`, a path, app or service ID, or
+/// process name, only dumps of that process are listed.
+pub(crate) fn core_dumps(
+    session: &DeviceConnection,
+    binary: Option<&str>,
+) -> Result<Vec<CoreDump>, Error> {
+    let command = format!(
+        "p=$(cat /proc/sys/kernel/core_pattern 2>/dev/null); \
+         case \"$p\" in /*) d=\"${{p%/*}}\";; *) d=;; esac; \
+         for d in \"$d\" {}; do [ -d \"$d\" ] && find \"$d\" -maxdepth 1 -type f -name 'core*' \
+         -exec stat -c '%Y %s %n' {{}} \\; ; done 2>/dev/null; true",
+        CORE_DIRS.join(" ")
+    );
+    let output = session.exec(&command, None)?;
+    let process = binary.map(|binary| binary.rsplit('/').next().unwrap_or(binary));
+    let mut dumps: Vec<CoreDump> = String::from_utf8_lossy(&output)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, ' ');
+            let modified = fields.next()?.parse().ok()?;
+            let size = fields.next()?.parse().ok()?;
+            let path = fields.next()?;
+            let name = path.rsplit('/').next().unwrap_or(path);
+            let (comm, pid) = parse_core_name(name);
+            return Some(CoreDump {
+                path: String::from(path),
+                name: String::from(name),
+                size,
+                modified,
+                process: comm,
+                pid,
+            });
+        })
+        .filter(|dump| match (process, &dump.process) {
+            (None, _) => true,
+            (Some(process), Some(comm)) => is_comm_of(comm, process),
+            (Some(_), None) => false,
+        })
+        .collect();
+    dumps.sort_by(|a, b| b.modified.cmp(&a.modified));
+    dumps.dedup_by(|a, b| a.path == b.path);
+    return Ok(dumps);
+}
+
+/// Compresses core dump `path` on the device unless it already is, and downloads it into local
+/// directory `dir`. The binary that crashed is looked up by `binary`, or by the process name, and
+/// recorded in a JSON file next to the dump, for loading both into gdb.
+pub(crate) fn download_core_dump(
+    session: &DeviceConnection,
+    path: &str,
+    binary: Option<&str>,
+    dir: &Path,
+) -> Result<CoreDownload, Error> {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    if !path.starts_with('/') || !name.starts_with("core") || path.split('/').any(|p| p == "..") {
+        return Err(Error::new(format!("{path} is not a core dump")));
+    }
+    let (process, _) = parse_core_name(name);
+    let binary = find_binary(session, binary.or(process.as_deref()))?;
+    std::fs::create_dir_all(dir)?;
+    let compressed = COMPRESSED.iter().any(|ext| name.ends_with(ext));
+    let (command, local_name) = match compressed {
+        true => (format!("cat {}", quote(path)), String::from(name)),
+        false => (format!("gzip -c {}", quote(path)), format!("{name}.gz")),
+    };
+    let target = dir.join(&local_name);
+    fetch(session, &command, &target)?;
+    let download = CoreDownload {
+        path: target.to_string_lossy().to_string(),
+        remote_path: String::from(path),
+        process,
+        binary,
+    };
+    std::fs::write(
+        dir.join(format!("{local_name}.json")),
+        serde_json::to_vec_pretty(&download)?,
+    )?;
+    return Ok(download);
+}
+
+/// Splits a core file name into process name and PID. Supports systemd-coredump names like
+/// `core.name.0.bootid.123.1700000000000000.zst`, where the name may contain dots. Other names
+/// like `core.123`, `core-name-123` or `core.name.123.1700000000` are taken to have the PID as
+/// their last number that can be one, and the process name before it.
+fn parse_core_name(name: &str) -> (Option<String>, Option<u32>) {
+    let name = COMPRESSED
+        .iter()
+        .find_map(|ext| name.strip_suffix(ext))
+        .unwrap_or(name);
+    let rest = name
+        .strip_prefix("core")
+        .unwrap_or(name)
+        .trim_start_matches(SEPARATORS);
+    let parts: Vec<&str> = rest.split('.').collect();
+    if parts.len() >= 5 && is_boot_id(parts[parts.len() - 3]) {
+        let comm = parts[..parts.len() - 4].join(".");
+        return (
+            Some(comm).filter(|comm| !comm.is_empty()),
+            parts[parts.len() - 2].parse().ok(),
+        );
+    }
+    let mut offset = 0;
+    let segments: Vec<(usize, &str)> = rest
+        .split(SEPARATORS)
+        .map(|segment| {
+            let start = offset;
+            offset += segment.len() + 1;
+            return (start, segment);
+        })
+        .collect();
+    let found = segments.iter().rev().find_map(|(start, segment)| {
+        let pid = segment.parse::<u32>().ok().filter(|pid| *pid <= PID_MAX)?;
+        return Some((*start, segment.len(), pid));
+    });
+    let Some((start, len, pid)) = found else {
+        return (
+            Some(String::from(rest)).filter(|comm| !comm.is_empty()),
+            None,
+        );
+    };
+    let before = rest[..start].trim_end_matches(SEPARATORS);
+    let comm = match before.is_empty() {
+        true => rest[start + len..].trim_start_matches(SEPARATORS),
+        false => before,
+    };
+    return (
+        Some(String::from(comm)).filter(|comm| !comm.is_empty()),
+        Some(pid),
+    );
+}
+
+/// Whether `field` is a boot ID as systemd writes it, 32 hex digits.
+fn is_boot_id(field: &str) -> bool {
+    return field.len() == 32 && field.chars().all(|c| c.is_ascii_hexdigit());
+}
+
+/// Whether process name `comm` from a core file name is that of `process`, which the kernel
+/// truncates.
+fn is_comm_of(comm: &str, process: &str) -> bool {
+    return comm == process.get(..COMM_LENGTH).unwrap_or(process);
+}
+
+/// Finds the binary of a process on the device. `binary` is a path, an app or service ID to look
+/// for its executable in, or a process name to look up in `PATH`.
+fn find_binary(session: &DeviceConnection, binary: Option<&str>) -> Result<Option<String>, Error> {
+    let Some(binary) = binary.filter(|b| !b.is_empty()) else {
+        return Ok(None);
+    };
+    if binary.starts_with('/') {
+        return Ok(Some(String::from(binary)));
+    }
+    if binary.contains('/') || binary.contains("..") {
+        return Err(Error::new(format!("Invalid binary {binary}")));
+    }
+    let dirs: Vec<String> = [
+        format!("{APPS_ROOT}/usr/palm/services/{binary}"),
+        format!("{APPS_ROOT}/usr/palm/applications/{binary}"),
+        format!("/usr/palm/services/{binary}"),
+    ]
+    .iter()
+    .map(|dir| quote(dir))
+    .collect();
+    let command = format!(
+        "for d in {}; do [ -d \"$d\" ] && find \"$d\" -type f -perm -u+x 2>/dev/null; done | \
+         head -n 1; command -v {} 2>/dev/null; true",
+        dirs.join(" "),
+        quote(binary)
+    );
+    let output = session.exec(&command, None)?;
+    return Ok(String::from_utf8_lossy(&output)
+        .lines()
+        .map(str::trim)
+        .find(|line| line.starts_with('/'))
+        .map(String::from));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_systemd_core_names() {
+        assert_eq!(
+            parse_core_name(
+                "core.sam.0.0123456789abcdef0123456789abcdef.1234.1700000000000000.zst"
+            ),
+            (Some(String::from("sam")), Some(1234))
+        );
+    }
+
+    #[test]
+    fn parses_systemd_names_with_dots() {
+        assert_eq!(
+            parse_core_name(
+                "core.my.app.0.0123456789abcdef0123456789abcdef.77.1700000000000000.lz4"
+            ),
+            (Some(String::from("my.app")), Some(77))
+        );
+    }
+
+    #[test]
+    fn parses_plain_core_names() {
+        assert_eq!(parse_core_name("core.123"), (None, Some(123)));
+        assert_eq!(
+            parse_core_name("core-browser-456.gz"),
+            (Some(String::from("browser")), Some(456))
+        );
+        assert_eq!(
+            parse_core_name("core-my-app-456"),
+            (Some(String::from("my-app")), Some(456))
+        );
+        // As many fields as systemd names, but no boot ID
+        assert_eq!(
+            parse_core_name("core.browser.0.bootid.456.1700000000"),
+            (Some(String::from("browser.0.bootid")), Some(456))
+        );
+        assert_eq!(
+            parse_core_name("core.browser.456.1700000000"),
+            (Some(String::from("browser")), Some(456))
+        );
+        assert_eq!(parse_core_name("core"), (None, None));
+    }
+
+    #[test]
+    fn matches_truncated_process_names() {
+        assert!(is_comm_of("WebAppMgr", "WebAppMgr"));
+        assert!(is_comm_of("com.webos.app.b", "com.webos.app.browser"));
+        assert!(!is_comm_of("Web", "WebAppMgr"));
+        assert!(!is_comm_of("com.webos.app.b", "com.webos.app"));
+    }
+}
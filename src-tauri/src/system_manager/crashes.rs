@@ -0,0 +1,120 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::session_manager::command::quote;
+use crate::system_manager::CrashReport;
+use crate::transfer_manager::tar_pipe::finish;
+
+/// Where RDX and crash reports are written, on TVs and OSE. Only existing ones are searched.
+const CRASH_DIRS: [&str; 4] = [
+    "/var/spool/rdxd",
+    "/var/log/reports/librdx",
+    "/var/spool/crashreport",
+    "/tmp/faultmanager/crash",
+];
+
+/// Words in report file names that don't name the crashed process
+const NAME_WORDS: [&str; 6] = ["rdx", "rdxreport", "report", "crash", "crashreport", "core"];
+
+/// Lists crash reports on the device, newest first.
+pub(crate) fn crash_reports(session: &DeviceConnection) -> Result<Vec<CrashReport>, Error> {
+    let dirs: Vec<String> = CRASH_DIRS.iter().map(|dir| quote(dir)).collect();
+    let command = format!(
+        "for d in {}; do [ -d $d ] && find $d -type f -exec stat -c '%Y %s %n' {{}} \\; ; \
+         done 2>/dev/null; true",
+        dirs.join(" ")
+    );
+    let output = session.exec(&command, None)?;
+    let mut reports: Vec<CrashReport> = String::from_utf8_lossy(&output)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, ' ');
+            let modified = fields.next()?.parse().ok()?;
+            let size = fields.next()?.parse().ok()?;
+            let path = fields.next()?;
+            let name = path.rsplit('/').next().unwrap_or(path);
+            return Some(CrashReport {
+                path: String::from(path),
+                name: String::from(name),
+                size,
+                modified,
+                process: crashed_process(name),
+            });
+        })
+        .collect();
+    reports.sort_by(|a, b| b.modified.cmp(&a.modified));
+    return Ok(reports);
+}
+
+/// Downloads crash reports at `paths` into local directory `dir`, and returns the local paths,
+/// which are named after the remote ones. Only files in the crash report directories can be
+/// downloaded.
+pub(crate) fn download_crash_reports(
+    session: &DeviceConnection,
+    paths: &[String],
+    dir: &Path,
+) -> Result<Vec<String>, Error> {
+    for path in paths {
+        let allowed = CRASH_DIRS
+            .iter()
+            .any(|dir| path.starts_with(&format!("{dir}/")));
+        if !allowed || path.split('/').any(|part| part == "..") {
+            return Err(Error::new(format!("{path} is not a crash report")));
+        }
+    }
+    std::fs::create_dir_all(dir)?;
+    let mut downloaded = Vec::new();
+    for path in paths {
+        // Named after the whole remote path, as reports in different directories share names
+        let name = path.trim_start_matches('/').replace('/', "_");
+        let target = dir.join(name);
+        fetch(session, &format!("cat {}", quote(path)), &target)?;
+        downloaded.push(target.to_string_lossy().to_string());
+    }
+    return Ok(downloaded);
+}
+
+/// Runs `command` on the device, and saves its output to local file `target`.
+pub(super) fn fetch(session: &DeviceConnection, command: &str, target: &Path) -> Result<(), Error> {
+    let _permit = session.acquire_channel();
+    let ch = session.new_channel()?;
+    ch.open_session()?;
+    ch.request_exec(command)?;
+    let mut file = File::create(target)?;
+    let result = match std::io::copy(&mut ch.stdout(), &mut file) {
+        Ok(_) => finish(ch, command),
+        Err(e) => {
+            ch.close().unwrap_or(());
+            Err(e.into())
+        }
+    };
+    if result.is_err() {
+        std::fs::remove_file(target).unwrap_or(());
+    }
+    return result;
+}
+
+/// Guesses the crashed process from a report file name like
+/// `RdxReport_com.webos.app.browser_SIGSEGV_1700000000.tar.gz`, taking the first word that isn't
+/// generic, a number or a signal.
+fn crashed_process(name: &str) -> Option<String> {
+    let stem = PathBuf::from(name.split(".tar").next().unwrap_or(name));
+    let stem = match stem.extension().and_then(|e| e.to_str()) {
+        Some("gz" | "tgz" | "zip" | "txt" | "log" | "json" | "zst" | "lz4") => {
+            stem.with_extension("")
+        }
+        _ => stem,
+    };
+    return stem
+        .to_string_lossy()
+        .split(['_', '-'])
+        .find(|word| {
+            return !word.is_empty()
+                && !NAME_WORDS.contains(&word.to_lowercase().as_str())
+                && !word.starts_with("SIG")
+                && !word.chars().all(|c| c.is_ascii_digit());
+        })
+        .map(String::from);
+}
@@ -0,0 +1,121 @@
+use crate::conn_pool::DeviceConnection;
+use crate::device_manager::DeviceInfo;
+use crate::error::Error;
+use crate::system_manager::{MemoryInfo, NetworkAddress, StorageInfo, SystemInfo};
+
+const SECTION: &str = "--- webos-dev-section ---";
+
+/// Collects the system overview of the device. Tools that aren't available on the device leave
+/// their part empty.
+pub(crate) fn system_info(session: &DeviceConnection) -> Result<SystemInfo, Error> {
+    let command = [
+        "cat /proc/uptime",
+        "cat /proc/loadavg",
+        "cat /proc/meminfo",
+        "df -P -k 2>/dev/null",
+        "ip -o addr show 2>/dev/null",
+    ]
+    .join(&format!("; echo '{SECTION}'; "));
+    let output = session.exec(&command, None)?;
+    let output = String::from_utf8_lossy(&output);
+    let sections: Vec<&str> = output.split(&format!("{SECTION}\n")).collect();
+    let section = |index: usize| sections.get(index).copied().unwrap_or("");
+    let uptime_secs = section(0)
+        .split_whitespace()
+        .next()
+        .and_then(|s| s.parse::<f64>().ok())
+        .ok_or_else(|| Error::new("Bad uptime of the device"))? as u64;
+    return Ok(SystemInfo {
+        info: DeviceInfo::fetch(session).ok(),
+        uptime_secs,
+        load: parse_loadavg(section(1)),
+        memory: parse_meminfo(section(2)),
+        storage: parse_df(section(3)),
+        addresses: parse_addresses(section(4)),
+    });
+}
+
+/// Parses the load averages of `/proc/loadavg`.
+pub(crate) fn parse_loadavg(loadavg: &str) -> [f64; 3] {
+    let mut load = [0.0; 3];
+    for (value, field) in load.iter_mut().zip(loadavg.split_whitespace()) {
+        *value = field.parse().unwrap_or(0.0);
+    }
+    return load;
+}
+
+/// Parses `/proc/meminfo`. Old kernels don't report `MemAvailable`, free memory and caches are
+/// counted instead.
+pub(crate) fn parse_meminfo(meminfo: &str) -> MemoryInfo {
+    let field = |name: &str| -> Option<u64> {
+        return meminfo.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            if key != name {
+                return None;
+            }
+            let kbytes: u64 = value.split_whitespace().next()?.parse().ok()?;
+            return Some(kbytes * 1024);
+        });
+    };
+    let available = field("MemAvailable").unwrap_or_else(|| {
+        return ["MemFree", "Buffers", "Cached"]
+            .into_iter()
+            .filter_map(field)
+            .sum();
+    });
+    return MemoryInfo {
+        total: field("MemTotal").unwrap_or(0),
+        available,
+        swap_total: field("SwapTotal").unwrap_or(0),
+        swap_free: field("SwapFree").unwrap_or(0),
+    };
+}
+
+/// Parses POSIX output of `df -k`, skipping pseudo filesystems without size.
+fn parse_df(df: &str) -> Vec<StorageInfo> {
+    return df
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 6 {
+                return None;
+            }
+            let kbytes = |index: usize| fields[index].parse::<u64>().ok().map(|k| k * 1024);
+            let size = kbytes(1)?;
+            if size == 0 {
+                return None;
+            }
+            return Some(StorageInfo {
+                filesystem: String::from(fields[0]),
+                mount: fields[5..].join(" "),
+                size,
+                used: kbytes(2)?,
+                available: kbytes(3)?,
+            });
+        })
+        .collect();
+}
+
+/// Parses `ip -o addr`, one address per line like
+/// `2: eth0    inet 192.168.0.2/24 brd 192.168.0.255 scope global eth0`.
+pub(crate) fn parse_addresses(addresses: &str) -> Vec<NetworkAddress> {
+    return addresses
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace().skip(1);
+            let interface = fields.next()?.trim_end_matches(':');
+            let family = fields.next()?;
+            if family != "inet" && family != "inet6" {
+                return None;
+            }
+            let (address, prefix) = fields.next()?.split_once('/')?;
+            return Some(NetworkAddress {
+                interface: String::from(interface),
+                family: String::from(family),
+                address: String::from(address),
+                prefix: prefix.parse().ok()?,
+            });
+        })
+        .collect();
+}
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+use crate::system_manager::{MemoryInfo, ProcessUsage, ResourceSample};
+
+/// Samples go to one file per day, so queries only read the days they cover
+const SEGMENT_MILLIS: u64 = 24 * 60 * 60 * 1000;
+/// Days of history kept, older segments are removed when a new one starts
+const RETENTION_DAYS: u64 = 30;
+
+/// Appends `sample` to the metrics history of the device with ID `id` in `dir`, one JSON object
+/// per line.
+pub(crate) fn record(dir: &Path, id: &str, sample: &ResourceSample) -> Result<(), Error> {
+    let dir = history_dir(dir, id);
+    std::fs::create_dir_all(&dir)?;
+    let segment = sample.time / SEGMENT_MILLIS;
+    let path = dir.join(format!("{segment}.jsonl"));
+    if !path.exists() {
+        prune(&dir, segment.saturating_sub(RETENTION_DAYS))?;
+    }
+    let mut line = serde_json::to_vec(sample)?;
+    line.push(b'\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(&line)?;
+    return Ok(());
+}
+
+/// Reads recorded samples of the device with ID `id` taken between `from` and `to`, in
+/// milliseconds since epoch. With `points`, samples are averaged into at most that many buckets of
+/// equal duration.
+pub(crate) fn query(
+    dir: &Path,
+    id: &str,
+    from: Option<u64>,
+    to: Option<u64>,
+    points: Option<usize>,
+) -> Result<Vec<ResourceSample>, Error> {
+    let from = from.unwrap_or(0);
+    let to = to.unwrap_or(u64::MAX);
+    let mut samples: Vec<ResourceSample> = Vec::new();
+    for (_, path) in segments(&history_dir(dir, id))?
+        .into_iter()
+        .filter(|(segment, _)| *segment >= from / SEGMENT_MILLIS && *segment <= to / SEGMENT_MILLIS)
+    {
+        // Lines cut short by a crash are skipped
+        samples.extend(
+            BufReader::new(std::fs::File::open(path)?)
+                .lines()
+                .map_while(Result::ok)
+                .filter_map(|line| serde_json::from_str::<ResourceSample>(&line).ok())
+                .filter(|sample| sample.time >= from && sample.time <= to),
+        );
+    }
+    return Ok(match points {
+        Some(points) if points > 0 && samples.len() > points => downsample(samples, points),
+        _ => samples,
+    });
+}
+
+/// Removes the metrics history of the device with ID `id`.
+pub(crate) fn clear(dir: &Path, id: &str) -> Result<(), Error> {
+    return match std::fs::remove_dir_all(history_dir(dir, id)) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    };
+}
+
+/// IDs are hex encoded, as the devices file may contain anything.
+fn history_dir(dir: &Path, id: &str) -> PathBuf {
+    return dir.join(hex::encode(id));
+}
+
+/// Segment files in `dir` by their number, oldest first.
+fn segments(dir: &Path) -> Result<Vec<(u64, PathBuf)>, Error> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut segments: Vec<(u64, PathBuf)> = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let segment = path
+            .file_name()
+            .and_then(|name| name.to_str()?.strip_suffix(".jsonl")?.parse().ok());
+        if let Some(segment) = segment {
+            segments.push((segment, path));
+        }
+    }
+    segments.sort_by_key(|(segment, _)| *segment);
+    return Ok(segments);
+}
+
+/// Removes segments older than `oldest`.
+fn prune(dir: &Path, oldest: u64) -> Result<(), Error> {
+    for (_, path) in segments(dir)?
+        .into_iter()
+        .filter(|(segment, _)| *segment < oldest)
+    {
+        std::fs::remove_file(path)?;
+    }
+    return Ok(());
+}
+
+fn downsample(samples: Vec<ResourceSample>, points: usize) -> Vec<ResourceSample> {
+    let first = samples.first().map_or(0, |s| s.time);
+    let last = samples.last().map_or(0, |s| s.time);
+    let width = (last - first) / points as u64 + 1;
+    let mut buckets: Vec<Vec<ResourceSample>> = Vec::new();
+    for sample in samples {
+        let bucket = ((sample.time - first) / width) as usize;
+        if buckets.len() <= bucket {
+            buckets.resize_with(bucket + 1, Vec::new);
+        }
+        buckets[bucket].push(sample);
+    }
+    return buckets
+        .into_iter()
+        .filter(|bucket| !bucket.is_empty())
+        .map(|bucket| average(&bucket))
+        .collect();
+}
+
+/// Averages the samples. Processes are averaged over the samples they appear in.
+fn average(samples: &[ResourceSample]) -> ResourceSample {
+    let count = samples.len() as u64;
+    let mean = |value: &dyn Fn(&ResourceSample) -> f64| {
+        samples.iter().map(value).sum::<f64>() / count as f64
+    };
+    let sum = |value: &dyn Fn(&ResourceSample) -> u64| samples.iter().map(value).sum::<u64>();
+    let mut processes: HashMap<u32, (ProcessUsage, u64)> = HashMap::new();
+    for process in samples.iter().flat_map(|s| &s.processes) {
+        let (total, seen) = processes.entry(process.pid).or_insert_with(|| {
+            (
+                ProcessUsage {
+                    cpu: 0.0,
+                    rss: 0,
+                    ..process.clone()
+                },
+                0,
+            )
+        });
+        total.cpu += process.cpu;
+        total.rss += process.rss;
+        *seen += 1;
+    }
+    let mut processes: Vec<ProcessUsage> = processes
+        .into_values()
+        .map(|(total, seen)| ProcessUsage {
+            cpu: total.cpu / seen as f64,
+            rss: total.rss / seen,
+            ..total
+        })
+        .collect();
+    processes.sort_by(|a, b| b.cpu.total_cmp(&a.cpu).then(b.rss.cmp(&a.rss)));
+    return ResourceSample {
+        time: sum(&|s| s.time) / count,
+        cpu: mean(&|s| s.cpu),
+        load: [
+            mean(&|s| s.load[0]),
+            mean(&|s| s.load[1]),
+            mean(&|s| s.load[2]),
+        ],
+        memory: MemoryInfo {
+            total: sum(&|s| s.memory.total) / count,
+            available: sum(&|s| s.memory.available) / count,
+            swap_total: sum(&|s| s.memory.swap_total) / count,
+            swap_free: sum(&|s| s.memory.swap_free) / count,
+        },
+        processes,
+    };
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::device_manager::DeviceInfo;
+use crate::session_manager::Proc;
+
+pub(crate) mod cores;
+pub(crate) mod crashes;
+pub(crate) mod info;
+pub(crate) mod metrics;
+mod monitor;
+pub(crate) mod processes;
+pub(crate) mod services;
+
+/// Running resource monitors, by token
+#[derive(Default)]
+pub struct SystemManager {
+    monitors: Mutex<HashMap<String, Arc<Proc>>>,
+}
+
+/// Overview of the device state, collected in one go
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemInfo {
+    /// Model and webOS version, if the device has the services to tell
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<DeviceInfo>,
+    pub uptime_secs: u64,
+    /// Over the last 1, 5 and 15 minutes
+    pub load: [f64; 3],
+    pub memory: MemoryInfo,
+    pub storage: Vec<StorageInfo>,
+    pub addresses: Vec<NetworkAddress>,
+}
+
+/// In bytes
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryInfo {
+    pub total: u64,
+    pub available: u64,
+    pub swap_total: u64,
+    pub swap_free: u64,
+}
+
+/// Mounted filesystem, sizes in bytes
+#[derive(Serialize, Clone, Debug)]
+pub struct StorageInfo {
+    pub filesystem: String,
+    pub mount: String,
+    pub size: u64,
+    pub used: u64,
+    pub available: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct NetworkAddress {
+    pub interface: String,
+    /// `inet` or `inet6`
+    pub family: String,
+    pub address: String,
+    pub prefix: u8,
+}
+
+/// Resource usage of the device since the previous sample
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ResourceSample {
+    /// Milliseconds since epoch
+    pub time: u64,
+    /// Percentage of time all CPUs were busy
+    pub cpu: f64,
+    pub load: [f64; 3],
+    pub memory: MemoryInfo,
+    /// Top consumers of CPU time
+    pub processes: Vec<ProcessUsage>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProcessUsage {
+    pub pid: u32,
+    pub name: String,
+    /// Percentage of the time of all CPUs
+    pub cpu: f64,
+    /// Resident memory in bytes
+    pub rss: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub ppid: u32,
+    pub user: String,
+    pub name: String,
+    /// Empty for kernel threads
+    pub cmdline: String,
+    /// Single letter, like `R` for running
+    pub state: String,
+    /// Percentage of one CPU, averaged over the process lifetime
+    pub cpu: f64,
+    /// Percentage of total memory
+    pub mem: f64,
+    /// Resident memory in bytes
+    pub rss: u64,
+}
+
+/// RDX or crash report file on the device
+#[derive(Serialize, Clone, Debug)]
+pub struct CrashReport {
+    pub path: String,
+    pub name: String,
+    pub size: u64,
+    /// Seconds since epoch
+    pub modified: u64,
+    /// Guessed from the file name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub process: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct CoreDump {
+    pub path: String,
+    pub name: String,
+    pub size: u64,
+    /// Seconds since epoch
+    pub modified: u64,
+    /// Process name, truncated to 15 characters by the kernel
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub process: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+}
+
+/// Downloaded core dump, also saved as JSON next to it
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CoreDownload {
+    /// Local path of the compressed dump
+    pub path: String,
+    pub remote_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub process: Option<String>,
+    /// Path of the crashed binary on the device, if found
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub binary: Option<String>,
+}
+
+/// Service of systemd, or job of upstart
+#[derive(Serialize, Clone, Debug)]
+pub struct SystemService {
+    pub name: String,
+    pub active: bool,
+    /// Like `running` or `exited` for systemd, and `waiting` or `running` for upstart
+    pub state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Only known for upstart jobs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// Stopping or restarting it needs confirmation
+    pub risky: bool,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceAction {
+    Start,
+    Stop,
+    Restart,
+}
+
+impl ServiceAction {
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            ServiceAction::Start => "start",
+            ServiceAction::Stop => "stop",
+            ServiceAction::Restart => "restart",
+        };
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use uuid::Uuid;
+
+use crate::device_manager::Device;
+use crate::error::Error;
+use crate::session_manager::{Proc, ProcCallback, SessionManager};
+use crate::system_manager::info::{parse_loadavg, parse_meminfo};
+use crate::system_manager::processes::{parse_stat, PAGE_SIZE};
+use crate::system_manager::{ProcessUsage, ResourceSample, SystemManager};
+
+const SAMPLE_START: &str = "--- webos-dev-sample";
+const SAMPLE_PROCESSES: &str = "--- webos-dev-processes";
+const SAMPLE_END: &str = "--- webos-dev-end";
+
+impl SystemManager {
+    /// Prepares a process on the device that prints `/proc` stats every `interval`, and returns
+    /// its token. Sampling starts with [SystemManager::run_monitor].
+    pub fn monitor(
+        &self,
+        sessions: &SessionManager,
+        device: Device,
+        interval: Duration,
+    ) -> (String, Arc<Proc>) {
+        let command = format!(
+            "while true; do echo '{SAMPLE_START}'; head -n 1 /proc/stat; cat /proc/loadavg; \
+             cat /proc/meminfo; echo '{SAMPLE_PROCESSES}'; cat /proc/[0-9]*/stat 2>/dev/null; \
+             echo '{SAMPLE_END}'; sleep {}; done",
+            interval.as_secs().max(1)
+        );
+        let token = Uuid::new_v4().to_string();
+        let proc = Arc::new(sessions.spawn(device, &command));
+        self.monitors
+            .lock()
+            .unwrap()
+            .insert(token.clone(), proc.clone());
+        return (token, proc);
+    }
+
+    /// Runs the monitor until it's stopped or the connection is lost, passing each sample to
+    /// `on_sample` with the `top` processes using most CPU.
+    pub fn run_monitor<F>(
+        &self,
+        sessions: &SessionManager,
+        token: &str,
+        proc: Arc<Proc>,
+        top: usize,
+        on_sample: F,
+    ) -> Result<(), Error>
+    where
+        F: Fn(ResourceSample) + Send + 'static,
+    {
+        *proc.callback.lock().unwrap() = Some(Box::new(MonitorCallback {
+            parser: Mutex::new(MonitorParser::new(top)),
+            on_sample,
+        }));
+        proc.notify_ready();
+        let result = proc.start().and_then(|_| proc.wait_close(sessions));
+        proc.callback.lock().unwrap().take();
+        self.monitors.lock().unwrap().remove(token);
+        return result.map(|_| ());
+    }
+
+    pub fn stop_monitor(&self, token: &str) -> Result<(), Error> {
+        let proc = self
+            .monitors
+            .lock()
+            .unwrap()
+            .remove(token)
+            .ok_or(Error::NotFound)?;
+        proc.interrupt();
+        return Ok(());
+    }
+}
+
+struct MonitorCallback<F> {
+    parser: Mutex<MonitorParser>,
+    on_sample: F,
+}
+
+impl<F: Fn(ResourceSample)> ProcCallback for MonitorCallback<F> {
+    fn rx(&self, fd: u32, data: &[u8]) {
+        if fd != 0 {
+            return;
+        }
+        let samples = self.parser.lock().unwrap().feed(data);
+        for sample in samples {
+            (self.on_sample)(sample);
+        }
+    }
+}
+
+/// Turns the monitor output into samples. Usage is the difference from the previous sample, so
+/// the first one only sets the baseline.
+struct MonitorParser {
+    buffer: Vec<u8>,
+    top: usize,
+    /// Total and idle CPU ticks
+    cpu: Option<(u64, u64)>,
+    /// CPU ticks by PID
+    processes: HashMap<u32, u64>,
+}
+
+impl MonitorParser {
+    fn new(top: usize) -> Self {
+        return Self {
+            buffer: Vec::new(),
+            top,
+            cpu: None,
+            processes: HashMap::new(),
+        };
+    }
+
+    fn feed(&mut self, data: &[u8]) -> Vec<ResourceSample> {
+        self.buffer.extend_from_slice(data);
+        let mut samples = Vec::new();
+        let end = format!("{SAMPLE_END}\n");
+        while let Some(index) = find(&self.buffer, end.as_bytes()) {
+            let chunk: Vec<u8> = self.buffer.drain(..index + end.len()).collect();
+            let chunk = String::from_utf8_lossy(&chunk);
+            let Some((_, text)) = chunk.split_once(&format!("{SAMPLE_START}\n")) else {
+                continue;
+            };
+            if let Some(sample) = self.parse(text) {
+                samples.push(sample);
+            }
+        }
+        return samples;
+    }
+
+    fn parse(&mut self, text: &str) -> Option<ResourceSample> {
+        let (system, processes) = text.split_once(&format!("{SAMPLE_PROCESSES}\n"))?;
+        let mut lines = system.splitn(3, '\n');
+        let (total, idle) = parse_cpu(lines.next()?)?;
+        let load = parse_loadavg(lines.next()?);
+        let memory = parse_meminfo(lines.next().unwrap_or(""));
+        let processes: HashMap<u32, (String, u64, u64)> = processes
+            .lines()
+            .filter_map(parse_stat)
+            .map(|stat| (stat.pid, (stat.name, stat.ticks, stat.rss)))
+            .collect();
+        let previous_cpu = self.cpu.replace((total, idle));
+        let previous = std::mem::replace(
+            &mut self.processes,
+            processes
+                .iter()
+                .map(|(pid, (_, ticks, _))| (*pid, *ticks))
+                .collect(),
+        );
+        let (previous_total, previous_idle) = previous_cpu?;
+        let elapsed = total.saturating_sub(previous_total);
+        if elapsed == 0 {
+            return None;
+        }
+        let percent = |ticks: u64| ticks as f64 * 100.0 / elapsed as f64;
+        let busy = elapsed.saturating_sub(idle.saturating_sub(previous_idle));
+        let mut usage: Vec<ProcessUsage> = processes
+            .into_iter()
+            .map(|(pid, (name, ticks, rss))| ProcessUsage {
+                pid,
+                name,
+                cpu: percent(ticks.saturating_sub(previous.get(&pid).copied().unwrap_or(ticks))),
+                rss: rss * PAGE_SIZE,
+            })
+            .collect();
+        usage.sort_by(|a, b| b.cpu.total_cmp(&a.cpu).then(b.rss.cmp(&a.rss)));
+        usage.truncate(self.top);
+        return Some(ResourceSample {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64),
+            cpu: percent(busy),
+            load,
+            memory,
+            processes: usage,
+        });
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    return haystack
+        .windows(needle.len())
+        .position(|window| window == needle);
+}
+
+/// Parses the aggregate line of `/proc/stat` into total and idle ticks.
+fn parse_cpu(line: &str) -> Option<(u64, u64)> {
+    let ticks: Vec<u64> = line
+        .strip_prefix("cpu ")?
+        .split_whitespace()
+        .take(8)
+        .filter_map(|field| field.parse().ok())
+        .collect();
+    if ticks.len() < 4 {
+        return None;
+    }
+    let idle = ticks[3] + ticks.get(4).copied().unwrap_or(0);
+    return Some((ticks.iter().sum(), idle));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(cpu: &str, ticks: u64) -> String {
+        return format!(
+            "{SAMPLE_START}\n{cpu}\n0.50 0.40 0.30 1/100 123\nMemTotal: 1000 kB\n\
+             MemAvailable: 500 kB\n{SAMPLE_PROCESSES}\n\
+             1 (init) S 0 1 1 0 -1 0 0 0 0 0 {ticks} {ticks} 0 0 20 0 1 0 5 1000 3\n{SAMPLE_END}\n"
+        );
+    }
+
+    #[test]
+    fn samples_usage_since_previous() {
+        let mut parser = MonitorParser::new(10);
+        let output = format!(
+            "{}{}",
+            sample("cpu  100 0 100 800 0 0 0 0", 10),
+            sample("cpu  150 0 150 900 0 0 0 0", 30)
+        );
+        let (head, tail) = output.split_at(output.len() - 20);
+        // The first sample only sets the baseline
+        assert!(parser.feed(head.as_bytes()).is_empty());
+        let samples = parser.feed(tail.as_bytes());
+        assert_eq!(samples.len(), 1);
+        let sample = &samples[0];
+        assert_eq!(sample.cpu, 50.0);
+        assert_eq!(sample.load, [0.5, 0.4, 0.3]);
+        assert_eq!(sample.memory.total, 1000 * 1024);
+        assert_eq!(sample.memory.available, 500 * 1024);
+        assert_eq!(sample.processes.len(), 1);
+        assert_eq!(sample.processes[0].name, "init");
+        assert_eq!(sample.processes[0].cpu, 20.0);
+        assert_eq!(sample.processes[0].rss, 3 * PAGE_SIZE);
+    }
+
+    #[test]
+    fn skips_output_outside_samples() {
+        let mut parser = MonitorParser::new(10);
+        let output = format!("noise\n{SAMPLE_END}\n{}", sample("cpu  1 0 1 8 0 0 0 0", 1));
+        assert!(parser.feed(output.as_bytes()).is_empty());
+        assert_eq!(parser.cpu, Some((10, 8)));
+        assert!(parser.buffer.is_empty());
+    }
+}
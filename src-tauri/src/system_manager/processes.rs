@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::system_manager::ProcessInfo;
+
+const SECTION: &str = "--- webos-dev-section ---";
+
+/// Size of memory pages in `/proc/<pid>/stat`, the same on all webOS devices
+pub(super) const PAGE_SIZE: u64 = 4096;
+
+/// Signals that can be sent with [kill], by name without the `SIG` prefix
+const SIGNALS: [&str; 9] = [
+    "TERM", "KILL", "INT", "HUP", "QUIT", "STOP", "CONT", "USR1", "USR2",
+];
+
+/// Fields of `/proc/<pid>/stat`
+pub(super) struct ProcStat {
+    pub pid: u32,
+    pub ppid: u32,
+    pub name: String,
+    pub state: String,
+    /// User and system CPU time, in clock ticks
+    pub ticks: u64,
+    /// Since boot, in clock ticks
+    pub start: u64,
+    /// Resident memory, in pages
+    pub rss: u64,
+}
+
+/// Lists processes of the device. CPU usage is averaged over the process lifetime, like `ps`
+/// does. Without root, only processes visible in the Developer Mode jail are listed.
+pub(crate) fn processes(session: &DeviceConnection) -> Result<Vec<ProcessInfo>, Error> {
+    let command = [
+        "cat /proc/uptime",
+        "getconf CLK_TCK 2>/dev/null || echo 100",
+        "grep '^MemTotal:' /proc/meminfo",
+        "cat /etc/passwd",
+        "for d in /proc/[0-9]*; do s=$(cat $d/stat 2>/dev/null) || continue; \
+         printf '%s\\t%s\\t%s\\n' \"$(stat -c %u $d)\" \"$s\" \"$(tr '\\0' ' ' < $d/cmdline)\"; \
+         done 2>/dev/null",
+    ]
+    .join(&format!("; echo '{SECTION}'; "));
+    let output = session.exec(&command, None)?;
+    let output = String::from_utf8_lossy(&output);
+    let sections: Vec<&str> = output.split(&format!("{SECTION}\n")).collect();
+    let section = |index: usize| sections.get(index).copied().unwrap_or("");
+    let uptime: f64 = section(0)
+        .split_whitespace()
+        .next()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| Error::new("Bad uptime of the device"))?;
+    let clock_ticks: f64 = section(1).trim().parse().unwrap_or(100.0);
+    let mem_total: u64 = section(2)
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse::<u64>().ok())
+        .map_or(0, |kbytes| kbytes * 1024);
+    let users: HashMap<&str, &str> = section(3)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            return Some((fields.nth(1)?, name));
+        })
+        .collect();
+    let mut processes: Vec<ProcessInfo> = section(4)
+        .lines()
+        .filter_map(|line| {
+            let (uid, rest) = line.split_once('\t')?;
+            let (stat, cmdline) = rest.rsplit_once('\t')?;
+            let stat = parse_stat(stat)?;
+            let elapsed = uptime - stat.start as f64 / clock_ticks;
+            let cpu = match elapsed > 0.0 {
+                true => stat.ticks as f64 / clock_ticks * 100.0 / elapsed,
+                false => 0.0,
+            };
+            let rss = stat.rss * PAGE_SIZE;
+            return Some(ProcessInfo {
+                pid: stat.pid,
+                ppid: stat.ppid,
+                user: String::from(users.get(uid).copied().unwrap_or(uid)),
+                name: stat.name,
+                cmdline: String::from(cmdline.trim_end()),
+                state: stat.state,
+                cpu,
+                mem: match mem_total {
+                    0 => 0.0,
+                    total => rss as f64 * 100.0 / total as f64,
+                },
+                rss,
+            });
+        })
+        .collect();
+    processes.sort_by_key(|process| process.pid);
+    return Ok(processes);
+}
+
+/// Sends `signal`, by name or number, to process `pid`.
+pub(crate) fn kill(session: &DeviceConnection, pid: u32, signal: &str) -> Result<(), Error> {
+    let signal = signal.trim_start_matches("SIG");
+    if !SIGNALS.contains(&signal) && signal.parse::<u8>().is_err() {
+        return Err(Error::new(format!("Unsupported signal {signal}")));
+    }
+    session.exec(&format!("kill -{signal} {pid}"), None)?;
+    return Ok(());
+}
+
+/// Parses `/proc/<pid>/stat`. The name is in parentheses, and may contain anything including
+/// them.
+pub(super) fn parse_stat(line: &str) -> Option<ProcStat> {
+    let open = line.find('(')?;
+    let close = line.rfind(')')?;
+    let fields: Vec<&str> = line[close + 1..].split_whitespace().collect();
+    // Fields after the name start with the state, which is field 3
+    let field = |number: usize| fields.get(number - 3)?.parse::<u64>().ok();
+    return Some(ProcStat {
+        pid: line[..open].trim().parse().ok()?,
+        ppid: field(4)? as u32,
+        name: String::from(line.get(open + 1..close)?),
+        state: String::from(*fields.first()?),
+        ticks: field(14)? + field(15)?,
+        start: field(22)?,
+        rss: field(24)?,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_stat_with_odd_names() {
+        let stat = parse_stat("42 (a (b) c) R 1 42 42 0 -1 0 0 0 0 0 7 3 0 0 20 0 1 0 900 1000 25")
+            .unwrap();
+        assert_eq!(stat.pid, 42);
+        assert_eq!(stat.ppid, 1);
+        assert_eq!(stat.name, "a (b) c");
+        assert_eq!(stat.state, "R");
+        assert_eq!(stat.ticks, 10);
+        assert_eq!(stat.start, 900);
+        assert_eq!(stat.rss, 25);
+    }
+
+    #[test]
+    fn rejects_truncated_stat() {
+        assert!(parse_stat("42 (sh) S 1 42").is_none());
+        assert!(parse_stat("garbage").is_none());
+    }
+}
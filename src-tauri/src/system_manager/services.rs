@@ -0,0 +1,146 @@
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::session_manager::command::quote;
+use crate::system_manager::{ServiceAction, SystemService};
+
+/// Services that take down the UI, the bus every service talks on, or the connection to the
+/// device, when stopped or restarted
+const RISKY_SERVICES: [&str; 11] = [
+    "ls-hubd",
+    "luna-hub",
+    "surface-manager",
+    "sam",
+    "dbus",
+    "systemd-journald",
+    "systemd-udevd",
+    "systemd-networkd",
+    "connman",
+    "sshd",
+    "dropbear",
+];
+
+/// Lists services of systemd, or jobs of upstart on older devices, sorted by name.
+pub(crate) fn services(session: &DeviceConnection) -> Result<Vec<SystemService>, Error> {
+    let command = "if command -v systemctl >/dev/null 2>&1; then echo systemd; \
+         systemctl list-units --type=service --all --no-legend --no-pager --plain; \
+         elif command -v initctl >/dev/null 2>&1; then echo upstart; initctl list; \
+         else echo none; fi";
+    let output = session.exec(command, None)?;
+    let output = String::from_utf8_lossy(&output);
+    let mut lines = output.lines();
+    let mut services: Vec<SystemService> = match lines.next().map(str::trim) {
+        Some("systemd") => lines.filter_map(parse_systemd_unit).collect(),
+        Some("upstart") => lines.filter_map(parse_upstart_job).collect(),
+        _ => return Err(Error::Unsupported),
+    };
+    services.sort_by(|a, b| a.name.cmp(&b.name));
+    return Ok(services);
+}
+
+/// Starts, stops or restarts service `name`. Stopping or restarting services in [RISKY_SERVICES]
+/// needs `confirmed`, otherwise [Error::ConfirmationRequired] is returned.
+pub(crate) fn control(
+    session: &DeviceConnection,
+    name: &str,
+    action: ServiceAction,
+    confirmed: bool,
+) -> Result<(), Error> {
+    let valid = !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '@' | '.' | '_' | ':' | '-'));
+    if !valid {
+        return Err(Error::new(format!("Invalid service {name}")));
+    }
+    let risky = is_risky(name) && !matches!(action, ServiceAction::Start);
+    let action = action.as_str();
+    if risky && !confirmed {
+        return Err(Error::ConfirmationRequired {
+            message: format!("Running {action} on {name} may disrupt the device or the connection"),
+        });
+    }
+    let name = quote(name);
+    session.exec(
+        &format!(
+            "if command -v systemctl >/dev/null 2>&1; then systemctl {action} {name}; \
+             else initctl {action} {name}; fi"
+        ),
+        None,
+    )?;
+    return Ok(());
+}
+
+fn is_risky(name: &str) -> bool {
+    let name = name.trim_end_matches(".service");
+    let name = name.split('@').next().unwrap_or(name);
+    return RISKY_SERVICES.contains(&name);
+}
+
+/// Parses a line of `systemctl list-units --plain`, like
+/// `appinstalld.service loaded active running App Install Service`.
+fn parse_systemd_unit(line: &str) -> Option<SystemService> {
+    let mut fields = line.split_whitespace();
+    let name = fields.next()?;
+    let _load = fields.next()?;
+    let active = fields.next()?;
+    let sub = fields.next()?;
+    let description = fields.collect::<Vec<&str>>().join(" ");
+    return Some(SystemService {
+        name: String::from(name),
+        active: active == "active",
+        state: String::from(sub),
+        description: Some(description).filter(|d| !d.is_empty()),
+        pid: None,
+        risky: is_risky(name),
+    });
+}
+
+/// Parses a line of `initctl list`, like `appinstalld start/running, process 1234`, or
+/// `job (instance) stop/waiting` for instances.
+fn parse_upstart_job(line: &str) -> Option<SystemService> {
+    let (name, rest) = line.split_once(' ')?;
+    let rest = match rest.strip_prefix('(') {
+        Some(instance) => instance.split_once(") ")?.1,
+        None => rest,
+    };
+    let mut fields = rest.split_whitespace();
+    let (goal, state) = fields.next()?.trim_end_matches(',').split_once('/')?;
+    let pid = fields
+        .skip_while(|field| *field != "process")
+        .nth(1)
+        .and_then(|pid| pid.parse().ok());
+    return Some(SystemService {
+        name: String::from(name),
+        active: goal == "start",
+        state: String::from(state),
+        description: None,
+        pid,
+        risky: is_risky(name),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_upstart_jobs() {
+        let job = parse_upstart_job("appinstalld start/running, process 1234").unwrap();
+        assert_eq!(job.name, "appinstalld");
+        assert!(job.active);
+        assert_eq!(job.state, "running");
+        assert_eq!(job.pid, Some(1234));
+        let job = parse_upstart_job("network-interface (eth0) stop/waiting").unwrap();
+        assert_eq!(job.name, "network-interface");
+        assert!(!job.active);
+        assert_eq!(job.state, "waiting");
+        assert_eq!(job.pid, None);
+    }
+
+    #[test]
+    fn rejects_other_lines() {
+        assert!(parse_upstart_job("none").is_none());
+        assert!(parse_upstart_job("job running").is_none());
+    }
+}
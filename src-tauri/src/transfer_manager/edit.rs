@@ -0,0 +1,85 @@
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::device_manager::Device;
+use crate::error::Error;
+use crate::transfer_manager::remote_fs::{RemoteFs, RemoteStat};
+use crate::transfer_manager::{RemoteEdit, TransferManager};
+
+impl TransferManager {
+    pub fn add_edit(&self, edit: RemoteEdit) -> Arc<RemoteEdit> {
+        let edit = Arc::new(edit);
+        self.edits
+            .lock()
+            .expect("Failed to lock TransferManager::edits")
+            .insert(edit.id.clone(), edit.clone());
+        return edit;
+    }
+
+    pub fn edit(&self, id: &str) -> Option<Arc<RemoteEdit>> {
+        return self
+            .edits
+            .lock()
+            .expect("Failed to lock TransferManager::edits")
+            .get(id)
+            .cloned();
+    }
+
+    pub fn remove_edit(&self, id: &str) -> Option<Arc<RemoteEdit>> {
+        return self
+            .edits
+            .lock()
+            .expect("Failed to lock TransferManager::edits")
+            .remove(id);
+    }
+}
+
+impl RemoteEdit {
+    /// Downloads the remote file to `local`, remembering its state for conflict detection.
+    pub fn open(
+        fs: &RemoteFs,
+        id: String,
+        device: Device,
+        path: String,
+        local: PathBuf,
+    ) -> Result<RemoteEdit, Error> {
+        let baseline = version(&fs.metadata(&path)?);
+        fs.read_into(&path, &mut File::create(&local)?)?;
+        return Ok(RemoteEdit {
+            id,
+            device,
+            path,
+            local,
+            watch: Mutex::default(),
+            baseline: Mutex::new(baseline),
+        });
+    }
+
+    /// Uploads the local copy, failing with [Error::Conflict] if the remote file changed since it
+    /// was downloaded or last written back, unless `force` is set.
+    pub fn write_back(&self, fs: &RemoteFs, force: bool) -> Result<(), Error> {
+        let mut baseline = self.baseline.lock().unwrap();
+        if !force {
+            let current = fs.metadata(&self.path).map(|s| version(&s)).ok();
+            if current != Some(*baseline) {
+                return Err(Error::Conflict);
+            }
+        }
+        let mut file = File::open(&self.local)?;
+        let mut writer = fs.create(&self.path)?;
+        std::io::copy(&mut file, &mut writer)?;
+        writer.finish()?;
+        *baseline = version(&fs.metadata(&self.path)?);
+        return Ok(());
+    }
+
+    pub fn set_watch(&self, id: Option<String>) -> Option<String> {
+        return std::mem::replace(&mut *self.watch.lock().unwrap(), id);
+    }
+}
+
+/// Modification time and size, as SFTP reports mtime with a resolution of seconds
+fn version(stat: &RemoteStat) -> (u64, u64) {
+    return (stat.mtime, stat.size);
+}
@@ -0,0 +1,82 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+use crate::error::Error;
+use crate::session_manager::command::quote;
+use crate::transfer_manager::Exclude;
+
+impl Exclude {
+    /// Compiles `.gitignore`-style patterns, matched against paths relative to the transfer root.
+    pub fn new(patterns: &[String]) -> Result<Self, Error> {
+        let mut builder = GitignoreBuilder::new("");
+        for pattern in patterns {
+            builder
+                .add_line(None, pattern)
+                .map_err(|e| Error::new(format!("Invalid exclude pattern {pattern}: {e}")))?;
+        }
+        let matcher = builder
+            .build()
+            .map_err(|e| Error::new(format!("Invalid exclude patterns: {e}")))?;
+        return Ok(Self {
+            matcher,
+            patterns: patterns.to_vec(),
+        });
+    }
+
+    /// `--exclude` options for `tar` archiving the transfer root as `.`, so excluded paths aren't
+    /// sent by the device at all. Patterns tar can't express are left to [Exclude::matches], and
+    /// none are passed if any is negated, as tar would still leave out what they re-include.
+    pub fn tar_args(&self) -> String {
+        let mut args = Vec::new();
+        for pattern in &self.patterns {
+            let pattern = pattern.trim();
+            if pattern.starts_with('!') {
+                return String::new();
+            }
+            if pattern.is_empty() || pattern.starts_with('#') {
+                continue;
+            }
+            let (pattern, anywhere) = match pattern.strip_prefix("**/") {
+                Some(pattern) => (pattern, true),
+                None => (pattern, false),
+            };
+            let (pattern, dir_only) = match pattern.strip_suffix('/') {
+                Some(pattern) => (pattern, true),
+                None => (pattern, false),
+            };
+            if pattern.is_empty() || pattern.contains("**") {
+                continue;
+            }
+            // Anchored like in .gitignore when starting with or containing a slash
+            let pattern = match pattern.strip_prefix('/') {
+                Some(rooted) => format!("./{rooted}"),
+                None if pattern.contains('/') && !anywhere => format!("./{pattern}"),
+                None => String::from(pattern),
+            };
+            // Only directories have content, so this leaves out just their entries
+            let pattern = if dir_only {
+                format!("{pattern}/*")
+            } else {
+                pattern
+            };
+            args.push(format!("--exclude={}", quote(&pattern)));
+        }
+        return args.join(" ");
+    }
+
+    /// Whether `path`, or any directory containing it, is excluded.
+    pub fn matches(&self, path: &str, dir: bool) -> bool {
+        return self
+            .matcher
+            .matched_path_or_any_parents(path, dir)
+            .is_ignore();
+    }
+}
+
+impl Default for Exclude {
+    fn default() -> Self {
+        return Self {
+            matcher: Gitignore::empty(),
+            patterns: Vec::new(),
+        };
+    }
+}
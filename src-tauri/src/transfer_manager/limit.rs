@@ -0,0 +1,39 @@
+use std::time::{Duration, Instant};
+
+use crate::transfer_manager::RateLimiter;
+
+impl RateLimiter {
+    /// Sets the limit in bytes per second, or removes it with `None`.
+    pub fn set_limit(&self, limit: Option<usize>) {
+        *self.limit.lock().unwrap() = limit.filter(|l| *l > 0);
+        *self.bucket.lock().unwrap() = None;
+    }
+
+    /// Blocks until `bytes` can be transferred without exceeding the limit. The bucket holds at
+    /// most a second worth of bytes, so bursts stay short.
+    pub fn acquire(&self, bytes: usize) {
+        let Some(limit) = *self.limit.lock().unwrap() else {
+            return;
+        };
+        if bytes == 0 {
+            return;
+        }
+        let limit = limit as f64;
+        // Held while sleeping, so concurrent transfers sharing the limiter queue up behind it
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        let available = match *bucket {
+            Some((updated, left)) => {
+                (left + now.duration_since(updated).as_secs_f64() * limit).min(limit)
+            }
+            None => 0.0,
+        };
+        let left = available - bytes as f64;
+        if left < 0.0 {
+            std::thread::sleep(Duration::from_secs_f64(-left / limit));
+            *bucket = Some((Instant::now(), 0.0));
+        } else {
+            *bucket = Some((now, left));
+        }
+    }
+}
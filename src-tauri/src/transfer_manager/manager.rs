@@ -0,0 +1,76 @@
+use std::sync::{Arc, Mutex};
+
+use uuid::Uuid;
+
+use crate::transfer_manager::{
+    RateLimiter, SyncProfiles, Transfer, TransferManager, TransferQueue,
+};
+
+impl TransferManager {
+    pub fn begin(&self, id: Option<String>) -> Arc<Transfer> {
+        let transfer = Arc::new(Transfer::new(
+            id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            self.limiter.clone(),
+        ));
+        self.items
+            .lock()
+            .expect("Failed to lock TransferManager::items")
+            .insert(transfer.id.clone(), transfer.clone());
+        return transfer;
+    }
+
+    pub fn finish(&self, transfer: &Transfer) {
+        self.items
+            .lock()
+            .expect("Failed to lock TransferManager::items")
+            .remove(&transfer.id);
+    }
+
+    /// Limits transfer `id` to `limit` bytes per second, or all transfers combined if `id` is
+    /// `None`. Returns false if there's no such transfer.
+    pub fn set_limit(&self, id: Option<&str>, limit: Option<usize>) -> bool {
+        let Some(id) = id else {
+            self.limiter.set_limit(limit);
+            return true;
+        };
+        let items = self
+            .items
+            .lock()
+            .expect("Failed to lock TransferManager::items");
+        if let Some(transfer) = items.get(id) {
+            transfer.set_limit(limit);
+            return true;
+        }
+        drop(items);
+        return self.queue.set_limit(id, limit);
+    }
+
+    pub fn cancel(&self, id: &str) -> bool {
+        let items = self
+            .items
+            .lock()
+            .expect("Failed to lock TransferManager::items");
+        let Some(transfer) = items.get(id) else {
+            return false;
+        };
+        transfer.cancel();
+        return true;
+    }
+}
+
+impl Default for TransferManager {
+    fn default() -> Self {
+        let limiter = Arc::new(RateLimiter::default());
+        return Self {
+            items: Mutex::default(),
+            watches: Mutex::default(),
+            edits: Mutex::default(),
+            limiter: limiter.clone(),
+            queue: Arc::new(TransferQueue {
+                limiter,
+                ..TransferQueue::default()
+            }),
+            profiles: SyncProfiles::default(),
+        };
+    }
+}
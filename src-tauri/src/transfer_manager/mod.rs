@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use ignore::gitignore::Gitignore;
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Channel;
+
+use crate::device_manager::Device;
+use crate::error::Error;
+
+mod edit;
+mod exclude;
+mod limit;
+mod manager;
+pub(crate) mod preserve;
+mod profiles;
+mod queue;
+pub(crate) mod remote_fs;
+mod resume;
+mod stream;
+mod sync;
+pub(crate) mod tar_pipe;
+mod transfer;
+mod tree;
+pub(crate) mod watch;
+
+pub(crate) struct TransferManager {
+    items: Mutex<HashMap<String, Arc<Transfer>>>,
+    watches: Mutex<HashMap<String, Watch>>,
+    edits: Mutex<HashMap<String, Arc<RemoteEdit>>>,
+    /// Shared by all transfers, including queued ones
+    limiter: Arc<RateLimiter>,
+    pub queue: Arc<TransferQueue>,
+    pub profiles: SyncProfiles,
+}
+
+#[derive(Default)]
+pub(crate) struct TransferQueue {
+    items: Mutex<Vec<QueueItem>>,
+    /// Threads still running items, by a token of each run. A paused item keeps its entry until
+    /// its thread exits, so it can't run twice at once.
+    running: Mutex<HashMap<String, QueueRun>>,
+    runner: Mutex<Option<Arc<dyn QueueRunner + Send + Sync>>>,
+    conf_dir: Mutex<Option<PathBuf>>,
+    limiter: Arc<RateLimiter>,
+}
+
+/// Token bucket limiting throughput in bytes per second
+#[derive(Default)]
+pub(crate) struct RateLimiter {
+    limit: Mutex<Option<usize>>,
+    /// When the bucket was last updated, and the bytes left in it
+    bucket: Mutex<Option<(Instant, f64)>>,
+}
+
+struct QueueRun {
+    item: String,
+    device: String,
+    transfer: Arc<Transfer>,
+}
+
+/// Performs queued transfers on behalf of [TransferQueue]
+pub(crate) trait QueueRunner {
+    /// Transfers the item, blocking until it's done or `transfer` is cancelled.
+    fn run(&self, item: &QueueItem, transfer: &Transfer) -> Result<(), Error>;
+
+    /// Concurrent transfers allowed on device `name`, if it sets its own limit.
+    fn transfer_limit(&self, name: &str) -> Option<usize>;
+
+    fn updated(&self, items: Vec<QueueItem>);
+}
+
+/// A remote file opened for editing in a local copy
+pub(crate) struct RemoteEdit {
+    pub id: String,
+    pub device: Device,
+    pub path: String,
+    pub local: PathBuf,
+    watch: Mutex<Option<String>>,
+    baseline: Mutex<(u64, u64)>,
+}
+
+/// Stops watching when dropped
+struct Watch {
+    _watcher: notify::RecommendedWatcher,
+}
+
+pub(crate) struct Transfer {
+    pub id: String,
+    cancelled: Mutex<bool>,
+    started: Instant,
+    state: Mutex<TransferState>,
+    limiter: RateLimiter,
+    global_limiter: Arc<RateLimiter>,
+}
+
+pub(crate) struct TransferReader<'a, R> {
+    transfer: &'a Transfer,
+    inner: R,
+    progress: &'a Channel,
+}
+
+#[derive(Default)]
+struct TransferState {
+    copied: usize,
+    /// Bytes counted as copied without being transferred, e.g. when resuming
+    skipped: usize,
+    total: usize,
+    file: Option<String>,
+    last_report: Option<Instant>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct TransferProgress {
+    pub copied: usize,
+    pub total: usize,
+    /// Average transfer rate in bytes per second
+    pub rate: f64,
+    /// Estimated seconds remaining, absent until the rate is known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eta: Option<f64>,
+    /// Relative path of the file being copied, for directory transfers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TreeEntry {
+    /// Path relative to the transfer root, separated by `/`
+    pub path: String,
+    pub dir: bool,
+    pub size: usize,
+    /// Modification time in seconds since epoch, 0 if unknown
+    pub mtime: u64,
+}
+
+/// Paths left out of recursive transfers
+pub struct Exclude {
+    matcher: Gitignore,
+    patterns: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncOptions {
+    /// Compare content of files with the same size, instead of their modification time
+    #[serde(default)]
+    pub checksum: bool,
+    /// Remove files on the device that don't exist locally
+    #[serde(default)]
+    pub delete: bool,
+    #[serde(default)]
+    pub follow_links: bool,
+    /// `.gitignore`-style patterns of paths to leave alone, both locally and on the device
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Only report what would be uploaded and deleted, without changing anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Saved sync of a local directory to a device, with its options such as exclude patterns
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SyncProfile {
+    pub name: String,
+    /// Name of the device
+    pub device: String,
+    pub source: String,
+    pub target: String,
+    #[serde(default)]
+    pub options: SyncOptions,
+}
+
+#[derive(Default)]
+pub(crate) struct SyncProfiles {
+    items: Mutex<Vec<SyncProfile>>,
+    conf_dir: Mutex<Option<PathBuf>>,
+}
+
+#[derive(Serialize, Default, Debug)]
+pub struct SyncSummary {
+    pub uploaded: Vec<String>,
+    /// Those of `uploaded` that replaced a file already on the device
+    pub overwritten: Vec<String>,
+    pub deleted: Vec<String>,
+    pub unchanged: usize,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct WatchSynced {
+    pub id: String,
+    pub path: String,
+    pub removed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Error>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteEditInfo {
+    pub id: String,
+    pub local_path: String,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct RemoteEditSynced {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Error>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct QueueItem {
+    pub id: String,
+    /// Name of the device, looked up when the item runs so its credentials aren't persisted
+    #[serde(deserialize_with = "device_name")]
+    pub device: String,
+    pub direction: TransferDirection,
+    pub source: String,
+    pub target: String,
+    #[serde(flatten)]
+    pub state: QueueItemState,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum TransferDirection {
+    #[serde(rename = "upload")]
+    Upload,
+    #[serde(rename = "download")]
+    Download,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "state")]
+pub enum QueueItemState {
+    Queued,
+    Running,
+    Paused,
+    Done,
+    Failed { message: String },
+}
+
+/// Reads the device name, also from queues saved with the whole device.
+fn device_name<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    return match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(name) => Ok(name),
+        serde_json::Value::Object(device) => match device.get("name") {
+            Some(serde_json::Value::String(name)) => Ok(name.clone()),
+            _ => Err(serde::de::Error::missing_field("name")),
+        },
+        _ => Err(serde::de::Error::custom("expected device name")),
+    };
+}
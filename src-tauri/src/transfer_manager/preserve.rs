@@ -0,0 +1,94 @@
+use std::fs::File;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use libssh_rs::{Metadata, SetAttributes, Sftp};
+
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::remote_files::exec::ExecStat;
+use crate::session_manager::command::quote;
+use crate::transfer_manager::remote_fs::RemoteFs;
+
+/// Copies permission bits and modification time of a local file to the remote file.
+pub(crate) fn to_remote(sftp: &Sftp, remote: &str, local: &Path) -> Result<(), Error> {
+    let stat = std::fs::metadata(local)?;
+    let mtime = stat.modified().unwrap_or_else(|_| SystemTime::now());
+    sftp.set_metadata(
+        remote,
+        &SetAttributes {
+            size: None,
+            uid_gid: None,
+            permissions: local_mode(&stat),
+            atime_mtime: Some((mtime, mtime)),
+        },
+    )?;
+    return Ok(());
+}
+
+/// Copies permission bits and modification time of a remote file to the local file.
+pub(crate) fn to_local(stat: &Metadata, local: &Path) -> Result<(), Error> {
+    return set_local(local, stat.modified(), stat.permissions());
+}
+
+/// Like [to_remote], with commands for devices without SFTP.
+pub(crate) fn to_remote_exec(
+    session: &DeviceConnection,
+    remote: &str,
+    local: &Path,
+) -> Result<(), Error> {
+    let stat = std::fs::metadata(local)?;
+    if let Some(mode) = local_mode(&stat) {
+        session.exec(&format!("chmod {mode:o} -- {}", quote(remote)), None)?;
+    }
+    let mtime = stat
+        .modified()
+        .unwrap_or_else(|_| SystemTime::now())
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    return RemoteFs::Exec(session).set_mtime(remote, mtime);
+}
+
+/// Like [to_local], with commands for devices without SFTP.
+pub(crate) fn to_local_exec(
+    session: &DeviceConnection,
+    remote: &str,
+    local: &Path,
+) -> Result<(), Error> {
+    let output = session.exec(&ExecStat::command(remote, true), None)?;
+    let stat = ExecStat::parse(&String::from_utf8_lossy(&output))
+        .into_iter()
+        .next()
+        .ok_or(Error::NotFound)?;
+    let mtime = UNIX_EPOCH + Duration::from_secs(stat.mtime);
+    return set_local(local, Some(mtime), Some(stat.mode));
+}
+
+fn set_local(local: &Path, mtime: Option<SystemTime>, mode: Option<u32>) -> Result<(), Error> {
+    if let Some(mtime) = mtime {
+        File::options()
+            .write(true)
+            .open(local)?
+            .set_modified(mtime)?;
+    }
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(local, std::fs::Permissions::from_mode(mode & 0o7777))?;
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+    return Ok(());
+}
+
+#[cfg(unix)]
+fn local_mode(stat: &std::fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    return Some(stat.permissions().mode() & 0o7777);
+}
+
+/// Windows has no POSIX permissions to carry over
+#[cfg(not(unix))]
+fn local_mode(_stat: &std::fs::Metadata) -> Option<u32> {
+    return None;
+}
@@ -0,0 +1,81 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+
+use crate::app_dirs::{GetConfDir, SetConfDir};
+use crate::error::Error;
+use crate::transfer_manager::{SyncProfile, SyncProfiles};
+
+impl SyncProfiles {
+    pub fn list(&self) -> Vec<SyncProfile> {
+        return self.items.lock().unwrap().clone();
+    }
+
+    pub fn get(&self, name: &str) -> Option<SyncProfile> {
+        return self
+            .items
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|p| p.name == name)
+            .cloned();
+    }
+
+    /// Adds the profile, or replaces the one with the same name.
+    pub fn save(&self, profile: SyncProfile) -> Result<(), Error> {
+        let mut items = self.items.lock().unwrap();
+        match items.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => items.push(profile),
+        }
+        return self.write(&items);
+    }
+
+    pub fn remove(&self, name: &str) -> Result<(), Error> {
+        let mut items = self.items.lock().unwrap();
+        let count = items.len();
+        items.retain(|p| p.name != name);
+        if items.len() == count {
+            return Err(Error::NotFound);
+        }
+        return self.write(&items);
+    }
+
+    fn write(&self, items: &Vec<SyncProfile>) -> Result<(), Error> {
+        let Some(path) = self.file_path() else {
+            return Ok(());
+        };
+        self.ensure_conf_dir()?;
+        serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), items)?;
+        return Ok(());
+    }
+
+    fn load(&self) -> Result<Vec<SyncProfile>, Error> {
+        let Some(path) = self.file_path().filter(|p| p.exists()) else {
+            return Ok(Vec::new());
+        };
+        return Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?);
+    }
+
+    fn file_path(&self) -> Option<PathBuf> {
+        return self
+            .get_conf_dir()
+            .map(|dir| dir.join("sync-profiles.json"));
+    }
+}
+
+impl GetConfDir for SyncProfiles {
+    fn get_conf_dir(&self) -> Option<PathBuf> {
+        return self.conf_dir.lock().unwrap().clone();
+    }
+}
+
+impl SetConfDir for SyncProfiles {
+    fn set_conf_dir(&self, dir: PathBuf) {
+        *self.conf_dir.lock().unwrap() = Some(dir);
+        match self.load() {
+            Ok(items) => *self.items.lock().unwrap() = items,
+            Err(e) => log::warn!("Failed to load sync profiles: {e:?}"),
+        }
+    }
+}
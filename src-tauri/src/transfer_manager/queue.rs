@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::app_dirs::{GetConfDir, SetConfDir};
+use crate::error::Error;
+use crate::transfer_manager::{
+    QueueItem, QueueItemState, QueueRun, QueueRunner, Transfer, TransferDirection, TransferQueue,
+};
+
+/// Concurrent transfers per device, unless the device sets its own limit
+const DEFAULT_TRANSFER_LIMIT: usize = 2;
+
+impl TransferQueue {
+    pub fn add(
+        self: &Arc<Self>,
+        device: &str,
+        direction: TransferDirection,
+        source: &str,
+        target: &str,
+    ) -> QueueItem {
+        let item = QueueItem {
+            id: Uuid::new_v4().to_string(),
+            device: String::from(device),
+            direction,
+            source: String::from(source),
+            target: String::from(target),
+            state: QueueItemState::Queued,
+        };
+        self.items
+            .lock()
+            .expect("Failed to lock TransferQueue::items")
+            .push(item.clone());
+        self.changed();
+        self.schedule();
+        return item;
+    }
+
+    pub fn list(&self) -> Vec<QueueItem> {
+        return self
+            .items
+            .lock()
+            .expect("Failed to lock TransferQueue::items")
+            .clone();
+    }
+
+    /// Stops a running or queued item. Its partial content is kept, so [TransferQueue::resume]
+    /// continues where it stopped.
+    pub fn pause(self: &Arc<Self>, id: &str) -> Result<(), Error> {
+        self.update(id, |item| {
+            if matches!(item.state, QueueItemState::Queued | QueueItemState::Running) {
+                item.state = QueueItemState::Paused;
+            }
+        })?;
+        self.cancel_runs(id);
+        return Ok(());
+    }
+
+    /// Queues a paused or failed item again.
+    pub fn resume(self: &Arc<Self>, id: &str) -> Result<(), Error> {
+        self.update(id, |item| {
+            if matches!(
+                item.state,
+                QueueItemState::Paused | QueueItemState::Failed { .. }
+            ) {
+                item.state = QueueItemState::Queued;
+            }
+        })?;
+        self.schedule();
+        return Ok(());
+    }
+
+    /// Stops the item if it's running, and removes it from the queue.
+    pub fn cancel(self: &Arc<Self>, id: &str) -> Result<(), Error> {
+        let mut items = self
+            .items
+            .lock()
+            .expect("Failed to lock TransferQueue::items");
+        let index = items
+            .iter()
+            .position(|item| item.id == id)
+            .ok_or(Error::NotFound)?;
+        items.remove(index);
+        drop(items);
+        self.cancel_runs(id);
+        self.changed();
+        return Ok(());
+    }
+
+    /// Limits the transfer of item `id` while it's running. Returns false if it isn't.
+    pub fn set_limit(&self, id: &str, limit: Option<usize>) -> bool {
+        let running = self.running.lock().unwrap();
+        let Some(run) = running.values().find(|run| run.item == id) else {
+            return false;
+        };
+        run.transfer.set_limit(limit);
+        return true;
+    }
+
+    /// Moves the item to `index`, so it's picked up earlier or later than other queued items.
+    pub fn reorder(self: &Arc<Self>, id: &str, index: usize) -> Result<(), Error> {
+        let mut items = self
+            .items
+            .lock()
+            .expect("Failed to lock TransferQueue::items");
+        let current = items
+            .iter()
+            .position(|item| item.id == id)
+            .ok_or(Error::NotFound)?;
+        let item = items.remove(current);
+        let index = index.min(items.len());
+        items.insert(index, item);
+        drop(items);
+        self.changed();
+        self.schedule();
+        return Ok(());
+    }
+
+    /// Removes finished items.
+    pub fn clear_done(&self) {
+        self.items
+            .lock()
+            .expect("Failed to lock TransferQueue::items")
+            .retain(|item| item.state != QueueItemState::Done);
+        self.changed();
+    }
+
+    pub fn set_runner(self: &Arc<Self>, runner: Box<dyn QueueRunner + Send + Sync>) {
+        *self.runner.lock().unwrap() = Some(Arc::from(runner));
+        self.schedule();
+    }
+
+    /// Starts queued items in order, as long as their device has capacity left. Threads of paused
+    /// items count until they exit, and their items wait for that before running again.
+    fn schedule(self: &Arc<Self>) {
+        let Some(runner) = self.runner.lock().unwrap().clone() else {
+            return;
+        };
+        let mut started = Vec::new();
+        {
+            let mut items = self
+                .items
+                .lock()
+                .expect("Failed to lock TransferQueue::items");
+            let mut running = self.running.lock().unwrap();
+            let mut counts: HashMap<String, usize> = HashMap::new();
+            for run in running.values() {
+                *counts.entry(run.device.clone()).or_default() += 1;
+            }
+            for item in items.iter_mut() {
+                if item.state != QueueItemState::Queued
+                    || running.values().any(|run| run.item == item.id)
+                {
+                    continue;
+                }
+                let limit = runner
+                    .transfer_limit(&item.device)
+                    .unwrap_or(DEFAULT_TRANSFER_LIMIT)
+                    .max(1);
+                let count = counts.entry(item.device.clone()).or_default();
+                if *count >= limit {
+                    continue;
+                }
+                *count += 1;
+                item.state = QueueItemState::Running;
+                let transfer = Arc::new(Transfer::new(item.id.clone(), self.limiter.clone()));
+                let token = Uuid::new_v4().to_string();
+                let run = QueueRun {
+                    item: item.id.clone(),
+                    device: item.device.clone(),
+                    transfer: transfer.clone(),
+                };
+                running.insert(token.clone(), run);
+                started.push((item.clone(), token, transfer));
+            }
+        }
+        if started.is_empty() {
+            return;
+        }
+        self.changed();
+        for (item, token, transfer) in started {
+            let queue = self.clone();
+            let runner = runner.clone();
+            std::thread::spawn(move || {
+                let result = runner.run(&item, &transfer);
+                queue.running.lock().unwrap().remove(&token);
+                // Paused or cancelled items already have their new state
+                if !transfer.is_cancelled() {
+                    queue
+                        .update(&item.id, |item| {
+                            item.state = match &result {
+                                Ok(()) => QueueItemState::Done,
+                                Err(e) => QueueItemState::Failed {
+                                    message: format!("{e:?}"),
+                                },
+                            };
+                        })
+                        .unwrap_or(());
+                }
+                queue.schedule();
+            });
+        }
+    }
+
+    /// Cancels the threads running item `id`.
+    fn cancel_runs(&self, id: &str) {
+        for run in self.running.lock().unwrap().values() {
+            if run.item == id {
+                run.transfer.cancel();
+            }
+        }
+    }
+
+    fn update<F>(&self, id: &str, action: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut QueueItem),
+    {
+        let mut items = self
+            .items
+            .lock()
+            .expect("Failed to lock TransferQueue::items");
+        let item = items
+            .iter_mut()
+            .find(|item| item.id == id)
+            .ok_or(Error::NotFound)?;
+        action(item);
+        drop(items);
+        self.changed();
+        return Ok(());
+    }
+
+    /// Persists the queue and notifies the runner.
+    fn changed(&self) {
+        let items = self.list();
+        if let Err(e) = self.save(&items) {
+            log::warn!("Failed to save transfer queue: {e:?}");
+        }
+        if let Some(runner) = self.runner.lock().unwrap().as_ref() {
+            runner.updated(items);
+        }
+    }
+
+    fn save(&self, items: &Vec<QueueItem>) -> Result<(), Error> {
+        let Some(path) = self.file_path() else {
+            return Ok(());
+        };
+        self.ensure_conf_dir()?;
+        serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), items)?;
+        return Ok(());
+    }
+
+    fn load(&self) -> Result<Vec<QueueItem>, Error> {
+        let Some(path) = self.file_path().filter(|p| p.exists()) else {
+            return Ok(Vec::new());
+        };
+        let mut items: Vec<QueueItem> = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        // Transfers interrupted by quitting the app wait for the user to resume them
+        for item in items.iter_mut() {
+            if item.state == QueueItemState::Running {
+                item.state = QueueItemState::Paused;
+            }
+        }
+        return Ok(items);
+    }
+
+    fn file_path(&self) -> Option<PathBuf> {
+        return self
+            .get_conf_dir()
+            .map(|dir| dir.join("transfer-queue.json"));
+    }
+}
+
+impl GetConfDir for TransferQueue {
+    fn get_conf_dir(&self) -> Option<PathBuf> {
+        return self.conf_dir.lock().unwrap().clone();
+    }
+}
+
+impl SetConfDir for TransferQueue {
+    fn set_conf_dir(&self, dir: PathBuf) {
+        *self.conf_dir.lock().unwrap() = Some(dir);
+        match self.load() {
+            Ok(items) => *self.items.lock().unwrap() = items,
+            Err(e) => log::warn!("Failed to load transfer queue: {e:?}"),
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use std::io::Write;
+use std::time::{Duration, UNIX_EPOCH};
+
+use libssh_rs::{Channel as SshChannel, FileType, SetAttributes, Sftp, SftpFile};
+
+use crate::conn_pool::{ChannelPermit, DeviceConnection};
+use crate::error::Error;
+use crate::remote_files::exec::ExecStat;
+use crate::session_manager::command::quote;
+use crate::transfer_manager::tar_pipe::finish;
+use crate::transfer_manager::tree::{create_remote_dir, walk_remote};
+use crate::transfer_manager::{Exclude, TreeEntry};
+
+/// Files of the device, over SFTP or, for devices without it, with commands over exec channels
+pub(crate) enum RemoteFs<'a> {
+    Sftp(Sftp),
+    Exec(&'a DeviceConnection),
+}
+
+/// Status of a remote file, following symlinks
+pub(crate) struct RemoteStat {
+    pub dir: bool,
+    pub size: u64,
+    /// Seconds since epoch
+    pub mtime: u64,
+}
+
+/// Remote file opened for writing by [RemoteFs::create]
+pub(crate) enum RemoteWriter {
+    Sftp(SftpFile),
+    Exec {
+        ch: SshChannel,
+        command: String,
+        _permit: ChannelPermit,
+    },
+}
+
+impl<'a> RemoteFs<'a> {
+    pub fn new(session: &'a DeviceConnection) -> Result<Self, Error> {
+        if session.use_stream() {
+            return Ok(RemoteFs::Exec(session));
+        }
+        return Ok(RemoteFs::Sftp(session.sftp()?));
+    }
+
+    pub fn metadata(&self, path: &str) -> Result<RemoteStat, Error> {
+        return match self {
+            RemoteFs::Sftp(sftp) => {
+                let stat = sftp.metadata(path)?;
+                Ok(RemoteStat {
+                    dir: stat.file_type() == Some(FileType::Directory),
+                    size: stat.len().unwrap_or(0),
+                    mtime: stat
+                        .modified()
+                        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                        .map_or(0, |d| d.as_secs()),
+                })
+            }
+            RemoteFs::Exec(session) => {
+                let output = session.exec(&ExecStat::command(path, true), None)?;
+                let stat = ExecStat::parse(&String::from_utf8_lossy(&output))
+                    .into_iter()
+                    .next()
+                    .ok_or(Error::NotFound)?;
+                Ok(RemoteStat {
+                    dir: stat.is_dir(),
+                    size: stat.size,
+                    mtime: stat.mtime,
+                })
+            }
+        };
+    }
+
+    /// Copies the content of remote file `path` into `writer`.
+    pub fn read_into<W: Write + ?Sized>(&self, path: &str, writer: &mut W) -> Result<(), Error> {
+        return match self {
+            RemoteFs::Sftp(sftp) => {
+                std::io::copy(&mut sftp.open(path, 0, 0)?, writer)?;
+                Ok(())
+            }
+            RemoteFs::Exec(session) => {
+                let command = format!("cat {}", quote(path));
+                let _permit = session.acquire_channel();
+                let ch = session.new_channel()?;
+                ch.open_session()?;
+                ch.request_exec(&command)?;
+                std::io::copy(&mut ch.stdout(), writer)?;
+                finish(ch, &command)
+            }
+        };
+    }
+
+    /// Opens remote file `path` for writing, truncating it. [RemoteWriter::finish] completes it.
+    pub fn create(&self, path: &str) -> Result<RemoteWriter, Error> {
+        return match self {
+            RemoteFs::Sftp(sftp) => Ok(RemoteWriter::Sftp(sftp.open(
+                path,
+                libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC,
+                0o644,
+            )?)),
+            RemoteFs::Exec(session) => {
+                let command = format!("cat > {}", quote(path));
+                let permit = session.acquire_channel();
+                let ch = session.new_channel()?;
+                ch.open_session()?;
+                ch.request_exec(&command)?;
+                Ok(RemoteWriter::Exec {
+                    ch,
+                    command,
+                    _permit: permit,
+                })
+            }
+        };
+    }
+
+    /// Creates directory `path`, unless it already exists.
+    pub fn create_dir(&self, path: &str) -> Result<(), Error> {
+        return match self {
+            RemoteFs::Sftp(sftp) => create_remote_dir(sftp, path),
+            RemoteFs::Exec(session) => {
+                let path = quote(path);
+                session.exec(&format!("[ -d {path} ] || mkdir -- {path}"), None)?;
+                Ok(())
+            }
+        };
+    }
+
+    /// Removes file or empty directory `path`.
+    pub fn remove(&self, path: &str) -> Result<(), Error> {
+        return match self {
+            RemoteFs::Sftp(sftp) => match sftp.remove_file(path) {
+                Ok(()) => Ok(()),
+                Err(_) => Ok(sftp.remove_dir(path)?),
+            },
+            RemoteFs::Exec(session) => {
+                let path = quote(path);
+                session.exec(
+                    &format!(
+                        "if [ -d {path} ] && [ ! -L {path} ]; then rmdir -- {path}; \
+                         else rm -f -- {path}; fi"
+                    ),
+                    None,
+                )?;
+                Ok(())
+            }
+        };
+    }
+
+    /// Sets the modification time of `path`, in seconds since epoch.
+    pub fn set_mtime(&self, path: &str, mtime: u64) -> Result<(), Error> {
+        return match self {
+            RemoteFs::Sftp(sftp) => {
+                let mtime = UNIX_EPOCH + Duration::from_secs(mtime);
+                sftp.set_metadata(
+                    path,
+                    &SetAttributes {
+                        size: None,
+                        uid_gid: None,
+                        permissions: None,
+                        atime_mtime: Some((mtime, mtime)),
+                    },
+                )?;
+                Ok(())
+            }
+            RemoteFs::Exec(session) => {
+                session.exec(&format!("touch -c -d @{mtime} -- {}", quote(path)), None)?;
+                Ok(())
+            }
+        };
+    }
+
+    /// Lists files and directories below `root`, without following symlinks.
+    pub fn walk(&self, root: &str, exclude: &Exclude) -> Result<Vec<TreeEntry>, Error> {
+        let mut entries = Vec::new();
+        match self {
+            RemoteFs::Sftp(sftp) => walk_remote(sftp, root, "", false, exclude, 0, &mut entries)?,
+            RemoteFs::Exec(session) => {
+                let output = session.exec(&ExecStat::walk_command(root), None)?;
+                for stat in ExecStat::parse(&String::from_utf8_lossy(&output)) {
+                    if !(stat.is_dir() || stat.is_file())
+                        || exclude.matches(&stat.name, stat.is_dir())
+                    {
+                        continue;
+                    }
+                    entries.push(TreeEntry {
+                        dir: stat.is_dir(),
+                        size: if stat.is_dir() { 0 } else { stat.size as usize },
+                        mtime: stat.mtime,
+                        path: stat.name,
+                    });
+                }
+            }
+        }
+        return Ok(entries);
+    }
+}
+
+impl RemoteWriter {
+    /// Waits for the content to be written, and reports failures of the remote command.
+    pub fn finish(self) -> Result<(), Error> {
+        return match self {
+            RemoteWriter::Sftp(file) => {
+                drop(file);
+                Ok(())
+            }
+            RemoteWriter::Exec { ch, command, .. } => {
+                ch.send_eof()?;
+                finish(ch, &command)
+            }
+        };
+    }
+}
+
+impl Write for RemoteWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        return match self {
+            RemoteWriter::Sftp(file) => file.write(buf),
+            RemoteWriter::Exec { ch, .. } => ch.stdin().write(buf),
+        };
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        return match self {
+            RemoteWriter::Sftp(file) => file.flush(),
+            RemoteWriter::Exec { ch, .. } => ch.stdin().flush(),
+        };
+    }
+}
@@ -0,0 +1,106 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use libssh_rs::Sftp;
+use sha2::{Digest, Sha256};
+use tauri::ipc::Channel;
+
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::session_manager::command::quote;
+use crate::transfer_manager::Transfer;
+
+/// Partial content is kept next to the destination with this suffix until the transfer completes
+const PART_SUFFIX: &str = ".part";
+
+impl Transfer {
+    /// Downloads `source` to `target`, continuing from a previous partial download if its content
+    /// matches the beginning of the remote file.
+    pub fn download_resumable(
+        &self,
+        session: &DeviceConnection,
+        sftp: &Sftp,
+        source: &str,
+        target: &Path,
+        progress: &Channel,
+    ) -> Result<(), Error> {
+        let mut part = target.as_os_str().to_owned();
+        part.push(PART_SUFFIX);
+        let part = PathBuf::from(part);
+        let size = sftp.metadata(source)?.len().unwrap_or(0) as usize;
+        let mut offset = std::fs::metadata(&part).map_or(0, |m| m.len() as usize);
+        if offset > size
+            || (offset > 0
+                && local_digest(&part, offset)? != remote_digest(session, source, offset)?)
+        {
+            log::warn!("Partial download {part:?} doesn't match {source}, restarting");
+            offset = 0;
+        }
+        let mut file = OpenOptions::new().create(true).write(true).open(&part)?;
+        file.set_len(offset as u64)?;
+        file.seek(SeekFrom::Start(offset as u64))?;
+        let mut sfile = sftp.open(source, 0, 0)?;
+        sfile.seek(SeekFrom::Start(offset as u64))?;
+        self.expect(size);
+        self.skip(offset);
+        self.copy(&mut sfile, &mut file, progress)?;
+        drop(file);
+        std::fs::rename(&part, target)?;
+        return Ok(());
+    }
+
+    /// Uploads `source` to `target`, continuing from a previous partial upload if its content
+    /// matches the beginning of the local file.
+    pub fn upload_resumable(
+        &self,
+        session: &DeviceConnection,
+        sftp: &Sftp,
+        source: &Path,
+        target: &str,
+        progress: &Channel,
+    ) -> Result<(), Error> {
+        let part = format!("{target}{PART_SUFFIX}");
+        let size = std::fs::metadata(source)?.len() as usize;
+        let mut offset = sftp
+            .metadata(&part)
+            .map_or(0, |m| m.len().unwrap_or(0) as usize);
+        if offset > size
+            || (offset > 0
+                && local_digest(source, offset)? != remote_digest(session, &part, offset)?)
+        {
+            log::warn!("Partial upload {part} doesn't match {source:?}, restarting");
+            offset = 0;
+        }
+        let mut flags = libc::O_WRONLY | libc::O_CREAT;
+        if offset == 0 {
+            flags |= libc::O_TRUNC;
+        }
+        let mut sfile = sftp.open(&part, flags, 0o644)?;
+        sfile.seek(SeekFrom::Start(offset as u64))?;
+        let mut file = File::open(source)?;
+        file.seek(SeekFrom::Start(offset as u64))?;
+        self.expect(size);
+        self.skip(offset);
+        self.copy(&mut file, &mut sfile, progress)?;
+        drop(sfile);
+        sftp.remove_file(target).unwrap_or(());
+        sftp.rename(&part, target)?;
+        return Ok(());
+    }
+}
+
+fn local_digest(path: &Path, len: usize) -> Result<String, Error> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut File::open(path)?.take(len as u64), &mut hasher)?;
+    return Ok(hex::encode(hasher.finalize()));
+}
+
+fn remote_digest(session: &DeviceConnection, path: &str, len: usize) -> Result<String, Error> {
+    let output = session.exec(&format!("head -c {len} {} | sha256sum", quote(path)), None)?;
+    return String::from_utf8_lossy(&output)
+        .split_whitespace()
+        .next()
+        .map(String::from)
+        .ok_or_else(|| Error::new("Unexpected sha256sum output"));
+}
@@ -0,0 +1,67 @@
+use std::fs::File;
+use std::path::Path;
+
+use tauri::ipc::Channel;
+
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::session_manager::command::quote;
+use crate::transfer_manager::tar_pipe::finish;
+use crate::transfer_manager::Transfer;
+
+impl Transfer {
+    /// Downloads with `cat` over an exec channel, for devices without SFTP.
+    pub fn download_stream(
+        &self,
+        session: &DeviceConnection,
+        source: &str,
+        target: &Path,
+        progress: &Channel,
+    ) -> Result<(), Error> {
+        let size = session
+            .exec(&format!("stat -c %s {}", quote(source)), None)
+            .ok()
+            .and_then(|output| {
+                String::from_utf8_lossy(&output)
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+            })
+            .unwrap_or(0);
+        self.expect(size);
+        let command = format!("cat {}", quote(source));
+        let _permit = session.acquire_channel();
+        let ch = session.new_channel()?;
+        ch.open_session()?;
+        ch.request_exec(&command)?;
+        let mut file = File::create(target)?;
+        if let Err(e) = self.copy(&mut ch.stdout(), &mut file, progress) {
+            ch.close()?;
+            return Err(e);
+        }
+        return finish(ch, &command);
+    }
+
+    /// Uploads with `cat` over an exec channel, for devices without SFTP.
+    pub fn upload_stream(
+        &self,
+        session: &DeviceConnection,
+        source: &Path,
+        target: &str,
+        progress: &Channel,
+    ) -> Result<(), Error> {
+        let mut file = File::open(source)?;
+        self.expect(file.metadata()?.len() as usize);
+        let command = format!("cat > {}", quote(target));
+        let _permit = session.acquire_channel();
+        let ch = session.new_channel()?;
+        ch.open_session()?;
+        ch.request_exec(&command)?;
+        if let Err(e) = self.copy(&mut file, &mut ch.stdin(), progress) {
+            ch.close()?;
+            return Err(e);
+        }
+        ch.send_eof()?;
+        return finish(ch, &command);
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+use tauri::ipc::Channel;
+
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::session_manager::command::quote;
+use crate::transfer_manager::remote_fs::RemoteFs;
+use crate::transfer_manager::tree::{join, walk_local};
+use crate::transfer_manager::{Exclude, SyncOptions, SyncSummary, Transfer, TreeEntry};
+
+/// Number of files hashed by a single `sha256sum` invocation
+const CHECKSUM_BATCH: usize = 64;
+
+impl Transfer {
+    /// Uploads files of `source` that are missing on the device or differ by size or modification
+    /// time (or content, with [SyncOptions::checksum]). Uploaded files get the local modification
+    /// time, so unchanged files are skipped on the next run. Paths matching [SyncOptions::exclude]
+    /// are neither uploaded nor deleted. With [SyncOptions::dry_run], the summary is only a plan.
+    pub fn sync_up(
+        &self,
+        session: &DeviceConnection,
+        fs: &RemoteFs,
+        source: &Path,
+        target: &str,
+        options: &SyncOptions,
+        progress: &Channel,
+    ) -> Result<SyncSummary, Error> {
+        let exclude = Exclude::new(&options.exclude)?;
+        let mut local = Vec::new();
+        walk_local(source, "", options.follow_links, &exclude, 0, &mut local)?;
+        let target_exists = fs.metadata(target).map_or(false, |s| s.dir);
+        let remote = if target_exists {
+            fs.walk(target, &exclude)?
+        } else {
+            Vec::new()
+        };
+        let remote_map: HashMap<&str, &TreeEntry> =
+            remote.iter().map(|e| (e.path.as_str(), e)).collect();
+
+        let mut changed: Vec<&TreeEntry> = Vec::new();
+        let mut candidates: Vec<&TreeEntry> = Vec::new();
+        let mut summary = SyncSummary::default();
+        for entry in local.iter().filter(|e| !e.dir) {
+            match remote_map.get(entry.path.as_str()) {
+                Some(r) if !r.dir && r.size == entry.size => {
+                    if options.checksum {
+                        candidates.push(entry);
+                    } else if r.mtime == entry.mtime {
+                        summary.unchanged += 1;
+                    } else {
+                        changed.push(entry);
+                    }
+                }
+                _ => changed.push(entry),
+            }
+        }
+        if !candidates.is_empty() {
+            let digests = remote_digests(session, target, &candidates)?;
+            for entry in candidates {
+                let local_digest = sha256::try_digest(source.join(&entry.path).as_path())?;
+                if digests.get(&entry.path) == Some(&local_digest) {
+                    summary.unchanged += 1;
+                } else {
+                    changed.push(entry);
+                }
+            }
+        }
+
+        let mut extraneous: Vec<&TreeEntry> = Vec::new();
+        if options.delete {
+            let local_paths: HashMap<&str, bool> =
+                local.iter().map(|e| (e.path.as_str(), e.dir)).collect();
+            extraneous = remote
+                .iter()
+                .filter(|r| local_paths.get(r.path.as_str()) != Some(&r.dir))
+                .collect();
+            // Children before their parent directories
+            extraneous.sort_by(|a, b| b.path.len().cmp(&a.path.len()));
+        }
+        if options.dry_run {
+            for entry in changed {
+                if remote_map.contains_key(entry.path.as_str()) {
+                    summary.overwritten.push(entry.path.clone());
+                }
+                summary.uploaded.push(entry.path.clone());
+            }
+            summary.deleted = extraneous.iter().map(|e| e.path.clone()).collect();
+            return Ok(summary);
+        }
+
+        self.expect(changed.iter().map(|e| e.size).sum());
+        fs.create_dir(target)?;
+        for entry in local.iter().filter(|e| e.dir) {
+            if !remote_map.get(entry.path.as_str()).map_or(false, |r| r.dir) {
+                fs.create_dir(&join(target, &entry.path))?;
+            }
+        }
+        for entry in changed {
+            self.set_file(Some(entry.path.clone()));
+            let remote = join(target, &entry.path);
+            let mut file = File::open(source.join(&entry.path))?;
+            let mut writer = fs.create(&remote)?;
+            self.copy(&mut file, &mut writer, progress)?;
+            writer.finish()?;
+            fs.set_mtime(&remote, entry.mtime)?;
+            if remote_map.contains_key(entry.path.as_str()) {
+                summary.overwritten.push(entry.path.clone());
+            }
+            summary.uploaded.push(entry.path.clone());
+        }
+        self.set_file(None);
+
+        for entry in extraneous {
+            fs.remove(&join(target, &entry.path))?;
+            summary.deleted.push(entry.path.clone());
+        }
+        self.report(progress)?;
+        return Ok(summary);
+    }
+}
+
+fn remote_digests(
+    session: &DeviceConnection,
+    root: &str,
+    entries: &[&TreeEntry],
+) -> Result<HashMap<String, String>, Error> {
+    let mut digests = HashMap::new();
+    for batch in entries.chunks(CHECKSUM_BATCH) {
+        let paths: Vec<String> = batch.iter().map(|e| quote(&e.path)).collect();
+        let command = format!("cd {} && sha256sum -- {}", quote(root), paths.join(" "));
+        let output = session.exec(&command, None)?;
+        for line in String::from_utf8_lossy(&output).lines() {
+            if let Some((digest, path)) = line.split_once("  ") {
+                digests.insert(String::from(path), String::from(digest));
+            }
+        }
+    }
+    return Ok(digests);
+}
@@ -0,0 +1,203 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use libssh_rs::Channel as SshChannel;
+use path_slash::PathExt;
+use tauri::ipc::Channel;
+
+use crate::conn_pool::DeviceConnection;
+use crate::error::Error;
+use crate::session_manager::command::quote;
+use crate::transfer_manager::tree::walk_local;
+use crate::transfer_manager::{Exclude, Transfer};
+
+impl Transfer {
+    /// Downloads the directory tree as a single gzipped tar stream, which is much faster than
+    /// SFTP for many small files. Progress counts uncompressed bytes against the size reported by
+    /// `du`, so it's approximate. Excluded paths are left out by `tar` on the device where it can
+    /// express the pattern, and are never unpacked.
+    pub fn download_tar(
+        &self,
+        session: &DeviceConnection,
+        source: &str,
+        target: &Path,
+        follow_links: bool,
+        exclude: &Exclude,
+        progress: &Channel,
+    ) -> Result<(), Error> {
+        let usage = session.exec(&format!("du -sk {}", quote(source)), None)?;
+        let kbytes = String::from_utf8_lossy(&usage)
+            .split_whitespace()
+            .next()
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(0);
+        self.expect(kbytes * 1024);
+        std::fs::create_dir_all(target)?;
+        let flags = if follow_links { "czhf" } else { "czf" };
+        let command = format!(
+            "tar {flags} - {} -C {} .",
+            exclude.tar_args(),
+            quote(source)
+        );
+        let _permit = session.acquire_channel();
+        let ch = session.new_channel()?;
+        ch.open_session()?;
+        ch.request_exec(&command)?;
+        let decoder = GzDecoder::new(ch.stdout());
+        let mut archive = tar::Archive::new(self.reader(decoder, progress));
+        let unpacked = unpack(&mut archive, target, exclude);
+        if self.is_cancelled() {
+            ch.close()?;
+            return Err(Error::Cancelled);
+        }
+        unpacked?;
+        drop(archive);
+        self.report(progress)?;
+        return finish(ch, &command);
+    }
+
+    /// Saves the directory tree as gzipped tar file `target`, compressed on the device and written
+    /// as it arrives. The archive contains the directory itself, not just its content.
+    pub fn download_archive(
+        &self,
+        session: &DeviceConnection,
+        source: &str,
+        target: &Path,
+        follow_links: bool,
+        progress: &Channel,
+    ) -> Result<(), Error> {
+        let trimmed = source.trim_end_matches('/');
+        let (parent, name) = match trimmed.rsplit_once('/') {
+            Some(("", name)) => ("/", name),
+            Some((parent, name)) => (parent, name),
+            None => ("/", "."),
+        };
+        let usage = session.exec(&format!("du -sk {}", quote(source)), None)?;
+        let kbytes = String::from_utf8_lossy(&usage)
+            .split_whitespace()
+            .next()
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(0);
+        self.expect(kbytes * 1024);
+        let flags = if follow_links { "czhf" } else { "czf" };
+        let command = format!("tar {flags} - -C {} {}", quote(parent), quote(name));
+        let _permit = session.acquire_channel();
+        let ch = session.new_channel()?;
+        ch.open_session()?;
+        ch.request_exec(&command)?;
+        let file = File::create(target)?;
+        // Decompressed only to count progress against the size reported by `du`
+        let mut decoder = GzDecoder::new(Tee {
+            inner: ch.stdout(),
+            copy: file,
+        });
+        if let Err(e) = self.copy(&mut decoder, &mut std::io::sink(), progress) {
+            ch.close()?;
+            drop(decoder);
+            std::fs::remove_file(target).unwrap_or(());
+            return Err(e);
+        }
+        std::io::copy(&mut decoder.into_inner(), &mut std::io::sink())?;
+        return finish(ch, &command);
+    }
+
+    /// Uploads the directory tree as a single gzipped tar stream, unpacked by `tar` on the device.
+    pub fn upload_tar(
+        &self,
+        session: &DeviceConnection,
+        source: &Path,
+        target: &str,
+        follow_links: bool,
+        exclude: &Exclude,
+        progress: &Channel,
+    ) -> Result<(), Error> {
+        let mut entries = Vec::new();
+        walk_local(source, "", follow_links, exclude, 0, &mut entries)?;
+        self.expect(entries.iter().map(|e| e.size).sum());
+        let target = quote(target);
+        let command = format!("mkdir -p {target} && tar xzf - -C {target}");
+        let _permit = session.acquire_channel();
+        let ch = session.new_channel()?;
+        ch.open_session()?;
+        ch.request_exec(&command)?;
+        let mut builder = tar::Builder::new(GzEncoder::new(ch.stdin(), Compression::fast()));
+        for entry in entries {
+            let local = source.join(&entry.path);
+            if entry.dir {
+                builder.append_dir(&entry.path, &local)?;
+                continue;
+            }
+            let file = File::open(&local)?;
+            let mut header = tar::Header::new_gnu();
+            header.set_metadata(&file.metadata()?);
+            let appended =
+                builder.append_data(&mut header, &entry.path, self.reader(file, progress));
+            if self.is_cancelled() {
+                ch.close()?;
+                return Err(Error::Cancelled);
+            }
+            appended?;
+        }
+        builder.into_inner()?.finish()?;
+        ch.send_eof()?;
+        self.report(progress)?;
+        return finish(ch, &command);
+    }
+}
+
+/// Writes everything read from `inner` to `copy` as well
+struct Tee<R, W> {
+    inner: R,
+    copy: W,
+}
+
+impl<R: Read, W: Write> Read for Tee<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let bytes = self.inner.read(buf)?;
+        self.copy.write_all(&buf[..bytes])?;
+        return Ok(bytes);
+    }
+}
+
+fn unpack<R: Read>(
+    archive: &mut tar::Archive<R>,
+    target: &Path,
+    exclude: &Exclude,
+) -> Result<(), Error> {
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_slash_lossy().to_string();
+        let path = path.trim_start_matches("./").trim_end_matches('/');
+        if path.is_empty() || path == "." {
+            continue;
+        }
+        if exclude.matches(path, entry.header().entry_type().is_dir()) {
+            log::debug!("Excluding {path}");
+            continue;
+        }
+        entry.unpack_in(target)?;
+    }
+    return Ok(());
+}
+
+/// Waits for the command to exit, failing with [Error::ExitStatus] if it didn't succeed.
+pub(crate) fn finish(ch: SshChannel, command: &str) -> Result<(), Error> {
+    let mut stderr = Vec::<u8>::new();
+    ch.stderr().read_to_end(&mut stderr)?;
+    let exit_code = ch.get_exit_status().unwrap_or(0);
+    ch.close()?;
+    if exit_code != 0 {
+        return Err(Error::ExitStatus {
+            message: format!("{command} failed"),
+            command: String::from(command),
+            exit_code,
+            stderr,
+            unhandled: true,
+        });
+    }
+    return Ok(());
+}
@@ -0,0 +1,155 @@
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tauri::ipc::Channel;
+
+use crate::error::Error;
+use crate::transfer_manager::{
+    RateLimiter, Transfer, TransferProgress, TransferReader, TransferState,
+};
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+impl Transfer {
+    pub fn new(id: String, global_limiter: Arc<RateLimiter>) -> Self {
+        return Transfer {
+            id,
+            cancelled: Mutex::new(false),
+            started: Instant::now(),
+            state: Mutex::new(TransferState::default()),
+            limiter: RateLimiter::default(),
+            global_limiter,
+        };
+    }
+
+    pub fn cancel(&self) {
+        *self.cancelled.lock().unwrap() = true;
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        return *self.cancelled.lock().unwrap();
+    }
+
+    /// Forgets the progress of a previous attempt, at the start of each attempt, so retries don't
+    /// count the same bytes twice.
+    pub fn restart(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.total = 0;
+        state.copied = 0;
+        state.skipped = 0;
+    }
+
+    /// Adds `size` bytes to the expected total of this transfer.
+    pub fn expect(&self, size: usize) {
+        self.state.lock().unwrap().total += size;
+    }
+
+    /// Limits this transfer to `limit` bytes per second, on top of the global limit.
+    pub fn set_limit(&self, limit: Option<usize>) {
+        self.limiter.set_limit(limit);
+    }
+
+    /// Counts `size` bytes as already transferred, without affecting the reported rate.
+    pub fn skip(&self, size: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.copied += size;
+        state.skipped += size;
+    }
+
+    /// Sets the file reported in progress events for the following copies.
+    pub fn set_file(&self, file: Option<String>) {
+        self.state.lock().unwrap().file = file;
+    }
+
+    /// Copies until EOF, reporting progress at most every [PROGRESS_INTERVAL] plus once at the end.
+    pub fn copy<R: ?Sized, W: ?Sized>(
+        &self,
+        reader: &mut R,
+        writer: &mut W,
+        progress: &Channel,
+    ) -> Result<usize, Error>
+    where
+        R: Read,
+        W: Write,
+    {
+        let mut buf = [0; 8192];
+        let mut copied: usize = 0;
+        loop {
+            if self.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            let bytes = reader.read(&mut buf)?;
+            if bytes == 0 {
+                break;
+            }
+            writer.write_all(&buf[..bytes])?;
+            copied += bytes;
+            self.advance(bytes, false, progress)?;
+        }
+        self.report(progress)?;
+        return Ok(copied);
+    }
+
+    /// Reports the current progress regardless of when it was last reported.
+    pub fn report(&self, progress: &Channel) -> Result<(), Error> {
+        return self.advance(0, true, progress);
+    }
+
+    /// Wraps `inner` so bytes read through it count towards this transfer, and reading fails once
+    /// the transfer is cancelled.
+    pub fn reader<'a, R: Read>(&'a self, inner: R, progress: &'a Channel) -> TransferReader<'a, R> {
+        return TransferReader {
+            transfer: self,
+            inner,
+            progress,
+        };
+    }
+
+    fn advance(&self, bytes: usize, force: bool, progress: &Channel) -> Result<(), Error> {
+        self.limiter.acquire(bytes);
+        self.global_limiter.acquire(bytes);
+        let mut state = self.state.lock().unwrap();
+        state.copied += bytes;
+        if !force
+            && state
+                .last_report
+                .map_or(false, |t| t.elapsed() < PROGRESS_INTERVAL)
+        {
+            return Ok(());
+        }
+        state.last_report = Some(Instant::now());
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let rate = if elapsed > 0.0 {
+            (state.copied - state.skipped) as f64 / elapsed
+        } else {
+            0.0
+        };
+        let eta = (rate > 0.0).then(|| state.total.saturating_sub(state.copied) as f64 / rate);
+        return progress
+            .send(TransferProgress {
+                copied: state.copied,
+                total: state.total,
+                rate,
+                eta,
+                file: state.file.clone(),
+            })
+            .map_err(|e| Error::new(format!("Failed to send progress: {e}")));
+    }
+}
+
+impl<'a, R: Read> Read for TransferReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.transfer.is_cancelled() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "Transfer cancelled",
+            ));
+        }
+        let bytes = self.inner.read(buf)?;
+        self.transfer
+            .advance(bytes, false, self.progress)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{e:?}")))?;
+        return Ok(bytes);
+    }
+}
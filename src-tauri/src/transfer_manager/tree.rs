@@ -0,0 +1,239 @@
+use std::fs::File;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use libssh_rs::{FileType, Metadata, Sftp};
+use tauri::ipc::Channel;
+
+use crate::error::Error;
+use crate::transfer_manager::{preserve, Exclude, Transfer, TreeEntry};
+
+/// Guards against symlink loops when following links
+const MAX_DEPTH: usize = 32;
+
+impl Transfer {
+    pub fn download_tree(
+        &self,
+        sftp: &Sftp,
+        source: &str,
+        target: &Path,
+        follow_links: bool,
+        preserve: bool,
+        exclude: &Exclude,
+        progress: &Channel,
+    ) -> Result<(), Error> {
+        let mut entries = Vec::new();
+        walk_remote(sftp, source, "", follow_links, exclude, 0, &mut entries)?;
+        self.expect(entries.iter().map(|e| e.size).sum());
+        std::fs::create_dir_all(target)?;
+        for entry in entries {
+            let local = target.join(&entry.path);
+            if entry.dir {
+                std::fs::create_dir_all(&local)?;
+                continue;
+            }
+            self.set_file(Some(entry.path.clone()));
+            let mut sfile = sftp.open(&join(source, &entry.path), 0, 0)?;
+            let mut file = File::create(&local)?;
+            self.copy(&mut sfile, &mut file, progress)?;
+            drop(file);
+            if preserve {
+                preserve::to_local(&sfile.metadata()?, &local)?;
+            }
+        }
+        self.set_file(None);
+        return Ok(());
+    }
+
+    pub fn upload_tree(
+        &self,
+        sftp: &Sftp,
+        source: &Path,
+        target: &str,
+        follow_links: bool,
+        preserve: bool,
+        exclude: &Exclude,
+        progress: &Channel,
+    ) -> Result<(), Error> {
+        let mut entries = Vec::new();
+        walk_local(source, "", follow_links, exclude, 0, &mut entries)?;
+        self.expect(entries.iter().map(|e| e.size).sum());
+        create_remote_dir(sftp, target)?;
+        for entry in entries {
+            let remote = join(target, &entry.path);
+            if entry.dir {
+                create_remote_dir(sftp, &remote)?;
+                continue;
+            }
+            self.set_file(Some(entry.path.clone()));
+            let local = source.join(&entry.path);
+            let mut file = File::open(&local)?;
+            let mut sfile = sftp.open(
+                &remote,
+                libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC,
+                0o644,
+            )?;
+            self.copy(&mut file, &mut sfile, progress)?;
+            drop(sfile);
+            if preserve {
+                preserve::to_remote(sftp, &remote, &local)?;
+            }
+        }
+        self.set_file(None);
+        return Ok(());
+    }
+}
+
+pub(super) fn walk_remote(
+    sftp: &Sftp,
+    root: &str,
+    rel: &str,
+    follow_links: bool,
+    exclude: &Exclude,
+    depth: usize,
+    entries: &mut Vec<TreeEntry>,
+) -> Result<(), Error> {
+    if depth > MAX_DEPTH {
+        return Err(Error::new(format!(
+            "Directory tree too deep at {}",
+            join(root, rel)
+        )));
+    }
+    for mut stat in sftp.read_dir(&join(root, rel))? {
+        let Some(name) = stat.name().map(String::from) else {
+            continue;
+        };
+        if name == "." || name == ".." {
+            continue;
+        }
+        let path = join(rel, &name);
+        if stat.file_type() == Some(FileType::Symlink) {
+            if !follow_links {
+                log::debug!("Skipping symlink {path}");
+                continue;
+            }
+            match sftp.metadata(&join(root, &path)) {
+                Ok(target) => stat = target,
+                Err(e) => {
+                    log::warn!("Skipping broken symlink {path}: {e:?}");
+                    continue;
+                }
+            }
+        }
+        if exclude.matches(&path, stat.file_type() == Some(FileType::Directory)) {
+            log::debug!("Excluding {path}");
+            continue;
+        }
+        match stat.file_type() {
+            Some(FileType::Directory) => {
+                entries.push(TreeEntry {
+                    path: path.clone(),
+                    dir: true,
+                    size: 0,
+                    mtime: remote_mtime(&stat),
+                });
+                walk_remote(sftp, root, &path, follow_links, exclude, depth + 1, entries)?;
+            }
+            Some(FileType::Regular) => entries.push(TreeEntry {
+                path,
+                dir: false,
+                size: stat.len().unwrap_or(0) as usize,
+                mtime: remote_mtime(&stat),
+            }),
+            _ => log::debug!("Skipping special file {path}"),
+        }
+    }
+    return Ok(());
+}
+
+pub(super) fn walk_local(
+    root: &Path,
+    rel: &str,
+    follow_links: bool,
+    exclude: &Exclude,
+    depth: usize,
+    entries: &mut Vec<TreeEntry>,
+) -> Result<(), Error> {
+    if depth > MAX_DEPTH {
+        return Err(Error::new(format!(
+            "Directory tree too deep at {}",
+            root.join(rel).display()
+        )));
+    }
+    for entry in std::fs::read_dir(root.join(rel))? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = join(rel, &name);
+        let mut stat = std::fs::symlink_metadata(entry.path())?;
+        if stat.is_symlink() {
+            if !follow_links {
+                log::debug!("Skipping symlink {path}");
+                continue;
+            }
+            match std::fs::metadata(entry.path()) {
+                Ok(target) => stat = target,
+                Err(e) => {
+                    log::warn!("Skipping broken symlink {path}: {e:?}");
+                    continue;
+                }
+            }
+        }
+        if exclude.matches(&path, stat.is_dir()) {
+            log::debug!("Excluding {path}");
+            continue;
+        }
+        if stat.is_dir() {
+            entries.push(TreeEntry {
+                path: path.clone(),
+                dir: true,
+                size: 0,
+                mtime: local_mtime(&stat),
+            });
+            walk_local(root, &path, follow_links, exclude, depth + 1, entries)?;
+        } else if stat.is_file() {
+            entries.push(TreeEntry {
+                path,
+                dir: false,
+                size: stat.len() as usize,
+                mtime: local_mtime(&stat),
+            });
+        } else {
+            log::debug!("Skipping special file {path}");
+        }
+    }
+    return Ok(());
+}
+
+fn remote_mtime(stat: &Metadata) -> u64 {
+    return stat
+        .modified()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+}
+
+fn local_mtime(stat: &std::fs::Metadata) -> u64 {
+    return stat
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+}
+
+pub(super) fn create_remote_dir(sftp: &Sftp, path: &str) -> Result<(), Error> {
+    if let Err(e) = sftp.create_dir(path, 0o755) {
+        let exists = sftp
+            .metadata(path)
+            .map_or(false, |s| s.file_type() == Some(FileType::Directory));
+        if !exists {
+            return Err(e.into());
+        }
+    }
+    return Ok(());
+}
+
+pub(super) fn join(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        return String::from(name);
+    }
+    return format!("{}/{}", parent.trim_end_matches('/'), name);
+}
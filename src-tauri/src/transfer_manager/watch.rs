@@ -0,0 +1,117 @@
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use notify::{Event, RecursiveMode, Watcher};
+use path_slash::PathExt;
+use uuid::Uuid;
+
+use crate::error::Error;
+use crate::transfer_manager::remote_fs::RemoteFs;
+use crate::transfer_manager::tree::join;
+use crate::transfer_manager::{TransferManager, Watch};
+
+/// Changes are collected until the directory has been quiet for this long
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+impl TransferManager {
+    /// Watches `root` recursively, calling `on_change` from a worker thread with the watch ID and
+    /// the debounced list of changed paths, relative to `root` and separated by `/`.
+    pub fn watch<F>(&self, root: &Path, on_change: F) -> Result<String, Error>
+    where
+        F: Fn(&str, Vec<String>) + Send + 'static,
+    {
+        let root = root.canonicalize()?;
+        let (sender, receiver) = mpsc::channel::<Event>();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            if let Ok(event) = event {
+                sender.send(event).unwrap_or(());
+            }
+        })
+        .map_err(|e| Error::new(format!("Failed to watch directory: {e}")))?;
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(|e| Error::new(format!("Failed to watch directory: {e}")))?;
+        let id = Uuid::new_v4().to_string();
+        let watch_id = id.clone();
+        std::thread::spawn(move || {
+            // Ends when the watcher, and with it the sender, is dropped
+            while let Ok(event) = receiver.recv() {
+                let mut changed = BTreeSet::new();
+                collect(&root, event, &mut changed);
+                while let Ok(event) = receiver.recv_timeout(DEBOUNCE) {
+                    collect(&root, event, &mut changed);
+                }
+                if !changed.is_empty() {
+                    on_change(&watch_id, changed.into_iter().collect());
+                }
+            }
+        });
+        self.watches
+            .lock()
+            .expect("Failed to lock TransferManager::watches")
+            .insert(id.clone(), Watch { _watcher: watcher });
+        return Ok(id);
+    }
+
+    pub fn unwatch(&self, id: &str) -> bool {
+        return self
+            .watches
+            .lock()
+            .expect("Failed to lock TransferManager::watches")
+            .remove(id)
+            .is_some();
+    }
+}
+
+/// Mirrors the local state of `path` to the device: uploads it if it's a file, creates it if it's
+/// a directory, and removes it if it's gone. Returns whether the path was removed.
+pub(crate) fn deploy(
+    fs: &RemoteFs,
+    source: &Path,
+    target: &str,
+    path: &str,
+) -> Result<bool, Error> {
+    let local = source.join(path);
+    let remote = join(target, path);
+    let stat = match std::fs::metadata(&local) {
+        Ok(stat) => stat,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            fs.remove(&remote).unwrap_or(());
+            return Ok(true);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if let Some((parent, _)) = remote.rsplit_once('/') {
+        let mut current = String::new();
+        for segment in parent.split('/').filter(|s| !s.is_empty()) {
+            current = format!("{current}/{segment}");
+            fs.create_dir(&current)?;
+        }
+    }
+    if stat.is_dir() {
+        fs.create_dir(&remote)?;
+    } else {
+        let mut file = File::open(&local)?;
+        let mut writer = fs.create(&remote)?;
+        std::io::copy(&mut file, &mut writer)?;
+        writer.finish()?;
+    }
+    return Ok(false);
+}
+
+fn collect(root: &Path, event: Event, changed: &mut BTreeSet<String>) {
+    if event.kind.is_access() {
+        return;
+    }
+    for path in event.paths {
+        if let Ok(relative) = path.strip_prefix(root) {
+            if !relative.as_os_str().is_empty() {
+                changed.insert(relative.to_slash_lossy().to_string());
+            }
+        }
+    }
+}
@@ -66,6 +66,10 @@ export interface IOErrorBody extends BackendErrorBody {
     code: 'PermissionDenied' | 'NotFound' | string;
 }
 
+export interface ValidationErrorBody extends BackendErrorBody {
+    fields: Record<string, string>;
+}
+
 export class BackendError extends Error {
     reason: ErrorReason;
 
@@ -89,8 +93,12 @@ export class BackendError extends Error {
 export type ErrorReason =
     'Authorization' |
     'BadPassphrase' |
+    'Cancelled' |
+    'ConfirmationRequired' |
+    'Conflict' |
     'Disconnected' |
     'ExitStatus' |
+    'HostKeyMismatch' |
     'IO' |
     'Message' |
     'NeedsReconnect' |
@@ -99,7 +107,8 @@ export type ErrorReason =
     'PassphraseRequired' |
     'Timeout' |
     'Unsupported' |
-    'UnsupportedKey';
+    'UnsupportedKey' |
+    'Validation';
 
 export class IOError extends BackendError {
     declare code: 'PermissionDenied' | 'NotFound' | string;
@@ -112,3 +121,15 @@ export class IOError extends BackendError {
         return BackendError.isCompatibleBody(e) && e.reason === 'IO';
     }
 }
+
+export class ValidationError extends BackendError {
+    declare fields: Record<string, string>;
+
+    static override isCompatible(e: unknown): e is ValidationError {
+        return BackendError.isCompatible(e) && e.reason === 'Validation';
+    }
+
+    static override isCompatibleBody(e: unknown): e is ValidationErrorBody {
+        return BackendError.isCompatibleBody(e) && e.reason === 'Validation';
+    }
+}